@@ -0,0 +1,140 @@
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+/// Environment variable that holds the password when running subcommands
+pub const PASSWORD_ENV_VAR: &str = "BURKEBUDGET_PASSWORD";
+
+/// Minimum number of characters in a password
+pub const MIN_PASSWORD_LENGTH: usize = 6;
+
+/// Wrong passwords allowed in a row before the account is locked
+pub const MAX_FAILED_LOGINS: u32 = 5;
+
+/// How long an account stays locked after too many wrong passwords
+pub const LOCKOUT_SECONDS: i64 = 5 * 60;
+
+/// Check a new password against the length limit
+pub fn validate_password(password: &str) -> Result<()> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(BudgetError::Validation(format!(
+            "The password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Hash a password with Argon2 and a random salt into a PHC string (the salt and settings are stored in it)
+fn hash_password(password: &str) -> Result<String> {
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes())
+        .map_err(|error| {
+            BudgetError::Validation(format!("The password could not be hashed: {}", error))
+        })?;
+    Ok(hash.to_string())
+}
+
+/// Get the password and lockout fields stored for a user
+/// Returns (password_hash, failed_logins, locked_until)
+fn password_row(conn: &Connection, user: &User) -> Result<(Option<String>, u32, i64)> {
+    Ok(conn.query_row(
+        "SELECT password_hash, failed_logins, locked_until FROM users WHERE username_lower = ?1",
+        [&user.username_lower],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?)
+}
+
+/// Check the password for a user before letting them in
+/// Too many wrong passwords in a row locks the account for LOCKOUT_SECONDS, even across restarts
+/// Users without a password are always let in
+pub fn authenticate(conn: &Connection, user: &User, password: &str) -> Result<()> {
+    let (password_hash, failed_logins, locked_until) = password_row(conn, user)?;
+    let password_hash = match password_hash {
+        Some(password_hash) => password_hash,
+        None => return Ok(()),
+    };
+
+    check_not_locked(user, locked_until)?;
+    let now = Utc::now().timestamp();
+
+    if Argon2::default()
+        .verify_password(password.as_bytes(), password_hash.as_str())
+        .is_ok()
+    {
+        conn.execute(
+            "UPDATE users SET failed_logins = 0, locked_until = 0 WHERE username_lower = ?1",
+            [&user.username_lower],
+        )?;
+        return Ok(());
+    }
+
+    // Count the failure and lock the account once there have been too many
+    let failed_logins = failed_logins + 1;
+    if failed_logins >= MAX_FAILED_LOGINS {
+        conn.execute(
+            "UPDATE users SET failed_logins = 0, locked_until = ?1 WHERE username_lower = ?2",
+            (now + LOCKOUT_SECONDS, &user.username_lower),
+        )?;
+        return Err(BudgetError::Validation(format!(
+            "Incorrect password. {} is now locked for {} minutes.",
+            user.username,
+            LOCKOUT_SECONDS / 60
+        )));
+    }
+    conn.execute(
+        "UPDATE users SET failed_logins = ?1 WHERE username_lower = ?2",
+        (failed_logins, &user.username_lower),
+    )?;
+    Err(BudgetError::Validation(format!(
+        "Incorrect password. {} attempt(s) left before the account is locked.",
+        MAX_FAILED_LOGINS - failed_logins
+    )))
+}
+
+/// Fail if the account is locked from too many wrong passwords
+fn check_not_locked(user: &User, locked_until: i64) -> Result<()> {
+    let now = Utc::now().timestamp();
+    if locked_until > now {
+        return Err(BudgetError::Validation(format!(
+            "{} is locked after too many wrong passwords. Try again in {} minute(s).",
+            user.username,
+            (locked_until - now + 59) / 60
+        )));
+    }
+    Ok(())
+}
+
+/// Fail if the user is currently locked out (so there's no point asking for a password)
+pub fn ensure_unlocked(conn: &Connection, user: &User) -> Result<()> {
+    let (_, _, locked_until) = password_row(conn, user)?;
+    check_not_locked(user, locked_until)
+}
+
+/// Set a new password, or remove it with None
+/// The current password must be given if the account already has one
+pub fn change_password(
+    conn: &Connection,
+    user: &User,
+    current_password: &str,
+    new_password: Option<&str>,
+) -> Result<User> {
+    authenticate(conn, user, current_password)?;
+    let password_hash = match new_password {
+        Some(new_password) => {
+            validate_password(new_password)?;
+            Some(hash_password(new_password)?)
+        }
+        None => None,
+    };
+    conn.execute(
+        "UPDATE users SET password_hash = ?1, failed_logins = 0, locked_until = 0
+        WHERE username_lower = ?2",
+        (&password_hash, &user.username_lower),
+    )?;
+    let mut user = user.clone();
+    user.has_password = password_hash.is_some();
+    Ok(user)
+}
@@ -0,0 +1,350 @@
+use crate::error::{BudgetError, Result};
+use crate::migrations::{schema_version, SCHEMA_VERSION};
+use crate::structs_utils::*;
+use crate::users::{find_user, get_user, USER_TABLES};
+use chrono::prelude::*;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::Connection;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The version of the backup document written by this build
+/// Restoring accepts this version and older ones
+/// Version 2 moved the exchange rates into the user's tables, since each user now has their own
+pub const BACKUP_VERSION: u32 = 2;
+
+/// Marks a JSON file as a backup
+const BACKUP_FORMAT: &str = "burkebudget-backup";
+
+/// Columns of the users table that are reset rather than restored
+const RESET_PROFILE_COLUMNS: [&str; 2] = ["failed_logins", "locked_until"];
+
+/// The column names of a table
+fn table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let mut columns: Vec<String> = vec![];
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let mut rows = stmt.query(())?;
+    while let Some(row) = rows.next()? {
+        columns.push(row.get(1)?);
+    }
+    Ok(columns)
+}
+
+/// Every row of a table matching a WHERE clause, as column names to values
+fn dump_rows(
+    conn: &Connection,
+    table: &str,
+    condition: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<BackupRow>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} WHERE {}", table, condition))?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    let mut rows = stmt.query(params)?;
+    let mut dumped: Vec<BackupRow> = vec![];
+    while let Some(row) = rows.next()? {
+        let mut object = BackupRow::new();
+        for (idx, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(idx)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(number) => Value::from(number),
+                ValueRef::Real(number) => Value::from(number),
+                ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text)),
+                ValueRef::Blob(_) => {
+                    return Err(BudgetError::Validation(format!(
+                        "The {} column of {} holds binary data, which can't be backed up",
+                        column, table
+                    )))
+                }
+            };
+            object.insert(column.clone(), value);
+        }
+        dumped.push(object);
+    }
+    Ok(dumped)
+}
+
+/// Take a backup of everything belonging to a user, including deleted items, versions and snapshots
+/// The user's exchange rates are included so converted totals come out the same after a restore
+pub fn create_backup(conn: &Connection, user: &User) -> Result<Backup> {
+    let mut profile = dump_rows(conn, "users", "username_lower = ?1", [&user.username_lower])?
+        .pop()
+        .ok_or_else(|| {
+            BudgetError::NotFound(format!("The user {} was not found", user.username))
+        })?;
+    for column in RESET_PROFILE_COLUMNS {
+        profile.remove(column);
+    }
+    let mut tables: Vec<(String, Vec<BackupRow>)> = vec![];
+    // Parents before the tables that reference them, the order they are restored in
+    for table in USER_TABLES.iter().rev() {
+        tables.push((
+            String::from(*table),
+            dump_rows(conn, table, "username_lower = ?1", [&user.username_lower])?,
+        ));
+    }
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        schema_version: schema_version(conn)?,
+        created: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        profile,
+        tables,
+        shared_exchange_rates: vec![],
+    })
+}
+
+/// Save a backup of a user to a JSON file, replacing it if it exists
+pub fn write_backup(conn: &Connection, user: &User, path: &str) -> Result<Backup> {
+    let backup = create_backup(conn, user)?;
+    let mut tables = serde_json::Map::new();
+    for (table, rows) in &backup.tables {
+        tables.insert(
+            table.clone(),
+            Value::Array(rows.iter().cloned().map(Value::Object).collect()),
+        );
+    }
+    let mut document = serde_json::Map::new();
+    document.insert(String::from("format"), Value::from(BACKUP_FORMAT));
+    document.insert(String::from("version"), Value::from(backup.version));
+    document.insert(
+        String::from("schema_version"),
+        Value::from(backup.schema_version),
+    );
+    document.insert(String::from("created"), Value::from(backup.created.clone()));
+    document.insert(
+        String::from("profile"),
+        Value::Object(backup.profile.clone()),
+    );
+    document.insert(String::from("tables"), Value::Object(tables));
+    let json = serde_json::to_string_pretty(&Value::Object(document)).map_err(|error| {
+        BudgetError::Parse(format!("The backup could not be written: {}", error))
+    })?;
+    std::fs::write(path, json)?;
+    Ok(backup)
+}
+
+/// Read the rows of a JSON array of objects
+fn parse_rows(value: &Value, name: &str) -> Result<Vec<BackupRow>> {
+    let invalid = || BudgetError::Parse(format!("The backup's {} should be a list of rows", name));
+    value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|row| row.as_object().cloned().ok_or_else(invalid))
+        .collect()
+}
+
+/// Read a backup from a JSON file and check that this build can restore it
+pub fn read_backup(path: &str) -> Result<Backup> {
+    let contents = std::fs::read_to_string(path).map_err(|error| {
+        BudgetError::NotFound(format!("The file {} could not be read: {}", path, error))
+    })?;
+    let document: Value = serde_json::from_str(&contents)
+        .map_err(|error| BudgetError::Parse(format!("{} is not valid JSON: {}", path, error)))?;
+    if document.get("format").and_then(Value::as_str) != Some(BACKUP_FORMAT) {
+        return Err(BudgetError::Parse(format!("{} is not a backup", path)));
+    }
+    let number = |name: &str| {
+        document
+            .get(name)
+            .and_then(Value::as_u64)
+            .map(|number| number as u32)
+            .ok_or_else(|| BudgetError::Parse(format!("The backup is missing its {}", name)))
+    };
+    let version = number("version")?;
+    if version > BACKUP_VERSION {
+        return Err(BudgetError::Validation(format!(
+            "The backup is version {}, but this version of the app can only restore up to version {}",
+            version, BACKUP_VERSION
+        )));
+    }
+    let schema_version = number("schema_version")?;
+    if schema_version > SCHEMA_VERSION {
+        return Err(BudgetError::Validation(format!(
+            "The backup was taken from a newer database (version {}, this app is at version {}). Update the app first.",
+            schema_version, SCHEMA_VERSION
+        )));
+    }
+
+    let mut tables: Vec<(String, Vec<BackupRow>)> = vec![];
+    let document_tables = document
+        .get("tables")
+        .and_then(Value::as_object)
+        .ok_or_else(|| BudgetError::Parse(String::from("The backup is missing its tables")))?;
+    for (table, rows) in document_tables {
+        if !USER_TABLES.contains(&table.as_str()) {
+            return Err(BudgetError::Parse(format!(
+                "The backup has an unknown table {}",
+                table
+            )));
+        }
+        tables.push((table.clone(), parse_rows(rows, table)?));
+    }
+    Ok(Backup {
+        version,
+        schema_version,
+        created: document
+            .get("created")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        profile: document
+            .get("profile")
+            .and_then(Value::as_object)
+            .cloned()
+            .ok_or_else(|| BudgetError::Parse(String::from("The backup is missing its profile")))?,
+        tables,
+        shared_exchange_rates: match document.get("exchange_rates") {
+            Some(rates) => parse_rows(rates, "exchange_rates")?,
+            None => vec![],
+        },
+    })
+}
+
+/// Turn a JSON value from a backup into a value for the database
+fn to_sql_value(value: &Value) -> Result<SqlValue> {
+    match value {
+        Value::Null => Ok(SqlValue::Null),
+        Value::Bool(flag) => Ok(SqlValue::Integer(*flag as i64)),
+        Value::Number(number) => match (number.as_i64(), number.as_f64()) {
+            (Some(integer), _) => Ok(SqlValue::Integer(integer)),
+            (None, Some(real)) => Ok(SqlValue::Real(real)),
+            (None, None) => Err(BudgetError::Parse(format!(
+                "{} is too large to restore",
+                number
+            ))),
+        },
+        Value::String(text) => Ok(SqlValue::Text(text.clone())),
+        Value::Array(_) | Value::Object(_) => Err(BudgetError::Parse(String::from(
+            "A backup row can only hold numbers, text and nulls",
+        ))),
+    }
+}
+
+/// Insert a backup row into a table, checking that every column exists
+/// Returns the new row's rowid
+fn insert_row(conn: &Connection, table: &str, row: &BackupRow) -> Result<i64> {
+    let columns = table_columns(conn, table)?;
+    let mut names: Vec<&str> = vec![];
+    let mut values: Vec<SqlValue> = vec![];
+    for (column, value) in row {
+        if !columns.contains(column) {
+            return Err(BudgetError::Parse(format!(
+                "The backup has an unknown column {} in {}",
+                column, table
+            )));
+        }
+        names.push(column);
+        values.push(to_sql_value(value)?);
+    }
+    let placeholders: Vec<String> = (1..=values.len()).map(|idx| format!("?{}", idx)).collect();
+    conn.execute(
+        &format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            names.join(", "),
+            placeholders.join(", ")
+        ),
+        rusqlite::params_from_iter(values),
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Recreate the user in a backup, with all of their data, under their own username or a new one
+/// The username must not be taken, so restoring into a database that already has the user needs a new name.
+/// Goals and transactions get new ids. The shared rates in a version 1 backup become the restored user's own,
+/// unless the backup's tables already have a rate for the pair.
+pub fn restore_backup(conn: &Connection, backup: &Backup, username: Option<&str>) -> Result<User> {
+    let username = username.unwrap_or(backup.username());
+    if username.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The username cannot be empty",
+        )));
+    }
+    if let Some(existing) = find_user(conn, username)? {
+        return Err(BudgetError::Conflict(format!(
+            "The user {} already exists. Restore the backup under a new username instead.",
+            existing.username
+        )));
+    }
+    let username_lower = username.to_lowercase();
+
+    in_transaction(conn, |conn| {
+        conn.execute("PRAGMA defer_foreign_keys = ON", ())?;
+        let mut profile = backup.profile.clone();
+        for column in RESET_PROFILE_COLUMNS {
+            profile.remove(column);
+        }
+        profile.insert(String::from("username"), Value::from(username));
+        profile.insert(
+            String::from("username_lower"),
+            Value::from(username_lower.as_str()),
+        );
+        insert_row(conn, "users", &profile)?;
+
+        // Goals get new ids, so their links are pointed at the new ones
+        let mut goal_ids: HashMap<i64, i64> = HashMap::new();
+        for table in USER_TABLES.iter().rev() {
+            let Some((_, rows)) = backup.tables.iter().find(|(name, _)| name == table) else {
+                continue;
+            };
+            for row in rows {
+                let mut row = row.clone();
+                row.insert(
+                    String::from("username_lower"),
+                    Value::from(username_lower.as_str()),
+                );
+                match *table {
+                    "goals" => {
+                        let old_id = row.remove("id").and_then(|id| id.as_i64());
+                        let new_id = insert_row(conn, table, &row)?;
+                        if let Some(old_id) = old_id {
+                            goal_ids.insert(old_id, new_id);
+                        }
+                    }
+                    "goal_links" => {
+                        let new_id = row
+                            .get("goal_id")
+                            .and_then(Value::as_i64)
+                            .and_then(|old_id| goal_ids.get(&old_id))
+                            .ok_or_else(|| {
+                                BudgetError::Parse(String::from(
+                                    "The backup has a goal link to a goal that isn't in it",
+                                ))
+                            })?;
+                        row.insert(String::from("goal_id"), Value::from(*new_id));
+                        insert_row(conn, table, &row)?;
+                    }
+                    "transactions" => {
+                        row.remove("id");
+                        insert_row(conn, table, &row)?;
+                    }
+                    _ => {
+                        insert_row(conn, table, &row)?;
+                    }
+                }
+            }
+        }
+
+        for rate in &backup.shared_exchange_rates {
+            let pair_exists: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM exchange_rates
+                WHERE username_lower = ?1 AND from_currency = ?2 AND to_currency = ?3",
+                (
+                    &username_lower,
+                    rate.get("from_currency").and_then(Value::as_str),
+                    rate.get("to_currency").and_then(Value::as_str),
+                ),
+                |row| row.get(0),
+            )?;
+            if !pair_exists {
+                let mut rate = rate.clone();
+                rate.insert(
+                    String::from("username_lower"),
+                    Value::from(username_lower.as_str()),
+                );
+                insert_row(conn, "exchange_rates", &rate)?;
+            }
+        }
+        get_user(conn, username)
+    })
+}
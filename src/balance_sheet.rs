@@ -1,253 +1,63 @@
+use crate::currency::{convert_sum, current_converter, normalize_currency};
+use crate::error::{BudgetError, Result};
 use crate::structs_utils::*;
-use chrono::prelude::*;
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, Row};
 
-mod bs_items_cats_timeline;
-use bs_items_cats_timeline::*;
-
-mod bs_snapshots;
-use bs_snapshots::*;
-
-mod bs_visualizers;
-use bs_visualizers::*;
-
-#[derive(Debug, PartialEq)]
-enum BalanceSheetSelection<'a> {
-    Some(&'a Item),
-    NewCategory,
-    NewItem,
-    RenameCategory,
-    GoBack,
-}
-
-pub fn balance_sheet_half_entry_point(
-    conn: &Connection,
-    user: &User,
-    which_half: BalanceSheetHalf,
-) {
-    initialize_balance_sheet(conn, user);
-    let (mut categories, mut items) = get_relevant_items_cats(conn, user, &which_half)
-        .expect("There was an error accessing the Balance Sheet Database");
-    loop {
-        let response = print_balance_sheet_half_get_response(&categories, &items, &which_half);
-
-        match response {
-            BalanceSheetSelection::Some(item) => {
-                let idx = items
-                    .iter()
-                    .position(|i| i == item)
-                    .expect("Error sending this item for updating");
-                update_item(conn, user, &which_half, &mut categories, &mut items, idx);
-            }
-            BalanceSheetSelection::NewCategory => {
-                create_new_category(conn, user, &which_half, &mut categories)
-            }
-            BalanceSheetSelection::NewItem => {
-                create_new_item(conn, user, &which_half, &mut categories, &mut items)
-            }
-            BalanceSheetSelection::RenameCategory => {
-                rename_category(conn, user, &which_half, &mut categories, &mut items)
-            }
-            BalanceSheetSelection::GoBack => return,
-        }
-    }
-}
-
-pub fn balance_sheet_whole_entry_point(conn: &Connection, user: &User) {
-    initialize_balance_sheet(conn, user);
-    let (asset_categories, asset_items) =
-        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)
-            .expect("There was an error accessing the Balance Sheet Assets from the Database");
-    let (liability_categories, liability_items) =
-        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)
-            .expect("There was an error accessing the Balance Sheet Liabilities from the Database");
-    loop {
-        let (response, net_worth) = print_balance_sheet_get_response(
-            &asset_categories,
-            &asset_items,
-            &liability_categories,
-            &liability_items,
-        );
+/// Add the starting rows this user needs in the balance sheet tables
+/// The tables themselves are created by the migrations when the database is opened
+pub fn initialize_balance_sheet(conn: &Connection, user: &User) -> Result<()> {
+    // Add an Uncategorized type to the table for assets if it's not there yet
+    conn.execute(
+        "INSERT OR IGNORE INTO balance_categories
+        (category, category_lower, username_lower, is_asset)
+        VALUES (\"Uncategorized\", \"uncategorized\", ?1, 1)",
+        rusqlite::params![&user.username_lower],
+    )?;
 
-        match response {
-            0 => return,
-            1 => create_snapshot(conn, user, net_worth).expect("Error creating snapshot"),
-            2 => view_snapshot_menu(conn, user).expect("Error accessing snapshots"),
-            3 => snapshot_visualizer_menu(conn, user),
-            x => panic!("Response {} is an error state. Exiting the program.", x),
-        }
-    }
-}
+    // Add an Uncategorized type to the table for liabilities if it's not there yet
+    conn.execute(
+        "INSERT OR IGNORE INTO balance_categories
+        (category, category_lower, username_lower, is_asset)
+        VALUES (\"Uncategorized\", \"uncategorized\", ?1, 0)",
+        rusqlite::params![&user.username_lower],
+    )?;
 
-/// Print out the half of the balance sheet and find out what the user wants to do
-/// It only receives the relevant half categories and items
-fn print_balance_sheet_half_get_response<'a>(
-    categories: &Vec<Category>,
-    items: &'a Vec<Item>,
-    which_half: &BalanceSheetHalf,
-) -> BalanceSheetSelection<'a> {
-    println!("\nCurrent list of {}:", which_half.to_str().to_lowercase());
-    let mut idx: usize = 1;
-    let mut sorted_items: Vec<&Item> = vec![];
+    // The timeline stores an incrementing integer to demarcate a timeline
+    // The timeline helps differentiate current a past values while avoiding unneccessary dates
+    // It stores the most recently used integer. It should be pulled from the DB, incremented, used, then returned.
+    // Initialize the timeline to 0 for this user if it's not there yet
+    conn.execute(
+        "INSERT OR IGNORE INTO balance_timeline (timestamp, username_lower) VALUES (0, ?1)",
+        rusqlite::params![&user.username_lower],
+    )?;
 
-    for category in categories {
-        let mut no_items_found_in_cat = true;
-        // Check if any of the items are in this category
-        for item in items {
-            if item.category_lower == category.category_lower {
-                no_items_found_in_cat = false;
-            }
-        }
-        if no_items_found_in_cat {
-            continue; // Don't need to print this category if it has no items
-        }
-        println!("{}", category.category);
-        for item in items {
-            if item.category_lower == category.category_lower {
-                print!("    {}. {} ", idx, item.item);
-                let mut num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 1 - item.item.len();
-                // Support prooper formatting up to 999 items
-                if idx >= 10 {
-                    num_dashes -= 1;
-                }
-                if idx >= 100 {
-                    num_dashes -= 1;
-                }
-                for _ in 0..num_dashes {
-                    print!("-");
-                }
-                println!(" {}", to_money_string(item.value));
-                idx += 1;
-                sorted_items.push(item);
-            }
-        }
-    }
-    println!("\n{}. NEW CATEGORY", idx);
-    idx += 1;
-    println!("    {}. NEW ITEM", idx);
-    idx += 1;
-    println!("{}. RENAME CATEGORY", idx);
-    println!("\n0. GO BACK - Balance Sheet Menu");
-    let response = print_instr_get_response(0, idx, || {
-        println!("\nEnter the number of the item you'd like to update / delete, or one of the other numbers");
-    });
-    match response {
-        0 => BalanceSheetSelection::GoBack,
-        x if x > 0 && x <= idx - 3 => BalanceSheetSelection::Some(sorted_items.remove(x - 1)),
-        x if x == idx - 2 => BalanceSheetSelection::NewCategory,
-        x if x == idx - 1 => BalanceSheetSelection::NewItem,
-        x if x == idx => BalanceSheetSelection::RenameCategory,
-        x => panic!("Response {} is an error state. Exiting the program.", x),
-    }
+    Ok(())
 }
 
-fn print_balance_sheet_get_response(
-    asset_categories: &Vec<Category>,
-    asset_items: &Vec<Item>,
-    liability_categories: &Vec<Category>,
-    liability_items: &Vec<Item>,
-) -> (usize, f64) {
-    let today_date = Local::now().format("%Y-%m-%d").to_string();
-    println!("\n\nCurrent Balance Sheet - {}", today_date);
-    println!("\nASSETS");
-    let mut asset_total: f64 = 0.0;
-    for category in asset_categories {
-        let mut no_items_found_in_cat = true;
-        // Check if any of the items are in this category
-        for item in asset_items {
-            if item.category_lower == category.category_lower {
-                no_items_found_in_cat = false;
-            }
-        }
-        if no_items_found_in_cat {
-            continue; // Don't need to print this category if it has no items
-        }
-        println!("{}", category.category);
-        for item in asset_items {
-            if item.category_lower == category.category_lower {
-                print!("    {} ", item.item);
-                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
-                for _ in 0..num_dashes {
-                    print!("-");
-                }
-                println!(" {}", to_money_string(item.value));
-                asset_total += item.value;
-            }
-        }
-    }
-    // Print sum
-    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
-        print!("_");
-    }
-    print!("\nTotal Assets");
-    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 2) {
-        print!(" ");
-    }
-    println!("{}", to_money_string(asset_total));
-
-    println!("\nLIABILITIES");
-    let mut liability_total: f64 = 0.0;
-    for category in liability_categories {
-        let mut no_items_found_in_cat = true;
-        // Check if any of the items are in this category
-        for item in liability_items {
-            if item.category_lower == category.category_lower {
-                no_items_found_in_cat = false;
-            }
-        }
-        if no_items_found_in_cat {
-            continue; // Don't need to print this category if it has no items
-        }
-        println!("{}", category.category);
-        for item in liability_items {
-            if item.category_lower == category.category_lower {
-                print!("    {} ", item.item);
-                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
-                for _ in 0..num_dashes {
-                    print!("-");
-                }
-                println!(" {}", to_money_string(item.value));
-                liability_total += item.value;
-            }
-        }
-    }
-    // Print sum
-    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
-        print!("_");
-    }
-    print!("\nTotal Liabilities");
-    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 7) {
-        print!(" ");
-    }
-    println!("{}", to_money_string(liability_total));
-
-    // Grand total
-    let total = asset_total - liability_total;
-    println!(
-        "\n\nTOTAL NET WORTH --------------------  {}",
-        to_money_string(total)
-    );
-
-    println!(
-        "\n\nBalance Sheet Snapshots can later be viewed or analyzed in aggregate for trends."
-    );
-    println!("It is recommended to do this periodically (such as monthly or quarterly).\n");
-    let response = print_instr_get_response(0, 3, || {
-        println!("1. Take a snapshot");
-        println!("2. View or delete a snapshot");
-        println!("3. Trend Analysis");
-        println!("\n0. Go Back - Balance Sheet Menu");
-    });
-    (response, total)
+/// Build an Item from a full row of the balance_items table
+pub(crate) fn item_from_row(row: &Row) -> rusqlite::Result<Item> {
+    Ok(Item {
+        item: row.get(0)?,
+        item_lower: row.get(1)?,
+        value: row.get(2)?,
+        category: row.get(3)?,
+        category_lower: row.get(4)?,
+        username_lower: row.get(5)?,
+        is_asset: row.get(6)?,
+        timeline_created: row.get(7)?,
+        timeline_original: row.get(8)?,
+        is_deleted: row.get(9)?,
+        timeline_deleted: row.get(10)?,
+        currency: row.get(11)?,
+    })
 }
 
-/// Get the relevant half of the balance sheet (items and categories)
-fn get_relevant_items_cats(
+/// Get the categories for one half of the balance sheet
+pub fn get_categories(
     conn: &Connection,
     user: &User,
     which_half: &BalanceSheetHalf,
-) -> Result<(Vec<Category>, Vec<Item>)> {
-    // Push all of the categories to a vector first
+) -> Result<Vec<Category>> {
     let mut categories: Vec<Category> = vec![];
     let mut stmt =
         conn.prepare("SELECT * FROM balance_categories WHERE is_asset=?1 AND username_lower=?2")?;
@@ -263,6 +73,18 @@ fn get_relevant_items_cats(
             is_asset: row.get(3)?,
         })
     }
+    Ok(categories)
+}
+
+/// Get the relevant half of the balance sheet (items and categories)
+/// Only the current version of each active item is returned
+pub fn get_relevant_items_cats(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+) -> Result<(Vec<Category>, Vec<Item>)> {
+    // Push all of the categories to a vector first
+    let categories = get_categories(conn, user, which_half)?;
     // Next push all of the active items to a vector
     let mut items: Vec<Item> = vec![];
     let mut stmt = conn.prepare(
@@ -273,117 +95,380 @@ fn get_relevant_items_cats(
         user.username_lower
     ])?;
     while let Some(row) = rows.next()? {
-        items.push(Item {
-            item: row.get(0)?,
-            item_lower: row.get(1)?,
-            value: row.get(2)?,
-            category: row.get(3)?,
-            category_lower: row.get(4)?,
-            username_lower: row.get(5)?,
-            is_asset: row.get(6)?,
-            timeline_created: row.get(7)?,
-            timeline_original: row.get(8)?,
-            is_deleted: row.get(9)?,
-            timeline_deleted: row.get(10)?,
-        })
+        items.push(item_from_row(row)?)
     }
     Ok((categories, items))
 }
 
-/// Set up the tables for the balance sheet for this user
-fn initialize_balance_sheet(conn: &Connection, user: &User) {
-    // Create the balance_categories table if it doesn't exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS balance_categories (
-            category TEXT NOT NULL,
-            category_lower TEXT NOT NULL,
-            username_lower TEXT NOT NULL,
-            is_asset INTEGER NOT NULL,
-            PRIMARY KEY (category_lower, username_lower, is_asset),
-            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
-        )",
-        (),
-    )
-    .expect("Error connecting with the balance sheet categories table");
+/// Get every version of every item for a user, including deleted ones
+/// This will return either the assets or liabilities (call once for each)
+pub fn get_all_items(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+) -> Result<Vec<Item>> {
+    let mut items: Vec<Item> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM balance_items
+        WHERE is_asset=?1 AND username_lower=?2",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![
+        which_half.to_bool_int(),
+        user.username_lower,
+    ])?;
+    while let Some(row) = rows.next()? {
+        items.push(item_from_row(row)?)
+    }
+    Ok(items)
+}
 
-    // Add an Uncategorized type to the table for assets if it's not there yet
-    conn.execute(
-        "INSERT OR IGNORE INTO balance_categories 
-        (category, category_lower, username_lower, is_asset) 
-        VALUES (\"Uncategorized\", \"uncategorized\", ?1, 1)",
-        rusqlite::params![&user.username_lower],
-    )
-    .expect("Error initializing the balance_categories table");
+/// Find the current version of an item by name (case insensitive) in either half
+pub fn find_item(conn: &Connection, user: &User, item_name: &str) -> Result<Item> {
+    for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+        let (_, items) = get_relevant_items_cats(conn, user, &which_half)?;
+        if let Some(item) = items
+            .into_iter()
+            .find(|i| i.item_lower == item_name.to_lowercase())
+        {
+            return Ok(item);
+        }
+    }
+    Err(BudgetError::NotFound(format!(
+        "The balance sheet item {} was not found",
+        item_name
+    )))
+}
 
-    // Add an Uncategorized type to the table for liabilities if it's not there yet
-    conn.execute(
-        "INSERT OR IGNORE INTO balance_categories 
-        (category, category_lower, username_lower, is_asset) 
-        VALUES (\"Uncategorized\", \"uncategorized\", ?1, 0)",
-        rusqlite::params![&user.username_lower],
-    )
-    .expect("Error initializing the balance_categories table");
+/// Find a category by name (case insensitive) in one half of the balance sheet
+pub fn find_category(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    cat_name: &str,
+) -> Result<Category> {
+    get_categories(conn, user, which_half)?
+        .into_iter()
+        .find(|c| c.category_lower == cat_name.to_lowercase())
+        .ok_or_else(|| {
+            BudgetError::NotFound(format!(
+                "The {} category {} was not found",
+                which_half.to_str().to_lowercase(),
+                cat_name
+            ))
+        })
+}
 
-    // Create the balance_items table if it doesn't exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS balance_items (
-                item TEXT NOT NULL,
-                item_lower TEXT NOT NULL,
-                value REAL NOT NULL,
-                category TEXT NOT NULL,
-                category_lower TEXT NOT NULL,
-                username_lower TEXT NOT NULL,
-                is_asset INTEGER NOT NULL,
-                timeline_created INTEGER NOT NULL,
-                timeline_original INTEGER NOT NULL,
-                is_deleted INTEGER NOT NULL,
-                timeline_deleted INTEGER NOT NULL,
-                PRIMARY KEY (item_lower, username_lower, timeline_created),
-                FOREIGN KEY (username_lower) REFERENCES users (username_lower),
-                FOREIGN KEY (category_lower, username_lower, is_asset) REFERENCES balance_categories 
-                    (category_lower, username_lower, is_asset) ON UPDATE CASCADE
-            );",
-        (),
-    )
-    .expect("Error connecting with the balance sheet items table");
+/// Sum of the current assets minus the sum of the current liabilities in the user's base currency
+pub fn current_net_worth(conn: &Connection, user: &User) -> Result<Amount> {
+    let (_, asset_items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    let (_, liability_items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    // Items can be in different currencies so convert them into the user's base currency
+    let converter = current_converter(conn, user)?;
+    let total_in = convert_sum(
+        &converter,
+        asset_items.iter().map(|i| (i.value, i.currency.as_str())),
+    )?;
+    let total_out = convert_sum(
+        &converter,
+        liability_items
+            .iter()
+            .map(|i| (i.value, i.currency.as_str())),
+    )?;
+    Ok(total_in - total_out)
+}
 
-    // Create the timeline table to persist a timeline value per user
-    // This stores an incrementing integer to demarcate a timeline
-    // The timeline helps differentiate current a past values while avoiding unneccessary dates
-    // It stores the most recently used integer. It should be pulled from the DB, incremented, used, then returned.
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS balance_timeline (
-                timestamp INTEGER NOT NULL,
-                username_lower TEXT NOT NULL,
-                PRIMARY KEY (username_lower)
-                FOREIGN KEY (username_lower) REFERENCES users (username_lower)
-            );",
-        (),
-    )
-    .expect("Error connecting with the balance sheet timeline table");
+/// Make sure no other active item in this half already uses the name
+/// The item being updated (if any) is identified by its timeline_original so it can keep its own name
+fn check_item_name_available(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    item_name: &str,
+    updating_original: Option<usize>,
+) -> Result<()> {
+    let (_, items) = get_relevant_items_cats(conn, user, which_half)?;
+    for item in items {
+        if item.item_lower == item_name.to_lowercase()
+            && Some(item.timeline_original) != updating_original
+        {
+            return Err(BudgetError::Conflict(format!(
+                "This item already exists as {}",
+                item.item
+            )));
+        }
+    }
+    Ok(())
+}
 
-    // Initialize the timeline to 0 for this user if it's not there yet
+/// Create a new category in one half of the balance sheet
+pub fn create_category(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    cat_name: &str,
+) -> Result<Category> {
+    if cat_name.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The category name cannot be empty",
+        )));
+    }
+    if let Ok(category) = find_category(conn, user, which_half, cat_name) {
+        return Err(BudgetError::Conflict(format!(
+            "This category already exists as {}",
+            category.category
+        )));
+    }
     conn.execute(
-        "INSERT OR IGNORE INTO balance_timeline (timestamp, username_lower) VALUES (0, ?1)",
-        rusqlite::params![&user.username_lower],
-    )
-    .expect("Error initializing the balance_timeline table");
+        "INSERT INTO balance_categories (category, category_lower, username_lower, is_asset) VALUES (?1, ?2, ?3, ?4)",
+        (cat_name, &cat_name.to_lowercase(), &user.username_lower, &which_half.to_bool_int()),
+    )?;
+    Ok(Category {
+        category: String::from(cat_name),
+        category_lower: cat_name.to_lowercase(),
+        username_lower: String::from(&user.username_lower),
+        is_asset: which_half.to_bool(),
+    })
+}
 
-    // Create the table to store snapshots
-    // Minimal information is stored here
-    // The snapshots are recreated as needed by accessing the category and item tables
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS balance_snapshots (
-                timestamp INTEGER NOT NULL,
-                username_lower TEXT NOT NULL,
-                date_text TEXT NOT NULL,
-                net_worth REAL NOT NULL,
-                comment TEXT NOT NULL,
-                is_deleted INTEGER NOT NULL,
-                PRIMARY KEY (timestamp, username_lower, is_deleted)
-                FOREIGN KEY (username_lower) REFERENCES users (username_lower)
-            );",
-        (),
-    )
-    .expect("Error connecting with the balance sheet timeline table");
+/// Rename a category and every item (current and past versions) that uses it
+pub fn rename_category(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    old_name: &str,
+    new_name: &str,
+) -> Result<Category> {
+    in_transaction(conn, |conn| {
+        let old_category = find_category(conn, user, which_half, old_name)?;
+        if new_name.is_empty() {
+            return Err(BudgetError::Validation(String::from(
+                "The category name cannot be empty",
+            )));
+        }
+        if let Ok(category) = find_category(conn, user, which_half, new_name) {
+            return Err(BudgetError::Conflict(format!(
+                "That name is already in use as {}",
+                category.category
+            )));
+        }
+
+        conn.execute(
+            "UPDATE balance_categories
+            SET category = ?1, category_lower = ?2
+            WHERE username_lower = ?3 AND category_lower = ?4 AND is_asset = ?5",
+            (
+                new_name,
+                &new_name.to_ascii_lowercase(),
+                &user.username_lower,
+                &old_category.category_lower,
+                &which_half.to_bool_int(),
+            ),
+        )?;
+
+        // Update all items that have the category with the updated name
+        // This is necessary since they have both the key lowercase category AND the non-key proper capitalization category
+        // Note that the lowercase category will cascade in the DB with the update above
+        conn.execute(
+            "UPDATE balance_items
+                SET category = ?1
+                WHERE username_lower = ?2 AND category_lower = ?3 AND is_asset = ?4",
+            (
+                new_name,
+                &user.username_lower,
+                &new_name.to_ascii_lowercase(),
+                &which_half.to_bool_int(),
+            ),
+        )?;
+
+        Ok(Category {
+            category: String::from(new_name),
+            category_lower: new_name.to_ascii_lowercase(),
+            username_lower: String::from(&user.username_lower),
+            is_asset: which_half.to_bool(),
+        })
+    })
+}
+
+/// Create a new item in one half of the balance sheet
+/// The category must already exist in that half
+pub fn create_item(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    item_name: &str,
+    value: Amount,
+    currency: &str,
+    category: &str,
+) -> Result<Item> {
+    in_transaction(conn, |conn| {
+        validate_item_name(item_name)?;
+        validate_item_value(value)?;
+        let currency = normalize_currency(currency)?;
+        check_item_name_available(conn, user, which_half, item_name, None)?;
+        let category = find_category(conn, user, which_half, category)?;
+
+        // Get the new item's timeline_created value and increment it
+        let timeline: usize = get_and_update_timeline(conn, user)?;
+
+        conn.execute(
+            "INSERT INTO balance_items
+            (item, item_lower, value, category, category_lower, username_lower,
+                is_asset, timeline_created, timeline_original, is_deleted, timeline_deleted, currency)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            (
+                item_name,
+                &item_name.to_lowercase(),
+                &value,
+                &category.category,
+                &category.category_lower,
+                &user.username_lower,
+                &which_half.to_bool_int(),
+                &timeline,
+                &timeline,
+                0,
+                usize::MAX / 4,
+                &currency,
+            ),
+        )?;
+        Ok(Item {
+            item: String::from(item_name),
+            item_lower: item_name.to_lowercase(),
+            value,
+            category: category.category,
+            category_lower: category.category_lower,
+            username_lower: String::from(&user.username_lower),
+            is_asset: which_half.to_bool(),
+            timeline_created: timeline,
+            timeline_original: timeline,
+            is_deleted: false,
+            timeline_deleted: usize::MAX / 4,
+            currency,
+        })
+    })
+}
+
+/// Replace the current version of an item with an updated version
+/// The former version is marked as deleted and the new version keeps the same timeline_original
+pub fn update_item(
+    conn: &Connection,
+    user: &User,
+    item_chosen: &Item,
+    item_name: &str,
+    value: Amount,
+    currency: &str,
+    category: &str,
+) -> Result<Item> {
+    in_transaction(conn, |conn| {
+        let which_half = if item_chosen.is_asset {
+            BalanceSheetHalf::Assets
+        } else {
+            BalanceSheetHalf::Liabilities
+        };
+        validate_item_name(item_name)?;
+        validate_item_value(value)?;
+        let currency = normalize_currency(currency)?;
+        check_item_name_available(
+            conn,
+            user,
+            &which_half,
+            item_name,
+            Some(item_chosen.timeline_original),
+        )?;
+        let category = find_category(conn, user, &which_half, category)?;
+
+        // Mark the former version of the item as deleted
+        delete_item(conn, user, item_chosen)?;
+
+        // Get the timeline_created value for the updated version of the item
+        let timeline: usize = get_and_update_timeline(conn, user)?;
+
+        // Insert the new item into the database
+        conn.execute(
+            "INSERT INTO balance_items
+            (item, item_lower, value, category, category_lower, username_lower,
+                is_asset, timeline_created, timeline_original, is_deleted, timeline_deleted, currency)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            (
+                item_name,
+                &item_name.to_lowercase(),
+                &value,
+                &category.category,
+                &category.category_lower,
+                &user.username_lower,
+                &which_half.to_bool_int(),
+                &timeline,
+                &item_chosen.timeline_original,
+                0,
+                usize::MAX / 4,
+                &currency,
+            ),
+        )?;
+        Ok(Item {
+            item: String::from(item_name),
+            item_lower: item_name.to_lowercase(),
+            value,
+            category: category.category,
+            category_lower: category.category_lower,
+            username_lower: String::from(&user.username_lower),
+            is_asset: which_half.to_bool(),
+            timeline_created: timeline,
+            timeline_original: item_chosen.timeline_original,
+            is_deleted: false,
+            timeline_deleted: usize::MAX / 4,
+            currency,
+        })
+    })
+}
+
+/// Mark the current version of an item as deleted
+/// Past versions are kept so that snapshots can still be reconstructed
+pub fn delete_item(conn: &Connection, user: &User, item: &Item) -> Result<()> {
+    in_transaction(conn, |conn| {
+        let timeline: usize = get_and_update_timeline(conn, user)?;
+        let num_updated = conn.execute(
+            "UPDATE balance_items
+            SET is_deleted = 1, timeline_deleted = ?1
+            WHERE item_lower = ?2 AND username_lower = ?3 AND timeline_created = ?4 AND is_deleted = 0",
+            (
+                &timeline,
+                &item.item_lower,
+                &user.username_lower,
+                &item.timeline_created,
+            ),
+        )?;
+        if num_updated == 0 {
+            return Err(BudgetError::NotFound(format!(
+                "The current version of {} was not found",
+                item.item
+            )));
+        }
+        Ok(())
+    })
+}
+
+/// Gets the timeline from the database and returns it ALREADY INCREMENTED and ready to use
+/// It also updates the value in the timeline database
+pub fn get_and_update_timeline(conn: &Connection, user: &User) -> Result<usize> {
+    in_transaction(conn, |conn| {
+        let mut stmt =
+            conn.prepare("SELECT timestamp FROM balance_timeline WHERE username_lower = ?1")?;
+        let mut rows = stmt.query(rusqlite::params![user.username_lower])?;
+        match rows.next()? {
+            // Timeline found
+            Some(row) => {
+                let timeline_returned: usize = row.get(0)?;
+                let timeline = timeline_returned + 1;
+                // Reinsert the new timeline value into the database
+                conn.execute(
+                    "UPDATE balance_timeline SET timestamp = ?1 WHERE username_lower = ?2",
+                    (timeline, &user.username_lower),
+                )?;
+                Ok(timeline)
+            }
+            // Timeline not found. This needs to be created during initialization.
+            None => Err(BudgetError::NotFound(String::from(
+                "The balance sheet timeline has not been initialized for this user",
+            ))),
+        }
+    })
 }
@@ -0,0 +1,451 @@
+use crate::budget::{find_category, get_categories, get_relevant_items};
+use crate::currency::normalize_currency;
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use crate::transactions::{parse_date, parse_month};
+use chrono::prelude::*;
+use chrono::Months;
+use rusqlite::{Connection, OptionalExtension, Row};
+
+/// Build a MonthItem from a full row of the budget_month_items table
+fn month_item_from_row(row: &Row) -> rusqlite::Result<MonthItem> {
+    Ok(MonthItem {
+        item: row.get(0)?,
+        item_lower: row.get(1)?,
+        value: row.get(2)?,
+        category_lower: row.get(3)?,
+        username_lower: row.get(4)?,
+        is_income: row.get(5)?,
+        month: row.get(6)?,
+        currency: row.get(7)?,
+        item_original: row.get(8)?,
+    })
+}
+
+/// The YYYY-MM month before or after a month
+pub fn shift_month(month: &str, forward: bool) -> Result<String> {
+    let first_day = parse_date(&format!("{}-01", parse_month(month)?))?;
+    let shifted = if forward {
+        first_day.checked_add_months(Months::new(1))
+    } else {
+        first_day.checked_sub_months(Months::new(1))
+    };
+    shifted
+        .map(|date| date.format("%Y-%m").to_string())
+        .ok_or_else(|| BudgetError::Validation(String::from("The date is out of range")))
+}
+
+/// Get every YYYY-MM month that has its own budget, in chronological order
+pub fn list_budget_months(conn: &Connection, user: &User) -> Result<Vec<String>> {
+    let mut months: Vec<String> = vec![];
+    let mut stmt =
+        conn.prepare("SELECT month FROM budget_months WHERE username_lower = ?1 ORDER BY month")?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        months.push(row.get(0)?);
+    }
+    Ok(months)
+}
+
+/// Whether a YYYY-MM month has its own budget
+pub fn has_budget_month(conn: &Connection, user: &User, month: &str) -> Result<bool> {
+    Ok(conn
+        .query_row(
+            "SELECT 1 FROM budget_months WHERE username_lower = ?1 AND month = ?2",
+            (&user.username_lower, month),
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some())
+}
+
+/// Fail unless the month has its own budget
+fn require_budget_month(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    if !has_budget_month(conn, user, month)? {
+        return Err(BudgetError::NotFound(format!(
+            "There is no budget for {} yet",
+            month
+        )));
+    }
+    Ok(())
+}
+
+/// Add an empty budget for a month, failing if it already has one
+fn insert_budget_month(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    if has_budget_month(conn, user, month)? {
+        return Err(BudgetError::Conflict(format!(
+            "There is already a budget for {}",
+            month
+        )));
+    }
+    conn.execute(
+        "INSERT INTO budget_months (username_lower, month) VALUES (?1, ?2)",
+        (&user.username_lower, month),
+    )?;
+    Ok(())
+}
+
+/// Insert one item into a month's budget
+fn insert_month_item(conn: &Connection, item: &MonthItem) -> Result<()> {
+    conn.execute(
+        "INSERT INTO budget_month_items
+        (item, item_lower, value, category_lower, username_lower, is_income, month, currency, item_original)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (
+            &item.item,
+            &item.item_lower,
+            &item.value,
+            &item.category_lower,
+            &item.username_lower,
+            &item.is_income,
+            &item.month,
+            &item.currency,
+            &item.item_original,
+        ),
+    )?;
+    Ok(())
+}
+
+/// An item of the current budget as it is planned in a month, or None when nothing lands in that month
+fn planned_month_item(user: &User, item: BudgetItem, month: &str) -> Result<Option<MonthItem>> {
+    let first_day = parse_date(&format!("{}-01", month))?;
+    let value = item
+        .frequency
+        .amount_in_month(item.value, first_day.year(), first_day.month());
+    if value == Amount::ZERO {
+        return Ok(None);
+    }
+    Ok(Some(MonthItem {
+        item: item.item,
+        item_lower: item.item_lower,
+        value,
+        category_lower: item.category_lower,
+        username_lower: String::from(&user.username_lower),
+        is_income: item.is_income,
+        month: String::from(month),
+        currency: item.currency,
+        item_original: Some(item.timeline_original),
+    }))
+}
+
+/// Give a month its own budget, starting from the current budget
+/// Each item is planned at the amount that lands in that month, and items that don't land in it are left out
+pub fn start_budget_month(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    let month = parse_month(month)?;
+    in_transaction(conn, |conn| {
+        insert_budget_month(conn, user, &month)?;
+        for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+            let (_, items) = get_relevant_items(conn, user, &which_half)?;
+            for item in items {
+                if let Some(month_item) = planned_month_item(user, item, &month)? {
+                    insert_month_item(conn, &month_item)?;
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Give a month its own budget by copying another month's items and rollover settings
+/// Quarterly, annual and one-off items of the current budget aren't copied, since what lands in one month isn't what lands in the next.
+/// They are planned at the amount that lands in the new month instead, as start_budget_month does.
+pub fn copy_budget_month(conn: &Connection, user: &User, from: &str, to: &str) -> Result<()> {
+    let from = parse_month(from)?;
+    let to = parse_month(to)?;
+    in_transaction(conn, |conn| {
+        require_budget_month(conn, user, &from)?;
+        insert_budget_month(conn, user, &to)?;
+        for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+            let (_, current_items) = get_relevant_items(conn, user, &which_half)?;
+            let lumpy_items: Vec<BudgetItem> = current_items
+                .into_iter()
+                .filter(|item| item.frequency.is_lumpy())
+                .collect();
+            let (_, items) = get_month_items(conn, user, &from, &which_half)?;
+            let mut copied: Vec<String> = vec![];
+            for item in items {
+                let is_lumpy = item.item_original.is_some_and(|original| {
+                    lumpy_items
+                        .iter()
+                        .any(|lumpy| lumpy.timeline_original == original)
+                });
+                if is_lumpy {
+                    continue;
+                }
+                copied.push(item.item_lower.clone());
+                insert_month_item(
+                    conn,
+                    &MonthItem {
+                        month: to.clone(),
+                        ..item
+                    },
+                )?;
+            }
+            // An item added to the month by hand keeps its place over a current item with the same name
+            for item in lumpy_items {
+                if copied.contains(&item.item_lower) {
+                    continue;
+                }
+                if let Some(month_item) = planned_month_item(user, item, &to)? {
+                    insert_month_item(conn, &month_item)?;
+                }
+            }
+        }
+        conn.execute(
+            "INSERT INTO budget_month_rollovers (category_lower, username_lower, is_income, month)
+            SELECT category_lower, username_lower, is_income, ?1
+            FROM budget_month_rollovers WHERE username_lower = ?2 AND month = ?3",
+            (&to, &user.username_lower, &from),
+        )?;
+        Ok(())
+    })
+}
+
+/// Permanently remove a month's budget
+/// The month goes back to being planned from the current budget, and its transactions are kept
+pub fn delete_budget_month(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    let month = parse_month(month)?;
+    in_transaction(conn, |conn| {
+        require_budget_month(conn, user, &month)?;
+        for table in [
+            "budget_month_rollovers",
+            "budget_month_items",
+            "budget_months",
+        ] {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {} WHERE username_lower = ?1 AND month = ?2",
+                    table
+                ),
+                (&user.username_lower, &month),
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Get one half of a month's budget, in the same form as get_relevant_items
+pub fn get_month_items(
+    conn: &Connection,
+    user: &User,
+    month: &str,
+    which_half: &BudgetHalf,
+) -> Result<(Vec<BudgetCategory>, Vec<MonthItem>)> {
+    require_budget_month(conn, user, month)?;
+    let categories = get_categories(conn, user, which_half)?;
+    let mut items: Vec<MonthItem> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM budget_month_items WHERE username_lower = ?1 AND month = ?2 AND is_income = ?3",
+    )?;
+    let mut rows = stmt.query((&user.username_lower, month, which_half.to_bool_int()))?;
+    while let Some(row) = rows.next()? {
+        items.push(month_item_from_row(row)?)
+    }
+    Ok((categories, items))
+}
+
+/// Add an item to a month's budget, or change it if the month already has an item with that name
+/// Only this month changes. An item that came from the current budget stays linked to it for transactions.
+#[allow(clippy::too_many_arguments)]
+pub fn set_month_item(
+    conn: &Connection,
+    user: &User,
+    month: &str,
+    which_half: &BudgetHalf,
+    item_name: &str,
+    value: Amount,
+    currency: &str,
+    category: &str,
+) -> Result<MonthItem> {
+    let month = parse_month(month)?;
+    in_transaction(conn, |conn| {
+        validate_item_name(item_name)?;
+        validate_item_value(value)?;
+        let currency = normalize_currency(currency)?;
+        let category = find_category(conn, user, which_half, category)?;
+        let (_, items) = get_month_items(conn, user, &month, which_half)?;
+        let existing = items
+            .into_iter()
+            .find(|i| i.item_lower == item_name.to_lowercase());
+
+        if existing.is_some() {
+            conn.execute(
+                "DELETE FROM budget_month_items
+                WHERE item_lower = ?1 AND username_lower = ?2 AND is_income = ?3 AND month = ?4",
+                (
+                    &item_name.to_lowercase(),
+                    &user.username_lower,
+                    which_half.to_bool_int(),
+                    &month,
+                ),
+            )?;
+        }
+        let item = MonthItem {
+            item: String::from(item_name),
+            item_lower: item_name.to_lowercase(),
+            value,
+            category_lower: category.category_lower,
+            username_lower: String::from(&user.username_lower),
+            is_income: which_half.to_bool(),
+            month: month.clone(),
+            currency,
+            item_original: existing.and_then(|i| i.item_original),
+        };
+        insert_month_item(conn, &item)?;
+        Ok(item)
+    })
+}
+
+/// Remove an item from one month's budget
+pub fn delete_month_item(conn: &Connection, user: &User, item: &MonthItem) -> Result<()> {
+    let num_deleted = conn.execute(
+        "DELETE FROM budget_month_items
+        WHERE item_lower = ?1 AND username_lower = ?2 AND is_income = ?3 AND month = ?4",
+        (
+            &item.item_lower,
+            &user.username_lower,
+            &item.is_income,
+            &item.month,
+        ),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "{} was not found in the budget for {}",
+            item.item, item.month
+        )));
+    }
+    Ok(())
+}
+
+/// The expense categories whose envelopes roll over at the end of a month
+pub fn get_rollovers(conn: &Connection, user: &User, month: &str) -> Result<Vec<String>> {
+    let mut categories: Vec<String> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT category_lower FROM budget_month_rollovers
+        WHERE username_lower = ?1 AND month = ?2 AND is_income = 0",
+    )?;
+    let mut rows = stmt.query((&user.username_lower, month))?;
+    while let Some(row) = rows.next()? {
+        categories.push(row.get(0)?);
+    }
+    Ok(categories)
+}
+
+/// Turn envelope rollover on or off for an expense category in one month
+/// When it is on, whatever is left unspent at the end of the month is added to the category next month
+/// Returns the category that was found, with its stored name
+pub fn set_rollover(
+    conn: &Connection,
+    user: &User,
+    month: &str,
+    category: &str,
+    rollover: bool,
+) -> Result<BudgetCategory> {
+    let month = parse_month(month)?;
+    in_transaction(conn, |conn| {
+        require_budget_month(conn, user, &month)?;
+        let category = find_category(conn, user, &BudgetHalf::Expenses, category)?;
+        conn.execute(
+            "DELETE FROM budget_month_rollovers
+            WHERE category_lower = ?1 AND username_lower = ?2 AND is_income = 0 AND month = ?3",
+            (&category.category_lower, &user.username_lower, &month),
+        )?;
+        if rollover {
+            conn.execute(
+                "INSERT INTO budget_month_rollovers (category_lower, username_lower, is_income, month)
+                VALUES (?1, ?2, 0, ?3)",
+                (&category.category_lower, &user.username_lower, &month),
+            )?;
+        }
+        Ok(category)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::budget::{create_item, initialize_budget};
+    use crate::migrations::migrate;
+    use crate::users::create_user;
+
+    #[test]
+    fn copying_a_month_plans_lumpy_items_for_the_new_month() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        let user = create_user(&conn, "alice", "Alice", "Smith").unwrap();
+        initialize_budget(&conn, &user).unwrap();
+        let expenses = BudgetHalf::Expenses;
+        for (name, value, frequency) in [
+            ("Rent", 100, Frequency::Monthly),
+            ("Insurance", 1200, Frequency::Annual(1)),
+            ("CarTax", 600, Frequency::Annual(2)),
+        ] {
+            let value = Amount::from_major(value);
+            create_item(
+                &conn,
+                &user,
+                &expenses,
+                name,
+                value,
+                "USD",
+                frequency,
+                "Uncategorized",
+            )
+            .unwrap();
+        }
+
+        start_budget_month(&conn, &user, "2025-01").unwrap();
+        // A change to a monthly item and an item only added to January are both copied
+        let rent = Amount::from_major(110);
+        set_month_item(
+            &conn,
+            &user,
+            "2025-01",
+            &expenses,
+            "Rent",
+            rent,
+            "USD",
+            "Uncategorized",
+        )
+        .unwrap();
+        let gift = Amount::from_major(50);
+        set_month_item(
+            &conn,
+            &user,
+            "2025-01",
+            &expenses,
+            "Gift",
+            gift,
+            "USD",
+            "Uncategorized",
+        )
+        .unwrap();
+        copy_budget_month(&conn, &user, "2025-01", "2025-02").unwrap();
+
+        let planned = |month: &str| {
+            let (_, items) = get_month_items(&conn, &user, month, &expenses).unwrap();
+            let mut planned: Vec<(String, Amount, bool)> = items
+                .into_iter()
+                .map(|item| (item.item, item.value, item.item_original.is_some()))
+                .collect();
+            planned.sort();
+            planned
+        };
+        assert_eq!(
+            planned("2025-01"),
+            vec![
+                (String::from("Gift"), gift, false),
+                (String::from("Insurance"), Amount::from_major(1200), true),
+                (String::from("Rent"), rent, true),
+            ]
+        );
+        assert_eq!(
+            planned("2025-02"),
+            vec![
+                (String::from("CarTax"), Amount::from_major(600), true),
+                (String::from("Gift"), gift, false),
+                (String::from("Rent"), rent, true),
+            ]
+        );
+    }
+}
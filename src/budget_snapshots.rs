@@ -0,0 +1,135 @@
+use crate::budget::{
+    budget_item_from_row, current_monthly_net, get_and_update_timeline, get_categories,
+};
+use crate::currency::freeze_budget_rates;
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::{Connection, Row};
+
+/// Build a BudgetSnapshot from a full row of the budget_snapshots table
+fn budget_snapshot_from_row(row: &Row) -> rusqlite::Result<BudgetSnapshot> {
+    Ok(BudgetSnapshot {
+        timeline: row.get(0)?,
+        username_lower: row.get(1)?,
+        date_today: row.get(2)?,
+        monthly_net: row.get(3)?,
+        comment: row.get(4)?,
+        is_deleted: row.get(5)?,
+        currency: row.get(6)?,
+    })
+}
+
+/// Store a snapshot of the current budget in the database and return it
+/// Like a balance sheet snapshot it only stores a timestamp and sparse details
+/// The budget is reconstructed from the budget timeline with budget_items_at
+/// The monthly net is in the user's base currency and the current exchange rates are frozen with it
+pub fn create_budget_snapshot(
+    conn: &Connection,
+    user: &User,
+    comment: &str,
+) -> Result<BudgetSnapshot> {
+    in_transaction(conn, |conn| {
+        let monthly_net = current_monthly_net(conn, user)?;
+
+        // Incrementing the timeline allows for multiple snapshots of the same budget state
+        let timestamp = get_and_update_timeline(conn, user)?;
+
+        let date_today = Local::now().format("%Y-%m-%d").to_string(); // YYYY-MM-DD
+
+        conn.execute(
+            "INSERT INTO budget_snapshots
+            (timestamp, username_lower, date_text, monthly_net, comment, is_deleted, currency)
+            VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6)",
+            (
+                timestamp,
+                &user.username_lower,
+                &date_today,
+                monthly_net,
+                comment,
+                &user.base_currency,
+            ),
+        )?;
+        freeze_budget_rates(conn, user, timestamp)?;
+
+        Ok(BudgetSnapshot {
+            timeline: timestamp,
+            username_lower: String::from(&user.username_lower),
+            date_today,
+            monthly_net,
+            comment: String::from(comment),
+            is_deleted: false,
+            currency: String::from(&user.base_currency),
+        })
+    })
+}
+
+/// Get all of the saved (non-deleted) budget snapshots for a user in chronological order
+pub fn list_budget_snapshots(conn: &Connection, user: &User) -> Result<Vec<BudgetSnapshot>> {
+    let mut snapshots: Vec<BudgetSnapshot> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM budget_snapshots WHERE username_lower = ?1 AND is_deleted = 0
+        ORDER BY timestamp",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        snapshots.push(budget_snapshot_from_row(row)?)
+    }
+    Ok(snapshots)
+}
+
+/// Reconstruct one half of the budget as it was at a point on the budget timeline
+/// Every item version that existed at that point is returned, including ones since updated or deleted
+pub fn budget_items_at(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    timeline: usize,
+) -> Result<(Vec<BudgetCategory>, Vec<BudgetItem>)> {
+    let categories = get_categories(conn, user, which_half)?;
+    let mut items: Vec<BudgetItem> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM budget_items
+        WHERE is_income=?1 AND username_lower=?2 AND timeline_created<=?3 AND timeline_deleted>?3",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![
+        which_half.to_bool_int(),
+        user.username_lower,
+        timeline
+    ])?;
+    while let Some(row) = rows.next()? {
+        items.push(budget_item_from_row(row)?)
+    }
+    Ok((categories, items))
+}
+
+/// Delete a saved budget snapshot
+/// All of the items and categories remain unchanged
+pub fn delete_budget_snapshot(
+    conn: &Connection,
+    user: &User,
+    snapshot: &BudgetSnapshot,
+) -> Result<()> {
+    in_transaction(conn, |conn| {
+        // Deleted snapshots at the same timeline are numbered so they don't clash in the primary key
+        let last_deleted: usize = conn.query_row(
+            "SELECT COALESCE(MAX(is_deleted), 0) FROM budget_snapshots
+            WHERE username_lower = ?1 AND timestamp = ?2",
+            (&user.username_lower, snapshot.timeline),
+            |row| row.get(0),
+        )?;
+        let num_updated = conn.execute(
+            "UPDATE budget_snapshots
+            SET is_deleted = ?1
+            WHERE timestamp = ?2 AND username_lower = ?3 AND is_deleted = 0",
+            (last_deleted + 1, snapshot.timeline, &user.username_lower),
+        )?;
+        if num_updated == 0 {
+            return Err(BudgetError::NotFound(format!(
+                "The budget snapshot from {} was not found",
+                snapshot.date_today
+            )));
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,1897 @@
+use crate::menu::balance_sheet::print_balance_sheet;
+use crate::menu::bs_visualizers::{
+    print_category_returns, print_fire_metrics, print_forecast_chart, print_trend_chart,
+    print_trend_table,
+};
+use crate::menu::budget::{print_budget, print_budget_body, print_budget_calendar};
+use crate::menu::debts::{print_debt_chart, print_debts, print_plan_comparison, print_plan_months};
+use crate::menu::goals::{goal_summary, print_goal};
+use crate::menu::import_export::{print_backup, print_balance_import, print_csv_import};
+use crate::menu::loans::{print_loan, print_schedule, print_what_if};
+use crate::menu::transactions::{
+    describe_payee_rule, print_budget_vs_actual, print_statement_import, target_name,
+    transaction_targets,
+};
+use burkebudget::auth::{authenticate, change_password, PASSWORD_ENV_VAR};
+use burkebudget::backup::{read_backup, restore_backup, write_backup};
+use burkebudget::balance_sheet::{self, get_relevant_items_cats, initialize_balance_sheet};
+use burkebudget::budget::{self, budget_calendar, get_relevant_items, initialize_budget};
+use burkebudget::budget_months::{
+    copy_budget_month, delete_budget_month, delete_month_item, get_month_items, list_budget_months,
+    set_month_item, set_rollover, start_budget_month,
+};
+use burkebudget::budget_snapshots::{
+    budget_items_at, create_budget_snapshot, list_budget_snapshots,
+};
+use burkebudget::csv_io::{
+    apply_csv_import, export_balance_sheet_csv, export_budget_csv, export_item_history_csv,
+    export_snapshots_csv, preview_csv_import,
+};
+use burkebudget::currency::{
+    budget_snapshot_converter, current_converter, import_exchange_rates, list_exchange_rates,
+    set_base_currency, set_exchange_rate,
+};
+use burkebudget::debts::{delete_debt_terms, get_debts, plan_debts, set_debt_terms};
+use burkebudget::encryption::{encrypt_db, is_encrypted, rekey, PASSPHRASE_ENV_VAR};
+use burkebudget::error::{BudgetError, Result};
+use burkebudget::forecast::{
+    fire_metrics, forecast_net_worth, get_category_returns, net_worth_history, parse_return_rate,
+    parse_withdrawal_rate, set_category_return,
+};
+use burkebudget::goals::{
+    create_goal, delete_goal, find_goal, goal_progress, list_goals, set_goal_budget_item,
+};
+use burkebudget::ledger::{data_dir, list_ledgers, open_db};
+use burkebudget::loans::{
+    delete_loan_terms, get_loan, parse_apr, projected_balance, set_loan_terms,
+};
+use burkebudget::plain_text::{apply_balance_import, export_plain_text, preview_balance_import};
+use burkebudget::report::{write_report, REPORT_SNAPSHOTS};
+use burkebudget::snapshots::{create_snapshot, list_snapshots, snapshot_trend};
+use burkebudget::statements::{
+    apply_statement_import, delete_payee_rule, list_payee_rules, preview_statement_import,
+    set_payee_rule,
+};
+use burkebudget::structs_utils::*;
+use burkebudget::transactions::{
+    add_transaction, budget_vs_actual, current_month, delete_transaction, list_transactions,
+    parse_date, parse_month,
+};
+use burkebudget::users::{
+    create_user, delete_user, get_user, list_users, purge_user, rename_user, restore_user,
+    update_name,
+};
+use chrono::{Local, NaiveDate};
+use clap::{Parser, Subcommand, ValueEnum};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Command line arguments
+/// Running without a subcommand starts the interactive menus
+#[derive(Parser)]
+#[command(name = "burkebudget", version, about = "A command line budgeting app")]
+pub struct Cli {
+    /// The user to run the command as (required by the budget, bs, goal, csv, journal, report, rates and backup create commands)
+    #[arg(short, long, global = true)]
+    pub user: Option<String>,
+
+    /// Path to the database file (overrides --ledger and BURKEBUDGET_DB)
+    #[arg(long, global = true)]
+    pub db: Option<PathBuf>,
+
+    /// Name of a ledger in the data directory (eg - household, business)
+    #[arg(short, long, global = true)]
+    pub ledger: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List or create users
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// View or update the monthly budget
+    Budget {
+        #[command(subcommand)]
+        command: BudgetCommand,
+    },
+    /// View or update the balance sheet and its snapshots
+    Bs {
+        #[command(subcommand)]
+        command: BsCommand,
+    },
+    /// Track savings and payoff goals for balance sheet items
+    Goal {
+        #[command(subcommand)]
+        command: GoalCommand,
+    },
+    /// Export to CSV files or import items from one
+    Csv {
+        #[command(subcommand)]
+        command: CsvCommand,
+    },
+    /// Export to ledger, hledger or beancount, or import their balances as snapshots
+    Journal {
+        #[command(subcommand)]
+        command: JournalCommand,
+    },
+    /// Write a report of the balance sheet, budget, snapshots side by side and net worth trend to share with a financial planner
+    Report {
+        file: String,
+        #[arg(long, value_enum, default_value_t = ReportKind::Html)]
+        format: ReportKind,
+        /// The number of a snapshot from bs snapshot list to show side by side (can be repeated, the last 3 by default)
+        #[arg(long = "snapshot")]
+        snapshots: Vec<usize>,
+    },
+    /// Back up everything belonging to a user to a JSON file, or restore one
+    Backup {
+        #[command(subcommand)]
+        command: BackupCommand,
+    },
+    /// View or update the --user's exchange rates between currencies
+    Rates {
+        #[command(subcommand)]
+        command: RatesCommand,
+    },
+    /// List the named ledgers in the data directory
+    Ledgers,
+    /// Encrypt the database or change its passphrase
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CsvCommand {
+    /// Export the balance sheet, budget, every snapshot or the full item history to a CSV file
+    Export {
+        #[arg(value_enum)]
+        what: CsvExport,
+        file: String,
+    },
+    /// Create items (and their categories) from a CSV file with a kind,category,item,value,currency,frequency header
+    ///
+    /// Every row is checked first, and nothing is imported if any of them has a problem
+    Import {
+        file: String,
+        /// Only show what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CsvExport {
+    BalanceSheet,
+    Budget,
+    Snapshots,
+    History,
+}
+
+#[derive(Subcommand)]
+pub enum JournalCommand {
+    /// Write the balance sheet history as balances at each snapshot, and the current budget as periodic transactions
+    Export {
+        file: String,
+        #[arg(long, value_enum, default_value_t = JournalFormat::Ledger)]
+        format: JournalFormat,
+    },
+    /// Take a snapshot for each day with balances in a beancount file (balance directives) or a ledger journal (balance assertions)
+    ///
+    /// Asset and liability accounts update the matching items, and items are created for new accounts
+    Import {
+        file: String,
+        /// Only show what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Ledger and hledger read the same journal format
+#[derive(Clone, Copy, ValueEnum)]
+pub enum JournalFormat {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+impl JournalFormat {
+    fn to_format(self) -> PlainTextFormat {
+        match self {
+            JournalFormat::Ledger | JournalFormat::Hledger => PlainTextFormat::Ledger,
+            JournalFormat::Beancount => PlainTextFormat::Beancount,
+        }
+    }
+}
+
+/// A standalone HTML page with inline styles and charts, or Markdown
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportKind {
+    Html,
+    Markdown,
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommand {
+    /// Save a backup of the --user to a JSON file
+    Create { file: String },
+    /// Recreate the user in a backup, in this database
+    ///
+    /// The username must not be taken, so give a new one to restore into a database that already has the user
+    Restore {
+        file: String,
+        /// Restore under this username instead of the one in the backup
+        #[arg(long = "as")]
+        username: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Encrypt a plaintext database (or create a new encrypted one)
+    ///
+    /// The passphrase is read from the first line of stdin
+    Encrypt,
+    /// Change the passphrase of an encrypted database
+    ///
+    /// The current passphrase is read from BURKEBUDGET_PASSPHRASE and the new one from stdin
+    Rekey,
+}
+
+#[derive(Subcommand)]
+pub enum UserCommand {
+    /// List all of the users
+    List,
+    /// Sign up a new user
+    Add {
+        username: String,
+        #[arg(long)]
+        first: String,
+        #[arg(long)]
+        last: String,
+    },
+    /// Change the currency that totals are shown in (eg - USD, EUR)
+    SetCurrency { currency: String },
+    /// Set or change the password, reading the new one from the first line of stdin
+    SetPassword,
+    /// Remove the password
+    RemovePassword,
+    /// Change the first and/or last name
+    Edit {
+        #[arg(long)]
+        first: Option<String>,
+        #[arg(long)]
+        last: Option<String>,
+    },
+    /// Change the username, moving all of the user's data with it
+    Rename { new_username: String },
+    /// Delete the user so they can be restored later, or permanently with --purge
+    Delete {
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Restore a deleted user
+    Restore,
+}
+
+#[derive(Subcommand)]
+pub enum RatesCommand {
+    /// List the current exchange rates
+    List,
+    /// Add or replace a rate where 1 FROM is worth RATE of TO
+    Set {
+        from: String,
+        to: String,
+        rate: String,
+    },
+    /// Import rates from a file with one FROM,TO,RATE line per pair
+    Import { file: String },
+}
+
+#[derive(Subcommand)]
+pub enum BudgetCommand {
+    /// Print the current monthly budget
+    Show,
+    /// Create budget categories
+    Category {
+        #[command(subcommand)]
+        command: BudgetCategoryCommand,
+    },
+    /// Create, update, or delete budget items
+    Item {
+        #[command(subcommand)]
+        command: BudgetItemCommand,
+    },
+    /// Create, list, or show budget snapshots
+    Snapshot {
+        #[command(subcommand)]
+        command: BudgetSnapshotCommand,
+    },
+    /// Record, list, import, or delete actual income and expenses
+    Tx {
+        #[command(subcommand)]
+        command: TxCommand,
+    },
+    /// Add, list, or remove the payee rules that place imported transactions
+    Rule {
+        #[command(subcommand)]
+        command: RuleCommand,
+    },
+    /// Give months their own budgets, change them, and roll envelopes over
+    Month {
+        #[command(subcommand)]
+        command: BudgetMonthCommand,
+    },
+    /// Show the income and expenses that land in each of the coming months
+    Calendar {
+        #[arg(long, default_value_t = 12)]
+        months: u32,
+    },
+    /// Compare the budget against the transactions in a month
+    Report {
+        /// The month to report on as YYYY-MM (defaults to this month)
+        #[arg(long)]
+        month: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TxCommand {
+    /// Record a transaction against a budget item, or against a category with --kind
+    Add {
+        #[arg(long)]
+        amount: Amount,
+        #[arg(long)]
+        item: Option<String>,
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long, value_enum)]
+        kind: Option<BudgetKind>,
+        /// The date as YYYY-MM-DD (defaults to today)
+        #[arg(long)]
+        date: Option<String>,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Currency of the amount (defaults to the user's base currency)
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// List transactions, only from one YYYY-MM month with --month
+    List {
+        #[arg(long)]
+        month: Option<String>,
+    },
+    /// Delete a transaction by the id shown in the list
+    Delete { id: i64 },
+    /// Import the transactions in an OFX, QFX or QIF bank statement
+    ///
+    /// Ones already recorded are skipped, and an account balance updates the balance sheet item linked to it
+    Import {
+        file: String,
+        /// Link the statement's account to this balance sheet item, so its balance updates the item
+        #[arg(long)]
+        item: Option<String>,
+        /// Only show what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RuleCommand {
+    /// List the payee rules
+    List,
+    /// Send imported transactions whose payee contains PAYEE to a budget item, or to a category with --kind
+    Add {
+        payee: String,
+        #[arg(long)]
+        item: Option<String>,
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long, value_enum)]
+        kind: Option<BudgetKind>,
+    },
+    /// Remove the rules for PAYEE, only the income or expense one with --kind
+    Remove {
+        payee: String,
+        #[arg(long, value_enum)]
+        kind: Option<BudgetKind>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GoalCommand {
+    /// List the goals and how far along they are
+    List,
+    /// Create a goal measured by one or more balance sheet items or categories
+    Add {
+        name: String,
+        #[arg(long)]
+        target: Amount,
+        /// Pay the linked items down to the target instead of saving up to it
+        #[arg(long)]
+        payoff: bool,
+        /// The date to reach it by as YYYY-MM or YYYY-MM-DD
+        #[arg(long)]
+        by: Option<String>,
+        /// Currency of the target (defaults to the user's base currency)
+        #[arg(long)]
+        currency: Option<String>,
+        /// A balance sheet item that counts towards the goal (can be repeated)
+        #[arg(long = "item")]
+        items: Vec<String>,
+        /// An asset category that counts towards the goal (can be repeated)
+        #[arg(long = "asset-category")]
+        asset_categories: Vec<String>,
+        /// A liability category that counts towards the goal (can be repeated)
+        #[arg(long = "liability-category")]
+        liability_categories: Vec<String>,
+        /// A budget expense item that pays into the goal each month
+        #[arg(long)]
+        budget_item: Option<String>,
+    },
+    /// Print a goal's progress and projections
+    Show { name: String },
+    /// Set the budget expense item that pays into a goal, or leave it out to remove it
+    Contribution {
+        name: String,
+        #[arg(long)]
+        budget_item: Option<String>,
+    },
+    /// Delete a goal
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum BudgetMonthCommand {
+    /// List the months that have their own budget
+    List,
+    /// Give a YYYY-MM month its own budget from the current budget, or copy another month with --from
+    Start {
+        month: String,
+        #[arg(long)]
+        from: Option<String>,
+    },
+    /// Add an item to one month, or change the amount planned for it
+    Set {
+        month: String,
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BudgetKind,
+        #[arg(long)]
+        value: Amount,
+        /// Currency of the value (defaults to the item's currency, or the user's base currency)
+        #[arg(long)]
+        currency: Option<String>,
+        /// Defaults to the item's category, or Uncategorized for a new item
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// Remove an item from one month
+    Remove {
+        month: String,
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BudgetKind,
+    },
+    /// Roll what is left in an expense category at the end of the month into the next month
+    Rollover {
+        month: String,
+        category: String,
+        /// Stop rolling the category over instead
+        #[arg(long)]
+        off: bool,
+    },
+    /// Delete a month's budget so it is planned from the current budget again
+    Delete { month: String },
+}
+
+#[derive(Subcommand)]
+pub enum BudgetSnapshotCommand {
+    /// Take a snapshot of the current budget
+    Create {
+        #[arg(long, default_value = "")]
+        comment: String,
+    },
+    /// List the saved budget snapshots
+    List,
+    /// Print the budget as it was when a snapshot was taken
+    Show {
+        /// The number of the snapshot from the list
+        number: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BudgetCategoryCommand {
+    /// Create a new category
+    Add {
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BudgetKind,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BudgetItemCommand {
+    /// Create a new item
+    Add {
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BudgetKind,
+        /// Amount each time it comes in or goes out
+        #[arg(long)]
+        value: Amount,
+        #[arg(long, default_value = "Uncategorized")]
+        category: String,
+        /// Currency of the value (defaults to the user's base currency)
+        #[arg(long)]
+        currency: Option<String>,
+        /// weekly, biweekly, semimonthly, monthly, quarterly, annual, or one-off
+        #[arg(long, default_value = "monthly")]
+        frequency: String,
+        /// Month it lands in: 1-12 for quarterly and annual items, YYYY-MM for one-off items
+        #[arg(long)]
+        month: Option<String>,
+    },
+    /// Update the name, value, frequency, or category of an existing item
+    Update {
+        name: String,
+        #[arg(long)]
+        new_name: Option<String>,
+        #[arg(long)]
+        value: Option<Amount>,
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long)]
+        currency: Option<String>,
+        #[arg(long)]
+        frequency: Option<String>,
+        #[arg(long)]
+        month: Option<String>,
+    },
+    /// Delete an existing item
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum BsCommand {
+    /// Print the current balance sheet
+    Show,
+    /// Create balance sheet categories
+    Category {
+        #[command(subcommand)]
+        command: BsCategoryCommand,
+    },
+    /// Create, update, or delete balance sheet items
+    Item {
+        #[command(subcommand)]
+        command: BsItemCommand,
+    },
+    /// Create or list snapshots
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommand,
+    },
+    /// Loan terms and amortization schedules for liabilities
+    Loan {
+        #[command(subcommand)]
+        command: LoanCommand,
+    },
+    /// Plan paying off the liabilities with the avalanche or snowball method
+    Debt {
+        #[command(subcommand)]
+        command: DebtCommand,
+    },
+    /// Forecast net worth and work out financial independence numbers
+    Forecast {
+        /// Continue the snapshot trend, or grow assets at their category's assumed return
+        #[arg(long, value_enum, default_value = "trend")]
+        method: ForecastKind,
+        #[arg(long, default_value_t = 10)]
+        years: u32,
+        /// Yearly withdrawal rate in retirement as a percentage
+        #[arg(long, default_value = "4")]
+        withdrawal_rate: String,
+        /// Net worth that counts as financially independent (defaults to yearly expenses / withdrawal rate)
+        #[arg(long)]
+        target: Option<Amount>,
+        /// Chart the snapshots and the forecast
+        #[arg(long)]
+        chart: bool,
+    },
+    /// Chart items, categories, total assets and total liabilities across the snapshots
+    ///
+    /// With nothing chosen, total assets and total liabilities are charted
+    Trend {
+        /// An item to chart (can be given more than once)
+        #[arg(long)]
+        item: Vec<String>,
+        /// A category to chart (can be given more than once)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Which half of the balance sheet the categories are in
+        #[arg(long, value_enum, default_value = "asset")]
+        kind: BsKind,
+        /// Also chart total assets and total liabilities
+        #[arg(long)]
+        totals: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ForecastKind {
+    Trend,
+    Returns,
+}
+
+#[derive(Subcommand)]
+pub enum BsCategoryCommand {
+    /// Create a new category
+    Add {
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BsKind,
+    },
+    /// Set the yearly return an asset category is assumed to earn in forecasts
+    Return {
+        name: String,
+        /// Percent a year (eg - 7)
+        #[arg(long, allow_hyphen_values = true)]
+        rate: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BsItemCommand {
+    /// Create a new item
+    Add {
+        name: String,
+        #[arg(long, value_enum)]
+        kind: BsKind,
+        /// Current value of the asset or total cost of the liability
+        #[arg(long)]
+        value: Amount,
+        #[arg(long, default_value = "Uncategorized")]
+        category: String,
+        /// Currency of the value (defaults to the user's base currency)
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// Update the name, value, or category of an existing item
+    Update {
+        name: String,
+        #[arg(long)]
+        new_name: Option<String>,
+        #[arg(long)]
+        value: Option<Amount>,
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long)]
+        currency: Option<String>,
+    },
+    /// Delete an existing item
+    Delete { name: String },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotCommand {
+    /// Take a snapshot of the current balance sheet
+    Create {
+        #[arg(long, default_value = "")]
+        comment: String,
+    },
+    /// List the saved snapshots
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum LoanCommand {
+    /// Set (or replace) the loan terms of a liability
+    Set {
+        item: String,
+        /// The amount borrowed
+        #[arg(long)]
+        principal: Amount,
+        /// Annual interest rate as a percentage (eg - 6.5)
+        #[arg(long)]
+        apr: String,
+        /// Length of the loan in months
+        #[arg(long)]
+        term: u32,
+        /// Date the loan started as YYYY-MM-DD. The first payment is a month later.
+        #[arg(long)]
+        start: String,
+        /// Monthly payment (defaults to the payment that pays it off over the term)
+        #[arg(long)]
+        payment: Option<Amount>,
+    },
+    /// Show a loan's payoff, total interest and how each snapshot compares to the schedule
+    Show {
+        item: String,
+        /// Also compare paying this much extra each month
+        #[arg(long)]
+        extra: Option<Amount>,
+    },
+    /// Print every payment in a loan's amortization schedule
+    Schedule {
+        item: String,
+        /// Extra paid on top of the regular payment each month
+        #[arg(long, default_value = "0")]
+        extra: Amount,
+    },
+    /// Print the projected balance of a loan on a date
+    Balance {
+        item: String,
+        /// YYYY-MM-DD
+        #[arg(long)]
+        date: String,
+    },
+    /// Remove the loan terms from a liability
+    Remove { item: String },
+}
+
+#[derive(Subcommand)]
+pub enum DebtCommand {
+    /// List the debts with their interest rates and minimum payments
+    List,
+    /// Set the interest rate and minimum monthly payment of a liability
+    Set {
+        item: String,
+        /// Annual interest rate as a percentage (eg - 19.99)
+        #[arg(long)]
+        apr: String,
+        /// Minimum monthly payment in the item's currency
+        #[arg(long)]
+        minimum: Amount,
+    },
+    /// Remove the interest rate and minimum payment from a liability
+    Remove { item: String },
+    /// Compare paying off the debts with the avalanche and snowball methods
+    Plan {
+        /// How much goes towards the debts each month, in the base currency
+        #[arg(long)]
+        budget: Amount,
+        /// Print the month by month balances for this method
+        #[arg(long, value_enum)]
+        months: Option<DebtMethod>,
+        /// Chart the total debt over time
+        #[arg(long)]
+        chart: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DebtMethod {
+    Avalanche,
+    Snowball,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BudgetKind {
+    Income,
+    Expense,
+}
+impl BudgetKind {
+    fn to_half(self) -> BudgetHalf {
+        match self {
+            BudgetKind::Income => BudgetHalf::Income,
+            BudgetKind::Expense => BudgetHalf::Expenses,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BsKind {
+    Asset,
+    Liability,
+}
+impl BsKind {
+    fn to_half(self) -> BalanceSheetHalf {
+        match self {
+            BsKind::Asset => BalanceSheetHalf::Assets,
+            BsKind::Liability => BalanceSheetHalf::Liabilities,
+        }
+    }
+}
+
+/// Run a single command without any prompting
+/// Errors are printed to stderr and reported through the exit code
+pub fn run(conn: &Connection, username: Option<String>, command: Command) -> ExitCode {
+    match run_command(conn, username, command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run a command that works on the database file itself rather than an open connection
+pub fn run_db(path: &Path, command: DbCommand) -> ExitCode {
+    match run_db_command(path, command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_db_command(path: &Path, command: DbCommand) -> Result<()> {
+    match command {
+        DbCommand::Encrypt => {
+            let passphrase = read_stdin_line()?;
+            encrypt_db(path, &passphrase)?;
+            println!("Encrypted {}", path.display());
+        }
+        DbCommand::Rekey => {
+            if !is_encrypted(path)? {
+                return Err(BudgetError::Validation(format!(
+                    "{} is not encrypted. Use encrypt instead.",
+                    path.display()
+                )));
+            }
+            let current = std::env::var(PASSPHRASE_ENV_VAR).unwrap_or_default();
+            let new_passphrase = read_stdin_line()?;
+            let conn = open_db(path, Some(&current))?;
+            rekey(&conn, &new_passphrase)?;
+            println!("Changed the passphrase for {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+fn run_command(conn: &Connection, username: Option<String>, command: Command) -> Result<()> {
+    match command {
+        Command::User { command } => run_user_command(conn, username, command),
+        Command::Budget { command } => {
+            let user = require_user(conn, username)?;
+            initialize_budget(conn, &user)?;
+            run_budget_command(conn, &user, command)
+        }
+        Command::Bs { command } => {
+            let user = require_user(conn, username)?;
+            initialize_balance_sheet(conn, &user)?;
+            run_bs_command(conn, &user, command)
+        }
+        Command::Goal { command } => {
+            let user = require_user(conn, username)?;
+            initialize_balance_sheet(conn, &user)?;
+            initialize_budget(conn, &user)?;
+            run_goal_command(conn, &user, command)
+        }
+        Command::Csv { command } => {
+            let user = require_user(conn, username)?;
+            run_csv_command(conn, &user, command)
+        }
+        Command::Journal { command } => {
+            let user = require_user(conn, username)?;
+            run_journal_command(conn, &user, command)
+        }
+        Command::Report {
+            file,
+            format,
+            snapshots,
+        } => {
+            let user = require_user(conn, username)?;
+            run_report_command(conn, &user, &file, format, &snapshots)
+        }
+        Command::Backup { command } => run_backup_command(conn, username, command),
+        Command::Rates { command } => {
+            let user = require_user(conn, username)?;
+            run_rates_command(conn, &user, command)
+        }
+        // Handled by run_db before any connection is opened
+        Command::Db { .. } => Ok(()),
+        Command::Ledgers => {
+            println!("Ledgers in {}", data_dir()?.display());
+            for ledger in list_ledgers()? {
+                println!("{}", ledger);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_backup_command(
+    conn: &Connection,
+    username: Option<String>,
+    command: BackupCommand,
+) -> Result<()> {
+    match command {
+        BackupCommand::Create { file } => {
+            let user = require_user(conn, username)?;
+            let backup = write_backup(conn, &user, &file)?;
+            print_backup(&backup);
+            println!("\nSaved the backup to {}", file);
+        }
+        BackupCommand::Restore {
+            file,
+            username: new_username,
+        } => {
+            let backup = read_backup(&file)?;
+            print_backup(&backup);
+            let user = restore_backup(conn, &backup, new_username.as_deref())?;
+            println!("\nRestored the backup as {}", user.username);
+        }
+    }
+    Ok(())
+}
+
+fn run_journal_command(conn: &Connection, user: &User, command: JournalCommand) -> Result<()> {
+    match command {
+        JournalCommand::Export { file, format } => {
+            let (num_snapshots, num_items) =
+                export_plain_text(conn, user, &file, format.to_format())?;
+            println!(
+                "Saved {} snapshots and {} budget items to {}",
+                num_snapshots, num_items, file
+            );
+        }
+        JournalCommand::Import { file, dry_run } => {
+            let import = preview_balance_import(conn, user, &file)?;
+            print_balance_import(&import);
+            if !dry_run {
+                let num_snapshots = apply_balance_import(conn, user, &import)?;
+                println!("\nTook {} snapshots", num_snapshots);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_report_command(
+    conn: &Connection,
+    user: &User,
+    file: &str,
+    format: ReportKind,
+    numbers: &[usize],
+) -> Result<()> {
+    let saved = list_snapshots(conn, user)?;
+    let snapshots: Vec<Snapshot> = match numbers.is_empty() {
+        true => saved[saved.len().saturating_sub(REPORT_SNAPSHOTS)..].to_vec(),
+        false => {
+            let mut snapshots: Vec<Snapshot> = vec![];
+            for number in numbers {
+                let snapshot = number
+                    .checked_sub(1)
+                    .and_then(|idx| saved.get(idx))
+                    .ok_or_else(|| {
+                        BudgetError::NotFound(format!("There is no snapshot number {}", number))
+                    })?;
+                snapshots.push(snapshot.clone());
+            }
+            snapshots
+        }
+    };
+    let format = match format {
+        ReportKind::Html => ReportFormat::Html,
+        ReportKind::Markdown => ReportFormat::Markdown,
+    };
+    let report = write_report(conn, user, file, format, &snapshots)?;
+    println!(
+        "Saved the {} report with {} snapshots side by side to {}",
+        format.to_str(),
+        report.snapshot_dates.len(),
+        file
+    );
+    Ok(())
+}
+
+fn run_csv_command(conn: &Connection, user: &User, command: CsvCommand) -> Result<()> {
+    match command {
+        CsvCommand::Export { what, file } => {
+            let num_rows = match what {
+                CsvExport::BalanceSheet => export_balance_sheet_csv(conn, user, &file)?,
+                CsvExport::Budget => export_budget_csv(conn, user, &file)?,
+                CsvExport::Snapshots => export_snapshots_csv(conn, user, &file)?,
+                CsvExport::History => export_item_history_csv(conn, user, &file)?,
+            };
+            println!("Saved {} rows to {}", num_rows, file);
+        }
+        CsvCommand::Import { file, dry_run } => {
+            let import = preview_csv_import(conn, user, &file)?;
+            print_csv_import(&import);
+            if !dry_run {
+                let num_items = apply_csv_import(conn, user, &import)?;
+                println!("\nImported {} items", num_items);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Find the user given with --user or fail
+/// Users with a password must also give it in the BURKEBUDGET_PASSWORD environment variable
+/// Deleted users are refused
+fn require_user(conn: &Connection, username: Option<String>) -> Result<User> {
+    let user = require_any_user(conn, username)?;
+    if user.is_deleted {
+        return Err(BudgetError::NotFound(format!(
+            "The user {} has been deleted. Use \"user restore\" to bring it back.",
+            user.username
+        )));
+    }
+    Ok(user)
+}
+
+/// Like require_user, but deleted users are allowed so they can be restored or purged
+fn require_any_user(conn: &Connection, username: Option<String>) -> Result<User> {
+    let username = username.ok_or_else(|| {
+        BudgetError::Validation(String::from(
+            "This command requires a user. Pass it with --user <USER>",
+        ))
+    })?;
+    let user = get_user(conn, &username)?;
+    authenticate(conn, &user, &env_password())?;
+    Ok(user)
+}
+
+/// The password given in BURKEBUDGET_PASSWORD, or an empty string
+fn env_password() -> String {
+    std::env::var(PASSWORD_ENV_VAR).unwrap_or_default()
+}
+
+/// Read a single line from stdin (eg - a new password piped in)
+fn read_stdin_line() -> Result<String> {
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn run_user_command(
+    conn: &Connection,
+    username: Option<String>,
+    command: UserCommand,
+) -> Result<()> {
+    match command {
+        UserCommand::List => {
+            for user in list_users(conn)?.iter().filter(|u| !u.is_deleted) {
+                println!("{} ({})", user.username, user.fullname());
+            }
+        }
+        UserCommand::Add {
+            username,
+            first,
+            last,
+        } => {
+            let user = create_user(conn, &username, &first, &last)?;
+            println!("Created user {}", user.username);
+        }
+        UserCommand::SetPassword => {
+            let user = require_user(conn, username)?;
+            let new_password = read_stdin_line()?;
+            change_password(conn, &user, &env_password(), Some(&new_password))?;
+            println!("Saved the password for {}", user.username);
+        }
+        UserCommand::RemovePassword => {
+            let user = require_user(conn, username)?;
+            change_password(conn, &user, &env_password(), None)?;
+            println!("Removed the password for {}", user.username);
+        }
+        UserCommand::SetCurrency { currency } => {
+            let user = require_user(conn, username)?;
+            let user = set_base_currency(conn, &user, &currency)?;
+            println!(
+                "Totals for {} are now in {}",
+                user.username, user.base_currency
+            );
+        }
+        UserCommand::Edit { first, last } => {
+            let user = require_user(conn, username)?;
+            let firstname = first.unwrap_or_else(|| user.firstname.clone());
+            let lastname = last.unwrap_or_else(|| user.lastname.clone());
+            let user = update_name(conn, &user, &firstname, &lastname)?;
+            println!("{} is now {}", user.username, user.fullname());
+        }
+        UserCommand::Rename { new_username } => {
+            let user = require_user(conn, username)?;
+            let old_username = user.username.clone();
+            let user = rename_user(conn, &user, &new_username)?;
+            println!("Renamed {} to {}", old_username, user.username);
+        }
+        UserCommand::Delete { purge } => {
+            if purge {
+                let user = require_any_user(conn, username)?;
+                purge_user(conn, &user)?;
+                println!("Permanently deleted {}", user.username);
+            } else {
+                let user = require_user(conn, username)?;
+                delete_user(conn, &user)?;
+                println!(
+                    "Deleted {}. It can be brought back with \"user restore\".",
+                    user.username
+                );
+            }
+        }
+        UserCommand::Restore => {
+            let user = require_any_user(conn, username)?;
+            let user = restore_user(conn, &user)?;
+            println!("Restored {}", user.username);
+        }
+    }
+    Ok(())
+}
+
+fn run_rates_command(conn: &Connection, user: &User, command: RatesCommand) -> Result<()> {
+    match command {
+        RatesCommand::List => {
+            for rate in list_exchange_rates(conn, user)? {
+                println!(
+                    "1 {} = {} {}  (set {})",
+                    rate.from_currency, rate.rate, rate.to_currency, rate.date_text
+                );
+            }
+        }
+        RatesCommand::Set { from, to, rate } => {
+            let rate = set_exchange_rate(conn, user, &from, &to, &rate)?;
+            println!(
+                "1 {} = {} {}",
+                rate.from_currency, rate.rate, rate.to_currency
+            );
+        }
+        RatesCommand::Import { file } => {
+            let num_rates = import_exchange_rates(conn, user, &file)?;
+            println!("Imported {} exchange rates", num_rates);
+        }
+    }
+    Ok(())
+}
+
+fn run_budget_command(conn: &Connection, user: &User, command: BudgetCommand) -> Result<()> {
+    match command {
+        BudgetCommand::Show => {
+            let (income_categories, income_items) =
+                get_relevant_items(conn, user, &BudgetHalf::Income)?;
+            let (expense_categories, expense_items) =
+                get_relevant_items(conn, user, &BudgetHalf::Expenses)?;
+            print_budget(
+                &current_converter(conn, user)?,
+                &income_categories,
+                &income_items,
+                &expense_categories,
+                &expense_items,
+            )?;
+        }
+        BudgetCommand::Category {
+            command: BudgetCategoryCommand::Add { name, kind },
+        } => {
+            let category = budget::create_category(conn, user, &kind.to_half(), &name)?;
+            println!("Created category {}", category.category);
+        }
+        BudgetCommand::Item { command } => match command {
+            BudgetItemCommand::Add {
+                name,
+                kind,
+                value,
+                category,
+                currency,
+                frequency,
+                month,
+            } => {
+                let item = budget::create_item(
+                    conn,
+                    user,
+                    &kind.to_half(),
+                    &name,
+                    value,
+                    currency.as_deref().unwrap_or(&user.base_currency),
+                    Frequency::from_parts(&frequency, month.as_deref())?,
+                    &category,
+                )?;
+                println!("Created item {}", item.item);
+            }
+            BudgetItemCommand::Update {
+                name,
+                new_name,
+                value,
+                category,
+                currency,
+                frequency,
+                month,
+            } => {
+                let item_chosen = budget::find_item(conn, user, &name)?;
+                let frequency = match frequency {
+                    Some(frequency) => Frequency::from_parts(&frequency, month.as_deref())?,
+                    None => item_chosen.frequency,
+                };
+                let item = budget::update_item(
+                    conn,
+                    user,
+                    &item_chosen,
+                    new_name.as_deref().unwrap_or(&item_chosen.item),
+                    value.unwrap_or(item_chosen.value),
+                    currency.as_deref().unwrap_or(&item_chosen.currency),
+                    frequency,
+                    category.as_deref().unwrap_or(&item_chosen.category),
+                )?;
+                println!("Updated item {}", item.item);
+            }
+            BudgetItemCommand::Delete { name } => {
+                let item = budget::find_item(conn, user, &name)?;
+                budget::delete_item(conn, user, &item)?;
+                println!("Deleted item {}", item.item);
+            }
+        },
+        BudgetCommand::Snapshot { command } => match command {
+            BudgetSnapshotCommand::Create { comment } => {
+                let snapshot = create_budget_snapshot(conn, user, &comment)?;
+                println!(
+                    "Created budget snapshot {}:  Monthly Net  {}",
+                    snapshot.date_today,
+                    to_money_string(snapshot.monthly_net, &snapshot.currency)
+                );
+            }
+            BudgetSnapshotCommand::List => {
+                for (idx, snapshot) in list_budget_snapshots(conn, user)?.iter().enumerate() {
+                    print!(
+                        "{}.  {}:  Monthly Net  {}",
+                        idx + 1,
+                        snapshot.date_today,
+                        to_money_string(snapshot.monthly_net, &snapshot.currency)
+                    );
+                    if snapshot.comment.is_empty() {
+                        println!();
+                    } else {
+                        println!("  \"{}\"", snapshot.comment);
+                    }
+                }
+            }
+            BudgetSnapshotCommand::Show { number } => {
+                let snapshots = list_budget_snapshots(conn, user)?;
+                let snapshot = number
+                    .checked_sub(1)
+                    .and_then(|idx| snapshots.get(idx))
+                    .ok_or_else(|| {
+                        BudgetError::NotFound(format!(
+                            "There is no budget snapshot number {}",
+                            number
+                        ))
+                    })?;
+                let (income_categories, income_items) =
+                    budget_items_at(conn, user, &BudgetHalf::Income, snapshot.timeline)?;
+                let (expense_categories, expense_items) =
+                    budget_items_at(conn, user, &BudgetHalf::Expenses, snapshot.timeline)?;
+                let converter =
+                    budget_snapshot_converter(conn, user, snapshot, &snapshot.currency)?;
+                println!(
+                    "SNAPSHOT of Monthly Budget - {}  (Totals in {})",
+                    snapshot.date_today,
+                    converter.base()
+                );
+                print_budget_body(
+                    &converter,
+                    &income_categories,
+                    &income_items,
+                    &expense_categories,
+                    &expense_items,
+                )?;
+            }
+        },
+        BudgetCommand::Tx { command } => match command {
+            TxCommand::Add {
+                amount,
+                item,
+                category,
+                kind,
+                date,
+                description,
+                currency,
+            } => {
+                let target = match (item, category, kind) {
+                    (Some(item), None, _) => {
+                        TransactionTarget::Item(budget::find_item(conn, user, &item)?)
+                    }
+                    (None, Some(category), Some(kind)) => TransactionTarget::Category(
+                        budget::find_category(conn, user, &kind.to_half(), &category)?,
+                    ),
+                    _ => {
+                        return Err(BudgetError::Validation(String::from(
+                            "Give either --item, or --category with --kind",
+                        )))
+                    }
+                };
+                let today = Local::now().format("%Y-%m-%d").to_string();
+                let transaction = add_transaction(
+                    conn,
+                    user,
+                    &target,
+                    date.as_deref().unwrap_or(&today),
+                    &description,
+                    amount,
+                    currency.as_deref().unwrap_or(&user.base_currency),
+                )?;
+                println!(
+                    "Recorded transaction {} of {} on {}",
+                    transaction.id,
+                    to_money_string(transaction.amount, &transaction.currency),
+                    transaction.date_text
+                );
+            }
+            TxCommand::List { month } => {
+                let month = month.as_deref().map(parse_month).transpose()?;
+                let (categories, items) = transaction_targets(conn, user)?;
+                for transaction in list_transactions(conn, user, month.as_deref())? {
+                    print!(
+                        "{}.  {}  {} {}  ({})",
+                        transaction.id,
+                        transaction.date_text,
+                        if transaction.is_income { "+" } else { "-" },
+                        to_money_string(transaction.amount, &transaction.currency),
+                        target_name(&transaction, &categories, &items)
+                    );
+                    if transaction.description.is_empty() {
+                        println!();
+                    } else {
+                        println!("  \"{}\"", transaction.description);
+                    }
+                }
+            }
+            TxCommand::Delete { id } => {
+                delete_transaction(conn, user, id)?;
+                println!("Deleted transaction {}", id);
+            }
+            TxCommand::Import {
+                file,
+                item,
+                dry_run,
+            } => {
+                let item = item
+                    .map(|item| balance_sheet::find_item(conn, user, &item))
+                    .transpose()?;
+                let import = preview_statement_import(conn, user, &file, item.as_ref())?;
+                print_statement_import(&import);
+                if !dry_run {
+                    let num_imported = apply_statement_import(conn, user, &import)?;
+                    println!("\nImported {} transactions", num_imported);
+                }
+            }
+        },
+        BudgetCommand::Rule { command } => match command {
+            RuleCommand::List => {
+                for rule in list_payee_rules(conn, user)? {
+                    println!("{}", describe_payee_rule(&rule));
+                }
+            }
+            RuleCommand::Add {
+                payee,
+                item,
+                category,
+                kind,
+            } => {
+                let target = match (item, category, kind) {
+                    (Some(item), None, _) => {
+                        TransactionTarget::Item(budget::find_item(conn, user, &item)?)
+                    }
+                    (None, Some(category), Some(kind)) => TransactionTarget::Category(
+                        budget::find_category(conn, user, &kind.to_half(), &category)?,
+                    ),
+                    _ => {
+                        return Err(BudgetError::Validation(String::from(
+                            "Give either --item, or --category with --kind",
+                        )))
+                    }
+                };
+                let rule = set_payee_rule(conn, user, &payee, &target)?;
+                println!("Added the payee rule {}", describe_payee_rule(&rule));
+            }
+            RuleCommand::Remove { payee, kind } => {
+                let is_income = kind.map(|kind| kind.to_half().to_bool());
+                delete_payee_rule(conn, user, &payee, is_income)?;
+                println!("Removed the payee rule for {}", payee);
+            }
+        },
+        BudgetCommand::Month { command } => run_budget_month_command(conn, user, command)?,
+        BudgetCommand::Calendar { months } => {
+            print_budget_calendar(&budget_calendar(
+                conn,
+                user,
+                Local::now().date_naive(),
+                months,
+            )?);
+        }
+        BudgetCommand::Report { month } => {
+            let month = month.unwrap_or_else(current_month);
+            print_budget_vs_actual(&budget_vs_actual(conn, user, &month)?);
+        }
+    }
+    Ok(())
+}
+
+fn run_budget_month_command(
+    conn: &Connection,
+    user: &User,
+    command: BudgetMonthCommand,
+) -> Result<()> {
+    match command {
+        BudgetMonthCommand::List => {
+            for month in list_budget_months(conn, user)? {
+                println!("{}", month);
+            }
+        }
+        BudgetMonthCommand::Start { month, from } => {
+            match from {
+                Some(from) => copy_budget_month(conn, user, &from, &month)?,
+                None => start_budget_month(conn, user, &month)?,
+            }
+            println!("Started the budget for {}", parse_month(&month)?);
+        }
+        BudgetMonthCommand::Set {
+            month,
+            name,
+            kind,
+            value,
+            currency,
+            category,
+        } => {
+            let (categories, items) =
+                get_month_items(conn, user, &parse_month(&month)?, &kind.to_half())?;
+            let existing = items.iter().find(|i| i.item_lower == name.to_lowercase());
+            let existing_category = existing.and_then(|item| {
+                categories
+                    .iter()
+                    .find(|c| c.category_lower == item.category_lower)
+                    .map(|c| c.category.as_str())
+            });
+            let item = set_month_item(
+                conn,
+                user,
+                &month,
+                &kind.to_half(),
+                &name,
+                value,
+                currency
+                    .as_deref()
+                    .or(existing.map(|item| item.currency.as_str()))
+                    .unwrap_or(&user.base_currency),
+                category
+                    .as_deref()
+                    .or(existing_category)
+                    .unwrap_or("Uncategorized"),
+            )?;
+            println!(
+                "Planned {} for {} in {}",
+                to_money_string(item.value, &item.currency),
+                item.item,
+                item.month
+            );
+        }
+        BudgetMonthCommand::Remove { month, name, kind } => {
+            let month = parse_month(&month)?;
+            let (_, items) = get_month_items(conn, user, &month, &kind.to_half())?;
+            let item = items
+                .iter()
+                .find(|i| i.item_lower == name.to_lowercase())
+                .ok_or_else(|| {
+                    BudgetError::NotFound(format!(
+                        "{} was not found in the budget for {}",
+                        name, month
+                    ))
+                })?;
+            delete_month_item(conn, user, item)?;
+            println!("Removed {} from {}", item.item, month);
+        }
+        BudgetMonthCommand::Rollover {
+            month,
+            category,
+            off,
+        } => {
+            let category = set_rollover(conn, user, &month, &category, !off)?;
+            println!(
+                "{} {} roll over at the end of {}",
+                category.category,
+                if off { "will not" } else { "will" },
+                parse_month(&month)?
+            );
+        }
+        BudgetMonthCommand::Delete { month } => {
+            delete_budget_month(conn, user, &month)?;
+            println!("Deleted the budget for {}", parse_month(&month)?);
+        }
+    }
+    Ok(())
+}
+
+fn run_goal_command(conn: &Connection, user: &User, command: GoalCommand) -> Result<()> {
+    let today = Local::now().date_naive();
+    match command {
+        GoalCommand::List => {
+            for goal in list_goals(conn, user)? {
+                let progress = goal_progress(conn, user, &goal, today)?;
+                println!("{}", goal_summary(&goal, &progress));
+            }
+        }
+        GoalCommand::Add {
+            name,
+            target,
+            payoff,
+            by,
+            currency,
+            items,
+            asset_categories,
+            liability_categories,
+            budget_item,
+        } => {
+            let mut links: Vec<GoalLink> = vec![];
+            for item_name in items {
+                let item = balance_sheet::find_item(conn, user, &item_name)?;
+                links.push(GoalLink::Item {
+                    is_asset: item.is_asset,
+                    item_original: item.timeline_original,
+                });
+            }
+            for (which_half, names) in [
+                (BalanceSheetHalf::Assets, asset_categories),
+                (BalanceSheetHalf::Liabilities, liability_categories),
+            ] {
+                for cat_name in names {
+                    let category =
+                        balance_sheet::find_category(conn, user, &which_half, &cat_name)?;
+                    links.push(GoalLink::Category {
+                        is_asset: category.is_asset,
+                        category_lower: category.category_lower,
+                    });
+                }
+            }
+            let budget_item = match budget_item {
+                Some(item_name) => Some(budget::find_item(conn, user, &item_name)?),
+                None => None,
+            };
+            let goal = create_goal(
+                conn,
+                user,
+                &name,
+                if payoff {
+                    GoalKind::PayOff
+                } else {
+                    GoalKind::Save
+                },
+                target,
+                currency.as_deref().unwrap_or(&user.base_currency),
+                by.as_deref(),
+                &links,
+                budget_item.as_ref(),
+            )?;
+            println!("Created goal {}", goal.name);
+        }
+        GoalCommand::Show { name } => {
+            let goal = find_goal(conn, user, &name)?;
+            let progress = goal_progress(conn, user, &goal, today)?;
+            print_goal(conn, user, &goal, &progress)?;
+        }
+        GoalCommand::Contribution { name, budget_item } => {
+            let goal = find_goal(conn, user, &name)?;
+            let budget_item = match budget_item {
+                Some(item_name) => Some(budget::find_item(conn, user, &item_name)?),
+                None => None,
+            };
+            set_goal_budget_item(conn, user, &goal, budget_item.as_ref())?;
+            match budget_item {
+                Some(item) => println!("{} now pays into {}", item.item, goal.name),
+                None => println!("{} no longer has a budget item paying into it", goal.name),
+            }
+        }
+        GoalCommand::Delete { name } => {
+            let goal = find_goal(conn, user, &name)?;
+            delete_goal(conn, user, &goal)?;
+            println!("Deleted goal {}", goal.name);
+        }
+    }
+    Ok(())
+}
+
+fn run_bs_command(conn: &Connection, user: &User, command: BsCommand) -> Result<()> {
+    match command {
+        BsCommand::Show => {
+            let (asset_categories, asset_items) =
+                get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+            let (liability_categories, liability_items) =
+                get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+            print_balance_sheet(
+                &current_converter(conn, user)?,
+                &asset_categories,
+                &asset_items,
+                &liability_categories,
+                &liability_items,
+            )?;
+        }
+        BsCommand::Category { command } => match command {
+            BsCategoryCommand::Add { name, kind } => {
+                let category = balance_sheet::create_category(conn, user, &kind.to_half(), &name)?;
+                println!("Created category {}", category.category);
+            }
+            BsCategoryCommand::Return { name, rate } => {
+                let rate = parse_return_rate(&rate)?;
+                let category = set_category_return(conn, user, &name, rate)?;
+                println!(
+                    "{} is assumed to return {}% a year",
+                    category.category, rate
+                );
+            }
+        },
+        BsCommand::Item { command } => match command {
+            BsItemCommand::Add {
+                name,
+                kind,
+                value,
+                category,
+                currency,
+            } => {
+                let item = balance_sheet::create_item(
+                    conn,
+                    user,
+                    &kind.to_half(),
+                    &name,
+                    value,
+                    currency.as_deref().unwrap_or(&user.base_currency),
+                    &category,
+                )?;
+                println!("Created item {}", item.item);
+            }
+            BsItemCommand::Update {
+                name,
+                new_name,
+                value,
+                category,
+                currency,
+            } => {
+                let item_chosen = balance_sheet::find_item(conn, user, &name)?;
+                let item = balance_sheet::update_item(
+                    conn,
+                    user,
+                    &item_chosen,
+                    new_name.as_deref().unwrap_or(&item_chosen.item),
+                    value.unwrap_or(item_chosen.value),
+                    currency.as_deref().unwrap_or(&item_chosen.currency),
+                    category.as_deref().unwrap_or(&item_chosen.category),
+                )?;
+                println!("Updated item {}", item.item);
+            }
+            BsItemCommand::Delete { name } => {
+                let item = balance_sheet::find_item(conn, user, &name)?;
+                balance_sheet::delete_item(conn, user, &item)?;
+                println!("Deleted item {}", item.item);
+            }
+        },
+        BsCommand::Snapshot { command } => match command {
+            SnapshotCommand::Create { comment } => {
+                let snapshot = create_snapshot(conn, user, &comment)?;
+                println!(
+                    "Created snapshot {}:  Net Worth  {}",
+                    snapshot.date_today,
+                    to_money_string(snapshot.net_worth, &snapshot.currency)
+                );
+            }
+            SnapshotCommand::List => {
+                for (idx, snapshot) in list_snapshots(conn, user)?.iter().enumerate() {
+                    print!(
+                        "{}.  {}:  Net Worth  {}",
+                        idx + 1,
+                        snapshot.date_today,
+                        to_money_string(snapshot.net_worth, &snapshot.currency)
+                    );
+                    if snapshot.comment.is_empty() {
+                        println!();
+                    } else {
+                        println!("  \"{}\"", snapshot.comment);
+                    }
+                }
+            }
+        },
+        BsCommand::Loan { command } => run_loan_command(conn, user, command)?,
+        BsCommand::Debt { command } => run_debt_command(conn, user, command)?,
+        BsCommand::Forecast {
+            method,
+            years,
+            withdrawal_rate,
+            target,
+            chart,
+        } => {
+            let method = match method {
+                ForecastKind::Trend => ForecastMethod::Trend,
+                ForecastKind::Returns => ForecastMethod::Returns,
+            };
+            let forecast =
+                forecast_net_worth(conn, user, method, years, Local::now().date_naive())?;
+            let metrics = fire_metrics(
+                conn,
+                user,
+                &forecast,
+                parse_withdrawal_rate(&withdrawal_rate)?,
+                target,
+            )?;
+            if chart {
+                print_forecast_chart(
+                    &net_worth_history(conn, user)?,
+                    &forecast,
+                    &user.base_currency,
+                );
+            }
+            if method == ForecastMethod::Returns {
+                print_category_returns(&get_category_returns(conn, user)?);
+            }
+            print_fire_metrics(&forecast, &metrics, &user.base_currency);
+        }
+        BsCommand::Trend {
+            item,
+            category,
+            kind,
+            totals,
+        } => {
+            let mut lines: Vec<(String, TrendLine)> = vec![];
+            for name in &item {
+                let item = balance_sheet::find_item(conn, user, name)?;
+                lines.push((
+                    item.item,
+                    TrendLine::Item {
+                        is_asset: item.is_asset,
+                        item_original: item.timeline_original,
+                    },
+                ));
+            }
+            for name in &category {
+                let category = balance_sheet::find_category(conn, user, &kind.to_half(), name)?;
+                lines.push((
+                    category.category,
+                    TrendLine::Category {
+                        is_asset: category.is_asset,
+                        category_lower: category.category_lower,
+                    },
+                ));
+            }
+            if totals || lines.is_empty() {
+                lines.push((
+                    String::from("Total Assets"),
+                    TrendLine::Total { is_asset: true },
+                ));
+                lines.push((
+                    String::from("Total Liabilities"),
+                    TrendLine::Total { is_asset: false },
+                ));
+            }
+            if lines.len() > 5 {
+                return Err(BudgetError::Validation(String::from(
+                    "At most 5 lines can be charted at once",
+                )));
+            }
+            let mut trends: Vec<(String, Vec<(NaiveDate, Amount)>)> = vec![];
+            for (name, line) in lines {
+                trends.push((name, snapshot_trend(conn, user, &line)?));
+            }
+            if list_snapshots(conn, user)?.len() >= 2 {
+                print_trend_chart(&trends, &user.base_currency);
+            }
+            print_trend_table(&trends, &user.base_currency);
+        }
+    }
+    Ok(())
+}
+
+fn run_debt_command(conn: &Connection, user: &User, command: DebtCommand) -> Result<()> {
+    match command {
+        DebtCommand::List => {
+            let (debts, missing) = get_debts(conn, user)?;
+            print_debts(&debts, &missing, &user.base_currency);
+        }
+        DebtCommand::Set { item, apr, minimum } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            set_debt_terms(conn, user, &item, parse_apr(&apr)?, minimum)?;
+            println!(
+                "{} is at {}% with a minimum payment of {}",
+                item.item,
+                apr.trim().trim_end_matches('%'),
+                to_money_string(minimum, &item.currency)
+            );
+        }
+        DebtCommand::Remove { item } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            delete_debt_terms(conn, user, &item)?;
+            println!(
+                "Removed the interest rate and minimum payment from {}",
+                item.item
+            );
+        }
+        DebtCommand::Plan {
+            budget,
+            months,
+            chart,
+        } => {
+            let (debts, _) = get_debts(conn, user)?;
+            let currency = &user.base_currency;
+            let start = Local::now().date_naive();
+            let avalanche = plan_debts(&debts, budget, currency, DebtStrategy::Avalanche, start)?;
+            let snowball = plan_debts(&debts, budget, currency, DebtStrategy::Snowball, start)?;
+            let minimums =
+                plan_debts(&debts, budget, currency, DebtStrategy::MinimumsOnly, start).ok();
+            print_plan_comparison(&avalanche, &snowball, minimums.as_ref(), currency);
+            match months {
+                Some(DebtMethod::Avalanche) => print_plan_months(&avalanche, currency),
+                Some(DebtMethod::Snowball) => print_plan_months(&snowball, currency),
+                None => {}
+            }
+            if chart {
+                print_debt_chart(&avalanche, &snowball, currency);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_loan_command(conn: &Connection, user: &User, command: LoanCommand) -> Result<()> {
+    match command {
+        LoanCommand::Set {
+            item,
+            principal,
+            apr,
+            term,
+            start,
+            payment,
+        } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            let loan = set_loan_terms(
+                conn,
+                user,
+                &item,
+                principal,
+                parse_apr(&apr)?,
+                term,
+                payment,
+                &start,
+            )?;
+            println!(
+                "{} now has loan terms of {} a month",
+                item.item,
+                to_money_string(loan.payment, &loan.currency)
+            );
+        }
+        LoanCommand::Show { item, extra } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            let loan = require_loan(conn, user, &item)?;
+            print_loan(conn, user, &item, &loan)?;
+            if let Some(extra) = extra {
+                print_what_if(&loan, extra)?;
+            }
+        }
+        LoanCommand::Schedule { item, extra } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            print_schedule(&require_loan(conn, user, &item)?, extra)?;
+        }
+        LoanCommand::Balance { item, date } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            let loan = require_loan(conn, user, &item)?;
+            let date = parse_date(&date)?;
+            println!(
+                "{} on {}: {}",
+                item.item,
+                date,
+                to_money_string(
+                    projected_balance(&loan, date, Amount::ZERO)?,
+                    &loan.currency
+                )
+            );
+        }
+        LoanCommand::Remove { item } => {
+            let item = balance_sheet::find_item(conn, user, &item)?;
+            delete_loan_terms(conn, user, &item)?;
+            println!("Removed the loan terms from {}", item.item);
+        }
+    }
+    Ok(())
+}
+
+/// Get a liability's loan terms, failing if it doesn't have any
+fn require_loan(conn: &Connection, user: &User, item: &Item) -> Result<Loan> {
+    get_loan(conn, user, item)?.ok_or_else(|| {
+        BudgetError::NotFound(format!(
+            "{} doesn't have loan terms. Add them with bs loan set.",
+            item.item
+        ))
+    })
+}
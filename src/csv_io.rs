@@ -0,0 +1,421 @@
+use crate::balance_sheet::{self, get_relevant_items_cats, initialize_balance_sheet};
+use crate::budget::{self, get_relevant_items, initialize_budget};
+use crate::currency::normalize_currency;
+use crate::error::{BudgetError, Result};
+use crate::snapshots::{list_snapshots, snapshot_items_at};
+use crate::structs_utils::*;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// The columns of an items CSV file, both exported and imported
+/// The frequency is only used by budget items, and is blank for balance sheet items
+pub const ITEM_COLUMNS: [&str; 6] = ["kind", "category", "item", "value", "currency", "frequency"];
+
+/// The timeline_deleted of a current item version
+const NOT_DELETED: usize = usize::MAX / 4;
+
+/// Turn a CSV error into a file error, or a parse error naming the file if the CSV itself is bad
+fn csv_error(path: &str, error: csv::Error) -> BudgetError {
+    if error.is_io_error() {
+        BudgetError::Io(error.into())
+    } else {
+        BudgetError::Parse(format!("The file {} could not be read: {}", path, error))
+    }
+}
+
+/// Write a header and rows to a CSV file, replacing it if it exists, and return the number of rows
+fn write_csv(path: &str, header: &[&str], rows: &[Vec<String>]) -> Result<usize> {
+    let mut writer = csv::Writer::from_path(path).map_err(|error| csv_error(path, error))?;
+    writer
+        .write_record(header)
+        .map_err(|error| csv_error(path, error))?;
+    for row in rows {
+        writer
+            .write_record(row)
+            .map_err(|error| csv_error(path, error))?;
+    }
+    writer.flush()?;
+    Ok(rows.len())
+}
+
+/// The kind column of a balance sheet item
+fn item_kind(item: &Item) -> CsvItemKind {
+    match item.is_asset {
+        true => CsvItemKind::Asset,
+        false => CsvItemKind::Liability,
+    }
+}
+
+/// The kind column of a budget item
+fn budget_item_kind(item: &BudgetItem) -> CsvItemKind {
+    match item.is_income {
+        true => CsvItemKind::Income,
+        false => CsvItemKind::Expense,
+    }
+}
+
+/// A row of an items CSV file for a balance sheet item
+fn item_record(item: &Item) -> Vec<String> {
+    vec![
+        String::from(item_kind(item).to_str()),
+        item.category.clone(),
+        item.item.clone(),
+        item.value.to_string(),
+        item.currency.clone(),
+        String::new(),
+    ]
+}
+
+/// A row of an items CSV file for a budget item
+fn budget_item_record(item: &BudgetItem) -> Vec<String> {
+    vec![
+        String::from(budget_item_kind(item).to_str()),
+        item.category.clone(),
+        item.item.clone(),
+        item.value.to_string(),
+        item.currency.clone(),
+        item.frequency.to_string(),
+    ]
+}
+
+/// Export the current balance sheet items to a CSV file that can be imported again
+pub fn export_balance_sheet_csv(conn: &Connection, user: &User, path: &str) -> Result<usize> {
+    let mut rows: Vec<Vec<String>> = vec![];
+    for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+        let (_, mut items) = get_relevant_items_cats(conn, user, &which_half)?;
+        items.sort_by(|a, b| {
+            (&a.category_lower, &a.item_lower).cmp(&(&b.category_lower, &b.item_lower))
+        });
+        rows.extend(items.iter().map(item_record));
+    }
+    write_csv(path, &ITEM_COLUMNS, &rows)
+}
+
+/// Export the current budget items to a CSV file that can be imported again
+pub fn export_budget_csv(conn: &Connection, user: &User, path: &str) -> Result<usize> {
+    let mut rows: Vec<Vec<String>> = vec![];
+    for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+        let (_, mut items) = get_relevant_items(conn, user, &which_half)?;
+        items.sort_by(|a, b| {
+            (&a.category_lower, &a.item_lower).cmp(&(&b.category_lower, &b.item_lower))
+        });
+        rows.extend(items.iter().map(budget_item_record));
+    }
+    write_csv(path, &ITEM_COLUMNS, &rows)
+}
+
+/// Export every item of every saved snapshot to a CSV file, one row per item
+/// Each snapshot's balance sheet is reconstructed from the item versions at its timeline
+pub fn export_snapshots_csv(conn: &Connection, user: &User, path: &str) -> Result<usize> {
+    let header = [
+        "snapshot", "date", "comment", "kind", "category", "item", "value", "currency",
+    ];
+    let mut rows: Vec<Vec<String>> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+            let (_, mut items) = snapshot_items_at(conn, user, &which_half, snapshot.timeline)?;
+            items.sort_by(|a, b| {
+                (&a.category_lower, &a.item_lower).cmp(&(&b.category_lower, &b.item_lower))
+            });
+            for item in &items {
+                rows.push(vec![
+                    snapshot.timeline.to_string(),
+                    snapshot.date_today.clone(),
+                    snapshot.comment.clone(),
+                    String::from(item_kind(item).to_str()),
+                    item.category.clone(),
+                    item.item.clone(),
+                    item.value.to_string(),
+                    item.currency.clone(),
+                ]);
+            }
+        }
+    }
+    write_csv(path, &header, &rows)
+}
+
+/// Export every version of every balance sheet and budget item, including deleted ones, to a CSV file
+/// Versions of the same item share an original, and are ordered by when they were created
+/// Balance sheet and budget items each have their own timeline, and deleted is blank for current versions
+pub fn export_item_history_csv(conn: &Connection, user: &User, path: &str) -> Result<usize> {
+    let header = [
+        "kind",
+        "original",
+        "created",
+        "deleted",
+        "category",
+        "item",
+        "value",
+        "currency",
+        "frequency",
+    ];
+    let deleted = |timeline_deleted: usize| match timeline_deleted {
+        NOT_DELETED => String::new(),
+        timeline => timeline.to_string(),
+    };
+    let mut rows: Vec<Vec<String>> = vec![];
+    for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+        let mut items = balance_sheet::get_all_items(conn, user, &which_half)?;
+        items.sort_by_key(|item| (item.timeline_original, item.timeline_created));
+        for item in &items {
+            let mut row = vec![
+                String::from(item_kind(item).to_str()),
+                item.timeline_original.to_string(),
+                item.timeline_created.to_string(),
+                deleted(item.timeline_deleted),
+            ];
+            row.extend(item_record(item).into_iter().skip(1));
+            rows.push(row);
+        }
+    }
+    for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+        let mut items = budget::get_all_items(conn, user, &which_half)?;
+        items.sort_by_key(|item| (item.timeline_original, item.timeline_created));
+        for item in &items {
+            let mut row = vec![
+                String::from(budget_item_kind(item).to_str()),
+                item.timeline_original.to_string(),
+                item.timeline_created.to_string(),
+                deleted(item.timeline_deleted),
+            ];
+            row.extend(budget_item_record(item).into_iter().skip(1));
+            rows.push(row);
+        }
+    }
+    write_csv(path, &header, &rows)
+}
+
+/// Check one row of an items CSV file
+/// The category is left as given, and a blank currency is the user's base currency
+fn parse_item_row(
+    user: &User,
+    columns: &HashMap<String, usize>,
+    record: &csv::StringRecord,
+    line: usize,
+) -> Result<CsvItemRow> {
+    let field = |name: &str| {
+        columns
+            .get(name)
+            .and_then(|idx| record.get(*idx))
+            .unwrap_or("")
+            .trim()
+    };
+    let kind: CsvItemKind = field("kind").parse()?;
+    let item = field("item");
+    validate_item_name(item)?;
+    let value: Amount = field("value").parse()?;
+    validate_item_value(value)?;
+    let currency = match field("currency") {
+        "" => user.base_currency.clone(),
+        currency => normalize_currency(currency)?,
+    };
+    let category = match field("category") {
+        "" => "Uncategorized",
+        category => category,
+    };
+    let frequency = match (kind.is_budget(), field("frequency")) {
+        (true, "") => Some(Frequency::Monthly),
+        (true, frequency) => Some(frequency.parse()?),
+        (false, "") => None,
+        (false, _) => {
+            return Err(BudgetError::Validation(String::from(
+                "Only income and expense items have a frequency",
+            )))
+        }
+    };
+    Ok(CsvItemRow {
+        line,
+        kind,
+        category: String::from(category),
+        item: String::from(item),
+        value,
+        currency,
+        frequency,
+    })
+}
+
+/// Read an items CSV file and check every row without changing anything
+/// The file needs a header with at least the kind, item and value columns (see ITEM_COLUMNS)
+/// Item names must not already be in use in their half of the balance sheet or budget, or appear twice in the file.
+/// Categories that don't exist yet are listed to be created.
+pub fn preview_csv_import(conn: &Connection, user: &User, path: &str) -> Result<CsvImport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .map_err(|error| csv_error(path, error))?;
+    let columns: HashMap<String, usize> = reader
+        .headers()
+        .map_err(|error| csv_error(path, error))?
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| (name.trim().to_lowercase(), idx))
+        .collect();
+    for required in ["kind", "item", "value"] {
+        if !columns.contains_key(required) {
+            return Err(BudgetError::Parse(format!(
+                "The file {} needs a header line with a {} column (the columns are {})",
+                path,
+                required,
+                ITEM_COLUMNS.join(",")
+            )));
+        }
+    }
+
+    // The names in use and the categories that exist in each half
+    // Uncategorized is always there once the balance sheet and budget are initialized
+    let mut taken: Vec<(CsvItemKind, String)> = vec![];
+    let mut categories: Vec<(CsvItemKind, String)> = [
+        CsvItemKind::Asset,
+        CsvItemKind::Liability,
+        CsvItemKind::Income,
+        CsvItemKind::Expense,
+    ]
+    .iter()
+    .map(|kind| (*kind, String::from("uncategorized")))
+    .collect();
+    for (kind, which_half) in [
+        (CsvItemKind::Asset, BalanceSheetHalf::Assets),
+        (CsvItemKind::Liability, BalanceSheetHalf::Liabilities),
+    ] {
+        let (half_categories, items) = get_relevant_items_cats(conn, user, &which_half)?;
+        taken.extend(items.into_iter().map(|item| (kind, item.item_lower)));
+        categories.extend(
+            half_categories
+                .into_iter()
+                .map(|c| (kind, c.category_lower)),
+        );
+    }
+    for (kind, which_half) in [
+        (CsvItemKind::Income, BudgetHalf::Income),
+        (CsvItemKind::Expense, BudgetHalf::Expenses),
+    ] {
+        let (half_categories, items) = get_relevant_items(conn, user, &which_half)?;
+        taken.extend(items.into_iter().map(|item| (kind, item.item_lower)));
+        categories.extend(
+            half_categories
+                .into_iter()
+                .map(|c| (kind, c.category_lower)),
+        );
+    }
+
+    let mut import = CsvImport {
+        rows: vec![],
+        new_categories: vec![],
+        errors: vec![],
+    };
+    for record in reader.records() {
+        let record = record.map_err(|error| csv_error(path, error))?;
+        let line = record
+            .position()
+            .map_or(0, |position| position.line() as usize);
+        if record.iter().all(|field| field.trim().is_empty()) {
+            continue;
+        }
+        let row = match parse_item_row(user, &columns, &record, line) {
+            Ok(row) => row,
+            Err(error) => {
+                import.errors.push(format!("Line {}: {}", line, error));
+                continue;
+            }
+        };
+        let item_lower = row.item.to_lowercase();
+        if let Some(earlier) = import
+            .rows
+            .iter()
+            .find(|earlier| earlier.kind == row.kind && earlier.item.to_lowercase() == item_lower)
+        {
+            import.errors.push(format!(
+                "Line {}: {} is already on line {}",
+                line, row.item, earlier.line
+            ));
+            continue;
+        }
+        if taken.contains(&(row.kind, item_lower)) {
+            import.errors.push(format!(
+                "Line {}: There is already a current {} named {}",
+                line,
+                row.kind.to_str(),
+                row.item
+            ));
+            continue;
+        }
+        let category_lower = row.category.to_lowercase();
+        if !categories.contains(&(row.kind, category_lower.clone())) {
+            categories.push((row.kind, category_lower));
+            import.new_categories.push((row.kind, row.category.clone()));
+        }
+        import.rows.push(row);
+    }
+    Ok(import)
+}
+
+/// Create a balance sheet item from an import row, and its category if it doesn't exist yet
+fn import_item(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    row: &CsvItemRow,
+) -> Result<()> {
+    if balance_sheet::find_category(conn, user, which_half, &row.category).is_err() {
+        balance_sheet::create_category(conn, user, which_half, &row.category)?;
+    }
+    balance_sheet::create_item(
+        conn,
+        user,
+        which_half,
+        &row.item,
+        row.value,
+        &row.currency,
+        &row.category,
+    )?;
+    Ok(())
+}
+
+/// Create a budget item from an import row, and its category if it doesn't exist yet
+fn import_budget_item(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    row: &CsvItemRow,
+) -> Result<()> {
+    if budget::find_category(conn, user, which_half, &row.category).is_err() {
+        budget::create_category(conn, user, which_half, &row.category)?;
+    }
+    budget::create_item(
+        conn,
+        user,
+        which_half,
+        &row.item,
+        row.value,
+        &row.currency,
+        row.frequency.unwrap_or_default(),
+        &row.category,
+    )?;
+    Ok(())
+}
+
+/// Create the categories and items of a checked import, all together or not at all
+/// Returns the number of items created
+pub fn apply_csv_import(conn: &Connection, user: &User, import: &CsvImport) -> Result<usize> {
+    if !import.errors.is_empty() {
+        return Err(BudgetError::Validation(format!(
+            "Nothing was imported because the file has {} problem(s). Fix them and try again.",
+            import.errors.len()
+        )));
+    }
+    in_transaction(conn, |conn| {
+        initialize_balance_sheet(conn, user)?;
+        initialize_budget(conn, user)?;
+        for row in &import.rows {
+            match row.kind {
+                CsvItemKind::Asset => import_item(conn, user, &BalanceSheetHalf::Assets, row)?,
+                CsvItemKind::Liability => {
+                    import_item(conn, user, &BalanceSheetHalf::Liabilities, row)?
+                }
+                CsvItemKind::Income => import_budget_item(conn, user, &BudgetHalf::Income, row)?,
+                CsvItemKind::Expense => import_budget_item(conn, user, &BudgetHalf::Expenses, row)?,
+            }
+        }
+        Ok(import.rows.len())
+    })
+}
@@ -0,0 +1,334 @@
+use crate::error::{BudgetError, Result};
+use crate::money::MINOR_PER_MAJOR;
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+use rust_decimal::prelude::*;
+use rust_decimal::RoundingStrategy;
+
+/// Currency used for existing data and new users until they choose their own
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Check that a currency is a known ISO code and return it in upper case
+pub fn normalize_currency(code: &str) -> Result<String> {
+    let code = code.trim().to_uppercase();
+    match rusty_money::iso::find(&code) {
+        Some(_) => Ok(code),
+        None => Err(BudgetError::Validation(format!(
+            "{} is not a known ISO currency code (eg - USD, EUR, GBP)",
+            code
+        ))),
+    }
+}
+
+/// Parse an exchange rate such as "1.0825" and make sure it is positive
+pub fn parse_rate(rate: &str) -> Result<Decimal> {
+    let rate = Decimal::from_str(rate.trim())
+        .map_err(|_| BudgetError::Parse(format!("{} is not a valid exchange rate", rate)))?;
+    if rate <= Decimal::ZERO {
+        return Err(BudgetError::Validation(String::from(
+            "The exchange rate must be greater than 0",
+        )));
+    }
+    Ok(rate)
+}
+
+/// Build an ExchangeRate from a (from_currency, to_currency, rate, date_text) row
+fn rate_from_row(row: &rusqlite::Row) -> Result<ExchangeRate> {
+    let rate: String = row.get(2)?;
+    Ok(ExchangeRate {
+        from_currency: row.get(0)?,
+        to_currency: row.get(1)?,
+        rate: parse_rate(&rate)?,
+        date_text: row.get(3)?,
+    })
+}
+
+/// Get all of a user's current exchange rates
+pub fn list_exchange_rates(conn: &Connection, user: &User) -> Result<Vec<ExchangeRate>> {
+    let mut stmt = conn.prepare(
+        "SELECT from_currency, to_currency, rate, date_text FROM exchange_rates
+        WHERE username_lower = ?1
+        ORDER BY from_currency, to_currency",
+    )?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    let mut rates: Vec<ExchangeRate> = vec![];
+    while let Some(row) = rows.next()? {
+        rates.push(rate_from_row(row)?);
+    }
+    Ok(rates)
+}
+
+/// Add or replace a user's rate between two currencies
+pub fn set_exchange_rate(
+    conn: &Connection,
+    user: &User,
+    from_currency: &str,
+    to_currency: &str,
+    rate: &str,
+) -> Result<ExchangeRate> {
+    let from_currency = normalize_currency(from_currency)?;
+    let to_currency = normalize_currency(to_currency)?;
+    if from_currency == to_currency {
+        return Err(BudgetError::Validation(String::from(
+            "An exchange rate needs two different currencies",
+        )));
+    }
+    let rate = parse_rate(rate)?;
+    let date_text = Local::now().format("%Y-%m-%d").to_string(); // YYYY-MM-DD
+
+    // Only one direction is kept for each pair so the two can't disagree
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "DELETE FROM exchange_rates
+            WHERE username_lower = ?1 AND from_currency = ?2 AND to_currency = ?3",
+            (&user.username_lower, &to_currency, &from_currency),
+        )?;
+        conn.execute(
+            "INSERT OR REPLACE INTO exchange_rates
+            (username_lower, from_currency, to_currency, rate, date_text)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            (
+                &user.username_lower,
+                &from_currency,
+                &to_currency,
+                rate.to_string(),
+                &date_text,
+            ),
+        )?;
+        Ok(())
+    })?;
+    Ok(ExchangeRate {
+        from_currency,
+        to_currency,
+        rate,
+        date_text,
+    })
+}
+
+/// Remove a user's rate between two currencies (in either direction)
+pub fn delete_exchange_rate(
+    conn: &Connection,
+    user: &User,
+    from_currency: &str,
+    to_currency: &str,
+) -> Result<()> {
+    let from_currency = normalize_currency(from_currency)?;
+    let to_currency = normalize_currency(to_currency)?;
+    let num_deleted = conn.execute(
+        "DELETE FROM exchange_rates
+        WHERE username_lower = ?1
+            AND ((from_currency = ?2 AND to_currency = ?3) OR (from_currency = ?3 AND to_currency = ?2))",
+        (&user.username_lower, &from_currency, &to_currency),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "There is no exchange rate between {} and {}",
+            from_currency, to_currency
+        )));
+    }
+    Ok(())
+}
+
+/// Import a user's rates from a text file with one FROM,TO,RATE line per pair (eg - EUR,USD,1.0825)
+/// Blank lines and lines starting with # are skipped
+/// Every line is checked before any rate is saved, and the number of rates saved is returned
+pub fn import_exchange_rates(conn: &Connection, user: &User, path: &str) -> Result<usize> {
+    let contents = std::fs::read_to_string(path).map_err(|error| {
+        BudgetError::NotFound(format!("The file {} could not be read: {}", path, error))
+    })?;
+
+    let mut parsed: Vec<(String, String, String)> = vec![];
+    for (line_num, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        if fields.len() != 3 {
+            return Err(BudgetError::Parse(format!(
+                "Line {} should look like FROM,TO,RATE but was: {}",
+                line_num + 1,
+                line
+            )));
+        }
+        let check = normalize_currency(fields[0])
+            .and_then(|_| normalize_currency(fields[1]))
+            .and_then(|_| parse_rate(fields[2]));
+        if let Err(error) = check {
+            return Err(BudgetError::Parse(format!(
+                "Line {}: {}",
+                line_num + 1,
+                error
+            )));
+        }
+        parsed.push((
+            String::from(fields[0]),
+            String::from(fields[1]),
+            String::from(fields[2]),
+        ));
+    }
+
+    in_transaction(conn, |conn| {
+        for (from_currency, to_currency, rate) in &parsed {
+            set_exchange_rate(conn, user, from_currency, to_currency, rate)?;
+        }
+        Ok(parsed.len())
+    })
+}
+
+/// Change the currency that a user's totals and snapshots are shown in
+pub fn set_base_currency(conn: &Connection, user: &User, code: &str) -> Result<User> {
+    let code = normalize_currency(code)?;
+    conn.execute(
+        "UPDATE users SET base_currency = ?1 WHERE username_lower = ?2",
+        (&code, &user.username_lower),
+    )?;
+    let mut user = user.clone();
+    user.base_currency = code;
+    Ok(user)
+}
+
+/// Copy the user's current rates so they stay with a snapshot
+pub(crate) fn freeze_rates(conn: &Connection, user: &User, timestamp: usize) -> Result<()> {
+    freeze_rates_into(conn, "snapshot_rates", user, timestamp)
+}
+
+/// Copy the user's current rates so they stay with a budget snapshot
+pub(crate) fn freeze_budget_rates(conn: &Connection, user: &User, timestamp: usize) -> Result<()> {
+    freeze_rates_into(conn, "budget_snapshot_rates", user, timestamp)
+}
+
+/// Copy the user's current rates into one of the frozen rate tables
+fn freeze_rates_into(conn: &Connection, table: &str, user: &User, timestamp: usize) -> Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {}
+            (timestamp, username_lower, from_currency, to_currency, rate, date_text)
+            SELECT ?1, username_lower, from_currency, to_currency, rate, date_text
+            FROM exchange_rates WHERE username_lower = ?2",
+            table
+        ),
+        (timestamp, &user.username_lower),
+    )?;
+    Ok(())
+}
+
+/// Converts amounts into one base currency using a list of exchange rates
+/// Rates can be used in either direction, and the first matching rate in the list wins
+pub struct Converter {
+    base: String,
+    rates: Vec<ExchangeRate>,
+}
+
+impl Converter {
+    pub fn new(base: &str, rates: Vec<ExchangeRate>) -> Converter {
+        Converter {
+            base: String::from(base),
+            rates,
+        }
+    }
+
+    /// The currency everything is converted into
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// The number of base currency units that 1 unit of the currency is worth
+    pub fn rate(&self, currency: &str) -> Result<Decimal> {
+        if currency == self.base {
+            return Ok(Decimal::ONE);
+        }
+        for rate in &self.rates {
+            if rate.from_currency == currency && rate.to_currency == self.base {
+                return Ok(rate.rate);
+            }
+            if rate.from_currency == self.base && rate.to_currency == currency {
+                return Ok(Decimal::ONE / rate.rate);
+            }
+        }
+        Err(BudgetError::NotFound(format!(
+            "There is no exchange rate between {} and {}. Add one in the Currencies menu.",
+            currency, self.base
+        )))
+    }
+
+    /// Convert an amount in the given currency into the base currency, rounded to the nearest cent
+    pub fn convert(&self, amount: Amount, currency: &str) -> Result<Amount> {
+        if currency == self.base {
+            return Ok(amount);
+        }
+        let converted = Decimal::new(amount.minor(), 2) * self.rate(currency)?;
+        let minor = (converted * Decimal::from(MINOR_PER_MAJOR))
+            .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+            .to_i64()
+            .ok_or_else(|| {
+                BudgetError::Validation(String::from("The converted amount is too large"))
+            })?;
+        Ok(Amount::from_minor(minor))
+    }
+}
+
+/// A converter into the user's base currency using the current rates
+pub fn current_converter(conn: &Connection, user: &User) -> Result<Converter> {
+    Ok(Converter::new(
+        &user.base_currency,
+        list_exchange_rates(conn, user)?,
+    ))
+}
+
+/// A converter into the given base currency for the values in a snapshot
+/// The rates frozen with the snapshot are used first, then the current rates for any pair that is missing
+pub fn snapshot_converter(
+    conn: &Connection,
+    user: &User,
+    snapshot: &Snapshot,
+    base: &str,
+) -> Result<Converter> {
+    frozen_converter(conn, "snapshot_rates", user, snapshot.timeline, base)
+}
+
+/// A converter into the given base currency for the values in a budget snapshot
+/// The rates frozen with the snapshot are used first, then the current rates for any pair that is missing
+pub fn budget_snapshot_converter(
+    conn: &Connection,
+    user: &User,
+    snapshot: &BudgetSnapshot,
+    base: &str,
+) -> Result<Converter> {
+    frozen_converter(conn, "budget_snapshot_rates", user, snapshot.timeline, base)
+}
+
+/// A converter using the rates frozen in one of the frozen rate tables, then the current rates
+fn frozen_converter(
+    conn: &Connection,
+    table: &str,
+    user: &User,
+    timestamp: usize,
+    base: &str,
+) -> Result<Converter> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT from_currency, to_currency, rate, date_text FROM {}
+        WHERE timestamp = ?1 AND username_lower = ?2",
+        table
+    ))?;
+    let mut rows = stmt.query((timestamp, &user.username_lower))?;
+    let mut rates: Vec<ExchangeRate> = vec![];
+    while let Some(row) = rows.next()? {
+        rates.push(rate_from_row(row)?);
+    }
+    rates.extend(list_exchange_rates(conn, user)?);
+    Ok(Converter::new(base, rates))
+}
+
+/// Sum amounts in mixed currencies into the converter's base currency
+pub fn convert_sum<'a, I>(converter: &Converter, values: I) -> Result<Amount>
+where
+    I: IntoIterator<Item = (Amount, &'a str)>,
+{
+    let mut total = Amount::ZERO;
+    for (value, currency) in values {
+        total += converter.convert(value, currency)?;
+    }
+    Ok(total)
+}
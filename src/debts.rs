@@ -0,0 +1,214 @@
+use crate::balance_sheet::get_relevant_items_cats;
+use crate::currency::current_converter;
+use crate::error::{BudgetError, Result};
+use crate::loans::{decimal_to_amount, get_loan, monthly_rate, parse_apr, MAX_PAYMENTS};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use chrono::Months;
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::prelude::*;
+
+/// Set the interest rate and minimum monthly payment of a liability, replacing any it already has
+/// The minimum payment is in the item's currency
+pub fn set_debt_terms(
+    conn: &Connection,
+    user: &User,
+    item: &Item,
+    apr: Decimal,
+    minimum_payment: Amount,
+) -> Result<()> {
+    if item.is_asset {
+        return Err(BudgetError::Validation(String::from(
+            "Only liabilities can have an interest rate and minimum payment",
+        )));
+    }
+    if minimum_payment <= Amount::ZERO {
+        return Err(BudgetError::Validation(String::from(
+            "The minimum payment must be greater than 0",
+        )));
+    }
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "DELETE FROM debt_terms WHERE username_lower = ?1 AND item_original = ?2",
+            (&user.username_lower, &item.timeline_original),
+        )?;
+        conn.execute(
+            "INSERT INTO debt_terms (username_lower, item_original, apr, minimum_payment)
+            VALUES (?1, ?2, ?3, ?4)",
+            (
+                &user.username_lower,
+                &item.timeline_original,
+                apr.to_string(),
+                &minimum_payment,
+            ),
+        )?;
+        Ok(())
+    })
+}
+
+/// Remove the interest rate and minimum payment from a liability
+pub fn delete_debt_terms(conn: &Connection, user: &User, item: &Item) -> Result<()> {
+    let num_deleted = conn.execute(
+        "DELETE FROM debt_terms WHERE username_lower = ?1 AND item_original = ?2",
+        (&user.username_lower, &item.timeline_original),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "{} doesn't have an interest rate and minimum payment set",
+            item.item
+        )));
+    }
+    Ok(())
+}
+
+/// The interest rate and minimum payment (in the item's currency) of a liability
+/// A liability with loan terms but nothing set here uses the loan's rate and payment
+pub fn get_debt_terms(
+    conn: &Connection,
+    user: &User,
+    item: &Item,
+) -> Result<Option<(Decimal, Amount)>> {
+    let terms: Option<(String, Amount)> = conn
+        .query_row(
+            "SELECT apr, minimum_payment FROM debt_terms WHERE username_lower = ?1 AND item_original = ?2",
+            (&user.username_lower, &item.timeline_original),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    match terms {
+        Some((apr, minimum_payment)) => Ok(Some((parse_apr(&apr)?, minimum_payment))),
+        None => Ok(get_loan(conn, user, item)?.map(|loan| (loan.apr, loan.payment))),
+    }
+}
+
+/// Get the current liabilities that are still owed, in the user's base currency
+/// Returns the ones with an interest rate and minimum payment, and the ones still missing them
+pub fn get_debts(conn: &Connection, user: &User) -> Result<(Vec<Debt>, Vec<Item>)> {
+    let converter = current_converter(conn, user)?;
+    let (_, items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let mut debts: Vec<Debt> = vec![];
+    let mut missing: Vec<Item> = vec![];
+    for item in items {
+        if item.value <= Amount::ZERO {
+            continue;
+        }
+        match get_debt_terms(conn, user, &item)? {
+            Some((apr, minimum_payment)) => debts.push(Debt {
+                balance: converter.convert(item.value, &item.currency)?,
+                minimum_payment: converter.convert(minimum_payment, &item.currency)?,
+                item: item.item,
+                item_original: item.timeline_original,
+                apr,
+            }),
+            None => missing.push(item),
+        }
+    }
+    Ok((debts, missing))
+}
+
+/// Work out month by month how the debts are paid off with a monthly budget, starting the month after start
+/// Interest is charged first, then every debt gets its minimum payment, then what is left of the budget
+/// goes to one debt at a time in the strategy's order. A paid off debt's minimum rolls into the others.
+/// The debts and the budget are in currency, the base currency get_debts converted them into
+pub fn plan_debts(
+    debts: &[Debt],
+    monthly_budget: Amount,
+    currency: &str,
+    strategy: DebtStrategy,
+    start: NaiveDate,
+) -> Result<DebtPlan> {
+    if debts.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "There are no debts with an interest rate and minimum payment to plan",
+        )));
+    }
+    let total_minimum: Amount = debts.iter().map(|debt| debt.minimum_payment).sum();
+    if strategy != DebtStrategy::MinimumsOnly && monthly_budget < total_minimum {
+        return Err(BudgetError::Validation(format!(
+            "The monthly budget must at least cover the minimum payments of {}",
+            to_money_string(total_minimum, currency)
+        )));
+    }
+
+    // The order extra money is put towards the debts
+    let mut order: Vec<usize> = (0..debts.len()).collect();
+    match strategy {
+        DebtStrategy::Avalanche => order.sort_by(|a, b| {
+            (debts[*b].apr, debts[*a].balance).cmp(&(debts[*a].apr, debts[*b].balance))
+        }),
+        DebtStrategy::Snowball => order.sort_by(|a, b| {
+            (debts[*a].balance, debts[*b].apr).cmp(&(debts[*b].balance, debts[*a].apr))
+        }),
+        DebtStrategy::MinimumsOnly => {}
+    }
+
+    let mut balances: Vec<Amount> = debts.iter().map(|debt| debt.balance).collect();
+    let mut months: Vec<DebtMonth> = vec![];
+    let mut payoff_order: Vec<(String, NaiveDate)> = vec![];
+    let mut paid_off: Vec<bool> = vec![false; debts.len()];
+    let mut number: u32 = 0;
+    while balances.iter().any(|balance| *balance > Amount::ZERO) {
+        number += 1;
+        if number > MAX_PAYMENTS {
+            return Err(BudgetError::Validation(format!(
+                "The debts wouldn't be paid off within {} years",
+                MAX_PAYMENTS / 12
+            )));
+        }
+        let date = start
+            .checked_add_months(Months::new(number))
+            .ok_or_else(|| BudgetError::Validation(String::from("The date is out of range")))?;
+
+        let mut interest = Amount::ZERO;
+        for (debt, balance) in debts.iter().zip(balances.iter_mut()) {
+            if *balance <= Amount::ZERO {
+                continue;
+            }
+            let charged = decimal_to_amount(
+                Decimal::new(balance.minor(), 2) * monthly_rate(debt.apr),
+                RoundingStrategy::MidpointAwayFromZero,
+            )?;
+            *balance += charged;
+            interest += charged;
+        }
+
+        let mut available = match strategy {
+            DebtStrategy::MinimumsOnly => total_minimum,
+            _ => monthly_budget,
+        };
+        for (debt, balance) in debts.iter().zip(balances.iter_mut()) {
+            let payment = debt.minimum_payment.min(*balance).min(available);
+            *balance -= payment;
+            available -= payment;
+        }
+        if strategy != DebtStrategy::MinimumsOnly {
+            for idx in &order {
+                let payment = balances[*idx].min(available);
+                balances[*idx] -= payment;
+                available -= payment;
+            }
+        }
+
+        for (idx, debt) in debts.iter().enumerate() {
+            if balances[idx] <= Amount::ZERO && !paid_off[idx] {
+                paid_off[idx] = true;
+                payoff_order.push((debt.item.clone(), date));
+            }
+        }
+        months.push(DebtMonth {
+            date,
+            balances: balances.clone(),
+            total: balances.iter().copied().sum(),
+            interest,
+        });
+    }
+
+    Ok(DebtPlan {
+        strategy,
+        debts: debts.to_vec(),
+        debt_free_date: months.last().map_or(start, |month| month.date),
+        total_interest: months.iter().map(|month| month.interest).sum(),
+        months,
+        payoff_order,
+    })
+}
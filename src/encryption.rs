@@ -0,0 +1,139 @@
+use crate::error::{BudgetError, Result};
+use crate::ledger::open_db;
+use crate::migrations::{migrate, schema_version};
+use rusqlite::Connection;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that holds the passphrase for an encrypted database
+pub const PASSPHRASE_ENV_VAR: &str = "BURKEBUDGET_PASSPHRASE";
+
+/// Minimum number of characters in a passphrase
+pub const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// Every plaintext SQLite file starts with this header
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Whether this build can read and write encrypted databases
+pub fn encryption_supported() -> bool {
+    cfg!(feature = "encryption")
+}
+
+/// Fail with a helpful message when this build has no encryption support
+fn require_encryption() -> Result<()> {
+    if !encryption_supported() {
+        return Err(BudgetError::Validation(String::from(
+            "This copy of Burke Budget was built without encryption support (build it with `--features encryption`)",
+        )));
+    }
+    Ok(())
+}
+
+/// Check a new passphrase against the length limit
+pub fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(BudgetError::Validation(format!(
+            "The passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Whether the file at the path is an encrypted database
+/// A missing or empty file isn't encrypted (it will be created as plaintext unless it is keyed first)
+pub fn is_encrypted(path: &Path) -> Result<bool> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    let mut num_read = 0;
+    while num_read < header.len() {
+        match file.read(&mut header[num_read..])? {
+            0 => break,
+            n => num_read += n,
+        }
+    }
+    if num_read == 0 {
+        return Ok(false);
+    }
+    Ok(num_read < header.len() || &header != SQLITE_HEADER)
+}
+
+/// Unlock an encrypted database on a connection that was just opened
+/// This must happen before anything else reads the database
+pub fn unlock(conn: &Connection, passphrase: &str) -> Result<()> {
+    require_encryption()?;
+    conn.pragma_update(None, "key", passphrase)?;
+    // The key isn't checked until the first read
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", (), |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|_| {
+        BudgetError::Validation(String::from(
+            "The passphrase is wrong, or the file is not a Burke Budget database",
+        ))
+    })?;
+    Ok(())
+}
+
+/// Encrypt a plaintext database file in place
+/// The encrypted copy is written next to it first and only replaces the original once it is complete
+/// A missing file is created as a new, empty encrypted database
+pub fn encrypt_db(path: &Path, passphrase: &str) -> Result<()> {
+    require_encryption()?;
+    validate_passphrase(passphrase)?;
+    if is_encrypted(path)? {
+        return Err(BudgetError::Conflict(format!(
+            "{} is already encrypted. Use rekey to change the passphrase.",
+            path.display()
+        )));
+    }
+
+    // Nothing is written to a new file until there is something in it, so create the tables right away
+    if !path.is_file() {
+        let conn = open_db(path, Some(passphrase))?;
+        migrate(&conn)?;
+        return Ok(());
+    }
+
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".encrypting");
+    let temp_path = PathBuf::from(temp_name);
+    if temp_path.exists() {
+        std::fs::remove_file(&temp_path)?;
+    }
+    let temp_text = temp_path.to_str().ok_or_else(|| {
+        BudgetError::Validation(String::from(
+            "The database path can't be used for encryption",
+        ))
+    })?;
+
+    {
+        let conn = Connection::open(path)?;
+        // sqlcipher_export copies everything except the schema version, so that is carried over by hand
+        let version = schema_version(&conn)?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            (temp_text, passphrase),
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", (), |_| Ok(()))?;
+        conn.pragma_update(
+            Some(rusqlite::DatabaseName::Attached("encrypted")),
+            "user_version",
+            version,
+        )?;
+        conn.execute("DETACH DATABASE encrypted", ())?;
+    }
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Change the passphrase of an encrypted database that is already unlocked on this connection
+pub fn rekey(conn: &Connection, new_passphrase: &str) -> Result<()> {
+    require_encryption()?;
+    validate_passphrase(new_passphrase)?;
+    conn.pragma_update(None, "rekey", new_passphrase)?;
+    Ok(())
+}
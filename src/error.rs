@@ -0,0 +1,56 @@
+use std::fmt;
+
+/// Errors returned by the library functions
+#[derive(Debug)]
+pub enum BudgetError {
+    /// An error from the underlying SQLite database
+    Db(rusqlite::Error),
+    /// The input did not pass validation (eg - an empty name or a negative value)
+    Validation(String),
+    /// The requested user, category, item, or snapshot does not exist
+    NotFound(String),
+    /// The change would clash with existing data (eg - a duplicate item name)
+    Conflict(String),
+    /// Stored or entered text could not be parsed (eg - a malformed snapshot date)
+    Parse(String),
+    /// A file or directory outside the database could not be read or written
+    Io(std::io::Error),
+}
+
+impl fmt::Display for BudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetError::Db(error) => write!(f, "Database error: {}", error),
+            BudgetError::Validation(message) => write!(f, "{}", message),
+            BudgetError::NotFound(message) => write!(f, "{}", message),
+            BudgetError::Conflict(message) => write!(f, "{}", message),
+            BudgetError::Parse(message) => write!(f, "{}", message),
+            BudgetError::Io(error) => write!(f, "File error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BudgetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BudgetError::Db(error) => Some(error),
+            BudgetError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for BudgetError {
+    fn from(error: rusqlite::Error) -> Self {
+        BudgetError::Db(error)
+    }
+}
+
+impl From<std::io::Error> for BudgetError {
+    fn from(error: std::io::Error) -> Self {
+        BudgetError::Io(error)
+    }
+}
+
+/// Result type used throughout the library
+pub type Result<T> = std::result::Result<T, BudgetError>;
@@ -0,0 +1,263 @@
+use crate::balance_sheet::{
+    current_net_worth, find_category, get_categories, get_relevant_items_cats,
+};
+use crate::budget::current_monthly_totals;
+use crate::currency::{current_converter, snapshot_converter};
+use crate::error::{BudgetError, Result};
+use crate::goals::{trend_per_day, DAYS_PER_MONTH};
+use crate::loans::decimal_to_amount;
+use crate::snapshots::{list_snapshots, snapshot_date};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use chrono::Months;
+use rusqlite::Connection;
+use rust_decimal::prelude::*;
+use std::str::FromStr;
+
+/// The longest forecast, in years
+pub const MAX_FORECAST_YEARS: u32 = 100;
+
+/// The withdrawal rate used when none is given (the "4% rule")
+pub const DEFAULT_WITHDRAWAL_RATE: Decimal = Decimal::from_parts(4, 0, 0, false, 0);
+
+/// Parse an assumed annual return such as "7" or "-2.5" (percent)
+pub fn parse_return_rate(rate: &str) -> Result<Decimal> {
+    let rate = Decimal::from_str(rate.trim().trim_end_matches('%'))
+        .map_err(|_| BudgetError::Parse(format!("{} is not a valid return rate", rate)))?;
+    if rate <= -Decimal::ONE_HUNDRED || rate >= Decimal::ONE_HUNDRED {
+        return Err(BudgetError::Validation(String::from(
+            "The return rate must be a percentage between -100 and 100",
+        )));
+    }
+    Ok(rate)
+}
+
+/// Parse a yearly withdrawal rate such as "4" (percent) and make sure it is above 0 and at most 100
+pub fn parse_withdrawal_rate(rate: &str) -> Result<Decimal> {
+    let rate = Decimal::from_str(rate.trim().trim_end_matches('%'))
+        .map_err(|_| BudgetError::Parse(format!("{} is not a valid withdrawal rate", rate)))?;
+    if rate <= Decimal::ZERO || rate > Decimal::ONE_HUNDRED {
+        return Err(BudgetError::Validation(String::from(
+            "The withdrawal rate must be a percentage above 0 and at most 100",
+        )));
+    }
+    Ok(rate)
+}
+
+/// Set the annual return assumed for an asset category when forecasting
+pub fn set_category_return(
+    conn: &Connection,
+    user: &User,
+    category: &str,
+    rate: Decimal,
+) -> Result<Category> {
+    in_transaction(conn, |conn| {
+        let category = find_category(conn, user, &BalanceSheetHalf::Assets, category)?;
+        conn.execute(
+            "DELETE FROM category_returns
+            WHERE category_lower = ?1 AND username_lower = ?2 AND is_asset = 1",
+            (&category.category_lower, &user.username_lower),
+        )?;
+        conn.execute(
+            "INSERT INTO category_returns (category_lower, username_lower, is_asset, rate)
+            VALUES (?1, ?2, 1, ?3)",
+            (
+                &category.category_lower,
+                &user.username_lower,
+                rate.to_string(),
+            ),
+        )?;
+        Ok(category)
+    })
+}
+
+/// Every asset category with its assumed annual return (percent)
+/// Categories without one are assumed to return 0
+pub fn get_category_returns(conn: &Connection, user: &User) -> Result<Vec<(Category, Decimal)>> {
+    let mut returns: Vec<(Category, Decimal)> = vec![];
+    for category in get_categories(conn, user, &BalanceSheetHalf::Assets)? {
+        let mut stmt = conn.prepare(
+            "SELECT rate FROM category_returns
+            WHERE category_lower = ?1 AND username_lower = ?2 AND is_asset = 1",
+        )?;
+        let mut rows = stmt.query((&category.category_lower, &user.username_lower))?;
+        let rate = match rows.next()? {
+            Some(row) => parse_return_rate(&row.get::<_, String>(0)?)?,
+            None => Decimal::ZERO,
+        };
+        returns.push((category, rate));
+    }
+    Ok(returns)
+}
+
+/// The net worth at each snapshot, in the user's base currency with the rates frozen in the snapshot
+pub fn net_worth_history(conn: &Connection, user: &User) -> Result<Vec<(NaiveDate, Amount)>> {
+    let mut history: Vec<(NaiveDate, Amount)> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        let converter = snapshot_converter(conn, user, &snapshot, &user.base_currency)?;
+        history.push((
+            snapshot_date(&snapshot)?,
+            converter.convert(snapshot.net_worth, &snapshot.currency)?,
+        ));
+    }
+    Ok(history)
+}
+
+/// Project the net worth at the start of every month for the given number of years from today
+/// The trend method continues the line through the snapshots and today's net worth
+/// The returns method grows each asset category at its assumed return and adds the budget's monthly net,
+/// which earns the average return of the assets. Liabilities are held where they are today.
+pub fn forecast_net_worth(
+    conn: &Connection,
+    user: &User,
+    method: ForecastMethod,
+    years: u32,
+    today: NaiveDate,
+) -> Result<Forecast> {
+    if years == 0 || years > MAX_FORECAST_YEARS {
+        return Err(BudgetError::Validation(format!(
+            "The forecast must be from 1 to {} years",
+            MAX_FORECAST_YEARS
+        )));
+    }
+    let net_worth = current_net_worth(conn, user)?;
+    let months = years * 12;
+    let month_date = |month: u32| {
+        today
+            .checked_add_months(Months::new(month))
+            .ok_or_else(|| BudgetError::Validation(String::from("The date is out of range")))
+    };
+    let mut points: Vec<(NaiveDate, Amount)> = vec![];
+
+    match method {
+        ForecastMethod::Trend => {
+            let mut history: Vec<(f64, f64)> = net_worth_history(conn, user)?
+                .iter()
+                .map(|(date, value)| ((*date - today).num_days() as f64, value.to_f64()))
+                .collect();
+            history.push((0.0, net_worth.to_f64()));
+            let slope = trend_per_day(&history).ok_or_else(|| {
+                BudgetError::Validation(String::from(
+                    "A trend needs at least one snapshot from before today",
+                ))
+            })?;
+            for month in 0..=months {
+                let date = month_date(month)?;
+                let days = (date - today).num_days() as f64;
+                points.push((date, net_worth + Amount::from_f64(slope * days)));
+            }
+        }
+        ForecastMethod::Returns => {
+            let converter = current_converter(conn, user)?;
+            let (_, assets) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+            let (_, liabilities) =
+                get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+            let mut liability_total = Amount::ZERO;
+            for item in &liabilities {
+                liability_total += converter.convert(item.value, &item.currency)?;
+            }
+
+            // Each category's balance and monthly growth rate
+            let mut buckets: Vec<(f64, f64)> = vec![];
+            for (category, rate) in get_category_returns(conn, user)? {
+                let mut balance = Amount::ZERO;
+                for item in assets
+                    .iter()
+                    .filter(|item| item.category_lower == category.category_lower)
+                {
+                    balance += converter.convert(item.value, &item.currency)?;
+                }
+                let monthly_rate = rate.to_f64().unwrap_or_default() / 1200.0;
+                buckets.push((balance.to_f64(), monthly_rate));
+            }
+            let asset_total: f64 = buckets.iter().map(|(balance, _)| balance).sum();
+            let average_rate = if asset_total > 0.0 {
+                buckets
+                    .iter()
+                    .map(|(balance, rate)| balance * rate)
+                    .sum::<f64>()
+                    / asset_total
+            } else {
+                0.0
+            };
+            let (income, expenses) = current_monthly_totals(conn, user)?;
+            let monthly_net = (income - expenses).to_f64();
+
+            let mut saved = 0.0;
+            for month in 0..=months {
+                if month > 0 {
+                    for (balance, rate) in buckets.iter_mut() {
+                        *balance *= 1.0 + *rate;
+                    }
+                    saved = saved * (1.0 + average_rate) + monthly_net;
+                }
+                let assets: f64 = buckets.iter().map(|(balance, _)| balance).sum();
+                points.push((
+                    month_date(month)?,
+                    Amount::from_f64(assets + saved) - liability_total,
+                ));
+            }
+        }
+    }
+
+    let monthly_change = points[1].1 - points[0].1;
+    Ok(Forecast {
+        method,
+        points,
+        monthly_change,
+    })
+}
+
+/// Work out the financial independence numbers for a forecast
+/// Without a target, it is the yearly budget expenses divided by the withdrawal rate
+pub fn fire_metrics(
+    conn: &Connection,
+    user: &User,
+    forecast: &Forecast,
+    withdrawal_rate: Decimal,
+    target: Option<Amount>,
+) -> Result<FireMetrics> {
+    let (monthly_income, monthly_expenses) = current_monthly_totals(conn, user)?;
+    let net_worth = current_net_worth(conn, user)?;
+    let rate = withdrawal_rate / Decimal::ONE_HUNDRED;
+    let yearly = |amount: Amount| Decimal::new(amount.minor(), 2) * Decimal::from(12);
+    let target = match target {
+        Some(target) => target,
+        None => decimal_to_amount(
+            yearly(monthly_expenses) / rate,
+            RoundingStrategy::MidpointAwayFromZero,
+        )?,
+    };
+    let withdrawal = |amount: Amount| {
+        decimal_to_amount(
+            Decimal::new(amount.minor(), 2).max(Decimal::ZERO) * rate,
+            RoundingStrategy::MidpointAwayFromZero,
+        )
+    };
+
+    let target_date = forecast
+        .points
+        .iter()
+        .find(|(_, value)| *value >= target)
+        .map(|(date, _)| *date);
+    let today = forecast.points[0].0;
+    let end = forecast.points[forecast.points.len() - 1].1;
+
+    Ok(FireMetrics {
+        net_worth,
+        monthly_income,
+        monthly_expenses,
+        savings_rate: if monthly_income > Amount::ZERO {
+            Some((monthly_income - monthly_expenses).to_f64() / monthly_income.to_f64() * 100.0)
+        } else {
+            None
+        },
+        withdrawal_rate,
+        target,
+        target_date,
+        years_to_target: target_date
+            .map(|date| (date - today).num_days() as f64 / (DAYS_PER_MONTH * 12.0)),
+        safe_withdrawal: withdrawal(net_worth)?,
+        projected_safe_withdrawal: withdrawal(end)?,
+    })
+}
@@ -0,0 +1,192 @@
+use crate::error::{BudgetError, Result};
+use crate::money::Amount;
+use chrono::prelude::*;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::fmt;
+use std::str::FromStr;
+
+/// The names accepted for each kind of frequency, in the order they are offered in the menus
+pub const FREQUENCY_KINDS: [&str; 7] = [
+    "weekly",
+    "biweekly",
+    "semimonthly",
+    "monthly",
+    "quarterly",
+    "annual",
+    "one-off",
+];
+
+/// How often a budget item's amount comes in or goes out
+/// Quarterly and annual items are paid in a given month (1-12), and quarterly ones again every 3 months after it
+/// A one-off item happens once, in a given year and month
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Frequency {
+    Weekly,
+    Biweekly,
+    SemiMonthly,
+    #[default]
+    Monthly,
+    Quarterly(u32),
+    Annual(u32),
+    OneOff(i32, u32),
+}
+
+impl Frequency {
+    /// Build a frequency from one of FREQUENCY_KINDS and the month it is paid in where that is needed
+    /// The month is 1-12 for quarterly and annual items, and YYYY-MM for one-off items
+    pub fn from_parts(kind: &str, month: Option<&str>) -> Result<Frequency> {
+        let needs_month =
+            || BudgetError::Validation(format!("A {} item needs the month it is paid in", kind));
+        match kind.to_lowercase().as_str() {
+            "weekly" => Ok(Frequency::Weekly),
+            "biweekly" => Ok(Frequency::Biweekly),
+            "semimonthly" => Ok(Frequency::SemiMonthly),
+            "monthly" => Ok(Frequency::Monthly),
+            "quarterly" => Ok(Frequency::Quarterly(parse_month_number(
+                month.ok_or_else(needs_month)?,
+            )?)),
+            "annual" => Ok(Frequency::Annual(parse_month_number(
+                month.ok_or_else(needs_month)?,
+            )?)),
+            "one-off" | "oneoff" => {
+                let month = month.ok_or_else(needs_month)?;
+                let date = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+                    .map_err(|_| {
+                        BudgetError::Parse(format!(
+                            "{} is not a valid month. Use the format YYYY-MM.",
+                            month
+                        ))
+                    })?;
+                Ok(Frequency::OneOff(date.year(), date.month()))
+            }
+            _ => Err(BudgetError::Parse(format!(
+                "{} is not a frequency. Use one of: {}",
+                kind,
+                FREQUENCY_KINDS.join(", ")
+            ))),
+        }
+    }
+
+    /// One of FREQUENCY_KINDS
+    pub fn kind(&self) -> &str {
+        match self {
+            Frequency::Weekly => "weekly",
+            Frequency::Biweekly => "biweekly",
+            Frequency::SemiMonthly => "semimonthly",
+            Frequency::Monthly => "monthly",
+            Frequency::Quarterly(_) => "quarterly",
+            Frequency::Annual(_) => "annual",
+            Frequency::OneOff(_, _) => "one-off",
+        }
+    }
+
+    /// Whether the amount lands in some months and not others
+    pub fn is_lumpy(&self) -> bool {
+        matches!(
+            self,
+            Frequency::Quarterly(_) | Frequency::Annual(_) | Frequency::OneOff(_, _)
+        )
+    }
+
+    /// The amount averaged out over a month
+    /// A one-off amount doesn't recur, so it has no monthly equivalent. It only counts in its own month (see amount_in_month).
+    pub fn monthly_equivalent(&self, amount: Amount) -> Amount {
+        match self {
+            Frequency::Weekly => amount.scale(52, 12),
+            Frequency::Biweekly => amount.scale(26, 12),
+            Frequency::SemiMonthly => amount.scale(2, 1),
+            Frequency::Monthly => amount,
+            Frequency::Quarterly(_) => amount.scale(1, 3),
+            Frequency::Annual(_) => amount.scale(1, 12),
+            Frequency::OneOff(_, _) => Amount::ZERO,
+        }
+    }
+
+    /// The amount that actually lands in a given calendar month
+    /// Weekly and biweekly amounts use their monthly equivalent rather than counting paydays
+    pub fn amount_in_month(&self, amount: Amount, year: i32, month: u32) -> Amount {
+        match self {
+            Frequency::Quarterly(first) if (month + 12 - first).is_multiple_of(3) => amount,
+            Frequency::Annual(due) if *due == month => amount,
+            Frequency::OneOff(due_year, due) if *due_year == year && *due == month => amount,
+            Frequency::Quarterly(_) | Frequency::Annual(_) | Frequency::OneOff(_, _) => {
+                Amount::ZERO
+            }
+            _ => self.monthly_equivalent(amount),
+        }
+    }
+
+    /// A short description for the menus (eg - "annually in Dec")
+    pub fn describe(&self) -> String {
+        match self {
+            Frequency::Weekly => String::from("weekly"),
+            Frequency::Biweekly => String::from("every 2 weeks"),
+            Frequency::SemiMonthly => String::from("twice a month"),
+            Frequency::Monthly => String::from("monthly"),
+            Frequency::Quarterly(month) => {
+                format!("quarterly from {}", month_abbreviation(*month))
+            }
+            Frequency::Annual(month) => format!("annually in {}", month_abbreviation(*month)),
+            Frequency::OneOff(year, month) => {
+                format!("once in {} {}", month_abbreviation(*month), year)
+            }
+        }
+    }
+}
+
+/// Parse a month number from 1 to 12
+fn parse_month_number(month: &str) -> Result<u32> {
+    match month.trim().parse::<u32>() {
+        Ok(month) if (1..=12).contains(&month) => Ok(month),
+        _ => Err(BudgetError::Parse(format!(
+            "{} is not a month. Use a number from 1 to 12.",
+            month
+        ))),
+    }
+}
+
+/// Jan, Feb, ... for a month number from 1 to 12
+pub fn month_abbreviation(month: u32) -> String {
+    NaiveDate::from_ymd_opt(2000, month, 1)
+        .map(|date| date.format("%b").to_string())
+        .unwrap_or_else(|| month.to_string())
+}
+
+/// Stored in the database as the kind, with the month after a colon where there is one
+/// eg - "monthly", "annual:12", "one-off:2026-12"
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Frequency::Quarterly(month) | Frequency::Annual(month) => {
+                write!(f, "{}:{}", self.kind(), month)
+            }
+            Frequency::OneOff(year, month) => write!(f, "{}:{}-{:02}", self.kind(), year, month),
+            _ => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = BudgetError;
+
+    fn from_str(input: &str) -> Result<Frequency> {
+        match input.split_once(':') {
+            Some((kind, month)) => Frequency::from_parts(kind, Some(month)),
+            None => Frequency::from_parts(input, None),
+        }
+    }
+}
+
+impl ToSql for Frequency {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for Frequency {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Frequency> {
+        String::column_result(value)?
+            .parse()
+            .map_err(|error| FromSqlError::Other(Box::new(error)))
+    }
+}
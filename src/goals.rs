@@ -0,0 +1,459 @@
+use crate::balance_sheet::{get_categories, get_relevant_items_cats};
+use crate::budget::get_relevant_items;
+use crate::currency::{list_exchange_rates, normalize_currency, snapshot_converter, Converter};
+use crate::error::{BudgetError, Result};
+use crate::snapshots::{list_snapshots, snapshot_date, snapshot_items_at};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use chrono::{Duration, Months};
+use rusqlite::types::Type;
+use rusqlite::{Connection, Row};
+
+/// The average number of days in a month, for turning a daily trend into a monthly one
+pub(crate) const DAYS_PER_MONTH: f64 = 365.25 / 12.0;
+
+/// Build a Goal (without its links) from a full row of the goals table
+fn goal_from_row(row: &Row) -> rusqlite::Result<Goal> {
+    let kind: String = row.get(4)?;
+    Ok(Goal {
+        id: row.get(0)?,
+        username_lower: row.get(1)?,
+        name: row.get(2)?,
+        kind: kind.parse().map_err(|error| {
+            rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(error))
+        })?,
+        target: row.get(5)?,
+        currency: row.get(6)?,
+        target_date: row.get(7)?,
+        start_value: row.get(8)?,
+        budget_item_original: row.get(9)?,
+        links: vec![],
+    })
+}
+
+/// Parse a target date given as YYYY-MM-DD, or as YYYY-MM for the last day of that month
+pub fn parse_target_date(date_text: &str) -> Result<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(date_text, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    NaiveDate::parse_from_str(&format!("{}-01", date_text), "%Y-%m-%d")
+        .ok()
+        .and_then(|first_day| first_day.checked_add_months(Months::new(1)))
+        .and_then(|next_month| next_month.pred_opt())
+        .ok_or_else(|| {
+            BudgetError::Parse(format!(
+                "{} is not a valid date. Use the format YYYY-MM or YYYY-MM-DD.",
+                date_text
+            ))
+        })
+}
+
+/// The total of the linked items in a set of assets and liabilities, converted into the converter's base currency
+/// An item is only counted once even if it is also in a linked category
+fn linked_value(
+    converter: &Converter,
+    links: &[GoalLink],
+    assets: &[Item],
+    liabilities: &[Item],
+) -> Result<Amount> {
+    let mut total = Amount::ZERO;
+    for item in assets.iter().chain(liabilities.iter()) {
+        let is_linked = links.iter().any(|link| match link {
+            GoalLink::Item {
+                is_asset,
+                item_original,
+            } => *is_asset == item.is_asset && *item_original == item.timeline_original,
+            GoalLink::Category {
+                is_asset,
+                category_lower,
+            } => *is_asset == item.is_asset && *category_lower == item.category_lower,
+        });
+        if is_linked {
+            total += converter.convert(item.value, &item.currency)?;
+        }
+    }
+    Ok(total)
+}
+
+/// The current value of a goal's links in the goal's currency
+fn current_linked_value(
+    conn: &Connection,
+    user: &User,
+    links: &[GoalLink],
+    currency: &str,
+) -> Result<Amount> {
+    let (_, assets) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    let (_, liabilities) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let converter = Converter::new(currency, list_exchange_rates(conn, user)?);
+    linked_value(&converter, links, &assets, &liabilities)
+}
+
+/// Create a goal measured by the given balance sheet items and categories
+/// A budget expense item can be given as the planned monthly contribution
+#[allow(clippy::too_many_arguments)]
+pub fn create_goal(
+    conn: &Connection,
+    user: &User,
+    name: &str,
+    kind: GoalKind,
+    target: Amount,
+    currency: &str,
+    target_date: Option<&str>,
+    links: &[GoalLink],
+    budget_item: Option<&BudgetItem>,
+) -> Result<Goal> {
+    if name.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The goal name cannot be empty",
+        )));
+    }
+    validate_item_value(target)?;
+    if links.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "A goal needs at least one balance sheet item or category",
+        )));
+    }
+    if budget_item.is_some_and(|item| item.is_income) {
+        return Err(BudgetError::Validation(String::from(
+            "A goal's contribution must be a budget expense item",
+        )));
+    }
+    let currency = normalize_currency(currency)?;
+    let target_date = match target_date {
+        Some(date_text) => Some(parse_target_date(date_text)?.format("%Y-%m-%d").to_string()),
+        None => None,
+    };
+
+    in_transaction(conn, |conn| {
+        if let Ok(goal) = find_goal(conn, user, name) {
+            return Err(BudgetError::Conflict(format!(
+                "This goal already exists as {}",
+                goal.name
+            )));
+        }
+        let start_value = current_linked_value(conn, user, links, &currency)?;
+        let budget_item_original = budget_item.map(|item| item.timeline_original);
+        conn.execute(
+            "INSERT INTO goals
+            (username_lower, name, name_lower, kind, target, currency, target_date, start_value, budget_item_original)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (
+                &user.username_lower,
+                name,
+                &name.to_lowercase(),
+                kind.to_str(),
+                target,
+                &currency,
+                &target_date,
+                start_value,
+                budget_item_original,
+            ),
+        )?;
+        let id = conn.last_insert_rowid();
+        for link in links {
+            let (is_asset, item_original, category_lower) = match link {
+                GoalLink::Item {
+                    is_asset,
+                    item_original,
+                } => (*is_asset, Some(*item_original), None),
+                GoalLink::Category {
+                    is_asset,
+                    category_lower,
+                } => (*is_asset, None, Some(category_lower)),
+            };
+            conn.execute(
+                "INSERT INTO goal_links (goal_id, username_lower, is_asset, item_original, category_lower)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+                (id, &user.username_lower, is_asset, item_original, category_lower),
+            )?;
+        }
+        Ok(Goal {
+            id,
+            username_lower: String::from(&user.username_lower),
+            name: String::from(name),
+            kind,
+            target,
+            currency,
+            target_date,
+            start_value,
+            budget_item_original,
+            links: links.to_vec(),
+        })
+    })
+}
+
+/// Get the items and categories a goal is measured by
+fn get_goal_links(conn: &Connection, goal_id: i64) -> Result<Vec<GoalLink>> {
+    let mut links: Vec<GoalLink> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT is_asset, item_original, category_lower FROM goal_links WHERE goal_id = ?1",
+    )?;
+    let mut rows = stmt.query([goal_id])?;
+    while let Some(row) = rows.next()? {
+        let is_asset: bool = row.get(0)?;
+        match (row.get::<_, Option<usize>>(1)?, row.get(2)?) {
+            (Some(item_original), _) => links.push(GoalLink::Item {
+                is_asset,
+                item_original,
+            }),
+            (None, Some(category_lower)) => links.push(GoalLink::Category {
+                is_asset,
+                category_lower,
+            }),
+            (None, None) => {}
+        }
+    }
+    Ok(links)
+}
+
+/// Get all of a user's goals in the order they were made
+pub fn list_goals(conn: &Connection, user: &User) -> Result<Vec<Goal>> {
+    let mut goals: Vec<Goal> = vec![];
+    let mut stmt = conn.prepare("SELECT * FROM goals WHERE username_lower = ?1 ORDER BY id")?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        goals.push(goal_from_row(row)?);
+    }
+    for goal in &mut goals {
+        goal.links = get_goal_links(conn, goal.id)?;
+    }
+    Ok(goals)
+}
+
+/// Find a goal by name (case insensitive)
+pub fn find_goal(conn: &Connection, user: &User, name: &str) -> Result<Goal> {
+    list_goals(conn, user)?
+        .into_iter()
+        .find(|goal| goal.name.to_lowercase() == name.to_lowercase())
+        .ok_or_else(|| BudgetError::NotFound(format!("The goal {} was not found", name)))
+}
+
+/// Change (or remove with None) the budget expense item that pays into a goal
+pub fn set_goal_budget_item(
+    conn: &Connection,
+    user: &User,
+    goal: &Goal,
+    budget_item: Option<&BudgetItem>,
+) -> Result<Goal> {
+    if budget_item.is_some_and(|item| item.is_income) {
+        return Err(BudgetError::Validation(String::from(
+            "A goal's contribution must be a budget expense item",
+        )));
+    }
+    let budget_item_original = budget_item.map(|item| item.timeline_original);
+    conn.execute(
+        "UPDATE goals SET budget_item_original = ?1 WHERE id = ?2 AND username_lower = ?3",
+        (budget_item_original, goal.id, &user.username_lower),
+    )?;
+    let mut goal = goal.clone();
+    goal.budget_item_original = budget_item_original;
+    Ok(goal)
+}
+
+/// Permanently remove a goal
+/// The balance sheet and budget items it was linked to are left alone
+pub fn delete_goal(conn: &Connection, user: &User, goal: &Goal) -> Result<()> {
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "DELETE FROM goal_links WHERE goal_id = ?1 AND username_lower = ?2",
+            (goal.id, &user.username_lower),
+        )?;
+        let num_deleted = conn.execute(
+            "DELETE FROM goals WHERE id = ?1 AND username_lower = ?2",
+            (goal.id, &user.username_lower),
+        )?;
+        if num_deleted == 0 {
+            return Err(BudgetError::NotFound(format!(
+                "The goal {} was not found",
+                goal.name
+            )));
+        }
+        Ok(())
+    })
+}
+
+/// A name for a goal link, such as "Savings Account" or "Investments (category)"
+/// A linked item that has since been deleted is shown as such
+pub fn describe_link(conn: &Connection, user: &User, link: &GoalLink) -> Result<String> {
+    match link {
+        GoalLink::Item {
+            is_asset,
+            item_original,
+        } => {
+            let which_half = if *is_asset {
+                BalanceSheetHalf::Assets
+            } else {
+                BalanceSheetHalf::Liabilities
+            };
+            let (_, items) = get_relevant_items_cats(conn, user, &which_half)?;
+            Ok(items
+                .into_iter()
+                .find(|item| item.timeline_original == *item_original)
+                .map(|item| item.item)
+                .unwrap_or_else(|| String::from("(deleted item)")))
+        }
+        GoalLink::Category {
+            is_asset,
+            category_lower,
+        } => {
+            let which_half = if *is_asset {
+                BalanceSheetHalf::Assets
+            } else {
+                BalanceSheetHalf::Liabilities
+            };
+            let name = get_categories(conn, user, &which_half)?
+                .into_iter()
+                .find(|category| category.category_lower == *category_lower)
+                .map(|category| category.category)
+                .unwrap_or_else(|| category_lower.clone());
+            Ok(format!(
+                "{} ({} category)",
+                name,
+                if *is_asset { "asset" } else { "liability" }
+            ))
+        }
+    }
+}
+
+/// Work out how far along a goal is as of today
+/// The linked value is measured at every snapshot (with the rates frozen in it) and now
+/// The projection follows the least squares trend line through those values
+pub fn goal_progress(
+    conn: &Connection,
+    user: &User,
+    goal: &Goal,
+    today: NaiveDate,
+) -> Result<GoalProgress> {
+    let current = current_linked_value(conn, user, &goal.links, &goal.currency)?;
+
+    let mut history: Vec<(String, Amount)> = vec![];
+    let mut points: Vec<(f64, f64)> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        let date = snapshot_date(&snapshot)?;
+        let converter = snapshot_converter(conn, user, &snapshot, &goal.currency)?;
+        let (_, assets) =
+            snapshot_items_at(conn, user, &BalanceSheetHalf::Assets, snapshot.timeline)?;
+        let (_, liabilities) = snapshot_items_at(
+            conn,
+            user,
+            &BalanceSheetHalf::Liabilities,
+            snapshot.timeline,
+        )?;
+        let value = linked_value(&converter, &goal.links, &assets, &liabilities)?;
+        points.push(((date - today).num_days() as f64, value.to_f64()));
+        history.push((snapshot.date_today, value));
+    }
+    points.push((0.0, current.to_f64()));
+
+    // What is still to go, positive while the goal hasn't been reached
+    let to_go = match goal.kind {
+        GoalKind::Save => goal.target - current,
+        GoalKind::PayOff => current - goal.target,
+    };
+    let reached = to_go.is_negative() || to_go == Amount::ZERO;
+    // A payoff goal's progress is measured from the most that was owed since snapshots began
+    let highest = history
+        .iter()
+        .map(|(_, value)| *value)
+        .fold(goal.start_value.max(current), Amount::max);
+    let percent = match goal.kind {
+        GoalKind::Save if goal.target != Amount::ZERO => {
+            current.to_f64() / goal.target.to_f64() * 100.0
+        }
+        GoalKind::PayOff if highest > goal.target => {
+            (highest - current).to_f64() / (highest - goal.target).to_f64() * 100.0
+        }
+        _ if reached => 100.0,
+        _ => 0.0,
+    }
+    .clamp(0.0, 100.0);
+
+    // The trend has to be moving towards the target for there to be a projection
+    let projected_date = match (reached, trend_per_day(&points)) {
+        (false, Some(slope)) => {
+            let towards = match goal.kind {
+                GoalKind::Save => slope,
+                GoalKind::PayOff => -slope,
+            };
+            if towards > 0.0 {
+                let days = (to_go.to_f64() / towards).ceil();
+                // Far enough out to be meaningless is treated as never
+                if days < 365.0 * 200.0 {
+                    today.checked_add_signed(Duration::days(days as i64))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+
+    let monthly_needed = match &goal.target_date {
+        Some(date_text) if !reached => {
+            let target_date = parse_target_date(date_text)?;
+            let days_left = (target_date - today).num_days();
+            // Once the date has passed, everything left is needed this month
+            let months_left = ((days_left as f64 / DAYS_PER_MONTH).ceil() as i64).max(1);
+            Some(to_go.scale(1, months_left))
+        }
+        _ => None,
+    };
+
+    let planned_contribution = match goal.budget_item_original {
+        Some(original) => {
+            let (_, items) = get_relevant_items(conn, user, &BudgetHalf::Expenses)?;
+            match items.iter().find(|item| item.timeline_original == original) {
+                Some(item) => {
+                    let converter =
+                        Converter::new(&goal.currency, list_exchange_rates(conn, user)?);
+                    Some(converter.convert(item.monthly_value(), &item.currency)?)
+                }
+                None => None,
+            }
+        }
+        None => None,
+    };
+    let planned_date = match planned_contribution {
+        Some(monthly) if !reached && monthly.minor() > 0 => {
+            let months = (to_go.minor() + monthly.minor() - 1) / monthly.minor();
+            u32::try_from(months)
+                .ok()
+                .and_then(|months| today.checked_add_months(Months::new(months)))
+        }
+        _ => None,
+    };
+
+    Ok(GoalProgress {
+        current,
+        percent,
+        reached,
+        history,
+        projected_date,
+        monthly_needed,
+        planned_contribution,
+        planned_date,
+    })
+}
+
+/// The slope (change in value per day) of the least squares line through the points
+/// None when there are fewer than 2 points or they are all on the same day
+pub(crate) fn trend_per_day(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for (x, y) in points {
+        numerator += (x - mean_x) * (y - mean_y);
+        denominator += (x - mean_x) * (x - mean_x);
+    }
+    if denominator == 0.0 {
+        return None;
+    }
+    Some(numerator / denominator)
+}
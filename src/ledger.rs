@@ -0,0 +1,163 @@
+use crate::encryption::unlock;
+use crate::error::{BudgetError, Result};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that points at the database file to use
+pub const DB_ENV_VAR: &str = "BURKEBUDGET_DB";
+
+/// Ledger used when none is chosen
+pub const DEFAULT_LEDGER: &str = "default";
+
+/// File name the app used before ledgers, relative to the working directory
+pub const LEGACY_DB_PATH: &str = "burkebudgetDB.db";
+
+/// Ledger files end with this extension inside the data directory
+const LEDGER_EXTENSION: &str = "db";
+
+/// Maximum number of characters in a ledger name
+pub const MAX_CHARACTERS_LEDGER_NAME: usize = 30;
+
+/// Directory that holds the named ledgers
+/// $XDG_DATA_HOME/burkebudget, or ~/.local/share/burkebudget when that isn't set
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(xdg) = std::env::var_os("XDG_DATA_HOME") {
+        let xdg = PathBuf::from(xdg);
+        // The XDG spec says relative paths should be ignored
+        if xdg.is_absolute() {
+            return Ok(xdg.join("burkebudget"));
+        }
+    }
+    match std::env::var_os("HOME") {
+        Some(home) if !home.is_empty() => Ok(PathBuf::from(home)
+            .join(".local")
+            .join("share")
+            .join("burkebudget")),
+        _ => Err(BudgetError::NotFound(format!(
+            "Could not find a home directory for the ledgers. Set HOME, or pass --db <PATH> or {}",
+            DB_ENV_VAR
+        ))),
+    }
+}
+
+/// Check that a ledger name is safe to use as a file name
+pub fn validate_ledger_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The ledger name cannot be empty",
+        )));
+    }
+    if name.len() > MAX_CHARACTERS_LEDGER_NAME {
+        return Err(BudgetError::Validation(format!(
+            "The ledger name must be {} characters or less",
+            MAX_CHARACTERS_LEDGER_NAME
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(BudgetError::Validation(String::from(
+            "The ledger name can only use letters, numbers, - and _",
+        )));
+    }
+    Ok(())
+}
+
+/// Path of the database file for a named ledger (eg - household is household.db in the data directory)
+pub fn ledger_path(name: &str) -> Result<PathBuf> {
+    let name = name.trim().to_lowercase();
+    validate_ledger_name(&name)?;
+    Ok(data_dir()?.join(format!("{}.{}", name, LEDGER_EXTENSION)))
+}
+
+/// Names of the ledgers in the data directory, in alphabetical order
+pub fn list_ledgers() -> Result<Vec<String>> {
+    let dir = data_dir()?;
+    let mut ledgers: Vec<String> = vec![];
+    // No directory just means no ledgers have been made yet
+    if !dir.is_dir() {
+        return Ok(ledgers);
+    }
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some(LEDGER_EXTENSION) {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|n| n.to_str()) {
+            if validate_ledger_name(name).is_ok() {
+                ledgers.push(String::from(name));
+            }
+        }
+    }
+    ledgers.sort();
+    Ok(ledgers)
+}
+
+/// The name of the ledger stored at this path, if it is one of the named ledgers
+pub fn ledger_name(path: &Path) -> Option<String> {
+    let dir = data_dir().ok()?;
+    if path.parent()? != dir.as_path() {
+        return None;
+    }
+    if path.extension()?.to_str()? != LEDGER_EXTENSION {
+        return None;
+    }
+    Some(String::from(path.file_stem()?.to_str()?))
+}
+
+/// Work out which database file to open
+/// In order: --db, --ledger, the BURKEBUDGET_DB environment variable, then the default ledger
+pub fn resolve_db_path(db: Option<&Path>, ledger: Option<&str>) -> Result<PathBuf> {
+    if let Some(db) = db {
+        return Ok(db.to_path_buf());
+    }
+    if let Some(ledger) = ledger {
+        return ledger_path(ledger);
+    }
+    if let Some(db) = std::env::var_os(DB_ENV_VAR) {
+        if !db.is_empty() {
+            return Ok(PathBuf::from(db));
+        }
+    }
+    ledger_path(DEFAULT_LEDGER)
+}
+
+/// Copy a database from the old location in the working directory to the default ledger
+/// Only happens when the default ledger doesn't exist yet, and the old file is left in place
+/// Returns true if a copy was made
+pub fn adopt_legacy_db(path: &Path) -> Result<bool> {
+    let legacy = Path::new(LEGACY_DB_PATH);
+    if path.exists() || !legacy.is_file() || path != ledger_path(DEFAULT_LEDGER)? {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(legacy, path)?;
+    Ok(true)
+}
+
+/// Open (or create) the database file at the path with foreign keys turned on
+/// An encrypted database is unlocked with the passphrase (a new file given a passphrase is created encrypted)
+pub fn open_db(path: &Path, passphrase: Option<&str>) -> Result<Connection> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)?;
+        }
+    }
+    let conn = Connection::open(path)?;
+    if let Some(passphrase) = passphrase {
+        unlock(&conn, passphrase)?;
+    }
+    conn.execute("PRAGMA foreign_keys = ON", ())?;
+    Ok(conn)
+}
+
+/// The ledger name if the path is a named ledger, otherwise the full path
+pub fn ledger_display_name(path: &Path) -> String {
+    match ledger_name(path) {
+        Some(name) => name,
+        None => path.display().to_string(),
+    }
+}
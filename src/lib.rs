@@ -0,0 +1,57 @@
+//! The Burke Budget library
+//!
+//! All of the database logic behind the Burke Budget app lives here without any prompting or printing,
+//! so it can be embedded in other tools. The interactive menus and the CLI in the binary are thin front-ends over it.
+//!
+//! - `users` - Login and signup
+//! - `auth` - Optional salted password hashes and locking an account after repeated wrong passwords
+//! - `balance_sheet` - Balance sheet categories and versioned items
+//! - `snapshots` - Balance sheet snapshots and reconstructing the balance sheet at a snapshot
+//! - `loans` - Loan terms on liabilities, amortization schedules and projected balances
+//! - `debts` - Interest rates and minimum payments on liabilities, and avalanche and snowball payoff plans
+//! - `forecast` - Net worth forecasts from the snapshot trend or assumed returns, and financial independence numbers
+//! - `goals` - Savings and payoff goals measured by balance sheet items, with progress and projections
+//! - `budget` - Budget categories and versioned items
+//! - `frequency` - How often a budget item comes in or goes out, and its monthly equivalent
+//! - `budget_months` - Month by month budgets started from the current budget, with envelope rollover
+//! - `budget_snapshots` - Budget snapshots and reconstructing the budget at any point on its timeline
+//! - `transactions` - Actual dated income and expenses and the monthly budget vs. actual report
+//! - `statements` - Importing OFX, QFX and QIF bank statements as transactions, with payee rules and account balances
+//! - `money` - Exact money amounts stored as integer cents
+//! - `currency` - Item currencies, exchange rates and converting totals into a user's base currency
+//! - `backup` - Versioned JSON backups of everything belonging to a user, and restoring them under any free username
+//! - `csv_io` - Exporting items, budgets, snapshots and item history to CSV, and checked CSV imports of items
+//! - `report` - Standalone HTML and Markdown reports of the balance sheet, budget, chosen snapshots and net worth trend
+//! - `plain_text` - Exporting the balance sheet history and budget to ledger, hledger and beancount, and importing their balances as snapshots
+//! - `ledger` - Finding the database file and the named ledgers in the data directory
+//! - `encryption` - Opening, creating and rekeying SQLCipher encrypted databases
+//! - `migrations` - Versioned schema migrations, run with `migrations::migrate` after opening a database
+
+pub mod auth;
+pub mod backup;
+pub mod balance_sheet;
+pub mod budget;
+pub mod budget_months;
+pub mod budget_snapshots;
+pub mod csv_io;
+pub mod currency;
+pub mod debts;
+pub mod encryption;
+pub mod error;
+pub mod forecast;
+pub mod frequency;
+pub mod goals;
+pub mod ledger;
+pub mod loans;
+pub mod migrations;
+pub mod money;
+pub mod plain_text;
+pub mod report;
+pub mod snapshots;
+pub mod statements;
+pub mod structs_utils;
+pub mod transactions;
+pub mod users;
+
+pub use error::BudgetError;
+pub use money::Amount;
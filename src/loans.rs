@@ -0,0 +1,438 @@
+use crate::currency::snapshot_converter;
+use crate::error::{BudgetError, Result};
+use crate::money::MINOR_PER_MAJOR;
+use crate::snapshots::{list_snapshots, snapshot_date, snapshot_items_at};
+use crate::structs_utils::*;
+use crate::transactions::parse_date;
+use chrono::prelude::*;
+use chrono::Months;
+use rusqlite::types::Type;
+use rusqlite::{Connection, OptionalExtension, Row};
+use rust_decimal::prelude::*;
+use std::str::FromStr;
+
+/// The most payments a schedule can have (100 years), so a payment that barely covers the interest still ends
+pub(crate) const MAX_PAYMENTS: u32 = 1200;
+
+/// Build a Loan from a full row of the loan_terms table
+fn loan_from_row(row: &Row) -> rusqlite::Result<Loan> {
+    let apr: String = row.get(3)?;
+    Ok(Loan {
+        username_lower: row.get(0)?,
+        item_original: row.get(1)?,
+        principal: row.get(2)?,
+        apr: parse_apr(&apr).map_err(|error| {
+            rusqlite::Error::FromSqlConversionFailure(3, Type::Text, Box::new(error))
+        })?,
+        term_months: row.get(4)?,
+        payment: row.get(5)?,
+        start_date: row.get(6)?,
+        currency: row.get(7)?,
+    })
+}
+
+/// Parse an annual percentage rate such as "6.5" and make sure it is from 0 up to 100
+pub fn parse_apr(apr: &str) -> Result<Decimal> {
+    let apr = Decimal::from_str(apr.trim().trim_end_matches('%'))
+        .map_err(|_| BudgetError::Parse(format!("{} is not a valid interest rate", apr)))?;
+    if apr < Decimal::ZERO || apr >= Decimal::ONE_HUNDRED {
+        return Err(BudgetError::Validation(String::from(
+            "The interest rate must be a percentage from 0 up to 100",
+        )));
+    }
+    Ok(apr)
+}
+
+/// Round a decimal number of major units to a whole number of cents
+pub(crate) fn decimal_to_amount(value: Decimal, strategy: RoundingStrategy) -> Result<Amount> {
+    (value * Decimal::from(MINOR_PER_MAJOR))
+        .round_dp_with_strategy(0, strategy)
+        .to_i64()
+        .map(Amount::from_minor)
+        .ok_or_else(|| BudgetError::Validation(String::from("The amount is too large")))
+}
+
+/// The monthly interest rate as a fraction (eg - 6% a year is 0.005 a month)
+pub(crate) fn monthly_rate(apr: Decimal) -> Decimal {
+    apr / Decimal::from(1200)
+}
+
+/// The fixed monthly payment that pays off the principal in term_months payments
+/// Rounded up to the cent so the loan is never left with a last payment of a few cents
+pub fn standard_payment(principal: Amount, apr: Decimal, term_months: u32) -> Result<Amount> {
+    let principal = Decimal::new(principal.minor(), 2);
+    let rate = monthly_rate(apr);
+    if rate == Decimal::ZERO {
+        return decimal_to_amount(
+            principal / Decimal::from(term_months),
+            RoundingStrategy::AwayFromZero,
+        );
+    }
+    let mut growth = Decimal::ONE;
+    for _ in 0..term_months {
+        growth = growth
+            .checked_mul(Decimal::ONE + rate)
+            .ok_or_else(|| BudgetError::Validation(String::from("The loan term is too long")))?;
+    }
+    decimal_to_amount(
+        principal * rate * growth / (growth - Decimal::ONE),
+        RoundingStrategy::AwayFromZero,
+    )
+}
+
+/// Attach loan terms to a liability, replacing any it already has
+/// Without a payment, the standard payment that pays it off over the term is used
+#[allow(clippy::too_many_arguments)]
+pub fn set_loan_terms(
+    conn: &Connection,
+    user: &User,
+    item: &Item,
+    principal: Amount,
+    apr: Decimal,
+    term_months: u32,
+    payment: Option<Amount>,
+    start_date: &str,
+) -> Result<Loan> {
+    if item.is_asset {
+        return Err(BudgetError::Validation(String::from(
+            "Only liabilities can have loan terms",
+        )));
+    }
+    if principal <= Amount::ZERO {
+        return Err(BudgetError::Validation(String::from(
+            "The amount borrowed must be greater than 0",
+        )));
+    }
+    if term_months == 0 || term_months > MAX_PAYMENTS {
+        return Err(BudgetError::Validation(format!(
+            "The term must be from 1 to {} months",
+            MAX_PAYMENTS
+        )));
+    }
+    if payment.is_some_and(|payment| payment <= Amount::ZERO) {
+        return Err(BudgetError::Validation(String::from(
+            "The monthly payment must be greater than 0",
+        )));
+    }
+    let start_date = parse_date(start_date)?.format("%Y-%m-%d").to_string();
+    let payment = match payment {
+        Some(payment) => payment,
+        None => standard_payment(principal, apr, term_months)?,
+    };
+    let loan = Loan {
+        username_lower: String::from(&user.username_lower),
+        item_original: item.timeline_original,
+        principal,
+        apr,
+        term_months,
+        payment,
+        start_date,
+        currency: String::from(&item.currency),
+    };
+    // Make sure the payment pays the loan off before storing it
+    amortization_schedule(&loan, Amount::ZERO)?;
+
+    in_transaction(conn, |conn| {
+        conn.execute(
+            "DELETE FROM loan_terms WHERE username_lower = ?1 AND item_original = ?2",
+            (&user.username_lower, &loan.item_original),
+        )?;
+        conn.execute(
+            "INSERT INTO loan_terms
+            (username_lower, item_original, principal, apr, term_months, payment, start_date, currency)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                &loan.username_lower,
+                &loan.item_original,
+                &loan.principal,
+                loan.apr.to_string(),
+                &loan.term_months,
+                &loan.payment,
+                &loan.start_date,
+                &loan.currency,
+            ),
+        )?;
+        Ok(())
+    })?;
+    Ok(loan)
+}
+
+/// Get the loan terms of a liability, if it has any
+pub fn get_loan(conn: &Connection, user: &User, item: &Item) -> Result<Option<Loan>> {
+    Ok(conn
+        .query_row(
+            "SELECT * FROM loan_terms WHERE username_lower = ?1 AND item_original = ?2",
+            (&user.username_lower, &item.timeline_original),
+            loan_from_row,
+        )
+        .optional()?)
+}
+
+/// Get the loan terms of every liability that has them
+pub fn list_loans(conn: &Connection, user: &User) -> Result<Vec<Loan>> {
+    let mut loans: Vec<Loan> = vec![];
+    let mut stmt =
+        conn.prepare("SELECT * FROM loan_terms WHERE username_lower = ?1 ORDER BY item_original")?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        loans.push(loan_from_row(row)?);
+    }
+    Ok(loans)
+}
+
+/// Remove the loan terms from a liability. The item itself is left alone.
+pub fn delete_loan_terms(conn: &Connection, user: &User, item: &Item) -> Result<()> {
+    let num_deleted = conn.execute(
+        "DELETE FROM loan_terms WHERE username_lower = ?1 AND item_original = ?2",
+        (&user.username_lower, &item.timeline_original),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "{} doesn't have loan terms",
+            item.item
+        )));
+    }
+    Ok(())
+}
+
+/// The date of a payment, counting the first payment as number 1
+fn payment_date(loan: &Loan, number: u32) -> Result<NaiveDate> {
+    parse_date(&loan.start_date)?
+        .checked_add_months(Months::new(number))
+        .ok_or_else(|| BudgetError::Validation(String::from("The date is out of range")))
+}
+
+/// Every payment from the first until the loan is paid off
+/// The extra is paid on top of the regular payment each month, and the last payment is only what is left
+/// Interest is charged monthly on the remaining balance and rounded to the cent
+pub fn amortization_schedule(loan: &Loan, extra: Amount) -> Result<Vec<LoanPayment>> {
+    if extra.is_negative() {
+        return Err(BudgetError::Validation(String::from(
+            "The extra payment cannot be negative",
+        )));
+    }
+    let rate = monthly_rate(loan.apr);
+    let regular = loan.payment + extra;
+    let mut schedule: Vec<LoanPayment> = vec![];
+    let mut balance = loan.principal;
+    let mut number: u32 = 0;
+    while balance > Amount::ZERO {
+        number += 1;
+        if number > MAX_PAYMENTS {
+            return Err(BudgetError::Validation(format!(
+                "At {} a month the loan wouldn't be paid off within {} years",
+                to_money_string(regular, &loan.currency),
+                MAX_PAYMENTS / 12
+            )));
+        }
+        let interest = decimal_to_amount(
+            Decimal::new(balance.minor(), 2) * rate,
+            RoundingStrategy::MidpointAwayFromZero,
+        )?;
+        if regular <= interest {
+            return Err(BudgetError::Validation(format!(
+                "A payment of {} doesn't cover the {} of monthly interest",
+                to_money_string(regular, &loan.currency),
+                to_money_string(interest, &loan.currency)
+            )));
+        }
+        let payment = regular.min(balance + interest);
+        let principal = payment - interest;
+        balance -= principal;
+        schedule.push(LoanPayment {
+            number,
+            date: payment_date(loan, number)?,
+            payment,
+            interest,
+            principal,
+            balance,
+        });
+    }
+    Ok(schedule)
+}
+
+/// The payoff date, number of payments and total interest of a schedule
+pub fn loan_summary(loan: &Loan, extra: Amount) -> Result<LoanSummary> {
+    let schedule = amortization_schedule(loan, extra)?;
+    Ok(LoanSummary {
+        payment: loan.payment + extra,
+        num_payments: schedule.len() as u32,
+        payoff_date: match schedule.last() {
+            Some(last) => last.date,
+            None => parse_date(&loan.start_date)?,
+        },
+        total_interest: schedule.iter().map(|payment| payment.interest).sum(),
+    })
+}
+
+/// What the loan terms say is still owed on a date, after every payment made by then
+pub fn projected_balance(loan: &Loan, date: NaiveDate, extra: Amount) -> Result<Amount> {
+    let mut balance = loan.principal;
+    for payment in amortization_schedule(loan, extra)? {
+        if payment.date > date {
+            break;
+        }
+        balance = payment.balance;
+    }
+    Ok(balance)
+}
+
+/// The value recorded for the liability at each snapshot since the loan started, next to the projected balance
+/// Recorded values in another currency are converted into the loan's currency at the snapshot's rates
+pub fn loan_checks(conn: &Connection, user: &User, loan: &Loan) -> Result<Vec<LoanCheck>> {
+    let start_date = parse_date(&loan.start_date)?;
+    let schedule = amortization_schedule(loan, Amount::ZERO)?;
+    let mut checks: Vec<LoanCheck> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        let date = snapshot_date(&snapshot)?;
+        if date < start_date {
+            continue;
+        }
+        let (_, items) = snapshot_items_at(
+            conn,
+            user,
+            &BalanceSheetHalf::Liabilities,
+            snapshot.timeline,
+        )?;
+        let Some(item) = items
+            .iter()
+            .find(|item| item.timeline_original == loan.item_original)
+        else {
+            continue;
+        };
+        let converter = snapshot_converter(conn, user, &snapshot, &loan.currency)?;
+        let projected = schedule
+            .iter()
+            .take_while(|payment| payment.date <= date)
+            .last()
+            .map_or(loan.principal, |payment| payment.balance);
+        checks.push(LoanCheck {
+            date_text: snapshot.date_today.clone(),
+            recorded: converter.convert(item.value, &item.currency)?,
+            projected,
+        });
+    }
+    Ok(checks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A loan in dollars with the given terms whose first payment is a month after 2024-01-31
+    fn test_loan(principal: Amount, apr: &str, term_months: u32, payment: Amount) -> Loan {
+        Loan {
+            username_lower: String::from("alice"),
+            item_original: 1,
+            principal,
+            apr: parse_apr(apr).unwrap(),
+            term_months,
+            payment,
+            start_date: String::from("2024-01-31"),
+            currency: String::from("USD"),
+        }
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn final_payment_is_only_what_is_left() {
+        let principal = Amount::from_major(100);
+        let payment = standard_payment(principal, parse_apr("12").unwrap(), 2).unwrap();
+        assert_eq!(payment, Amount::from_minor(5076));
+        let schedule =
+            amortization_schedule(&test_loan(principal, "12", 2, payment), Amount::ZERO).unwrap();
+        assert_eq!(
+            schedule,
+            vec![
+                LoanPayment {
+                    number: 1,
+                    date: date(2024, 2, 29),
+                    payment: Amount::from_minor(5076),
+                    interest: Amount::from_minor(100),
+                    principal: Amount::from_minor(4976),
+                    balance: Amount::from_minor(5024),
+                },
+                // Interest of 0.5024 rounds to 0.50, so the last payment is 2 cents short of the others
+                LoanPayment {
+                    number: 2,
+                    date: date(2024, 3, 31),
+                    payment: Amount::from_minor(5074),
+                    interest: Amount::from_minor(50),
+                    principal: Amount::from_minor(5024),
+                    balance: Amount::ZERO,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn standard_payment_pays_off_over_the_term() {
+        let principal = Amount::from_major(25000);
+        let payment = standard_payment(principal, parse_apr("6.5").unwrap(), 60).unwrap();
+        let schedule =
+            amortization_schedule(&test_loan(principal, "6.5", 60, payment), Amount::ZERO).unwrap();
+        assert_eq!(schedule.len(), 60);
+        let last = schedule.last().unwrap();
+        assert_eq!(last.balance, Amount::ZERO);
+        assert!(last.payment <= payment);
+        assert!(schedule[..59].iter().all(|line| line.payment == payment));
+        assert_eq!(
+            schedule.iter().map(|line| line.principal).sum::<Amount>(),
+            principal
+        );
+    }
+
+    #[test]
+    fn zero_rate() {
+        let principal = Amount::from_major(1000);
+        let payment = standard_payment(principal, Decimal::ZERO, 3).unwrap();
+        assert_eq!(payment, Amount::from_minor(33334));
+        let schedule =
+            amortization_schedule(&test_loan(principal, "0", 3, payment), Amount::ZERO).unwrap();
+        let summary: Vec<(NaiveDate, i64, i64, i64)> = schedule
+            .iter()
+            .map(|line| {
+                (
+                    line.date,
+                    line.payment.minor(),
+                    line.interest.minor(),
+                    line.balance.minor(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (date(2024, 2, 29), 33334, 0, 66666),
+                (date(2024, 3, 31), 33334, 0, 33332),
+                (date(2024, 4, 30), 33332, 0, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn extra_payment_shortens_the_schedule() {
+        let loan = test_loan(Amount::from_major(1000), "0", 4, Amount::from_major(250));
+        let schedule = amortization_schedule(&loan, Amount::from_major(250)).unwrap();
+        assert_eq!(schedule.len(), 2);
+        assert_eq!(schedule[1].payment, Amount::from_major(500));
+        assert_eq!(schedule[1].balance, Amount::ZERO);
+    }
+
+    #[test]
+    fn schedule_errors() {
+        let message = |result: Result<Vec<LoanPayment>>| result.unwrap_err().to_string();
+        let loan = test_loan(Amount::from_major(1000), "12", 12, Amount::from_major(10));
+        assert_eq!(
+            message(amortization_schedule(&loan, Amount::ZERO)),
+            "A payment of $10.00 doesn't cover the $10.00 of monthly interest"
+        );
+        assert_eq!(
+            message(amortization_schedule(&loan, Amount::from_minor(-1))),
+            "The extra payment cannot be negative"
+        );
+    }
+}
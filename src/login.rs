@@ -1,23 +1,12 @@
-use crate::structs_utils::*;
-use rusqlite::{Connection, Result};
+use crate::menu::read_password;
+use burkebudget::auth::{authenticate, change_password, ensure_unlocked, MIN_PASSWORD_LENGTH};
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
+use burkebudget::users::{create_user, find_user, list_users, purge_user, restore_user};
+use rusqlite::Connection;
 
 /// Login a user and return that user as a result
 pub fn login(conn: &Connection) -> Result<User> {
-    // Create the users table if it doesn't exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS users (
-            username TEXT NOT NULL,
-            username_lower TEXT NOT NULL,
-            firstname TEXT NOT NULL,
-            lastname TEXT NOT NULL,
-            is_deleted INTEGER NOT NULL,
-            PRIMARY KEY (username_lower)
-        )",
-        (), // empty list of parameters.
-    )
-    .expect("Error connecting with the list of users");
-    // It would be unrecoverable if it can't connect with the users table
-
     // Get username from user
     println!("Enter username to login or signup:");
     let mut username: String = read_or_quit();
@@ -30,17 +19,11 @@ pub fn login(conn: &Connection) -> Result<User> {
     }
 
     // Check if the username is already in the database
-    let mut stmt = conn.prepare("SELECT * FROM users WHERE username_lower = ?1")?;
-    let mut rows = stmt.query(rusqlite::params![username.to_lowercase()])?;
-    match rows.next()? {
-        // Username found, return User to main function
-        Some(row) => Ok(User {
-            username: row.get(0)?,
-            username_lower: row.get(1)?,
-            firstname: row.get(2)?,
-            lastname: row.get(3)?,
-            is_deleted: row.get(4)?,
-        }),
+    match find_user(conn, &username)? {
+        // A deleted user can be restored or permanently deleted
+        Some(user) if user.is_deleted => restoreorpurge(conn, user),
+        // Username found, check their password and return User to main function
+        Some(user) => enter_password(conn, user),
         // Username not found, may need to see list or signup
         None => {
             println!("\nThat user was not found.");
@@ -48,11 +31,7 @@ pub fn login(conn: &Connection) -> Result<User> {
                 // View list of users and choose one or signup
                 "view" => chooseorsignup(conn, username),
                 // Sign up the user
-                "signup" => signup(conn, username),
-                // Should not get any other responses
-                _ => {
-                    panic!("Error: Unknown response from view or signup function");
-                }
+                _ => signup(conn, username),
             }
         }
     }
@@ -83,18 +62,11 @@ fn vieworsignup(username: &String) -> String {
 /// User will choose one or choose to signup instead
 fn chooseorsignup(conn: &Connection, username: String) -> Result<User> {
     // Push all of the users into a vector as may need to re-use
-    let mut users: Vec<User> = vec![];
-    let mut stmt = conn.prepare("SELECT * FROM users")?;
-    let mut rows = stmt.query(rusqlite::params![])?;
-    while let Some(row) = rows.next()? {
-        users.push(User {
-            username: row.get(0)?,
-            username_lower: row.get(1)?,
-            firstname: row.get(2)?,
-            lastname: row.get(3)?,
-            is_deleted: row.get(4)?,
-        })
-    }
+    // Deleted users are left out (they can still be restored by typing their username)
+    let mut users: Vec<User> = list_users(conn)?
+        .into_iter()
+        .filter(|user| !user.is_deleted)
+        .collect();
 
     if users.is_empty() {
         println!("\nYou are the first user to sign up!");
@@ -124,7 +96,64 @@ fn chooseorsignup(conn: &Connection, username: String) -> Result<User> {
     if selection == 0 {
         signup(conn, username)
     } else {
-        Ok(users.remove(selection - 1))
+        enter_password(conn, users.remove(selection - 1))
+    }
+}
+
+/// Offer to restore a deleted user or to delete them permanently
+/// The password is needed either way
+fn restoreorpurge(conn: &Connection, user: User) -> Result<User> {
+    println!("\n{} has been deleted.", user.username);
+    match print_instr_get_response(1, 3, || {
+        println!("Would you like to: (Enter the number)");
+        println!("1 - Restore {} and login", user.username);
+        println!(
+            "2 - Permanently delete {} and everything in it",
+            user.username
+        );
+        println!("3 - Start over");
+    }) {
+        1 => {
+            let user = enter_password(conn, user)?;
+            restore_user(conn, &user)
+        }
+        2 => {
+            let user = enter_password(conn, user)?;
+            println!(
+                "\nThis cannot be undone. Type the username {} to permanently delete it:",
+                user.username
+            );
+            if read_or_quit() == user.username {
+                purge_user(conn, &user)?;
+                println!("\n{} has been permanently deleted.\n", user.username);
+            } else {
+                println!("\nThe username didn't match, so nothing was deleted.\n");
+            }
+            login(conn)
+        }
+        // 3 (or anything unexpected) starts over
+        _ => {
+            println!();
+            login(conn)
+        }
+    }
+}
+
+/// Ask for the user's password until it is right or the account gets locked
+/// Users without a password go straight through
+fn enter_password(conn: &Connection, user: User) -> Result<User> {
+    if !user.has_password {
+        return Ok(user);
+    }
+    loop {
+        // Once locked there's no point asking again
+        ensure_unlocked(conn, &user)?;
+        println!("Enter the password for {}:", user.username);
+        let password = read_password();
+        match authenticate(conn, &user, &password) {
+            Ok(()) => return Ok(user),
+            Err(error) => println!("\n{}", error),
+        }
     }
 }
 
@@ -138,17 +167,28 @@ fn signup(conn: &Connection, username: String) -> Result<User> {
     let lastname: String = read_or_quit();
 
     // Insert the new user into the database
-    conn.execute(
-        "INSERT INTO users (username, username_lower, firstname, lastname, is_deleted) VALUES (?1, ?2, ?3, ?4, 0)",
-        (&username, &username.to_lowercase(), &firstname, &lastname),
-    )?;
+    let user = create_user(conn, &username, &firstname, &lastname)?;
 
-    // Return the new User to be returned to the main function
-    Ok(User {
-        username: username.clone(),
-        username_lower: username.to_lowercase(),
-        firstname,
-        lastname,
-        is_deleted: false,
-    })
+    // A password is optional and can be added later from the Profile menu
+    println!(
+        "Enter a password (at least {} characters), or just hit Enter to skip:",
+        MIN_PASSWORD_LENGTH
+    );
+    loop {
+        let password = read_password();
+        if password.is_empty() {
+            return Ok(user);
+        }
+        println!("Enter the password again:");
+        if read_password() != password {
+            println!("\nThe passwords didn't match. Enter a password, or just hit Enter to skip:");
+            continue;
+        }
+        match change_password(conn, &user, "", Some(&password)) {
+            Ok(user) => return Ok(user),
+            Err(error) => {
+                println!("\n{}. Enter a password, or just hit Enter to skip:", error)
+            }
+        }
+    }
 }
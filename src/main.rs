@@ -1,8 +1,18 @@
+use burkebudget::encryption::{is_encrypted, PASSPHRASE_ENV_VAR};
+use burkebudget::error::{BudgetError, Result};
+use burkebudget::ledger::{
+    adopt_legacy_db, ledger_display_name, open_db, resolve_db_path, LEGACY_DB_PATH,
+};
+use burkebudget::migrations::migrate;
+use burkebudget::structs_utils::read_or_quit;
+use clap::Parser;
 use rusqlite::Connection;
+use std::path::Path;
+use std::process::ExitCode;
 
+mod cli;
 mod login;
 mod menu;
-mod structs_utils;
 
 fn write_welcome() {
     println!("\n||PBPB\\\\");
@@ -18,28 +28,138 @@ fn write_welcome() {
     println!("\nType \"quit\" to quit at any time. This will also logout the current user\n");
 }
 
-fn main() {
-    const DB_PATH: &str = "burkebudgetDB.db";
+/// Open the database and bring its schema up to date, saying so if it had to be upgraded
+fn open_ledger(path: &Path, passphrase: Option<&str>) -> Result<Connection> {
+    let conn = open_db(path, passphrase)?;
+    let outcome = migrate(&conn)?;
+    if outcome.migrated() && outcome.from_version > 0 {
+        eprintln!(
+            "Upgraded the database from version {} to {}.",
+            outcome.from_version, outcome.to_version
+        );
+    }
+    if let Some(backup) = outcome.backup {
+        eprintln!(
+            "A copy from before the upgrade was saved to {}",
+            backup.display()
+        );
+    }
+    Ok(conn)
+}
+
+/// The passphrase for an encrypted database, or None if it isn't encrypted
+/// It comes from BURKEBUDGET_PASSPHRASE, or is asked for when the menus are being used
+fn get_passphrase(path: &Path, interactive: bool) -> Result<Option<String>> {
+    if !is_encrypted(path)? {
+        return Ok(None);
+    }
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+        return Ok(Some(passphrase));
+    }
+    if !interactive {
+        return Err(BudgetError::Validation(format!(
+            "{} is encrypted. Give the passphrase in {}",
+            path.display(),
+            PASSPHRASE_ENV_VAR
+        )));
+    }
+    println!(
+        "The ledger {} is encrypted. Enter the passphrase:",
+        ledger_display_name(path)
+    );
+    Ok(Some(menu::read_password()))
+}
+
+fn main() -> ExitCode {
+    let args = cli::Cli::parse();
+
+    let mut db_path = match resolve_db_path(args.db.as_deref(), args.ledger.as_deref()) {
+        Ok(path) => path,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Carry over the database from the old location next to the executable
+    match adopt_legacy_db(&db_path) {
+        Ok(true) => eprintln!(
+            "Copied {} to {}. The original file was left in place.",
+            LEGACY_DB_PATH,
+            db_path.display()
+        ),
+        Ok(false) => {}
+        Err(error) => eprintln!("Warning: Could not copy {}: {}", LEGACY_DB_PATH, error),
+    }
+
+    // Subcommands run without any prompting and report through the exit code
+    match args.command {
+        // Encrypting replaces the file, so it works on the path rather than an open connection
+        Some(cli::Command::Db { command }) => return cli::run_db(&db_path, command),
+        Some(command) => {
+            let conn = match get_passphrase(&db_path, false)
+                .and_then(|passphrase| open_ledger(&db_path, passphrase.as_deref()))
+            {
+                Ok(conn) => conn,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return ExitCode::FAILURE;
+                }
+            };
+            return cli::run(&conn, args.user, command);
+        }
+        None => {}
+    }
 
     write_welcome();
 
-    let conn = Connection::open(DB_PATH).expect("There was an error connecting to the database");
+    // Keep going until the user quits rather than switching to another ledger
+    loop {
+        // A wrong passphrase can be tried again
+        let conn = loop {
+            let passphrase = match get_passphrase(&db_path, true) {
+                Ok(passphrase) => passphrase,
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return ExitCode::FAILURE;
+                }
+            };
+            match open_ledger(&db_path, passphrase.as_deref()) {
+                Ok(conn) => break conn,
+                Err(error) if passphrase.is_some() => {
+                    println!("\nThat didn't work: {}", error);
+                }
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    return ExitCode::FAILURE;
+                }
+            }
+        };
+        println!("Using ledger {}\n", ledger_display_name(&db_path));
 
-    // Turn on foreign keys
-    conn.execute("PRAGMA foreign_keys = ON", ())
-        .expect("Error turning on foreign keys");
+        // Login the user, letting them try again if something goes wrong
+        let user = loop {
+            match login::login(&conn) {
+                Ok(user) => break user,
+                Err(error) => {
+                    println!("\nThere was an error with login: {}", error);
+                    println!("Hit Enter to try again.\n");
+                    read_or_quit(); // Give the user a chance to acknowledge
+                }
+            }
+        };
 
-    // Login the user
-    let user_result = login::login(&conn);
-    match user_result {
-        Err(error) => println!("There was an error with login: {}", error),
-        Ok(user) => {
-            // Display the menu
-            // The menu will handle the rest of the functionality until the user quits
-            menu::main_menu(&conn, &user);
+        // Display the menu
+        // The menu will handle the rest of the functionality until the user quits or switches ledgers
+        match menu::main_menu(&conn, &user, &db_path) {
+            Some(new_path) => {
+                println!("\nYou have been logged out of {}.\n", user.username);
+                db_path = new_path;
+            }
+            None => break,
         }
     }
 
-    // Close the application by returning a Result
+    // Close the application
     println!("\nYour budget is saved, and you have been logged out. See you next time!\n");
+    ExitCode::SUCCESS
 }
@@ -1,24 +1,122 @@
-use crate::structs_utils::*;
+use burkebudget::currency::normalize_currency;
+use burkebudget::error::BudgetError;
+use burkebudget::structs_utils::*;
 use rusqlite::Connection;
-#[path = "balance_sheet.rs"]
-mod balance_sheet;
-#[path = "budget.rs"]
-mod budget;
+use std::path::{Path, PathBuf};
+
+pub mod balance_sheet;
+mod bs_items_cats_timeline;
+mod bs_snapshots;
+pub mod bs_visualizers;
+pub mod budget;
+mod budget_months;
+mod budget_snapshots;
+mod currencies;
+pub mod debts;
+pub mod goals;
+pub mod import_export;
+mod ledgers;
+pub mod loans;
+mod profile;
+pub mod transactions;
+
+/// Tell the user what went wrong and wait for them to acknowledge it
+/// The menu they came from is shown again afterwards so they can retry
+pub fn report_error(error: &BudgetError) {
+    println!("\nThat didn't work: {}", error);
+    println!("Hit Enter to go back and try again.");
+    read_or_quit(); // Give the user a chance to acknowledge
+}
+
+/// Ask which currency an amount is in until a known ISO code is entered
+/// Just hitting Enter keeps the default
+pub fn get_currency_response(default: &str) -> String {
+    loop {
+        println!(
+            "Which currency is this in? (eg - USD, EUR, GBP) Just hit Enter to use {}.",
+            default
+        );
+        let response = read_or_quit();
+        if response.is_empty() {
+            return String::from(default);
+        }
+        match normalize_currency(&response) {
+            Ok(code) => return code,
+            Err(error) => println!("\n{}.", error),
+        }
+    }
+}
+
+/// Read a password without showing it on the screen where the terminal allows it
+/// Like read_or_quit, typing "quit" (or the end of the input) quits
+pub fn read_password() -> String {
+    let hidden = set_echo(false);
+    let mut input = String::new();
+    let read = std::io::stdin().read_line(&mut input);
+    if hidden {
+        set_echo(true);
+        println!(); // The Enter key wasn't echoed either
+    }
+    let input = input.trim_end_matches(['\r', '\n']).to_string();
+    if !matches!(read, Ok(n) if n > 0) || input.eq_ignore_ascii_case("quit") {
+        println!("\nYour budget is saved, and you have been logged out. See you next time!\n");
+        std::process::exit(0);
+    }
+    input
+}
+
+/// Turn the terminal echo on or off with stty
+/// Returns false if it couldn't be changed (eg - the input isn't a terminal)
+fn set_echo(on: bool) -> bool {
+    std::process::Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(std::process::Stdio::inherit())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
 
 /// Display the main menu and handle response
-pub fn main_menu(conn: &Connection, user: &User) {
+/// Returns the path of the ledger to login to next if the user switched ledgers or deleted their account
+/// Returns None when they quit
+pub fn main_menu(conn: &Connection, user: &User, db_path: &Path) -> Option<PathBuf> {
+    // Settings such as the base currency can change while the menus are open
+    let mut user = user.clone();
     loop {
         println!("\n\nWelcome {}\n", user.fullname());
-        match print_instr_get_response(1, 3, || {
+        match print_instr_get_response(1, 8, || {
             println!("Which section would you like to use? (Enter the number)");
             println!("1. Budget");
             println!("2. Balance Sheet");
-            println!("3. Quit");
+            println!("3. Goals");
+            println!("4. Currencies and Exchange Rates");
+            println!("5. Import and Export");
+            println!("6. Profile and Password");
+            println!("7. Switch Ledger");
+            println!("8. Quit");
         }) {
-            1 => budget_menu(conn, user),
-            2 => balance_sheet_menu(conn, user),
-            3 => return,
-            x => panic!("Response {} is an error state. Exiting the program.", x),
+            1 => budget_menu(conn, &user),
+            2 => balance_sheet_menu(conn, &user),
+            3 => {
+                if let Err(error) = goals::goals_menu(conn, &user) {
+                    report_error(&error);
+                }
+            }
+            4 => currencies::currencies_menu(conn, &mut user),
+            5 => import_export::import_export_menu(conn, &user),
+            6 => {
+                // A deleted account is logged out, and someone else can login to the same ledger
+                if profile::profile_menu(conn, &mut user) {
+                    return Some(db_path.to_path_buf());
+                }
+            }
+            7 => match ledgers::switch_ledger_menu(db_path) {
+                Ok(Some(new_path)) => return Some(new_path),
+                Ok(None) => {}
+                Err(error) => report_error(&error),
+            },
+            // 8 (or anything unexpected) quits
+            _ => return None,
         }
     }
 }
@@ -26,32 +124,43 @@ pub fn main_menu(conn: &Connection, user: &User) {
 /// Display the budget menu
 pub fn budget_menu(conn: &Connection, user: &User) {
     loop {
-        match print_instr_get_response(1, 5, || {
+        let result = match print_instr_get_response(1, 9, || {
             println!(
                 "\n\nBUDGET: For tracking money entering and leaving your possession each month"
             );
             println!(
-                "Longer and shorter term expenses / income should be averaged on a monthly basis"
+                "Items that aren't monthly (such as an annual bonus) are counted as their monthly equivalent"
             );
-            println!("Example: A holiday bonus can be divided by 12 to reflect its effect on your monthly budget");
+            println!("The calendar shows which months they actually land in");
             println!("\nWhat would you like to do with your budget? (Enter the number)");
-            println!("1. View Budget");
-            println!("2. Update Monthly Income");
-            println!("3. Update Monthly Expenses");
-            println!("4. Main Menu");
-            println!("5. Quit");
+            println!("1. View Budget / Create Snapshot");
+            println!("2. Update Income");
+            println!("3. Update Expenses");
+            println!("4. Record or View Transactions");
+            println!("5. Budget vs. Actual Report");
+            println!("6. Budget Calendar");
+            println!("7. Monthly Budgets and Rollover");
+            println!("8. Main Menu");
+            println!("9. Quit");
         }) {
             1 => budget::budget_whole_entry_point(conn, user),
             2 => budget::budget_half_entry_point(conn, user, BudgetHalf::Income),
             3 => budget::budget_half_entry_point(conn, user, BudgetHalf::Expenses),
-            4 => return,
-            5 => {
+            4 => transactions::transactions_menu(conn, user),
+            5 => transactions::budget_vs_actual_menu(conn, user),
+            6 => budget::budget_calendar_menu(conn, user),
+            7 => budget_months::budget_months_menu(conn, user),
+            9 => {
                 println!(
                     "\nYour budget is saved, and you have been logged out. See you next time!\n"
                 );
                 std::process::exit(0);
             }
-            x => panic!("Response {} is an error state. Exiting the program.", x),
+            // 8 (or anything unexpected) goes back
+            _ => return,
+        };
+        if let Err(error) = result {
+            report_error(&error);
         }
     }
 }
@@ -59,7 +168,7 @@ pub fn budget_menu(conn: &Connection, user: &User) {
 /// Display the balance sheet menu
 pub fn balance_sheet_menu(conn: &Connection, user: &User) {
     loop {
-        match print_instr_get_response(1, 5, || {
+        let result = match print_instr_get_response(1, 7, || {
             println!("\n\nBALANCE SHEET: For tracking long term assets and liabilities");
             println!("This does not track money that it moving, rather it tracks your net worth");
             println!("The only points you can later return to are the ones saved as a Snapshot");
@@ -67,8 +176,10 @@ pub fn balance_sheet_menu(conn: &Connection, user: &User) {
             println!("1. View Balance Sheet / Create Snapshot");
             println!("2. Update Assets");
             println!("3. Update Liabilities");
-            println!("4. Main Menu");
-            println!("5. Quit");
+            println!("4. Loans and Amortization");
+            println!("5. Debt Payoff Planner");
+            println!("6. Main Menu");
+            println!("7. Quit");
         }) {
             1 => balance_sheet::balance_sheet_whole_entry_point(conn, user),
             2 => {
@@ -79,14 +190,19 @@ pub fn balance_sheet_menu(conn: &Connection, user: &User) {
                 user,
                 BalanceSheetHalf::Liabilities,
             ),
-            4 => return,
-            5 => {
+            4 => loans::loans_menu(conn, user),
+            5 => debts::debt_planner_menu(conn, user),
+            7 => {
                 println!(
                     "\nYour budget is saved, and you have been logged out. See you next time!\n"
                 );
                 std::process::exit(0);
             }
-            x => panic!("Response {} is an error state. Exiting the program.", x),
+            // 6 (or anything unexpected) goes back
+            _ => return,
+        };
+        if let Err(error) = result {
+            report_error(&error);
         }
     }
 }
@@ -0,0 +1,272 @@
+use super::bs_items_cats_timeline::*;
+use super::bs_snapshots::*;
+use super::bs_visualizers::*;
+use super::report_error;
+use burkebudget::balance_sheet::{get_relevant_items_cats, initialize_balance_sheet};
+use burkebudget::currency::{current_converter, Converter};
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+#[derive(Debug, PartialEq)]
+enum BalanceSheetSelection<'a> {
+    Some(&'a Item),
+    NewCategory,
+    NewItem,
+    RenameCategory,
+    GoBack,
+}
+
+pub fn balance_sheet_half_entry_point(
+    conn: &Connection,
+    user: &User,
+    which_half: BalanceSheetHalf,
+) -> Result<()> {
+    initialize_balance_sheet(conn, user)?;
+    let (mut categories, mut items) = get_relevant_items_cats(conn, user, &which_half)?;
+    loop {
+        let response = print_balance_sheet_half_get_response(&categories, &items, &which_half);
+
+        let result = match response {
+            BalanceSheetSelection::Some(item) => {
+                // The item was selected from this vector so it is always found
+                let idx = items.iter().position(|i| i == item).unwrap_or_default();
+                update_item(conn, user, &which_half, &mut categories, &mut items, idx)
+            }
+            BalanceSheetSelection::NewCategory => {
+                create_new_category(conn, user, &which_half, &mut categories)
+            }
+            BalanceSheetSelection::NewItem => {
+                create_new_item(conn, user, &which_half, &mut categories, &mut items)
+            }
+            BalanceSheetSelection::RenameCategory => {
+                rename_category(conn, user, &which_half, &mut categories, &mut items)
+            }
+            BalanceSheetSelection::GoBack => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+            // Reload from the database since the vectors may no longer match it
+            (categories, items) = get_relevant_items_cats(conn, user, &which_half)?;
+        }
+    }
+}
+
+pub fn balance_sheet_whole_entry_point(conn: &Connection, user: &User) -> Result<()> {
+    initialize_balance_sheet(conn, user)?;
+    let (asset_categories, asset_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    let (liability_categories, liability_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let converter = current_converter(conn, user)?;
+    loop {
+        let response = print_balance_sheet_get_response(
+            &converter,
+            &asset_categories,
+            &asset_items,
+            &liability_categories,
+            &liability_items,
+        )?;
+
+        let result = match response {
+            1 => create_snapshot(conn, user),
+            2 => view_snapshot_menu(conn, user),
+            3 => {
+                snapshot_visualizer_menu(conn, user);
+                Ok(())
+            }
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print out the half of the balance sheet and find out what the user wants to do
+/// It only receives the relevant half categories and items
+fn print_balance_sheet_half_get_response<'a>(
+    categories: &Vec<Category>,
+    items: &'a Vec<Item>,
+    which_half: &BalanceSheetHalf,
+) -> BalanceSheetSelection<'a> {
+    println!("\nCurrent list of {}:", which_half.to_str().to_lowercase());
+    let mut idx: usize = 1;
+    let mut sorted_items: Vec<&Item> = vec![];
+
+    for category in categories {
+        let mut no_items_found_in_cat = true;
+        // Check if any of the items are in this category
+        for item in items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in items {
+            if item.category_lower == category.category_lower {
+                print!("    {}. {} ", idx, item.item);
+                let mut num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 1 - item.item.len();
+                // Support prooper formatting up to 999 items
+                if idx >= 10 {
+                    num_dashes -= 1;
+                }
+                if idx >= 100 {
+                    num_dashes -= 1;
+                }
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                println!(" {}", to_money_string(item.value, &item.currency));
+                idx += 1;
+                sorted_items.push(item);
+            }
+        }
+    }
+    println!("\n{}. NEW CATEGORY", idx);
+    idx += 1;
+    println!("    {}. NEW ITEM", idx);
+    idx += 1;
+    println!("{}. RENAME CATEGORY", idx);
+    println!("\n0. GO BACK - Balance Sheet Menu");
+    let response = print_instr_get_response(0, idx, || {
+        println!("\nEnter the number of the item you'd like to update / delete, or one of the other numbers");
+    });
+    match response {
+        x if x > 0 && x <= idx - 3 => BalanceSheetSelection::Some(sorted_items.remove(x - 1)),
+        x if x == idx - 2 => BalanceSheetSelection::NewCategory,
+        x if x == idx - 1 => BalanceSheetSelection::NewItem,
+        x if x == idx => BalanceSheetSelection::RenameCategory,
+        // 0 (or anything unexpected) goes back
+        _ => BalanceSheetSelection::GoBack,
+    }
+}
+
+/// Print out the whole balance sheet and find out what the user wants to do next
+fn print_balance_sheet_get_response(
+    converter: &Converter,
+    asset_categories: &Vec<Category>,
+    asset_items: &Vec<Item>,
+    liability_categories: &Vec<Category>,
+    liability_items: &Vec<Item>,
+) -> Result<usize> {
+    print_balance_sheet(
+        converter,
+        asset_categories,
+        asset_items,
+        liability_categories,
+        liability_items,
+    )?;
+
+    println!(
+        "\n\nBalance Sheet Snapshots can later be viewed or analyzed in aggregate for trends."
+    );
+    println!("It is recommended to do this periodically (such as monthly or quarterly).\n");
+    Ok(print_instr_get_response(0, 3, || {
+        println!("1. Take a snapshot");
+        println!("2. View or delete a snapshot");
+        println!("3. Trend Analysis");
+        println!("\n0. Go Back - Balance Sheet Menu");
+    }))
+}
+
+/// Print out the whole balance sheet without prompting and return the net worth
+/// Items are shown in their own currency and the totals are converted into the converter's base currency
+pub fn print_balance_sheet(
+    converter: &Converter,
+    asset_categories: &Vec<Category>,
+    asset_items: &Vec<Item>,
+    liability_categories: &Vec<Category>,
+    liability_items: &Vec<Item>,
+) -> Result<Amount> {
+    let today_date = Local::now().format("%Y-%m-%d").to_string();
+    println!(
+        "\n\nCurrent Balance Sheet - {}  (Totals in {})",
+        today_date,
+        converter.base()
+    );
+    println!("\nASSETS");
+    let mut asset_total = Amount::ZERO;
+    for category in asset_categories {
+        let mut no_items_found_in_cat = true;
+        // Check if any of the items are in this category
+        for item in asset_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in asset_items {
+            if item.category_lower == category.category_lower {
+                print!("    {} ", item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                println!(" {}", to_money_string(item.value, &item.currency));
+                asset_total += converter.convert(item.value, &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Assets");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 2) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(asset_total, converter.base()));
+
+    println!("\nLIABILITIES");
+    let mut liability_total = Amount::ZERO;
+    for category in liability_categories {
+        let mut no_items_found_in_cat = true;
+        // Check if any of the items are in this category
+        for item in liability_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in liability_items {
+            if item.category_lower == category.category_lower {
+                print!("    {} ", item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                println!(" {}", to_money_string(item.value, &item.currency));
+                liability_total += converter.convert(item.value, &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Liabilities");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 7) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(liability_total, converter.base()));
+
+    // Grand total
+    let total = asset_total - liability_total;
+    println!(
+        "\n\nTOTAL NET WORTH --------------------  {}",
+        to_money_string(total, converter.base())
+    );
+    Ok(total)
+}
@@ -1,4 +1,7 @@
-use crate::structs_utils::*;
+use super::get_currency_response;
+use burkebudget::balance_sheet;
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
 use rusqlite::Connection;
 
 /// Category Creator
@@ -8,7 +11,7 @@ pub fn create_new_category(
     user: &User,
     which_half: &BalanceSheetHalf,
     categories: &mut Vec<Category>,
-) {
+) -> Result<()> {
     println!("What would you like to call the new category?");
     let cat_name = read_or_quit();
 
@@ -18,7 +21,7 @@ pub fn create_new_category(
         println!("Hit Enter to go back.");
         // Give the user a chance to acknowledge
         read_or_quit();
-        return;
+        return Ok(());
     }
 
     // Check if the category already exists
@@ -29,21 +32,13 @@ pub fn create_new_category(
             println!(
                 "If you would like to edit the name, select \"Rename Category\" on the {} listing page.", which_half.to_str()
             );
-            return;
+            return Ok(());
         }
     }
-    // Insert the new category into the database
-    conn.execute(
-        "INSERT INTO balance_categories (category, category_lower, username_lower, is_asset) VALUES (?1, ?2, ?3, ?4)",
-        (&cat_name, &cat_name.to_lowercase(), &user.username_lower, &which_half.to_bool_int()),
-    ).expect("Error creating new category");
-    // Add the category into the categories vector
-    categories.push(Category {
-        category: cat_name.clone(),
-        category_lower: cat_name.to_lowercase(),
-        username_lower: String::from(&user.username_lower),
-        is_asset: which_half.to_bool(),
-    });
+    // Insert the new category into the database and add it into the categories vector
+    let category = balance_sheet::create_category(conn, user, which_half, &cat_name)?;
+    categories.push(category);
+    Ok(())
 }
 
 /// Item Creator
@@ -54,7 +49,7 @@ pub fn create_new_item(
     which_half: &BalanceSheetHalf,
     categories: &mut Vec<Category>,
     items: &mut Vec<Item>,
-) {
+) -> Result<()> {
     println!("What would you like to name the new item?");
     // Get The new item's name
     let item_name = read_or_quit();
@@ -62,14 +57,14 @@ pub fn create_new_item(
     if item_name.is_empty() {
         println!("Cannot have an empty name. Press Enter to go back");
         read_or_quit(); // Allow user to acknowledge
-        return;
+        return Ok(());
     }
     // Check if the item already exists
     for item in &mut *items {
         if item_name.to_lowercase() == item.item_lower {
             println!("This item already exists as {}.", item.item);
             println!("If you would like to edit the item, select it on the next page.");
-            return;
+            return Ok(());
         }
     }
     // Enforce MAX_CHARACTERS_ITEM_NAME character maximum
@@ -78,7 +73,7 @@ pub fn create_new_item(
             "There is currently a {} character limit on the item name. Please try again.",
             MAX_CHARACTERS_ITEM_NAME
         );
-        return;
+        return Ok(());
     }
 
     // Return if the new name is empty
@@ -87,7 +82,7 @@ pub fn create_new_item(
         println!("Hit Enter to go back.");
         // Give the user a chance to acknowledge
         read_or_quit();
-        return;
+        return Ok(());
     }
 
     // Get the new item's value
@@ -97,13 +92,13 @@ pub fn create_new_item(
     } else {
         println!("What is the current total cost of this liability? (positive number or 0)");
     }
-    let mut value: f64 = -1.0;
-    while value < 0.0 {
+    let mut value = Amount::from_minor(-1);
+    while value.is_negative() {
         let val_response = read_or_quit();
         // Make sure the input is valid
-        match val_response.parse::<f64>() {
+        match val_response.parse::<Amount>() {
             Ok(poss_value) => {
-                if poss_value < 0.0 {
+                if poss_value.is_negative() {
                     println!("\nPlease enter a positive number.");
                 } else if poss_value > MAX_ITEM_VALUE {
                     println!("\n1 quadrillion is the maximum value for an item currently.");
@@ -111,11 +106,14 @@ pub fn create_new_item(
                     value = poss_value;
                 }
             }
-            Err(_err) => {
-                println!("\nPlease enter a valid number.");
+            Err(error) => {
+                println!("\n{}. Please enter a valid number.", error);
             }
         }
     }
+    // Get the new item's currency
+    let currency = get_currency_response(&user.base_currency);
+
     // Get the new item's category
     // Can also make a new category during this process
     let mut still_need_category: bool = true;
@@ -131,76 +129,42 @@ pub fn create_new_item(
         println!("\n{}. NEW CATEGORY", idx);
         let response = print_instr_get_response(0, idx, || {});
         match response {
-            0 => return,
+            0 => return Ok(()),
             x if x > 0 && x < idx => {
                 // Selected category
-                chosen_cat = categories
-                    .get(x - 1)
-                    .expect("Unable to access chosen category")
-                    .category
-                    .clone();
+                chosen_cat = categories[x - 1].category.clone();
                 still_need_category = false;
             }
             x if x == idx => {
                 // New category
                 let num_cats_before: usize = categories.len();
-                create_new_category(conn, user, which_half, categories);
+                create_new_category(conn, user, which_half, categories)?;
                 let num_cats_after: usize = categories.len();
                 if num_cats_before == num_cats_after {
                     // This only happens if there was an error in category creation
                     // Must loop again
                     continue;
                 } else {
-                    chosen_cat = categories
-                        .get(num_cats_after - 1)
-                        .expect("Unable to access new category")
-                        .category
-                        .clone();
+                    chosen_cat = categories[num_cats_after - 1].category.clone();
                     still_need_category = false;
                 }
             }
-            x => panic!("Response {} is an error state. Exiting the program.", x),
+            _ => println!("\nThat is not one of the options. Please try again."),
         }
     }
 
-    // Get the new item's timeline_created value and increment it
-    let timeline: usize = get_and_update_timeline(conn, user);
-
-    // Insert the new item into the database
-    conn.execute(
-        "INSERT INTO balance_items 
-        (item, item_lower, value, category, category_lower, username_lower, 
-            is_asset, timeline_created, timeline_original, is_deleted, timeline_deleted) 
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-        (
-            &item_name,
-            &item_name.to_lowercase(),
-            &value,
-            &chosen_cat,
-            &chosen_cat.to_lowercase(),
-            &user.username_lower,
-            &which_half.to_bool_int(),
-            &timeline,
-            &timeline,
-            0,
-            usize::MAX / 4,
-        ),
-    )
-    .expect("Error creating new item");
-    // Add the item into the items vector
-    items.push(Item {
-        item: item_name.clone(),
-        item_lower: item_name.to_lowercase(),
+    // Insert the new item into the database and add it into the items vector
+    let item = balance_sheet::create_item(
+        conn,
+        user,
+        which_half,
+        &item_name,
         value,
-        category: chosen_cat.clone(),
-        category_lower: chosen_cat.to_lowercase(),
-        username_lower: String::from(&user.username_lower),
-        is_asset: which_half.to_bool(),
-        timeline_created: timeline,
-        timeline_original: timeline,
-        is_deleted: false,
-        timeline_deleted: usize::MAX / 4,
-    });
+        &currency,
+        &chosen_cat,
+    )?;
+    items.push(item);
+    Ok(())
 }
 
 /// Rename a category
@@ -211,7 +175,7 @@ pub fn rename_category(
     which_half: &BalanceSheetHalf,
     categories: &mut Vec<Category>,
     items: &mut Vec<Item>,
-) {
+) -> Result<()> {
     println!("\nRename a category ({}):", which_half.to_str());
     let mut idx: usize = 0;
 
@@ -230,11 +194,7 @@ pub fn rename_category(
         x if x > 0 && x <= idx => {
             println!(
                 "What would you like to rename {}?",
-                categories
-                    .get(x - 1)
-                    .expect("Unable to access chosen category")
-                    .category
-                    .clone()
+                categories[x - 1].category.clone()
             );
             let new_name = read_or_quit();
 
@@ -244,7 +204,7 @@ pub fn rename_category(
                 println!("Hit Enter to go back.");
                 // Give the user a chance to acknowledge
                 read_or_quit();
-                return;
+                return Ok(());
             }
 
             // Check if it exists already, and return if it does
@@ -255,55 +215,30 @@ pub fn rename_category(
                     println!("Hit Enter to go back.");
                     // Give the user a chance to acknowledge
                     read_or_quit();
-                    return;
+                    return Ok(());
                 }
             }
 
             let old_cat_name_lower = String::from(&mut *categories[x - 1].category_lower);
 
-            // Update the Vector and DB
+            // Update the DB (including every item with this category) then the Vectors
+            balance_sheet::rename_category(conn, user, which_half, &old_cat_name_lower, &new_name)?;
+
             categories[x - 1].category = new_name.clone();
             categories[x - 1].category_lower = new_name.to_ascii_lowercase();
 
-            conn.execute(
-                "UPDATE balance_categories 
-                SET category = ?1, category_lower = ?2 
-                WHERE username_lower = ?3 AND category_lower = ?4 AND is_asset = ?5",
-                (
-                    &new_name,
-                    &new_name.to_ascii_lowercase(),
-                    &user.username_lower,
-                    &old_cat_name_lower,
-                    &which_half.to_bool_int(),
-                ),
-            )
-            .expect("Error updating the balance sheet categories database");
-
             // Update all items that have the category with the updated name
             // This is necessary since they have both the key lowercase category AND the non-key proper capitalization category
-            // Note that the lowercase category will cascade in the DB with the update above
             for item in items {
                 if item.category_lower == old_cat_name_lower {
                     item.category = new_name.clone();
                     item.category_lower = new_name.to_ascii_lowercase();
                 }
             }
-
-            conn.execute(
-                "UPDATE balance_items 
-                    SET category = ?1 
-                    WHERE username_lower = ?2 AND category_lower = ?3 AND is_asset = ?4",
-                (
-                    &new_name,
-                    &user.username_lower,
-                    &new_name.to_ascii_lowercase(),
-                    &which_half.to_bool_int(),
-                ),
-            )
-            .expect("Error updating the balance sheet items database");
         }
-        x => panic!("Response {} is an error state. Exiting the program.", x),
+        _ => println!("\nThat is not one of the options. Please try again."),
     }
+    Ok(())
 }
 
 /// Item Update or Delete
@@ -315,7 +250,7 @@ pub fn update_item(
     categories: &mut Vec<Category>,
     items: &mut Vec<Item>,
     idx: usize,
-) {
+) -> Result<()> {
     // Need to remove the item from the vector to get ownership
     let item_chosen = items.remove(idx);
     println!("\nUpdate item {}", item_chosen.item);
@@ -337,19 +272,7 @@ pub fn update_item(
             match print_instr_get_response(1, 2, || {}) {
                 1 => {
                     // Delete the item from the database and from the mutable vector
-                    let timeline: usize = get_and_update_timeline(conn, user);
-                    conn.execute(
-                        "UPDATE balance_items 
-                        SET is_deleted = 1, timeline_deleted = ?1
-                        WHERE item_lower = ?2 AND username_lower = ?3 AND timeline_created = ?4",
-                        (
-                            &timeline,
-                            &item_chosen.item_lower,
-                            &user.username_lower,
-                            &item_chosen.timeline_created,
-                        ),
-                    )
-                    .expect("Error deleting the item");
+                    balance_sheet::delete_item(conn, user, &item_chosen)?;
                     // The item is already removed from the vector and will go out of scope here
                     // return
                 }
@@ -358,7 +281,7 @@ pub fn update_item(
                     items.push(item_chosen);
                     // return
                 }
-                x => panic!("Response {} is an error state. Exiting the program.", x),
+                _ => println!("\nThat is not one of the options. Please try again."),
             }
         }
         1 => {
@@ -375,7 +298,7 @@ pub fn update_item(
                 if item_name.to_lowercase() == item.item_lower {
                     println!("This item already exists as {}.", item.item);
                     println!("If you would like to edit that item, select it on the next page.");
-                    return;
+                    return Ok(());
                 }
             }
             if item_name.is_empty() {
@@ -389,17 +312,17 @@ pub fn update_item(
                 println!(
                     "The current value of {} is listed as {}. Enter a new value to change it.",
                     item_name,
-                    to_money_string(item_chosen.value)
+                    to_money_string(item_chosen.value, &item_chosen.currency)
                 );
             } else {
                 println!(
                     "The current cost of {} is listed as {}. Enter a new liability cost to change it (positive number or 0).",
-                    item_name, to_money_string(item_chosen.value)
+                    item_name, to_money_string(item_chosen.value, &item_chosen.currency)
                 );
             }
             println!("Or just leave it blank and hit Enter to keep the value the same.");
-            let mut value: f64 = -1.0;
-            while value < 0.0 {
+            let mut value = Amount::from_minor(-1);
+            while value.is_negative() {
                 let val_response = read_or_quit();
                 if val_response.is_empty() {
                     // The user would like to not change the value
@@ -407,9 +330,9 @@ pub fn update_item(
                     break;
                 }
                 // Make sure the input is valid
-                match val_response.parse::<f64>() {
+                match val_response.parse::<Amount>() {
                     Ok(poss_value) => {
-                        if poss_value < 0.0 {
+                        if poss_value.is_negative() {
                             println!("\nPlease enter a positive number.");
                         } else if poss_value > MAX_ITEM_VALUE {
                             println!("\n1 quadrillion is the maximum value for an item currently.");
@@ -417,12 +340,15 @@ pub fn update_item(
                             value = poss_value;
                         }
                     }
-                    Err(_err) => {
-                        println!("\nPlease enter a valid number.");
+                    Err(error) => {
+                        println!("\n{}. Please enter a valid number.", error);
                     }
                 }
             }
 
+            // Get the new item's currency
+            let currency = get_currency_response(&item_chosen.currency);
+
             // Get the new item's category
             // Can also make a new category during this process
             let mut still_need_category: bool = true;
@@ -450,125 +376,41 @@ pub fn update_item(
                     }
                     x if x > 0 && x < idx => {
                         // Selected category
-                        chosen_cat = categories
-                            .get(x - 1)
-                            .expect("Unable to access chosen category")
-                            .category
-                            .clone();
+                        chosen_cat = categories[x - 1].category.clone();
                         still_need_category = false;
                     }
                     x if x == idx => {
                         // New category
                         let num_cats_before: usize = categories.len();
-                        create_new_category(conn, user, which_half, categories);
+                        create_new_category(conn, user, which_half, categories)?;
                         let num_cats_after: usize = categories.len();
                         if num_cats_before == num_cats_after {
                             // This only happens if there was an error in category creation
                             // Must loop again
                             continue;
                         } else {
-                            chosen_cat = categories
-                                .get(num_cats_after - 1)
-                                .expect("Unable to access new category")
-                                .category
-                                .clone();
+                            chosen_cat = categories[num_cats_after - 1].category.clone();
                             still_need_category = false;
                         }
                     }
-                    x => panic!("Response {} is an error state. Exiting the program.", x),
+                    _ => println!("\nThat is not one of the options. Please try again."),
                 }
             }
 
-            // Get the timeline_deleted value for the former version of the item
-            let timeline: usize = get_and_update_timeline(conn, user);
-
-            // Mark the former version of the item as deleted
-            conn.execute(
-                "UPDATE balance_items 
-                SET is_deleted = 1, timeline_deleted = ?1
-                WHERE item_lower = ?2 AND username_lower = ?3 AND timeline_created = ?4",
-                (
-                    &timeline,
-                    &item_chosen.item_lower,
-                    &user.username_lower,
-                    &item_chosen.timeline_created,
-                ),
-            )
-            .expect("Error marking the former version of the item as deleted");
-
-            // Get the timeline_created value for the fupdated version of the item
-            let timeline: usize = get_and_update_timeline(conn, user);
-
-            // Insert the new item into the database
-            conn.execute(
-                "INSERT INTO balance_items 
-                (item, item_lower, value, category, category_lower, username_lower, 
-                    is_asset, timeline_created, timeline_original, is_deleted, timeline_deleted) 
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-                (
-                    &item_name,
-                    &item_name.to_lowercase(),
-                    &value,
-                    &chosen_cat,
-                    &chosen_cat.to_lowercase(),
-                    &user.username_lower,
-                    &which_half.to_bool_int(),
-                    &timeline,
-                    &item_chosen.timeline_original,
-                    0,
-                    usize::MAX / 4,
-                ),
-            )
-            .expect("Error creating new item");
-            // Add the updated item into the items vector (since the old one was already removed)
-            items.push(Item {
-                item: item_name.clone(),
-                item_lower: item_name.to_lowercase(),
+            // Replace the former version of the item with the updated one
+            let item = balance_sheet::update_item(
+                conn,
+                user,
+                &item_chosen,
+                &item_name,
                 value,
-                category: chosen_cat.clone(),
-                category_lower: chosen_cat.to_lowercase(),
-                username_lower: String::from(&user.username_lower),
-                is_asset: which_half.to_bool(),
-                timeline_created: timeline,
-                timeline_original: item_chosen.timeline_original,
-                is_deleted: false,
-                timeline_deleted: usize::MAX / 4,
-            });
-        }
-        x => panic!("Response {} is an error state. Exiting the program.", x),
-    }
-}
-
-/// Gets the timeline from the database and returns it ALREADY INCREMENTED and ready to use
-/// It also updates the value in the timeline database
-pub fn get_and_update_timeline(conn: &Connection, user: &User) -> usize {
-    // Get the new item's timeline_created value and increment it
-    let timeline: usize;
-    let mut stmt = conn
-        .prepare("SELECT timestamp FROM balance_timeline WHERE username_lower = ?1")
-        .expect("Error preparing timeline statement");
-    let mut rows = stmt
-        .query(rusqlite::params![user.username.to_lowercase()])
-        .expect("Error accessing timeline");
-    match rows
-        .next()
-        .expect("Error accessing timeline query response")
-    {
-        // Timeline found
-        Some(row) => {
-            let timeline_returned: usize = row.get(0).expect("Unable to get timeline from query");
-            timeline = timeline_returned + 1;
-            // Reinsert the new timeline value into the database
-            conn.execute(
-                "UPDATE balance_timeline SET timestamp = ?1 WHERE username_lower = ?2",
-                (timeline, &user.username_lower),
-            )
-            .expect("Error updating the timeline database");
-            timeline
-        }
-        // Timeline not found. This is an error state as this needs to be created during initialization.
-        None => {
-            panic!("Timeline query returned empty");
+                &currency,
+                &chosen_cat,
+            )?;
+            // Add the updated item into the items vector (since the old one was already removed)
+            items.push(item);
         }
+        _ => println!("\nThat is not one of the options. Please try again."),
     }
+    Ok(())
 }
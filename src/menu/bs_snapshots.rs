@@ -0,0 +1,203 @@
+use super::report_error;
+use burkebudget::currency::snapshot_converter;
+use burkebudget::error::Result;
+use burkebudget::snapshots::{self, delete_snapshot, list_snapshots, snapshot_items_at};
+use burkebudget::structs_utils::*;
+use rusqlite::Connection;
+
+/// Prompt for a comment and store a snapshot of the current balance sheet
+pub fn create_snapshot(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter an optional comment about this snapshot (Just hit Enter to skip):");
+    let comment: String = read_or_quit();
+
+    snapshots::create_snapshot(conn, user, &comment)?;
+
+    println!("\nSnapshot successfully created. Press Enter to continue.");
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// List the snapshots and offer to open one of them up
+pub fn view_snapshot_menu(conn: &Connection, user: &User) -> Result<()> {
+    // Push all of the snapshots to a vector first (in chronological order)
+    let mut snapshots: Vec<Snapshot> = list_snapshots(conn, user)?;
+
+    // Print out listing of snapshots
+    loop {
+        println!("\n\nSaved Snapshots:");
+        let mut idx: usize = 0;
+        for snapshot in &snapshots {
+            idx += 1;
+            println!(
+                "{}.  {}:  Net Worth  {}",
+                idx,
+                snapshot.date_today,
+                to_money_string(snapshot.net_worth, &snapshot.currency)
+            );
+        }
+        println!("\n0. GO BACK");
+        let response = print_instr_get_response(0, idx, || {
+            println!("\nWhich snapshot would you like to view");
+        });
+        // Go back or go to snapshot viewer
+        match response {
+            0 => {
+                return Ok(());
+            }
+            x if x > 0 && x <= idx => {
+                if let Err(error) = view_single_snapshot(conn, user, &mut snapshots, x - 1) {
+                    report_error(&error);
+                    // Reload the list in case it no longer matches the database
+                    snapshots = list_snapshots(conn, user)?;
+                }
+            }
+            _ => println!("\nThat is not one of the options. Please try again."),
+        }
+    }
+}
+
+/// Display the Balance Sheet represented by the snapshot
+fn view_single_snapshot(
+    conn: &Connection,
+    user: &User,
+    snapshots: &mut Vec<Snapshot>,
+    snapshot_idx: usize,
+) -> Result<()> {
+    let relevant_snapshot = &snapshots[snapshot_idx];
+    let (asset_categories, asset_items) = snapshot_items_at(
+        conn,
+        user,
+        &BalanceSheetHalf::Assets,
+        relevant_snapshot.timeline,
+    )?;
+    let (liability_categories, liability_items) = snapshot_items_at(
+        conn,
+        user,
+        &BalanceSheetHalf::Liabilities,
+        relevant_snapshot.timeline,
+    )?;
+    // Totals use the exchange rates frozen when the snapshot was taken
+    let converter = snapshot_converter(conn, user, relevant_snapshot, &relevant_snapshot.currency)?;
+    println!(
+        "\n\nSNAPSHOT of Balance Sheet - {}  (Totals in {})",
+        relevant_snapshot.date_today,
+        converter.base()
+    );
+
+    println!("\nASSETS");
+    let mut idx: usize = 1;
+    let mut asset_total = Amount::ZERO;
+    for category in &asset_categories {
+        let mut no_items_found_in_cat = true;
+        // Check if any of the items are in this category
+        for item in &asset_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in &asset_items {
+            if item.category_lower == category.category_lower {
+                print!("    {}. {} ", idx, item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 1 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                println!(" {}", to_money_string(item.value, &item.currency));
+                idx += 1;
+                asset_total += converter.convert(item.value, &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Assets");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 2) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(asset_total, converter.base()));
+
+    println!("\nLIABILITIES");
+    let mut idx: usize = 1;
+    let mut liability_total = Amount::ZERO;
+    for category in &liability_categories {
+        let mut no_items_found_in_cat = true;
+        // Check if any of the items are in this category
+        for item in &liability_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in &liability_items {
+            if item.category_lower == category.category_lower {
+                print!("    {}. {} ", idx, item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 1 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                println!(" {}", to_money_string(item.value, &item.currency));
+                idx += 1;
+                liability_total += converter.convert(item.value, &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Liabilities");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 7) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(liability_total, converter.base()));
+
+    // Grand total
+    let total = asset_total - liability_total;
+    println!(
+        "\n\nTOTAL NET WORTH -------------------  {}",
+        to_money_string(total, converter.base())
+    );
+
+    // Print out the comment under the snapshot
+    if !relevant_snapshot.comment.is_empty() {
+        println!("\nComment: \"{}\"", relevant_snapshot.comment);
+    }
+
+    // Get response
+    println!("\n\nWhat would you like to do next?");
+    println!("1. Go Back");
+    println!("2. Delete this Snapshot");
+    let response = print_instr_get_response(1, 2, || {});
+    match response {
+        2 => {
+            // The deletion branch
+            println!("\nAre you sure you'd like to delete this Snapshot? This cannot be undone.");
+            println!("1. Yes");
+            println!("2. No (Go back)");
+            match print_instr_get_response(1, 2, || {}) {
+                1 => {
+                    // Delete the snapshot from the database and from the mutable vector
+                    delete_snapshot(conn, user, relevant_snapshot)?;
+                    // All of the items and categories should remain unchanged
+                    // Remove the snapshot from the Vector of Snapshots
+                    // The remove function should maintain the sorted order of the snapshots
+                    snapshots.remove(snapshot_idx);
+                    Ok(())
+                }
+                // 2 (or anything unexpected) goes back
+                _ => Ok(()),
+            }
+        }
+        // 1 (or anything unexpected) goes back
+        _ => Ok(()),
+    }
+}
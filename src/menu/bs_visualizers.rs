@@ -0,0 +1,1017 @@
+use super::report_error;
+use burkebudget::balance_sheet::{get_all_items, get_categories};
+use burkebudget::currency::{snapshot_converter, Converter};
+use burkebudget::error::Result;
+use burkebudget::forecast::{
+    fire_metrics, forecast_net_worth, get_category_returns, net_worth_history, parse_return_rate,
+    parse_withdrawal_rate, set_category_return, DEFAULT_WITHDRAWAL_RATE,
+};
+use burkebudget::snapshots::{list_snapshots, snapshot_date, snapshot_trend};
+use burkebudget::structs_utils::*;
+use chrono::prelude::*;
+use chrono::Duration;
+use rgb::RGB8;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use textplots::{
+    Chart, ColorPlot, LabelBuilder, LabelFormat, Plot, Shape, TickDisplay, TickDisplayBuilder,
+};
+
+/// The color of the projected part of the forecast chart
+const FORECAST_COLOR: RGB8 = RGB8 {
+    r: 70,
+    g: 180,
+    b: 90,
+};
+
+/// The colors of the lines in the trend lines chart, in the order they are chosen
+const TREND_COLORS: [(&str, RGB8); 5] = [
+    (
+        "Red",
+        RGB8 {
+            r: 220,
+            g: 60,
+            b: 60,
+        },
+    ),
+    (
+        "Blue",
+        RGB8 {
+            r: 70,
+            g: 130,
+            b: 230,
+        },
+    ),
+    (
+        "Green",
+        RGB8 {
+            r: 70,
+            g: 180,
+            b: 90,
+        },
+    ),
+    (
+        "Yellow",
+        RGB8 {
+            r: 230,
+            g: 200,
+            b: 60,
+        },
+    ),
+    (
+        "Purple",
+        RGB8 {
+            r: 170,
+            g: 90,
+            b: 210,
+        },
+    ),
+];
+
+/// Select which type of visualization user would like
+pub fn snapshot_visualizer_menu(conn: &Connection, user: &User) {
+    loop {
+        println!("\n\nTrend Analysis - How would you like to visualize your snapshots?");
+        println!("1. Side-By-Side Comparison");
+        println!("2. Net Worth Graph Over Time");
+        println!("3. Net Worth Forecast and Financial Independence");
+        println!("4. Item, Category and Total Trends");
+        println!("\n0. GO BACK");
+
+        let response = print_instr_get_response(0, 4, || {});
+        let result = match response {
+            0 => return,
+            1 => side_by_side_snapshots(conn, user),
+            2 => net_worth_graph(conn, user),
+            3 => forecast_menu(conn, user),
+            4 => trend_lines_menu(conn, user),
+            _ => {
+                println!("\nThat is not one of the options. Please try again.");
+                Ok(())
+            }
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Allows the user to select which snapshots they would like to view side-by-side
+fn side_by_side_snapshots(conn: &Connection, user: &User) -> Result<()> {
+    // Push all of the snapshots to a vector first (in chronological order)
+    let snapshots: Vec<Snapshot> = list_snapshots(conn, user)?;
+
+    if snapshots.is_empty() {
+        println!("\n\nYou don't have any saved snapshots yet. Hit Enter to go back.");
+        read_or_quit(); // Just to give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    // Print out listing of snapshots
+    println!("\n\nSide-By-Side Comparison");
+    println!("Select up to 5 snapshots to view side-by-side");
+    println!("\nSaved Snapshots:");
+    let mut idx: usize = 0;
+    for snapshot in &snapshots {
+        idx += 1;
+        println!(
+            "{}.  {}:  Net Worth  {}",
+            idx,
+            snapshot.date_today,
+            to_money_string(snapshot.net_worth, &snapshot.currency)
+        );
+    }
+
+    println!("\n0. GO BACK");
+    println!("\nSelect snapshots by entering their numbers separated by a space (eg - 1 5 6)");
+
+    let response = read_or_quit();
+    if response == "0" {
+        return Ok(());
+    }
+    match parse_space_delim_response_for_int_to_index(response, 1, idx) {
+        Some(selected_indices) => print_side_by_side(conn, user, snapshots, selected_indices)?,
+        None => {
+            println!("\nNo correct inputs were given. Please try again.");
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+/// Print out the side by side comparison, then let the user return
+fn print_side_by_side(
+    conn: &Connection,
+    user: &User,
+    snapshots: Vec<Snapshot>,
+    mut selected_indices: Vec<usize>,
+) -> Result<()> {
+    const COL_WIDTH: usize = 20;
+    selected_indices.sort();
+
+    // Get all of the items and categories for this user
+    let mut asset_items = get_all_items(conn, user, &BalanceSheetHalf::Assets)?;
+    let mut liability_items = get_all_items(conn, user, &BalanceSheetHalf::Liabilities)?;
+
+    // Sort the assets and liabilities by timeline original then secondarily timeline created
+    // This will sort unique items based on the unchanging timeline original then put them in order of updates
+    asset_items.sort_unstable_by_key(|a| (a.timeline_original, a.timeline_created));
+    liability_items.sort_unstable_by_key(|a| (a.timeline_original, a.timeline_created));
+
+    // Each column converts its totals into the current base currency with the rates frozen in its snapshot
+    let mut converters: Vec<Converter> = vec![];
+    for selected_index in &selected_indices {
+        converters.push(snapshot_converter(
+            conn,
+            user,
+            &snapshots[*selected_index],
+            &user.base_currency,
+        )?);
+    }
+    println!("\n\nTotals in {}", user.base_currency);
+
+    // Print the dates of the snapshots
+    print!("\n{}", " ".repeat(MAX_CHARACTERS_ITEM_NAME + 2));
+    for selected_index in &selected_indices {
+        print!(
+            "{}{}",
+            snapshots[*selected_index].date_today,
+            " ".repeat(COL_WIDTH - 10 + 2)
+        );
+    }
+    println!();
+
+    // This code is difficult to maintain and is not readable
+    // This could be improved by breaking logic into functions
+    //   and by creating a new struct for items that include timeline timestamps
+    //   when they are read from the database
+
+    //////////// ASSETS //////////////////////////////
+    println!("ASSETS");
+    let asset_totals = helper_print_half_side_by_side(
+        &snapshots,
+        &selected_indices,
+        &converters,
+        &asset_items,
+        COL_WIDTH,
+    )?;
+
+    // Print Asset totals
+    print!("{}", "_".repeat(MAX_CHARACTERS_ITEM_NAME + 2));
+    for _ in 0..selected_indices.len() {
+        print!("{}", "_".repeat(COL_WIDTH + 2));
+    }
+    println!();
+    print!(
+        "TOTAL ASSETS {} ",
+        " ".repeat(MAX_CHARACTERS_ITEM_NAME - 12)
+    );
+    for asset_total in &asset_totals {
+        let money_len = to_money_string(*asset_total, &user.base_currency).len();
+        print!(
+            "{}{}",
+            to_money_string(*asset_total, &user.base_currency),
+            " ".repeat(COL_WIDTH - money_len + 2)
+        );
+    }
+    print!("\n\n");
+
+    //////////// LIABILITIES //////////////////////////////
+    println!("LIABILITIES");
+    let liability_totals = helper_print_half_side_by_side(
+        &snapshots,
+        &selected_indices,
+        &converters,
+        &liability_items,
+        COL_WIDTH,
+    )?;
+
+    // Print Liability totals
+    print!("{}", "_".repeat(MAX_CHARACTERS_ITEM_NAME + 2));
+    for _ in 0..selected_indices.len() {
+        print!("{}", "_".repeat(COL_WIDTH + 2));
+    }
+    println!();
+    print!(
+        "TOTAL LIABILITIES {} ",
+        " ".repeat(MAX_CHARACTERS_ITEM_NAME - 17)
+    );
+    for liability_total in &liability_totals {
+        let money_len = to_money_string(*liability_total, &user.base_currency).len();
+        print!(
+            "{}{}",
+            to_money_string(*liability_total, &user.base_currency),
+            " ".repeat(COL_WIDTH - money_len + 2)
+        );
+    }
+    print!("\n\n");
+
+    ///////  GRAND TOTALS  ////////////////////////////////
+    // Print Grand totals
+    print!("{}", "_".repeat(MAX_CHARACTERS_ITEM_NAME + 2));
+    for _ in 0..selected_indices.len() {
+        print!("{}", "_".repeat(COL_WIDTH + 2));
+    }
+    println!();
+    print!(
+        "TOTAL NET WORTH {} ",
+        " ".repeat(MAX_CHARACTERS_ITEM_NAME - 15)
+    );
+    for i in 0..selected_indices.len() {
+        let grand_total = asset_totals[i] - liability_totals[i];
+        let money_len = to_money_string(grand_total, &user.base_currency).len();
+        print!(
+            "{}{}",
+            to_money_string(grand_total, &user.base_currency),
+            " ".repeat(COL_WIDTH - money_len + 2)
+        );
+    }
+    print!("\n\n");
+    Ok(())
+}
+
+/// Helper function to print one half of the side-by-side visualizer
+/// Returns the sum totals for each snapshot (half) as a Vector in the converters' base currency
+fn helper_print_half_side_by_side(
+    snapshots: &[Snapshot],
+    selected_indices: &[usize],
+    converters: &[Converter],
+    items: &[Item],
+    col_width: usize,
+) -> Result<Vec<Amount>> {
+    // Running total of printed values matching snapshot by index
+    let mut totals: Vec<Amount> = vec![Amount::ZERO; selected_indices.len()];
+
+    // Loop through the items and print a new one every time it's crossed
+    let mut prev_item_origin: usize = usize::MAX;
+    for (idx, item) in items.iter().enumerate() {
+        if item.timeline_original == prev_item_origin {
+            // This item name is already printed
+            continue;
+        }
+        // This is a new item to be examined
+        prev_item_origin = item.timeline_original;
+        // Check if this item is involved in snapshots. Don't print if not
+        // Must get timeline_origin and the last iteration of this item's timeline_deleted
+        // Then see if any of the snapshot timelines fall into this range
+        let mut idx_offset: usize = 0;
+        // Traverse to the last iteration of this item
+        loop {
+            // Check if next index exists
+            if idx + idx_offset + 1 < items.len() {
+                // Check if it is the same item
+                if items[idx + idx_offset + 1].timeline_original == prev_item_origin {
+                    // Increment idx_offset
+                    idx_offset += 1;
+                } else {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        let first_created = &items[idx].timeline_original;
+        let last_deleted = &items[idx + idx_offset].timeline_deleted;
+        // Check if any of the timelines fall in this range
+        let mut never_used: bool = true;
+        for selected_index in selected_indices {
+            let current_timeline = &snapshots[*selected_index].timeline;
+            if current_timeline >= first_created && current_timeline < last_deleted {
+                never_used = false;
+            }
+        }
+        if never_used {
+            continue;
+        }
+
+        // If the code gets here, then the item is used an should be printed
+        // Print the most recent version of the name by using the index and offset
+        print!(
+            "{} {} ",
+            items[idx + idx_offset].item,
+            "-".repeat(MAX_CHARACTERS_ITEM_NAME - items[idx + idx_offset].item.len())
+        );
+
+        // After printing item name, must print value for each snapshot
+        // Subsequent items in the vector will be other instances of this item if needed
+        for (col, selected_index) in selected_indices.iter().enumerate() {
+            let current_timeline = snapshots[*selected_index].timeline;
+            if item.timeline_created <= current_timeline && item.timeline_deleted > current_timeline
+            {
+                // Correct value, print it here and add to running total
+                print!("{}", to_money_string(item.value, &item.currency));
+                totals[col] += converters[col].convert(item.value, &item.currency)?;
+                // If this isn't the last column, print more dashes, otherwise new line
+                let money_len = to_money_string(item.value, &item.currency).len();
+                if col < selected_indices.len() - 1 {
+                    print!(" {} ", "-".repeat(col_width - money_len));
+                } else {
+                    println!();
+                }
+            } else if item.timeline_created > current_timeline {
+                // This item was created after this timeline point
+                // Print a placeholder
+                print!(" {} ", "-".repeat(col_width));
+                // New line if this is the last column
+                if col == selected_indices.len() - 1 {
+                    println!();
+                }
+            } else {
+                // This item was deleted before this timeline point
+                let mut offset: usize = 0;
+                loop {
+                    // Step through the vector trying to find a later version of this same item
+                    offset += 1;
+                    // Make sure this is a valid index
+                    if idx + offset < items.len() {
+                        let item_to_check = &items[idx + offset];
+                        if item_to_check.timeline_original != prev_item_origin {
+                            // Not the same item so just print dashes and move on
+                            print!(" {} ", "-".repeat(col_width));
+                            // New line if this is the last column
+                            if col == selected_indices.len() - 1 {
+                                println!();
+                            }
+                            break;
+                        } else {
+                            // Same item, check again if the timeline matches. If not, just try on next one via loop
+                            if item_to_check.timeline_created <= current_timeline
+                                && item_to_check.timeline_deleted > current_timeline
+                            {
+                                // Can print its value here
+                                print!(
+                                    "{}",
+                                    to_money_string(item_to_check.value, &item_to_check.currency)
+                                );
+                                totals[col] += converters[col]
+                                    .convert(item_to_check.value, &item_to_check.currency)?;
+                                // If this isn't the last column, print more dashes, otherwise new line
+                                let money_len =
+                                    to_money_string(item_to_check.value, &item_to_check.currency)
+                                        .len();
+                                if col < selected_indices.len() - 1 {
+                                    print!(" {} ", "-".repeat(col_width - money_len));
+                                } else {
+                                    println!();
+                                }
+                                // Break out of the loop since the correct item was found
+                                break;
+                            }
+                        }
+                    } else {
+                        // End of vector, print dashes
+                        print!(" {} ", "-".repeat(col_width));
+                        // New line if this is the last column
+                        if col == selected_indices.len() - 1 {
+                            println!();
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(totals)
+}
+
+/// Get the first five numbers delimited by spaces within the specified range
+/// Used with the side-by-side snapshots viewer, the trend lines chart and choosing the snapshots in a report
+/// NOTE: This converts the number from the number listed to an index by subtracting 1
+pub fn parse_space_delim_response_for_int_to_index(
+    response: String,
+    minval: usize,
+    maxval: usize,
+) -> Option<Vec<usize>> {
+    let vals_str: Vec<&str> = response.split(' ').collect();
+    let mut vals: Vec<usize> = vec![];
+    for val in vals_str {
+        match val.parse::<usize>() {
+            Ok(parsed) => {
+                // There is a number here
+                if parsed >= minval && parsed <= maxval && !vals.contains(&(parsed - 1)) {
+                    // This is a correct number, subtract one to convert input number to index in snapshots vector
+                    vals.push(parsed - 1);
+                } else if parsed >= minval && parsed <= maxval && vals.contains(&(parsed - 1)) {
+                    // This value is already in the vals vector
+                    println!("\n{} was a duplicate. It only can be entered once.", val);
+                    continue;
+                } else {
+                    // This is a number but outside the acceptable range
+                    println!("\n{} wasn't included as it was an invalid response.", val);
+                    continue;
+                }
+            }
+            Err(_) => {
+                if !val.is_empty() {
+                    println!(
+                        "\n\"{}\" wasn't included as it was not a valid number.",
+                        val
+                    );
+                    println!("Enter only the numbers you'd like to include, separated by a single space.");
+                }
+                continue;
+            }
+        }
+    }
+    if vals.is_empty() {
+        None
+    } else if vals.len() > 5 {
+        Some(vals[0..5].to_vec())
+    } else {
+        Some(vals)
+    }
+}
+
+/// Uses the textplots package to plot the net worth trend over the snapshots in the terminal
+fn net_worth_graph(conn: &Connection, user: &User) -> Result<()> {
+    // Push all of the snapshots to a vector first (in chronological order)
+    let snapshots: Vec<Snapshot> = list_snapshots(conn, user)?;
+
+    if snapshots.is_empty() {
+        println!("\n\nYou don't have any saved snapshots yet. Hit Enter to go back.");
+        read_or_quit(); // Just to give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    if snapshots.len() == 1 {
+        println!("\n\nYou need at least 2 snapshots for a trend line. Hit Enter to go back.");
+        read_or_quit(); // Just to give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    // Put every snapshot in the current base currency using the rates frozen with it
+    let base_currency = user.base_currency.clone();
+    let mut net_worths: Vec<Amount> = vec![];
+    for snapshot in &snapshots {
+        let converter = snapshot_converter(conn, user, snapshot, &user.base_currency)?;
+        net_worths.push(converter.convert(snapshot.net_worth, &snapshot.currency)?);
+    }
+
+    let mut show_unscaled = true;
+
+    // Switch back and forth between scaled and unscaled x-axis displays
+    loop {
+        if show_unscaled {
+            // UNSCALED X-AXIS VERSION OF THE PLOT
+            // Create a Vector of points with snapshot index as x
+            let mut points: Vec<(f32, f32)> = vec![];
+            let mut min_val: f64 = f64::MAX;
+            let mut max_val: f64 = f64::MIN;
+            for (idx, net_worth) in net_worths.iter().enumerate() {
+                // Will later index back to snapshots vector to get date
+                points.push((idx as f32, net_worth.to_f64() as f32));
+                if net_worth.to_f64() < min_val {
+                    min_val = net_worth.to_f64();
+                }
+                if net_worth.to_f64() > max_val {
+                    max_val = net_worth.to_f64();
+                }
+            }
+            let lines = Shape::Lines(points.as_slice());
+            let mut plot = Chart::new_with_y_range(
+                250,
+                80,
+                0.0,
+                (snapshots.len() - 1) as f32,
+                (min_val - (0.2 * min_val.abs())) as f32,
+                (max_val + (0.2 * min_val.abs())) as f32,
+            );
+
+            println!("\n\n\nYour Net Worth Trend  (in {})", base_currency);
+
+            // Clone the dates for labeling the x-axis within the closure below which consumes the values
+            // Avoids cloning the entire snapshots vector
+            let closure_dates: Vec<String> = snapshots
+                .iter()
+                .map(|snapshot| snapshot.date_today.clone())
+                .collect();
+
+            let closure_currency = base_currency.clone();
+
+            plot.lineplot(&lines)
+                .x_label_format(LabelFormat::Custom(Box::new(move |xval| {
+                    closure_dates[xval as usize].to_owned()
+                })))
+                .y_label_format(LabelFormat::Custom(Box::new(move |yval| {
+                    to_money_string(Amount::from_f64(yval as f64), &closure_currency)
+                })))
+                .y_tick_display(TickDisplay::Sparse)
+                .nice();
+
+            println!("\n\nNote: X-Axis not to scale\n");
+            println!("What would you like to do next?");
+            println!("1. View the trend with the x-axis to scale");
+            println!("0. GO BACK");
+
+            match print_instr_get_response(0, 1, || {}) {
+                0 => break,
+                1 => show_unscaled = false,
+                _ => println!("\nThat is not one of the options. Please try again."),
+            }
+        } else {
+            // SCALED X-AXIS VERSION OF THE PLOT
+            // Guaranteed to have at least 2 snapshots
+            let first_date = snapshot_date(&snapshots[0])?;
+            let last_date = snapshot_date(&snapshots[snapshots.len() - 1])?;
+
+            // Make a new vector of points with duration in days from the first date for x
+            // Min and max y values don't change from above
+            let mut points: Vec<(f32, f32)> = vec![];
+            let mut min_val: f64 = f64::MAX;
+            let mut max_val: f64 = f64::MIN;
+            for (snapshot, net_worth) in snapshots.iter().zip(&net_worths) {
+                let snap_date = snapshot_date(snapshot)?;
+                points.push((
+                    (snap_date - first_date).num_days() as f32,
+                    net_worth.to_f64() as f32,
+                ));
+                if net_worth.to_f64() < min_val {
+                    min_val = net_worth.to_f64();
+                }
+                if net_worth.to_f64() > max_val {
+                    max_val = net_worth.to_f64();
+                }
+            }
+            let lines = Shape::Lines(points.as_slice());
+            let mut plot = Chart::new_with_y_range(
+                250,
+                80,
+                0.0,
+                (last_date - first_date).num_days() as f32,
+                (min_val - (0.2 * min_val.abs())) as f32,
+                (max_val + (0.2 * min_val.abs())) as f32,
+            );
+
+            println!("\n\n\nYour Net Worth Trend  (in {})", base_currency);
+            let closure_currency = base_currency.clone();
+
+            plot.lineplot(&lines)
+                .x_label_format(LabelFormat::Custom(Box::new(move |xval| {
+                    format!("{}", first_date + Duration::days(xval as i64))
+                })))
+                .y_label_format(LabelFormat::Custom(Box::new(move |yval| {
+                    to_money_string(Amount::from_f64(yval as f64), &closure_currency)
+                })))
+                .y_tick_display(TickDisplay::Sparse)
+                .nice();
+
+            println!("\n\nNote: X-Axis is now to scale\n");
+            println!("What would you like to do next?");
+            println!("1. View the trend with the x-axis unscaled");
+            println!("0. GO BACK");
+
+            match print_instr_get_response(0, 1, || {}) {
+                0 => break,
+                1 => show_unscaled = true,
+                _ => println!("\nThat is not one of the options. Please try again."),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Every item ever on the balance sheet, current or removed, in its latest version
+fn latest_item_versions(conn: &Connection, user: &User) -> Result<Vec<Item>> {
+    let mut latest: Vec<Item> = vec![];
+    for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+        let mut items = get_all_items(conn, user, &which_half)?;
+        items.sort_by_key(|item| (item.timeline_original, item.timeline_created));
+        for item in items {
+            match latest.last_mut() {
+                Some(last)
+                    if last.is_asset == item.is_asset
+                        && last.timeline_original == item.timeline_original =>
+                {
+                    *last = item
+                }
+                _ => latest.push(item),
+            }
+        }
+    }
+    Ok(latest)
+}
+
+/// Choose up to 5 items, categories or totals and chart their value across the snapshots
+fn trend_lines_menu(conn: &Connection, user: &User) -> Result<()> {
+    if list_snapshots(conn, user)?.len() < 2 {
+        println!("\n\nYou need at least 2 snapshots for a trend line. Hit Enter to go back.");
+        read_or_quit(); // Just to give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    // Everything that can be charted, with the name it goes by in the chart
+    let mut choices: Vec<(String, TrendLine)> = vec![
+        (
+            String::from("Total Assets"),
+            TrendLine::Total { is_asset: true },
+        ),
+        (
+            String::from("Total Liabilities"),
+            TrendLine::Total { is_asset: false },
+        ),
+    ];
+    for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+        for category in get_categories(conn, user, &which_half)? {
+            choices.push((
+                format!("{} ({})", category.category, which_half.to_str()),
+                TrendLine::Category {
+                    is_asset: category.is_asset,
+                    category_lower: category.category_lower,
+                },
+            ));
+        }
+    }
+    let num_totals_categories = choices.len();
+    for item in latest_item_versions(conn, user)? {
+        let removed = if item.is_deleted { ", removed" } else { "" };
+        let which_half = match item.is_asset {
+            true => BalanceSheetHalf::Assets,
+            false => BalanceSheetHalf::Liabilities,
+        };
+        choices.push((
+            format!("{} ({}{})", item.item, which_half.to_str(), removed),
+            TrendLine::Item {
+                is_asset: item.is_asset,
+                item_original: item.timeline_original,
+            },
+        ));
+    }
+
+    println!("\n\nItem, Category and Total Trends");
+    println!("Select up to 5 lines to chart across your snapshots");
+    println!("\nTotals and Categories:");
+    for (idx, (name, _)) in choices.iter().enumerate() {
+        if idx == num_totals_categories {
+            println!("\nItems:");
+        }
+        println!("{}. {}", idx + 1, name);
+    }
+    println!("\n0. GO BACK");
+    println!("\nSelect lines by entering their numbers separated by a space (eg - 1 2 5)");
+    let selected_indices =
+        match parse_space_delim_response_for_int_to_index(read_or_quit(), 1, choices.len()) {
+            Some(indices) => indices,
+            None => return Ok(()),
+        };
+
+    let mut lines: Vec<(String, Vec<(NaiveDate, Amount)>)> = vec![];
+    for idx in selected_indices {
+        let (name, line) = &choices[idx];
+        let trend = snapshot_trend(conn, user, line)?;
+        if trend.is_empty() {
+            println!("\n{} isn't in any of your snapshots.", name);
+        } else {
+            lines.push((name.clone(), trend));
+        }
+    }
+    if !lines.is_empty() {
+        print_trend_chart(&lines, &user.base_currency);
+        print_trend_table(&lines, &user.base_currency);
+    }
+    println!("\nHit Enter to go back.");
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Chart each named line across the snapshots in its own color, with the x-axis to scale
+/// Only the first 5 lines are charted
+pub fn print_trend_chart(lines: &[(String, Vec<(NaiveDate, Amount)>)], currency: &str) {
+    let lines = &lines[..lines.len().min(TREND_COLORS.len())];
+    let dates = lines
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|(date, _)| *date));
+    let Some(first_date) = dates.clone().min() else {
+        return;
+    };
+    let last_date = dates.max().unwrap_or(first_date);
+    let x = |date: NaiveDate| (date - first_date).num_days() as f32;
+
+    let all_points: Vec<Vec<(f32, f32)>> = lines
+        .iter()
+        .map(|(_, points)| {
+            points
+                .iter()
+                .map(|(date, value)| (x(*date), value.to_f64() as f32))
+                .collect()
+        })
+        .collect();
+    let values = all_points.iter().flatten();
+    let min_val = values.clone().map(|(_, y)| *y).fold(f32::MAX, f32::min);
+    let max_val = values.map(|(_, y)| *y).fold(f32::MIN, f32::max);
+    let padding = ((max_val - min_val) * 0.1).max(1.0);
+
+    println!("\n\n\nYour Trends  (in {})", currency);
+    for ((name, _), (color_name, _)) in lines.iter().zip(TREND_COLORS.iter()) {
+        println!("{} is {}", color_name, name);
+    }
+    println!();
+    let shapes: Vec<Shape> = all_points
+        .iter()
+        .map(|points| Shape::Lines(points.as_slice()))
+        .collect();
+    let mut chart = Chart::new_with_y_range(
+        250,
+        80,
+        0.0,
+        x(last_date).max(1.0),
+        min_val - padding,
+        max_val + padding,
+    );
+    let mut plot = &mut chart;
+    for (shape, (_, color)) in shapes.iter().zip(TREND_COLORS.iter()) {
+        plot = plot.linecolorplot(shape, *color);
+    }
+    let closure_currency = String::from(currency);
+    plot.x_label_format(LabelFormat::Custom(Box::new(move |xval| {
+        format!("{}", first_date + Duration::days(xval as i64))
+    })))
+    .y_label_format(LabelFormat::Custom(Box::new(move |yval| {
+        to_money_string(Amount::from_f64(yval as f64), &closure_currency)
+    })))
+    .y_tick_display(TickDisplay::Sparse)
+    .nice();
+}
+
+/// Print the value of each named line at every snapshot date, leaving a blank where a line has no value
+pub fn print_trend_table(lines: &[(String, Vec<(NaiveDate, Amount)>)], currency: &str) {
+    const COL_WIDTH: usize = 20;
+    let mut dates: Vec<NaiveDate> = lines
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|(date, _)| *date))
+        .collect();
+    dates.sort();
+    dates.dedup();
+
+    print!("\n{:<12}", "Date");
+    for (name, _) in lines {
+        let name: String = name.chars().take(COL_WIDTH - 2).collect();
+        print!("{:>width$}", name, width = COL_WIDTH);
+    }
+    println!();
+    for date in dates {
+        print!("{:<12}", date.to_string());
+        for (_, points) in lines {
+            // The last snapshot of the day is the one that counts
+            let value = match points
+                .iter()
+                .rev()
+                .find(|(point_date, _)| *point_date == date)
+            {
+                Some((_, value)) => to_money_string(*value, currency),
+                None => String::new(),
+            };
+            print!("{:>width$}", value, width = COL_WIDTH);
+        }
+        println!();
+    }
+}
+
+/// Forecast the net worth forward and work out the financial independence numbers
+fn forecast_menu(conn: &Connection, user: &User) -> Result<()> {
+    loop {
+        println!("\n\nNet Worth Forecast - How should your net worth be projected?");
+        println!("1. Continue the Trend of Your Snapshots");
+        println!("2. Grow Assets at Assumed Returns and Add the Budget's Monthly Net");
+        println!("3. Set the Assumed Return of an Asset Category");
+        println!("\n0. GO BACK");
+        let method = match print_instr_get_response(0, 3, || {}) {
+            1 => ForecastMethod::Trend,
+            2 => ForecastMethod::Returns,
+            3 => {
+                category_return_prompt(conn, user)?;
+                continue;
+            }
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+
+        println!("\nHow many years ahead? Just hit Enter for 10.");
+        let years = loop {
+            let response = read_or_quit();
+            if response.is_empty() {
+                break 10;
+            }
+            match response.trim().parse::<u32>() {
+                Ok(years) if years > 0 => break years,
+                _ => println!("\nPlease enter a whole number of years."),
+            }
+        };
+        println!(
+            "\nWhat yearly withdrawal rate is safe in retirement? (percent) Just hit Enter for {}.",
+            DEFAULT_WITHDRAWAL_RATE
+        );
+        let withdrawal_rate = loop {
+            let response = read_or_quit();
+            if response.is_empty() {
+                break DEFAULT_WITHDRAWAL_RATE;
+            }
+            match parse_withdrawal_rate(&response) {
+                Ok(rate) => break rate,
+                Err(error) => println!("\n{}", error),
+            }
+        };
+        println!("\nWhat net worth would make you financially independent?");
+        println!(
+            "Just hit Enter to use your yearly budget expenses divided by the withdrawal rate."
+        );
+        let target = loop {
+            let response = read_or_quit();
+            if response.is_empty() {
+                break None;
+            }
+            match response.parse::<Amount>() {
+                Ok(amount) if amount.is_negative() => println!("\nPlease enter a positive number."),
+                Ok(amount) => break Some(amount),
+                Err(error) => println!("\n{}. Please enter a valid number.", error),
+            }
+        };
+
+        let forecast = forecast_net_worth(conn, user, method, years, Local::now().date_naive())?;
+        let metrics = fire_metrics(conn, user, &forecast, withdrawal_rate, target)?;
+        print_forecast_chart(
+            &net_worth_history(conn, user)?,
+            &forecast,
+            &user.base_currency,
+        );
+        if method == ForecastMethod::Returns {
+            print_category_returns(&get_category_returns(conn, user)?);
+        }
+        print_fire_metrics(&forecast, &metrics, &user.base_currency);
+        println!("\nHit Enter to go back.");
+        read_or_quit(); // Give the user a chance to acknowledge
+    }
+}
+
+/// Ask for an asset category and the annual return to assume for it
+fn category_return_prompt(conn: &Connection, user: &User) -> Result<()> {
+    let returns = get_category_returns(conn, user)?;
+    println!("\nWhich asset category would you like to set?");
+    for (idx, (category, rate)) in returns.iter().enumerate() {
+        println!("{}. {}  ({}% a year)", idx + 1, category.category, rate);
+    }
+    println!("\n0. GO BACK");
+    let category = match print_instr_get_response(0, returns.len(), || {}) {
+        x if x > 0 && x <= returns.len() => &returns[x - 1].0,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(()),
+    };
+    let rate = loop {
+        println!(
+            "\nWhat yearly return should {} assume? (percent, eg - 7 for stocks or 0 for cash)",
+            category.category
+        );
+        match parse_return_rate(&read_or_quit()) {
+            Ok(rate) => break rate,
+            Err(error) => println!("\n{}", error),
+        }
+    };
+    set_category_return(conn, user, &category.category, rate)?;
+    Ok(())
+}
+
+/// Print the assumed annual return of each asset category
+pub fn print_category_returns(returns: &[(Category, Decimal)]) {
+    println!("\nAssumed yearly returns:");
+    for (category, rate) in returns {
+        println!("    {}  {}%", category.category, rate);
+    }
+}
+
+/// Chart the net worth at each snapshot followed by the forecast, with the x-axis to scale
+pub fn print_forecast_chart(history: &[(NaiveDate, Amount)], forecast: &Forecast, currency: &str) {
+    let (today, _) = forecast.points[0];
+    let first_date = history.first().map_or(today, |(date, _)| *date.min(&today));
+    let (last_date, _) = forecast.points[forecast.points.len() - 1];
+    let x = |date: NaiveDate| (date - first_date).num_days() as f32;
+
+    let history_points: Vec<(f32, f32)> = history
+        .iter()
+        .map(|(date, value)| (x(*date), value.to_f64() as f32))
+        .collect();
+    let forecast_points: Vec<(f32, f32)> = forecast
+        .points
+        .iter()
+        .map(|(date, value)| (x(*date), value.to_f64() as f32))
+        .collect();
+    let values = history_points.iter().chain(forecast_points.iter());
+    let min_val = values.clone().map(|(_, y)| *y).fold(f32::MAX, f32::min);
+    let max_val = values.map(|(_, y)| *y).fold(f32::MIN, f32::max);
+    let padding = ((max_val - min_val) * 0.1).max(1.0);
+
+    println!(
+        "\n\n\nYour Net Worth Forecast - {}  (in {})",
+        forecast.method.to_str(),
+        currency
+    );
+    println!("Green is the forecast from today, the rest is your snapshots\n");
+    let history_lines = Shape::Lines(history_points.as_slice());
+    let forecast_lines = Shape::Lines(forecast_points.as_slice());
+    let mut plot = Chart::new_with_y_range(
+        250,
+        80,
+        0.0,
+        x(last_date),
+        min_val - padding,
+        max_val + padding,
+    );
+    let closure_currency = String::from(currency);
+    plot.lineplot(&history_lines)
+        .linecolorplot(&forecast_lines, FORECAST_COLOR)
+        .x_label_format(LabelFormat::Custom(Box::new(move |xval| {
+            format!("{}", first_date + Duration::days(xval as i64))
+        })))
+        .y_label_format(LabelFormat::Custom(Box::new(move |yval| {
+            to_money_string(Amount::from_f64(yval as f64), &closure_currency)
+        })))
+        .y_tick_display(TickDisplay::Sparse)
+        .nice();
+}
+
+/// Print the forecast's end point and the financial independence numbers
+pub fn print_fire_metrics(forecast: &Forecast, metrics: &FireMetrics, currency: &str) {
+    let (end_date, end_value) = forecast.points[forecast.points.len() - 1];
+    println!(
+        "\nNet worth today: {}",
+        to_money_string(metrics.net_worth, currency)
+    );
+    println!(
+        "Forecast for {}: {}  (changing {} a month to begin with)",
+        end_date,
+        to_money_string(end_value, currency),
+        to_money_string(forecast.monthly_change, currency)
+    );
+
+    println!("\nFINANCIAL INDEPENDENCE");
+    println!(
+        "Budget: {} a month in, {} a month out",
+        to_money_string(metrics.monthly_income, currency),
+        to_money_string(metrics.monthly_expenses, currency)
+    );
+    match metrics.savings_rate {
+        Some(rate) => println!("Savings rate: {:.1}% of income", rate),
+        None => println!("Savings rate: there is no income in the budget"),
+    }
+    println!(
+        "Target: {}  (withdrawing {}% a year)",
+        to_money_string(metrics.target, currency),
+        metrics.withdrawal_rate
+    );
+    match (metrics.target_date, metrics.years_to_target) {
+        (Some(_), Some(years)) if years <= 0.0 => println!("You have already reached it!"),
+        (Some(date), Some(years)) => {
+            println!(
+                "The forecast reaches it around {} ({:.1} years)",
+                date, years
+            )
+        }
+        _ => println!("The forecast doesn't reach it by {}", end_date),
+    }
+    println!(
+        "Safe withdrawal: {} a year now, {} a year by {}",
+        to_money_string(metrics.safe_withdrawal, currency),
+        to_money_string(metrics.projected_safe_withdrawal, currency),
+        end_date
+    );
+}
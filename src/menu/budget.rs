@@ -0,0 +1,793 @@
+use super::budget_snapshots::{create_budget_snapshot, view_budget_snapshot_menu};
+use super::{get_currency_response, report_error};
+use burkebudget::budget::{self, budget_calendar, get_relevant_items, initialize_budget};
+use burkebudget::currency::{current_converter, Converter};
+use burkebudget::error::Result;
+use burkebudget::frequency::FREQUENCY_KINDS;
+use burkebudget::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+#[derive(Debug, PartialEq)]
+enum BudgetSelection<'a> {
+    Some(&'a BudgetItem),
+    NewCategory,
+    NewItem,
+    RenameCategory,
+    GoBack,
+}
+
+pub fn budget_half_entry_point(
+    conn: &Connection,
+    user: &User,
+    which_half: BudgetHalf,
+) -> Result<()> {
+    initialize_budget(conn, user)?;
+    let (mut categories, mut items) = get_relevant_items(conn, user, &which_half)?;
+    loop {
+        let response = print_budget_half_get_response(&categories, &items, &which_half);
+
+        let result = match response {
+            BudgetSelection::Some(item) => {
+                // The item was selected from this vector so it is always found
+                let idx = items.iter().position(|i| i == item).unwrap_or_default();
+                update_item(conn, user, &which_half, &mut categories, &mut items, idx)
+            }
+            BudgetSelection::NewCategory => {
+                create_new_category(conn, user, &which_half, &mut categories)
+            }
+            BudgetSelection::NewItem => {
+                create_new_item(conn, user, &which_half, &mut categories, &mut items)
+            }
+            BudgetSelection::RenameCategory => {
+                rename_category(conn, user, &which_half, &mut categories, &mut items)
+            }
+            BudgetSelection::GoBack => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+            // Reload from the database since the vectors may no longer match it
+            (categories, items) = get_relevant_items(conn, user, &which_half)?;
+        }
+    }
+}
+
+pub fn budget_whole_entry_point(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    let (income_categories, income_items) = get_relevant_items(conn, user, &BudgetHalf::Income)?;
+    let (expense_categories, expense_items) =
+        get_relevant_items(conn, user, &BudgetHalf::Expenses)?;
+
+    let converter = current_converter(conn, user)?;
+    loop {
+        let response = print_budget_get_response(
+            &converter,
+            &income_categories,
+            &income_items,
+            &expense_categories,
+            &expense_items,
+        )?;
+
+        let result = match response {
+            1 => create_budget_snapshot(conn, user),
+            2 => view_budget_snapshot_menu(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print out the half of the budget and find out what the user wants to do
+/// It only receives the relevant half categories and items
+fn print_budget_half_get_response<'a>(
+    categories: &Vec<BudgetCategory>,
+    items: &'a Vec<BudgetItem>,
+    which_half: &BudgetHalf,
+) -> BudgetSelection<'a> {
+    println!("\nCurrent list of {}:", which_half.to_str().to_lowercase());
+    let mut idx: usize = 1;
+    let mut sorted_items: Vec<&BudgetItem> = vec![];
+
+    for category in categories {
+        // Check if any of the items are in this category
+        let mut no_items_found_in_cat = true;
+        for item in items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in items {
+            if item.category_lower == category.category_lower {
+                print!("    {}. {} ", idx, item.item);
+                let mut num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 1 - item.item.len();
+                // Support prooper formatting up to 999 items
+                if idx >= 10 {
+                    num_dashes -= 1;
+                }
+                if idx >= 100 {
+                    num_dashes -= 1;
+                }
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                print!(" {}", to_money_string(item.value, &item.currency));
+                if item.frequency == Frequency::Monthly {
+                    println!();
+                } else {
+                    println!(" {}", item.frequency.describe());
+                }
+                idx += 1;
+                sorted_items.push(item);
+            }
+        }
+    }
+    println!("\n{}. NEW CATEGORY", idx);
+    idx += 1;
+    println!("    {}. NEW ITEM", idx);
+    idx += 1;
+    println!("{}. RENAME CATEGORY", idx);
+    println!("\n 0. GO BACK - Budget Menu");
+    let response = print_instr_get_response(0, idx, || {
+        println!("\nEnter the number of the item you'd like to update / delete, or one of the other numbers");
+    });
+    match response {
+        x if x > 0 && x <= idx - 3 => BudgetSelection::Some(sorted_items.remove(x - 1)),
+        x if x == idx - 2 => BudgetSelection::NewCategory,
+        x if x == idx - 1 => BudgetSelection::NewItem,
+        x if x == idx => BudgetSelection::RenameCategory,
+        // 0 (or anything unexpected) goes back
+        _ => BudgetSelection::GoBack,
+    }
+}
+
+/// Print out the whole budget and find out what the user wants to do next
+fn print_budget_get_response(
+    converter: &Converter,
+    income_categories: &Vec<BudgetCategory>,
+    income_items: &Vec<BudgetItem>,
+    expense_categories: &Vec<BudgetCategory>,
+    expense_items: &Vec<BudgetItem>,
+) -> Result<usize> {
+    print_budget(
+        converter,
+        income_categories,
+        income_items,
+        expense_categories,
+        expense_items,
+    )?;
+
+    println!(
+        "\n\nBudget Snapshots keep a copy of the budget as it is today to look back on later."
+    );
+    println!("It is recommended to take one whenever the budget changes a lot.\n");
+    Ok(print_instr_get_response(0, 2, || {
+        println!("1. Take a budget snapshot");
+        println!("2. View or delete a budget snapshot");
+        println!("\n0. Go Back - Budget Menu");
+    }))
+}
+
+/// Print out the whole budget without prompting and return the monthly net
+/// Items are shown in their own currency and the totals are converted into the converter's base currency
+pub fn print_budget(
+    converter: &Converter,
+    income_categories: &Vec<BudgetCategory>,
+    income_items: &Vec<BudgetItem>,
+    expense_categories: &Vec<BudgetCategory>,
+    expense_items: &Vec<BudgetItem>,
+) -> Result<Amount> {
+    let today_date = Local::now().format("%Y-%m-%d").to_string();
+    println!(
+        "\n\nCurrent Monthly Budget - {}  (Totals in {})",
+        today_date,
+        converter.base()
+    );
+    print_budget_body(
+        converter,
+        income_categories,
+        income_items,
+        expense_categories,
+        expense_items,
+    )
+}
+
+/// Print the income, expenses and totals of a budget under a heading printed by the caller
+/// Returns the monthly net in the converter's base currency
+pub fn print_budget_body(
+    converter: &Converter,
+    income_categories: &Vec<BudgetCategory>,
+    income_items: &Vec<BudgetItem>,
+    expense_categories: &Vec<BudgetCategory>,
+    expense_items: &Vec<BudgetItem>,
+) -> Result<Amount> {
+    println!("\nINCOME");
+    let mut income_total = Amount::ZERO;
+    for category in income_categories {
+        // Check if any of the items are in this category
+        let mut no_items_found_in_cat = true;
+        for item in income_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in income_items {
+            if item.category_lower == category.category_lower {
+                print!("    {} ", item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                print_monthly_value(item);
+                income_total += converter.convert(item.monthly_value(), &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Income");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 2) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(income_total, converter.base()));
+
+    println!("\nEXPENSES");
+    let mut expense_total = Amount::ZERO;
+    for category in expense_categories {
+        // Check if any of the items are in this category
+        let mut no_items_found_in_cat = true;
+        for item in expense_items {
+            if item.category_lower == category.category_lower {
+                no_items_found_in_cat = false;
+            }
+        }
+        if no_items_found_in_cat {
+            continue; // Don't need to print this category if it has no items
+        }
+        println!("{}", category.category);
+        for item in expense_items {
+            if item.category_lower == category.category_lower {
+                print!("    {} ", item.item);
+                let num_dashes: usize = MAX_CHARACTERS_ITEM_NAME + 4 - item.item.len();
+                for _ in 0..num_dashes {
+                    print!("-");
+                }
+                print_monthly_value(item);
+                expense_total += converter.convert(item.monthly_value(), &item.currency)?;
+            }
+        }
+    }
+    // Print sum
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 24) {
+        print!("_");
+    }
+    print!("\nTotal Expenses");
+    for _ in 0..(MAX_CHARACTERS_ITEM_NAME - 4) {
+        print!(" ");
+    }
+    println!("{}", to_money_string(expense_total, converter.base()));
+
+    // Grand total
+    let total = income_total - expense_total;
+    println!(
+        "\n\nTOTAL MONTHLY NET ------------------  {}",
+        to_money_string(total, converter.base())
+    );
+    Ok(total)
+}
+
+/// Show what lands in each of the next 12 months
+pub fn budget_calendar_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    let calendar = budget_calendar(conn, user, Local::now().date_naive(), 12)?;
+    print_budget_calendar(&calendar);
+    println!("\n\nPress enter to return.");
+    read_or_quit();
+    Ok(())
+}
+
+/// Print the income, expenses and net of each month, with the quarterly, annual and one-off items under it
+pub fn print_budget_calendar(calendar: &[CalendarMonth]) {
+    let currency = match calendar.first() {
+        Some(month) => &month.currency,
+        None => return,
+    };
+    println!("\n\nBudget Calendar  (Totals in {})", currency);
+    println!(
+        "\n{:<10}{:>18}{:>18}{:>18}",
+        "Month", "Income", "Expenses", "Net"
+    );
+    for month in calendar {
+        println!(
+            "{:<10}{:>18}{:>18}{:>18}",
+            month.month,
+            to_money_string(month.income, currency),
+            to_money_string(month.expenses, currency),
+            to_money_string(month.income - month.expenses, currency)
+        );
+        for (item, amount) in &month.lumpy_items {
+            println!(
+                "    {} {} {}  ({})",
+                if item.is_income { "+" } else { "-" },
+                item.item,
+                to_money_string(*amount, currency),
+                item.frequency.describe()
+            );
+        }
+    }
+}
+
+/// Print an item's monthly value, with the amount each time when it isn't monthly
+fn print_monthly_value(item: &BudgetItem) {
+    print!(" {}", to_money_string(item.monthly_value(), &item.currency));
+    if item.frequency == Frequency::Monthly {
+        println!();
+    } else {
+        println!(
+            "  ({} {})",
+            to_money_string(item.value, &item.currency),
+            item.frequency.describe()
+        );
+    }
+}
+
+/// Category Creator
+/// Mutates the categories Vector and updates the DB
+fn create_new_category(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    categories: &mut Vec<BudgetCategory>,
+) -> Result<()> {
+    println!("What would you like to call the new category?");
+    let cat_name = read_or_quit();
+
+    // Return if the new name is empty
+    if cat_name.is_empty() {
+        println!("The category name cannot be empty. Please try again.");
+        println!("Hit Enter to go back.");
+        // Give the user a chance to acknowledge
+        read_or_quit();
+        return Ok(());
+    }
+
+    // Check if the category already exists
+    for category in &mut *categories {
+        if cat_name.to_lowercase() == category.category_lower {
+            println!("This category already exists as {}.", category.category);
+            println!("It can already be used to create new items.");
+            println!(
+                "If you would like to edit the name, select \"Rename Category\" on the {} listing page.", which_half.to_str()
+            );
+            return Ok(());
+        }
+    }
+    // Insert the new category into the database and add it into the categories vector
+    let category = budget::create_category(conn, user, which_half, &cat_name)?;
+    categories.push(category);
+    Ok(())
+}
+
+/// Item Creator
+/// Mutates that categories and items Vectors and updates the DB
+fn create_new_item(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    categories: &mut Vec<BudgetCategory>,
+    items: &mut Vec<BudgetItem>,
+) -> Result<()> {
+    println!("What would you like to name the new item?");
+    // Get The new item's name
+    let item_name = read_or_quit();
+    // Check if the item already exists
+    for item in &mut *items {
+        if item_name.to_lowercase() == item.item_lower {
+            println!("This item already exists as {}.", item.item);
+            println!("If you would like to edit the item, select it on the next page.");
+            return Ok(());
+        }
+    }
+    // Enforce MAX_CHARACTERS_ITEM_NAME character maximum
+    if item_name.len() > MAX_CHARACTERS_ITEM_NAME {
+        println!(
+            "There is currently a {} character limit on the item name. Please try again.",
+            MAX_CHARACTERS_ITEM_NAME
+        );
+        return Ok(());
+    }
+
+    // Return if the new name is empty
+    if item_name.is_empty() {
+        println!("The item name cannot be empty. Please try again.");
+        println!("Hit Enter to go back.");
+        // Give the user a chance to acknowledge
+        read_or_quit();
+        return Ok(());
+    }
+
+    // Get how often the item happens, then the amount each time
+    let frequency = get_frequency_response(None);
+    if which_half.to_bool() {
+        // is_income
+        println!("How much income comes in each time? (positive number or 0)");
+    } else {
+        println!("How much does this item cost each time? (positive number or 0)");
+    }
+    let mut value = Amount::from_minor(-1);
+    while value.is_negative() {
+        let val_response = read_or_quit();
+        // Make sure the input is valid
+        match val_response.parse::<Amount>() {
+            Ok(poss_value) => {
+                if poss_value.is_negative() {
+                    println!("\nPlease enter a positive number.");
+                } else {
+                    value = poss_value;
+                }
+            }
+            Err(error) => {
+                println!("\n{}. Please enter a valid number.", error);
+            }
+        }
+    }
+    // Get the new item's currency
+    let currency = get_currency_response(&user.base_currency);
+
+    // Get the new item's category
+    // Can also make a new category during this process
+    let mut still_need_category: bool = true;
+    let mut chosen_cat: String = String::new();
+    while still_need_category {
+        println!("\nWhich category would you like to use for this item?");
+        let mut idx: usize = 1;
+        println!("0. GO BACK");
+        for category in &mut *categories {
+            println!("{}. {}", idx, category.category);
+            idx += 1;
+        }
+        println!("\n{}. NEW CATEGORY", idx);
+        let response = print_instr_get_response(0, idx, || {});
+        match response {
+            0 => return Ok(()),
+            x if x > 0 && x < idx => {
+                // Selected category
+                chosen_cat = categories[x - 1].category.clone();
+                still_need_category = false;
+            }
+            x if x == idx => {
+                // New category
+                let num_cats_before: usize = categories.len();
+                create_new_category(conn, user, which_half, categories)?;
+                let num_cats_after: usize = categories.len();
+                if num_cats_before == num_cats_after {
+                    // This only happens if there was an error in category creation
+                    // Must loop again
+                    continue;
+                } else {
+                    chosen_cat = categories[num_cats_after - 1].category.clone();
+                    still_need_category = false;
+                }
+            }
+            _ => println!("\nThat is not one of the options. Please try again."),
+        }
+    }
+
+    // Insert the new item into the database and add it into the items vector
+    let item = budget::create_item(
+        conn,
+        user,
+        which_half,
+        &item_name,
+        value,
+        &currency,
+        frequency,
+        &chosen_cat,
+    )?;
+    items.push(item);
+    Ok(())
+}
+
+/// Ask how often an item comes in or goes out, and which month where that matters
+/// With a current frequency there is also an option to keep it
+fn get_frequency_response(current: Option<Frequency>) -> Frequency {
+    let labels = [
+        "Weekly",
+        "Every 2 weeks",
+        "Twice a month",
+        "Monthly",
+        "Quarterly",
+        "Annually",
+        "One-off (in a single month)",
+    ];
+    println!("\nHow often does this item come in or go out? (Enter the number)");
+    if let Some(current) = current {
+        println!("0. NO CHANGE ({})", current.describe());
+    }
+    for (idx, label) in labels.iter().enumerate() {
+        println!("{}. {}", idx + 1, label);
+    }
+    let min = if current.is_some() { 0 } else { 1 };
+    let response = print_instr_get_response(min, labels.len(), || {});
+    let kind = match (response, current) {
+        (0, Some(current)) => return current,
+        (x, _) if x > 0 && x <= FREQUENCY_KINDS.len() => FREQUENCY_KINDS[x - 1],
+        _ => return Frequency::Monthly,
+    };
+
+    // Quarterly, annual and one-off items also need the month they land in
+    let question = match kind {
+        "quarterly" => "Which month is the first payment of the year in? (1-12)",
+        "annual" => "Which month is it paid in? (1-12)",
+        "one-off" => "Which month is it in? (YYYY-MM)",
+        _ => return Frequency::from_parts(kind, None).unwrap_or_default(),
+    };
+    loop {
+        println!("{}", question);
+        let month = read_or_quit();
+        match Frequency::from_parts(kind, Some(&month)) {
+            Ok(frequency) => return frequency,
+            Err(error) => println!("\n{}", error),
+        }
+    }
+}
+
+/// Rename a category
+/// Mutates the categories and items Vectors and updates the DB
+fn rename_category(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    categories: &mut Vec<BudgetCategory>,
+    items: &mut Vec<BudgetItem>,
+) -> Result<()> {
+    println!("\nRename a category ({}):", which_half.to_str());
+    let mut idx: usize = 0;
+
+    for category in &mut *categories {
+        if category.is_income == which_half.to_bool() {
+            idx += 1;
+            println!("{}. {}", idx, category.category);
+        }
+    }
+    println!("\n0. GO BACK");
+    let response = print_instr_get_response(0, idx, || {
+        println!("Enter the number of the category you'd like to rename.");
+    });
+    match response {
+        0 => (), // return
+        x if x > 0 && x <= idx => {
+            println!(
+                "What would you like to rename {}?",
+                categories[x - 1].category.clone()
+            );
+            let new_name = read_or_quit();
+
+            // Return if the new name is empty
+            if new_name.is_empty() {
+                println!("The category name cannot be empty. Please try again.");
+                println!("Hit Enter to go back.");
+                // Give the user a chance to acknowledge
+                read_or_quit();
+                return Ok(());
+            }
+
+            // Check if it exists already, and return if it does
+            for category in &mut *categories {
+                if new_name.to_ascii_lowercase() == category.category_lower {
+                    println!("That name is already in use as {}.", category.category);
+                    println!("You cannot rename this category to the same name.");
+                    println!("Hit Enter to go back.");
+                    // Give the user a chance to acknowledge
+                    read_or_quit();
+                    return Ok(());
+                }
+            }
+
+            let old_cat_name_lower = String::from(&mut *categories[x - 1].category_lower);
+
+            // Update the DB (including every item with this category) then the Vectors
+            budget::rename_category(conn, user, which_half, &old_cat_name_lower, &new_name)?;
+
+            categories[x - 1].category = new_name.clone();
+            categories[x - 1].category_lower = new_name.to_ascii_lowercase();
+
+            // Update all items that have the category with the updated name
+            // This is necessary since they have both the key lowercase category AND the non-key proper capitalization category
+            for item in items {
+                if item.category_lower == old_cat_name_lower {
+                    item.category = new_name.clone();
+                    item.category_lower = new_name.to_ascii_lowercase();
+                }
+            }
+        }
+        _ => println!("\nThat is not one of the options. Please try again."),
+    }
+    Ok(())
+}
+
+/// Item Update or Delete
+/// Mutates that categories and items Vectors and updates the DB
+fn update_item(
+    conn: &Connection,
+    user: &User,
+    which_half: &BudgetHalf,
+    categories: &mut Vec<BudgetCategory>,
+    items: &mut Vec<BudgetItem>,
+    idx: usize,
+) -> Result<()> {
+    // Need to remove the item from the vector to get ownership
+    let item_chosen = items.remove(idx);
+    println!("\nUpdate item {}", item_chosen.item);
+    println!("\nWould you like to update or delete it?");
+    println!("1. Update");
+    println!("2. Delete");
+    println!("0. GO BACK");
+    let response = print_instr_get_response(0, 2, || {});
+    match response {
+        0 => {
+            // Must push the item back into the vector
+            items.push(item_chosen);
+            // return
+        }
+        2 => {
+            println!("Are you sure you'd like to delete this item? This cannot be undone.");
+            println!("1. Yes");
+            println!("2. No (Go back)");
+            match print_instr_get_response(1, 2, || {}) {
+                1 => {
+                    // Delete the item from the database and from the mutable vector
+                    budget::delete_item(conn, user, &item_chosen)?;
+                    // The item is already removed from the vector and will go out of scope here
+                    // return
+                }
+                2 => {
+                    // Must push the item back into the vector
+                    items.push(item_chosen);
+                    // return
+                }
+                _ => println!("\nThat is not one of the options. Please try again."),
+            }
+        }
+        1 => {
+            // Mark current one as deleted with proper timestamp
+            // Create new one with proper timestamp
+            // First get the new item name
+            println!(
+                "If you would like to change the item's name from {}, what would you like to change it to?", item_chosen.item
+            );
+            println!("Or just leave this blank to keep it the same. (Just hit Enter)");
+            let mut item_name = read_or_quit();
+            // Check if the item already exists
+            for item in &mut *items {
+                if item_name.to_lowercase() == item.item_lower {
+                    println!("This item already exists as {}.", item.item);
+                    println!("If you would like to edit that item, select it on the next page.");
+                    return Ok(());
+                }
+            }
+            if item_name.is_empty() {
+                // They would like to not change the item name
+                item_name = String::from(&item_chosen.item);
+            }
+
+            // Get how often the item happens, then the amount each time
+            let frequency = get_frequency_response(Some(item_chosen.frequency));
+            if which_half.to_bool() {
+                // is_income
+                println!(
+                    "The income each time for {} is listed as {}. Enter a new value to change it.",
+                    item_name,
+                    to_money_string(item_chosen.value, &item_chosen.currency)
+                );
+            } else {
+                println!(
+                    "The cost each time for {} is listed as {}. Enter a new cost to change it (positive number or 0).",
+                    item_name, to_money_string(item_chosen.value, &item_chosen.currency)
+                );
+            }
+            println!("Or just leave it blank and hit Enter to keep the value the same.");
+            let mut value = Amount::from_minor(-1);
+            while value.is_negative() {
+                let val_response = read_or_quit();
+                if val_response.is_empty() {
+                    // The user would like to not change the value
+                    value = item_chosen.value;
+                    break;
+                }
+                // Make sure the input is valid
+                match val_response.parse::<Amount>() {
+                    Ok(poss_value) => {
+                        if poss_value.is_negative() {
+                            println!("\nPlease enter a positive number.");
+                        } else {
+                            value = poss_value;
+                        }
+                    }
+                    Err(error) => {
+                        println!("\n{}. Please enter a valid number.", error);
+                    }
+                }
+            }
+
+            // Get the new item's currency
+            let currency = get_currency_response(&item_chosen.currency);
+
+            // Get the new item's category
+            // Can also make a new category during this process
+            let mut still_need_category: bool = true;
+            let mut chosen_cat: String = String::new();
+            println!(
+                "The current category for {} is {}.",
+                item_name, item_chosen.category
+            );
+            println!("Select a new category, make a new category, or Enter a \"0\" (zero) to keep the same category.");
+            while still_need_category {
+                println!("\nWhich category would you like to use for this item?");
+                let mut idx: usize = 1;
+                println!("0. NO CHANGE");
+                for category in &mut *categories {
+                    println!("{}. {}", idx, category.category);
+                    idx += 1;
+                }
+                println!("\n{}. NEW CATEGORY", idx);
+                let response = print_instr_get_response(0, idx, || {});
+                match response {
+                    0 => {
+                        // Same category
+                        chosen_cat = String::from(&item_chosen.category);
+                        still_need_category = false;
+                    }
+                    x if x > 0 && x < idx => {
+                        // Selected category
+                        chosen_cat = categories[x - 1].category.clone();
+                        still_need_category = false;
+                    }
+                    x if x == idx => {
+                        // New category
+                        let num_cats_before: usize = categories.len();
+                        create_new_category(conn, user, which_half, categories)?;
+                        let num_cats_after: usize = categories.len();
+                        if num_cats_before == num_cats_after {
+                            // This only happens if there was an error in category creation
+                            // Must loop again
+                            continue;
+                        } else {
+                            chosen_cat = categories[num_cats_after - 1].category.clone();
+                            still_need_category = false;
+                        }
+                    }
+                    _ => println!("\nThat is not one of the options. Please try again."),
+                }
+            }
+
+            // Replace the former version of the item with the updated one
+            let item = budget::update_item(
+                conn,
+                user,
+                &item_chosen,
+                &item_name,
+                value,
+                &currency,
+                frequency,
+                &chosen_cat,
+            )?;
+            // Add the updated item into the items vector (since the old one was already removed)
+            items.push(item);
+        }
+        _ => println!("\nThat is not one of the options. Please try again."),
+    }
+    Ok(())
+}
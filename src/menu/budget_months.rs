@@ -0,0 +1,299 @@
+use super::transactions::{get_month_response, print_budget_vs_actual};
+use super::{get_currency_response, report_error};
+use burkebudget::budget::{get_categories, initialize_budget};
+use burkebudget::budget_months::{
+    copy_budget_month, delete_budget_month, delete_month_item, get_month_items, get_rollovers,
+    list_budget_months, set_month_item, set_rollover, shift_month, start_budget_month,
+};
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
+use burkebudget::transactions::budget_vs_actual;
+use rusqlite::Connection;
+
+/// List the months that have their own budget and offer to open one or start a new one
+pub fn budget_months_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    loop {
+        let months = list_budget_months(conn, user)?;
+        println!("\n\nMONTHLY BUDGETS");
+        println!("A month with its own budget can be changed without touching any other month");
+        println!("Months without one are planned from the current budget\n");
+        if months.is_empty() {
+            println!("No month has its own budget yet");
+        }
+        let mut idx: usize = 0;
+        for month in &months {
+            idx += 1;
+            println!("{}. {}", idx, month);
+        }
+        println!("\n{}. START A MONTH", idx + 1);
+        println!("\n0. GO BACK - Budget Menu");
+        let response = print_instr_get_response(0, idx + 1, || {
+            println!("\nEnter the number of a month to open it, or one of the other numbers");
+        });
+        let result = match response {
+            x if x > 0 && x <= idx => single_month_menu(conn, user, &months[x - 1]),
+            x if x == idx + 1 => start_month_prompt(conn, user, &months),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Ask for a month and whether to start it from the current budget or copy an earlier month
+fn start_month_prompt(conn: &Connection, user: &User, months: &[String]) -> Result<()> {
+    let month = get_month_response();
+    // The closest earlier month is offered to copy forward
+    let earlier = months.iter().rev().find(|m| **m < month);
+
+    println!("\nHow should the budget for {} start?", month);
+    println!("1. From the current budget");
+    if let Some(earlier) = earlier {
+        println!("2. Copy the budget for {}", earlier);
+    }
+    println!("\n0. GO BACK");
+    let max = if earlier.is_some() { 2 } else { 1 };
+    match (print_instr_get_response(0, max, || {}), earlier) {
+        (1, _) => start_budget_month(conn, user, &month)?,
+        (2, Some(earlier)) => copy_budget_month(conn, user, earlier, &month)?,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(()),
+    }
+    single_month_menu(conn, user, &month)
+}
+
+/// Show a month's budget against its transactions and offer to change it
+fn single_month_menu(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    loop {
+        print_budget_vs_actual(&budget_vs_actual(conn, user, month)?);
+        let next_month = shift_month(month, true)?;
+        println!(
+            "\n\nWhat would you like to do with the budget for {}?",
+            month
+        );
+        let result = match print_instr_get_response(0, 5, || {
+            println!("1. Add or Change an Item");
+            println!("2. Remove an Item");
+            println!("3. Turn Rollover On or Off for an Expense Category");
+            println!("4. Copy Forward to {}", next_month);
+            println!("5. Delete the Budget for {}", month);
+            println!("\n0. GO BACK");
+        }) {
+            1 => set_month_item_prompt(conn, user, month),
+            2 => delete_month_item_prompt(conn, user, month),
+            3 => rollover_prompt(conn, user, month),
+            4 => {
+                copy_budget_month(conn, user, month, &next_month)?;
+                return single_month_menu(conn, user, &next_month);
+            }
+            5 => {
+                println!(
+                    "\nAre you sure you'd like to delete the budget for {}? This cannot be undone.",
+                    month
+                );
+                println!("Its transactions are kept, and it will be planned from the current budget again.");
+                println!("1. Yes");
+                println!("2. No (Go back)");
+                if print_instr_get_response(1, 2, || {}) == 1 {
+                    delete_budget_month(conn, user, month)?;
+                    return Ok(());
+                }
+                Ok(())
+            }
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Ask whether an item is income or an expense
+fn get_half_response() -> Option<BudgetHalf> {
+    println!("\nIncome or expenses?");
+    match print_instr_get_response(0, 2, || {
+        println!("1. Income");
+        println!("2. Expenses");
+        println!("\n0. GO BACK");
+    }) {
+        1 => Some(BudgetHalf::Income),
+        2 => Some(BudgetHalf::Expenses),
+        // 0 (or anything unexpected) goes back
+        _ => None,
+    }
+}
+
+/// Ask for an amount of 0 or more until a valid one is given
+/// Just hitting Enter keeps the current amount if there is one
+fn get_amount_response(current: Option<Amount>) -> Amount {
+    loop {
+        let response = read_or_quit();
+        if let (true, Some(current)) = (response.is_empty(), current) {
+            return current;
+        }
+        match response.parse::<Amount>() {
+            Ok(amount) if amount.is_negative() => println!("\nPlease enter a positive number."),
+            Ok(amount) => return amount,
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    }
+}
+
+/// Change the amount planned for one of the month's items, or add a new item to just this month
+fn set_month_item_prompt(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    let which_half = match get_half_response() {
+        Some(which_half) => which_half,
+        None => return Ok(()),
+    };
+    let (categories, items) = get_month_items(conn, user, month, &which_half)?;
+
+    println!("\nWhich item would you like to change?");
+    let mut idx: usize = 0;
+    for item in &items {
+        idx += 1;
+        println!(
+            "{}. {}  {}",
+            idx,
+            item.item,
+            to_money_string(item.value, &item.currency)
+        );
+    }
+    println!("\n{}. NEW ITEM", idx + 1);
+    println!("\n0. GO BACK");
+    let response = print_instr_get_response(0, idx + 1, || {});
+
+    if response > 0 && response <= idx {
+        let item = &items[response - 1];
+        println!(
+            "\nThe amount planned for {} in {} is {}. Enter a new amount to change it.",
+            item.item,
+            month,
+            to_money_string(item.value, &item.currency)
+        );
+        println!("Or just leave it blank and hit Enter to keep it the same.");
+        let value = get_amount_response(Some(item.value));
+        let currency = get_currency_response(&item.currency);
+        let category = categories
+            .iter()
+            .find(|c| c.category_lower == item.category_lower)
+            .map(|c| c.category.clone())
+            .unwrap_or_else(|| item.category_lower.clone());
+        set_month_item(
+            conn,
+            user,
+            month,
+            &which_half,
+            &item.item,
+            value,
+            &currency,
+            &category,
+        )?;
+        return Ok(());
+    }
+    if response != idx + 1 {
+        return Ok(());
+    }
+
+    println!("\nWhat would you like to name the new item?");
+    let item_name = read_or_quit();
+    if let Some(item) = items
+        .iter()
+        .find(|i| i.item_lower == item_name.to_lowercase())
+    {
+        println!("This item already exists as {}.", item.item);
+        println!("If you would like to change the item, select it from the list instead.");
+        println!("Hit Enter to go back.");
+        read_or_quit(); // Give the user a chance to acknowledge
+        return Ok(());
+    }
+    println!(
+        "How much is planned for it in {}? (positive number or 0)",
+        month
+    );
+    let value = get_amount_response(None);
+    let currency = get_currency_response(&user.base_currency);
+    println!("\nWhich category would you like to use for this item?");
+    for (idx, category) in categories.iter().enumerate() {
+        println!("{}. {}", idx + 1, category.category);
+    }
+    println!("\n0. GO BACK");
+    let response = print_instr_get_response(0, categories.len(), || {});
+    if response == 0 || response > categories.len() {
+        return Ok(());
+    }
+    set_month_item(
+        conn,
+        user,
+        month,
+        &which_half,
+        &item_name,
+        value,
+        &currency,
+        &categories[response - 1].category,
+    )?;
+    Ok(())
+}
+
+/// Remove one of the month's items, leaving every other month alone
+fn delete_month_item_prompt(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    let which_half = match get_half_response() {
+        Some(which_half) => which_half,
+        None => return Ok(()),
+    };
+    let (_, items) = get_month_items(conn, user, month, &which_half)?;
+    println!("\nWhich item would you like to remove from {}?", month);
+    for (idx, item) in items.iter().enumerate() {
+        println!(
+            "{}. {}  {}",
+            idx + 1,
+            item.item,
+            to_money_string(item.value, &item.currency)
+        );
+    }
+    println!("\n0. GO BACK");
+    match print_instr_get_response(0, items.len(), || {}) {
+        x if x > 0 && x <= items.len() => delete_month_item(conn, user, &items[x - 1]),
+        // 0 (or anything unexpected) goes back
+        _ => Ok(()),
+    }
+}
+
+/// Choose an expense category and flip whether its envelope rolls over into next month
+fn rollover_prompt(conn: &Connection, user: &User, month: &str) -> Result<()> {
+    let categories = get_categories(conn, user, &BudgetHalf::Expenses)?;
+    let rollovers = get_rollovers(conn, user, month)?;
+    println!("\nWhat is left unspent in a category that rolls over is added to it the next month.");
+    println!("Which category would you like to change?");
+    for (idx, category) in categories.iter().enumerate() {
+        println!(
+            "{}. {}  ({})",
+            idx + 1,
+            category.category,
+            if rollovers.contains(&category.category_lower) {
+                "rolls over"
+            } else {
+                "doesn't roll over"
+            }
+        );
+    }
+    println!("\n0. GO BACK");
+    match print_instr_get_response(0, categories.len(), || {}) {
+        x if x > 0 && x <= categories.len() => {
+            let category = &categories[x - 1];
+            set_rollover(
+                conn,
+                user,
+                month,
+                &category.category,
+                !rollovers.contains(&category.category_lower),
+            )?;
+            Ok(())
+        }
+        // 0 (or anything unexpected) goes back
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,124 @@
+use super::budget::print_budget_body;
+use super::report_error;
+use burkebudget::budget_snapshots::{
+    self, budget_items_at, delete_budget_snapshot, list_budget_snapshots,
+};
+use burkebudget::currency::budget_snapshot_converter;
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
+use rusqlite::Connection;
+
+/// Prompt for a comment and store a snapshot of the current budget
+pub fn create_budget_snapshot(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter an optional comment about this budget snapshot (Just hit Enter to skip):");
+    let comment: String = read_or_quit();
+
+    budget_snapshots::create_budget_snapshot(conn, user, &comment)?;
+
+    println!("\nBudget snapshot successfully created. Press Enter to continue.");
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// List the budget snapshots and offer to open one of them up
+pub fn view_budget_snapshot_menu(conn: &Connection, user: &User) -> Result<()> {
+    // Push all of the snapshots to a vector first (in chronological order)
+    let mut snapshots: Vec<BudgetSnapshot> = list_budget_snapshots(conn, user)?;
+
+    // Print out listing of snapshots
+    loop {
+        println!("\n\nSaved Budget Snapshots:");
+        let mut idx: usize = 0;
+        for snapshot in &snapshots {
+            idx += 1;
+            println!(
+                "{}.  {}:  Monthly Net  {}",
+                idx,
+                snapshot.date_today,
+                to_money_string(snapshot.monthly_net, &snapshot.currency)
+            );
+        }
+        println!("\n0. GO BACK");
+        let response = print_instr_get_response(0, idx, || {
+            println!("\nWhich budget snapshot would you like to view");
+        });
+        // Go back or go to snapshot viewer
+        match response {
+            0 => {
+                return Ok(());
+            }
+            x if x > 0 && x <= idx => {
+                if let Err(error) = view_single_budget_snapshot(conn, user, &mut snapshots, x - 1) {
+                    report_error(&error);
+                    // Reload the list in case it no longer matches the database
+                    snapshots = list_budget_snapshots(conn, user)?;
+                }
+            }
+            _ => println!("\nThat is not one of the options. Please try again."),
+        }
+    }
+}
+
+/// Display the budget represented by the snapshot
+fn view_single_budget_snapshot(
+    conn: &Connection,
+    user: &User,
+    snapshots: &mut Vec<BudgetSnapshot>,
+    snapshot_idx: usize,
+) -> Result<()> {
+    let relevant_snapshot = &snapshots[snapshot_idx];
+    let (income_categories, income_items) =
+        budget_items_at(conn, user, &BudgetHalf::Income, relevant_snapshot.timeline)?;
+    let (expense_categories, expense_items) = budget_items_at(
+        conn,
+        user,
+        &BudgetHalf::Expenses,
+        relevant_snapshot.timeline,
+    )?;
+    // Totals use the exchange rates frozen when the snapshot was taken
+    let converter =
+        budget_snapshot_converter(conn, user, relevant_snapshot, &relevant_snapshot.currency)?;
+    println!(
+        "\n\nSNAPSHOT of Monthly Budget - {}  (Totals in {})",
+        relevant_snapshot.date_today,
+        converter.base()
+    );
+    print_budget_body(
+        &converter,
+        &income_categories,
+        &income_items,
+        &expense_categories,
+        &expense_items,
+    )?;
+
+    // Print out the comment under the snapshot
+    if !relevant_snapshot.comment.is_empty() {
+        println!("\nComment: \"{}\"", relevant_snapshot.comment);
+    }
+
+    // Get response
+    println!("\n\nWhat would you like to do next?");
+    println!("1. Go Back");
+    println!("2. Delete this Budget Snapshot");
+    match print_instr_get_response(1, 2, || {}) {
+        2 => {
+            println!(
+                "\nAre you sure you'd like to delete this Budget Snapshot? This cannot be undone."
+            );
+            println!("1. Yes");
+            println!("2. No (Go back)");
+            match print_instr_get_response(1, 2, || {}) {
+                1 => {
+                    // The items and categories remain unchanged, only the snapshot goes
+                    delete_budget_snapshot(conn, user, relevant_snapshot)?;
+                    snapshots.remove(snapshot_idx);
+                    Ok(())
+                }
+                // 2 (or anything unexpected) goes back
+                _ => Ok(()),
+            }
+        }
+        // 1 (or anything unexpected) goes back
+        _ => Ok(()),
+    }
+}
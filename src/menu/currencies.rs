@@ -0,0 +1,141 @@
+use super::{get_currency_response, report_error};
+use burkebudget::currency::{
+    delete_exchange_rate, import_exchange_rates, list_exchange_rates, normalize_currency,
+    set_base_currency, set_exchange_rate,
+};
+use burkebudget::error::Result;
+use burkebudget::structs_utils::*;
+use rusqlite::Connection;
+
+/// Display the currencies menu
+/// The user is updated in place when the base currency changes
+pub fn currencies_menu(conn: &Connection, user: &mut User) {
+    loop {
+        if let Err(error) = print_exchange_rates(conn, user) {
+            report_error(&error);
+            return;
+        }
+        let result = match print_instr_get_response(1, 6, || {
+            println!("\nWhat would you like to do? (Enter the number)");
+            println!("1. Change Base Currency");
+            println!("2. Add / Update an Exchange Rate");
+            println!("3. Delete an Exchange Rate");
+            println!("4. Import Exchange Rates from a File");
+            println!("5. Main Menu");
+            println!("6. Quit");
+        }) {
+            1 => change_base_currency(conn, user),
+            2 => add_exchange_rate(conn, user),
+            3 => remove_exchange_rate(conn, user),
+            4 => import_rates_from_file(conn, user),
+            6 => {
+                println!(
+                    "\nYour budget is saved, and you have been logged out. See you next time!\n"
+                );
+                std::process::exit(0);
+            }
+            // 5 (or anything unexpected) goes back
+            _ => return,
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print the base currency and all of the user's current exchange rates
+fn print_exchange_rates(conn: &Connection, user: &User) -> Result<()> {
+    println!("\n\nCURRENCIES AND EXCHANGE RATES");
+    println!(
+        "Totals are shown in your base currency: {}",
+        user.base_currency
+    );
+    let rates = list_exchange_rates(conn, user)?;
+    if rates.is_empty() {
+        println!("\nNo exchange rates have been added yet");
+    } else {
+        println!();
+        for rate in &rates {
+            println!(
+                "1 {} = {} {}  (set {})",
+                rate.from_currency, rate.rate, rate.to_currency, rate.date_text
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Ask for a new base currency and save it
+fn change_base_currency(conn: &Connection, user: &mut User) -> Result<()> {
+    println!("\nEnter your new base currency.");
+    let currency = get_currency_response(&user.base_currency);
+    *user = set_base_currency(conn, user, &currency)?;
+    println!(
+        "\nTotals will now be shown in {}. Press Enter to continue.",
+        user.base_currency
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for a pair of currencies until two known ISO codes are entered
+fn get_currency_pair() -> (String, String) {
+    let mut currencies: Vec<String> = vec![];
+    for which in ["first", "second"] {
+        loop {
+            println!("\nEnter the {} currency (eg - USD, EUR, GBP):", which);
+            match normalize_currency(&read_or_quit()) {
+                Ok(code) => {
+                    currencies.push(code);
+                    break;
+                }
+                Err(error) => println!("\n{}.", error),
+            }
+        }
+    }
+    (currencies.remove(0), currencies.remove(0))
+}
+
+/// Ask for a pair of currencies and the rate between them
+fn add_exchange_rate(conn: &Connection, user: &User) -> Result<()> {
+    let (from_currency, to_currency) = get_currency_pair();
+    println!(
+        "\nHow many {} is 1 {} worth? (eg - 1.0825)",
+        to_currency, from_currency
+    );
+    let rate = read_or_quit();
+    let rate = set_exchange_rate(conn, user, &from_currency, &to_currency, &rate)?;
+    println!(
+        "\nSaved 1 {} = {} {}. Press Enter to continue.",
+        rate.from_currency, rate.rate, rate.to_currency
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for a pair of currencies and remove the rate between them
+fn remove_exchange_rate(conn: &Connection, user: &User) -> Result<()> {
+    let (from_currency, to_currency) = get_currency_pair();
+    delete_exchange_rate(conn, user, &from_currency, &to_currency)?;
+    println!(
+        "\nDeleted the rate between {} and {}. Press Enter to continue.",
+        from_currency, to_currency
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for the path to a FROM,TO,RATE file and import it
+fn import_rates_from_file(conn: &Connection, user: &User) -> Result<()> {
+    println!(
+        "\nEnter the path to a file with one FROM,TO,RATE line per pair (eg - EUR,USD,1.0825):"
+    );
+    let path = read_or_quit();
+    let num_rates = import_exchange_rates(conn, user, &path)?;
+    println!(
+        "\nImported {} exchange rates. Press Enter to continue.",
+        num_rates
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
@@ -0,0 +1,339 @@
+use super::report_error;
+use burkebudget::balance_sheet::{get_relevant_items_cats, initialize_balance_sheet};
+use burkebudget::debts::{
+    delete_debt_terms, get_debt_terms, get_debts, plan_debts, set_debt_terms,
+};
+use burkebudget::error::Result;
+use burkebudget::loans::parse_apr;
+use burkebudget::structs_utils::*;
+use chrono::prelude::*;
+use rgb::RGB8;
+use rusqlite::Connection;
+use textplots::{
+    Chart, ColorPlot, LabelBuilder, LabelFormat, Shape, TickDisplay, TickDisplayBuilder,
+};
+
+/// The colors of the avalanche and snowball lines in the debt chart
+const AVALANCHE_COLOR: RGB8 = RGB8 {
+    r: 220,
+    g: 60,
+    b: 60,
+};
+const SNOWBALL_COLOR: RGB8 = RGB8 {
+    r: 70,
+    g: 130,
+    b: 230,
+};
+
+/// Show the debts and offer to plan paying them off or to set their interest rates and minimum payments
+pub fn debt_planner_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_balance_sheet(conn, user)?;
+    loop {
+        let (debts, missing) = get_debts(conn, user)?;
+        println!("\n\nDEBT PAYOFF PLANNER: Avalanche (highest interest first) vs. Snowball (smallest balance first)");
+        print_debts(&debts, &missing, &user.base_currency);
+        println!("\n\nWhat would you like to do?");
+        let result = match print_instr_get_response(0, 3, || {
+            println!("1. Make a Payoff Plan");
+            println!("2. Set a Debt's Interest Rate and Minimum Payment");
+            println!("3. Remove a Debt's Interest Rate and Minimum Payment");
+            println!("\n0. GO BACK - Balance Sheet Menu");
+        }) {
+            1 => payoff_plan_prompt(&debts, &user.base_currency),
+            2 => set_debt_terms_prompt(conn, user),
+            3 => delete_debt_terms_prompt(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print the debts in the plan and the liabilities that still need an interest rate and minimum payment
+pub fn print_debts(debts: &[Debt], missing: &[Item], currency: &str) {
+    if debts.is_empty() && missing.is_empty() {
+        println!("\nYou don't owe anything on your liabilities");
+        return;
+    }
+    println!(
+        "\n{:<width$}{:>16}{:>10}{:>16}",
+        "Debt",
+        "Balance",
+        "APR",
+        "Minimum",
+        width = MAX_CHARACTERS_ITEM_NAME + 2
+    );
+    for debt in debts {
+        println!(
+            "{:<width$}{:>16}{:>10}{:>16}",
+            debt.item,
+            to_money_string(debt.balance, currency),
+            format!("{}%", debt.apr),
+            to_money_string(debt.minimum_payment, currency),
+            width = MAX_CHARACTERS_ITEM_NAME + 2
+        );
+    }
+    if !missing.is_empty() {
+        println!("\nThese need an interest rate and minimum payment before they can be planned:");
+        for item in missing {
+            println!(
+                "    {}  {}",
+                item.item,
+                to_money_string(item.value, &item.currency)
+            );
+        }
+    }
+}
+
+/// Print the debt-free date, interest and payoff order of the avalanche and snowball plans side by side
+/// The minimums only plan is the baseline for the interest saved, and is None if it never pays the debts off
+pub fn print_plan_comparison(
+    avalanche: &DebtPlan,
+    snowball: &DebtPlan,
+    minimums: Option<&DebtPlan>,
+    currency: &str,
+) {
+    for plan in [avalanche, snowball] {
+        println!("\n{}", plan.strategy.to_str().to_uppercase());
+        println!(
+            "Debt free by {} after {} months",
+            plan.debt_free_date.format("%Y-%m"),
+            plan.months.len()
+        );
+        print!(
+            "Total interest: {}",
+            to_money_string(plan.total_interest, currency)
+        );
+        match minimums {
+            Some(minimums) => println!(
+                "  (saves {} over paying only the minimums)",
+                to_money_string(minimums.total_interest - plan.total_interest, currency)
+            ),
+            None => println!(),
+        }
+        println!("Payoff order:");
+        for (idx, (item, date)) in plan.payoff_order.iter().enumerate() {
+            println!("    {}. {}  {}", idx + 1, item, date.format("%Y-%m"));
+        }
+    }
+
+    match minimums {
+        Some(minimums) => println!(
+            "\nPaying only the minimums, it would take until {} and cost {} in interest",
+            minimums.debt_free_date.format("%Y-%m"),
+            to_money_string(minimums.total_interest, currency)
+        ),
+        None => println!("\nPaying only the minimums would never pay the debts off"),
+    }
+    let difference = snowball.total_interest - avalanche.total_interest;
+    if difference > Amount::ZERO {
+        println!(
+            "Avalanche saves {} in interest over Snowball",
+            to_money_string(difference, currency)
+        );
+    } else {
+        println!("Avalanche and Snowball cost the same in interest");
+    }
+}
+
+/// Print the balance of every debt at the end of each month of a plan
+pub fn print_plan_months(plan: &DebtPlan, currency: &str) {
+    const COL_WIDTH: usize = 16;
+    println!("\n{}", plan.strategy.to_str().to_uppercase());
+    print!("{:<10}", "Month");
+    for debt in &plan.debts {
+        let name: String = debt.item.chars().take(COL_WIDTH - 2).collect();
+        print!("{:>width$}", name, width = COL_WIDTH);
+    }
+    println!("{:>width$}", "Total", width = COL_WIDTH);
+    for month in &plan.months {
+        print!("{:<10}", month.date.format("%Y-%m").to_string());
+        for balance in &month.balances {
+            print!(
+                "{:>width$}",
+                to_money_string(*balance, currency),
+                width = COL_WIDTH
+            );
+        }
+        println!(
+            "{:>width$}",
+            to_money_string(month.total, currency),
+            width = COL_WIDTH
+        );
+    }
+}
+
+/// Chart the total debt each month under the avalanche and snowball plans
+pub fn print_debt_chart(avalanche: &DebtPlan, snowball: &DebtPlan, currency: &str) {
+    let starting_total: Amount = avalanche.debts.iter().map(|debt| debt.balance).sum();
+    let points = |plan: &DebtPlan| -> Vec<(f32, f32)> {
+        let mut points = vec![(0.0, starting_total.to_f64() as f32)];
+        for (idx, month) in plan.months.iter().enumerate() {
+            points.push(((idx + 1) as f32, month.total.to_f64() as f32));
+        }
+        points
+    };
+    let avalanche_points = points(avalanche);
+    let snowball_points = points(snowball);
+    let num_months = avalanche.months.len().max(snowball.months.len()).max(1);
+
+    println!("\n\n\nTotal Debt Over Time  (in {})", currency);
+    println!("Red is Avalanche, Blue is Snowball. The x-axis is months from now.\n");
+    let avalanche_lines = Shape::Lines(&avalanche_points);
+    let snowball_lines = Shape::Lines(&snowball_points);
+    let mut plot = Chart::new_with_y_range(
+        200,
+        60,
+        0.0,
+        num_months as f32,
+        0.0,
+        starting_total.to_f64() as f32,
+    );
+    let closure_currency = String::from(currency);
+    plot.linecolorplot(&avalanche_lines, AVALANCHE_COLOR)
+        .linecolorplot(&snowball_lines, SNOWBALL_COLOR)
+        .y_label_format(LabelFormat::Custom(Box::new(move |yval| {
+            to_money_string(Amount::from_f64(yval as f64), &closure_currency)
+        })))
+        .y_tick_display(TickDisplay::Sparse)
+        .nice();
+}
+
+/// Ask for the monthly payoff budget, compare the plans, and offer the month by month details and chart
+fn payoff_plan_prompt(debts: &[Debt], currency: &str) -> Result<()> {
+    if debts.is_empty() {
+        println!("\nThere are no debts to plan yet. Set their interest rates and minimum payments first.");
+        println!("Hit Enter to go back.");
+        read_or_quit(); // Give the user a chance to acknowledge
+        return Ok(());
+    }
+    let total_minimum: Amount = debts.iter().map(|debt| debt.minimum_payment).sum();
+    println!(
+        "\nHow much can you put towards your debts each month? (at least the minimums of {})",
+        to_money_string(total_minimum, currency)
+    );
+    let monthly_budget = loop {
+        match read_or_quit().parse::<Amount>() {
+            Ok(amount) if amount < total_minimum => println!(
+                "\nPlease enter at least {}.",
+                to_money_string(total_minimum, currency)
+            ),
+            Ok(amount) => break amount,
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    };
+
+    let start = Local::now().date_naive();
+    let avalanche = plan_debts(
+        debts,
+        monthly_budget,
+        currency,
+        DebtStrategy::Avalanche,
+        start,
+    )?;
+    let snowball = plan_debts(
+        debts,
+        monthly_budget,
+        currency,
+        DebtStrategy::Snowball,
+        start,
+    )?;
+    let minimums = plan_debts(
+        debts,
+        monthly_budget,
+        currency,
+        DebtStrategy::MinimumsOnly,
+        start,
+    )
+    .ok();
+    loop {
+        println!(
+            "\n\nPAYOFF PLAN: {} a month",
+            to_money_string(monthly_budget, currency)
+        );
+        print_plan_comparison(&avalanche, &snowball, minimums.as_ref(), currency);
+        println!("\n\nWhat would you like to see?");
+        match print_instr_get_response(0, 3, || {
+            println!("1. Avalanche Month by Month");
+            println!("2. Snowball Month by Month");
+            println!("3. Chart of Total Debt Over Time");
+            println!("\n0. GO BACK");
+        }) {
+            1 => print_plan_months(&avalanche, currency),
+            2 => print_plan_months(&snowball, currency),
+            3 => print_debt_chart(&avalanche, &snowball, currency),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        }
+        println!("\nHit Enter to go back.");
+        read_or_quit(); // Give the user a chance to acknowledge
+    }
+}
+
+/// Choose a liability
+fn get_liability_response(conn: &Connection, user: &User) -> Result<Option<Item>> {
+    let (_, mut items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    for (idx, item) in items.iter().enumerate() {
+        let terms = match get_debt_terms(conn, user, item)? {
+            Some((apr, minimum_payment)) => format!(
+                "  ({}%, {} minimum)",
+                apr,
+                to_money_string(minimum_payment, &item.currency)
+            ),
+            None => String::new(),
+        };
+        println!(
+            "{}. {}  {}{}",
+            idx + 1,
+            item.item,
+            to_money_string(item.value, &item.currency),
+            terms
+        );
+    }
+    println!("\n0. GO BACK");
+    match print_instr_get_response(0, items.len(), || {}) {
+        x if x > 0 && x <= items.len() => Ok(Some(items.remove(x - 1))),
+        // 0 (or anything unexpected) goes back
+        _ => Ok(None),
+    }
+}
+
+/// Ask for a liability's interest rate and minimum payment
+fn set_debt_terms_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nWhich debt would you like to set?");
+    let item = match get_liability_response(conn, user)? {
+        Some(item) => item,
+        None => return Ok(()),
+    };
+    let apr = loop {
+        println!("\nWhat is its annual interest rate? (percent, eg - 19.99)");
+        match parse_apr(&read_or_quit()) {
+            Ok(apr) => break apr,
+            Err(error) => println!("\n{}", error),
+        }
+    };
+    println!(
+        "\nWhat is its minimum monthly payment? (positive number, in {})",
+        item.currency
+    );
+    let minimum_payment = loop {
+        match read_or_quit().parse::<Amount>() {
+            Ok(amount) if amount > Amount::ZERO => break amount,
+            Ok(_) => println!("\nThe minimum payment must be greater than 0."),
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    };
+    set_debt_terms(conn, user, &item, apr, minimum_payment)
+}
+
+/// Remove a liability's interest rate and minimum payment
+fn delete_debt_terms_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nWhich debt's interest rate and minimum payment would you like to remove?");
+    println!("A debt with loan terms goes back to using the loan's interest rate and payment.");
+    match get_liability_response(conn, user)? {
+        Some(item) => delete_debt_terms(conn, user, &item),
+        None => Ok(()),
+    }
+}
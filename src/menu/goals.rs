@@ -0,0 +1,318 @@
+use super::{get_currency_response, report_error};
+use burkebudget::balance_sheet::{get_relevant_items_cats, initialize_balance_sheet};
+use burkebudget::budget::{get_relevant_items, initialize_budget};
+use burkebudget::error::Result;
+use burkebudget::goals::{
+    create_goal, delete_goal, describe_link, goal_progress, list_goals, parse_target_date,
+    set_goal_budget_item,
+};
+use burkebudget::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+/// List the goals with how far along they are and offer to open one or make a new one
+pub fn goals_menu(conn: &Connection, user: &User) -> Result<()> {
+    loop {
+        let goals = list_goals(conn, user)?;
+        let today = Local::now().date_naive();
+        println!("\n\nGOALS: Saving up or paying down the items on your balance sheet\n");
+        if goals.is_empty() {
+            println!("You don't have any goals yet");
+        }
+        let mut idx: usize = 0;
+        for goal in &goals {
+            idx += 1;
+            let progress = goal_progress(conn, user, goal, today)?;
+            println!("{}.  {}", idx, goal_summary(goal, &progress));
+        }
+        println!("\n{}. NEW GOAL", idx + 1);
+        println!("\n0. GO BACK - Main Menu");
+        let response = print_instr_get_response(0, idx + 1, || {
+            println!("\nEnter the number of a goal to open it, or one of the other numbers");
+        });
+        let result = match response {
+            x if x > 0 && x <= idx => single_goal_menu(conn, user, &goals[x - 1]),
+            x if x == idx + 1 => create_new_goal(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// One line with a goal's name and how far along it is
+pub fn goal_summary(goal: &Goal, progress: &GoalProgress) -> String {
+    let currency = &goal.currency;
+    match goal.kind {
+        GoalKind::Save => format!(
+            "{}  {} of {}  ({:.0}%)",
+            goal.name,
+            to_money_string(progress.current, currency),
+            to_money_string(goal.target, currency),
+            progress.percent
+        ),
+        GoalKind::PayOff => format!(
+            "{}  {} left, paying down to {}  ({:.0}%)",
+            goal.name,
+            to_money_string(progress.current, currency),
+            to_money_string(goal.target, currency),
+            progress.percent
+        ),
+    }
+}
+
+/// Print a goal's links, progress and projections
+pub fn print_goal(
+    conn: &Connection,
+    user: &User,
+    goal: &Goal,
+    progress: &GoalProgress,
+) -> Result<()> {
+    let currency = &goal.currency;
+    println!("\n\nGOAL: {}", goal.name);
+    match goal.kind {
+        GoalKind::Save => print!("Save up to {}", to_money_string(goal.target, currency)),
+        GoalKind::PayOff => print!("Pay down to {}", to_money_string(goal.target, currency)),
+    }
+    match &goal.target_date {
+        Some(date) => println!(" by {}", date),
+        None => println!(),
+    }
+    println!("\nMeasured by:");
+    for link in &goal.links {
+        println!("    {}", describe_link(conn, user, link)?);
+    }
+
+    if !progress.history.is_empty() {
+        println!("\nAt each snapshot:");
+        for (date, value) in &progress.history {
+            println!("    {}  {}", date, to_money_string(*value, currency));
+        }
+    }
+    println!(
+        "\nNow: {}  ({:.0}% of the way there)",
+        to_money_string(progress.current, currency),
+        progress.percent
+    );
+    if progress.reached {
+        println!("This goal has been reached!");
+        return Ok(());
+    }
+
+    match progress.projected_date {
+        Some(date) => println!("At the current trend it will be reached around {}", date),
+        None => println!("The snapshots so far aren't trending towards the target"),
+    }
+    if let Some(needed) = progress.monthly_needed {
+        println!(
+            "To reach it by the target date takes {} a month",
+            to_money_string(needed, currency)
+        );
+    }
+    if let Some(planned) = progress.planned_contribution {
+        print!(
+            "The budget plans {} a month towards it",
+            to_money_string(planned, currency)
+        );
+        match progress.planned_date {
+            Some(date) => println!(", which reaches it around {}", date),
+            None => println!(),
+        }
+    }
+    Ok(())
+}
+
+/// Show one goal and offer to change its planned contribution or delete it
+fn single_goal_menu(conn: &Connection, user: &User, goal: &Goal) -> Result<()> {
+    let mut goal = goal.clone();
+    loop {
+        let progress = goal_progress(conn, user, &goal, Local::now().date_naive())?;
+        print_goal(conn, user, &goal, &progress)?;
+        println!("\n\nWhat would you like to do with this goal?");
+        match print_instr_get_response(0, 2, || {
+            println!("1. Choose the Budget Item that Pays Into it");
+            println!("2. Delete this Goal");
+            println!("\n0. GO BACK");
+        }) {
+            1 => {
+                if let Some(budget_item) = get_budget_item_response(conn, user)? {
+                    goal = set_goal_budget_item(conn, user, &goal, budget_item.as_ref())?;
+                }
+            }
+            2 => {
+                println!("\nAre you sure you'd like to delete this goal? This cannot be undone.");
+                println!("The items it is linked to won't be changed.");
+                println!("1. Yes");
+                println!("2. No (Go back)");
+                if print_instr_get_response(1, 2, || {}) == 1 {
+                    return delete_goal(conn, user, &goal);
+                }
+            }
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        }
+    }
+}
+
+/// Ask which budget expense item pays into a goal
+/// Returns None to go back, and Some(None) for no budget item
+fn get_budget_item_response(conn: &Connection, user: &User) -> Result<Option<Option<BudgetItem>>> {
+    initialize_budget(conn, user)?;
+    let (_, mut items) = get_relevant_items(conn, user, &BudgetHalf::Expenses)?;
+    println!("\nWhich budget expense item pays into this goal?");
+    for (idx, item) in items.iter().enumerate() {
+        println!(
+            "{}. {}  {}",
+            idx + 1,
+            item.item,
+            to_money_string(item.monthly_value(), &item.currency)
+        );
+    }
+    println!("\n{}. NONE", items.len() + 1);
+    println!("\n0. GO BACK");
+    match print_instr_get_response(0, items.len() + 1, || {}) {
+        x if x > 0 && x <= items.len() => Ok(Some(Some(items.remove(x - 1)))),
+        x if x == items.len() + 1 => Ok(Some(None)),
+        // 0 (or anything unexpected) goes back
+        _ => Ok(None),
+    }
+}
+
+/// Goal Creator
+/// Asks for the target, then the balance sheet items and categories that measure it
+fn create_new_goal(conn: &Connection, user: &User) -> Result<()> {
+    initialize_balance_sheet(conn, user)?;
+    println!("\nWhat would you like to name the new goal? (eg - Emergency Fund)");
+    let name = read_or_quit();
+    if name.is_empty() {
+        println!("The goal name cannot be empty. Hit Enter to go back.");
+        read_or_quit(); // Give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    println!("\nIs this goal to save up or to pay something down?");
+    let kind = match print_instr_get_response(0, 2, || {
+        println!("1. Save up to a target (eg - an emergency fund)");
+        println!("2. Pay down to a target (eg - a car loan)");
+        println!("\n0. GO BACK");
+    }) {
+        1 => GoalKind::Save,
+        2 => GoalKind::PayOff,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(()),
+    };
+
+    match kind {
+        GoalKind::Save => println!("\nHow much is the target? (positive number)"),
+        GoalKind::PayOff => {
+            println!("\nWhat should it be paid down to? (Enter 0 to pay it off completely)")
+        }
+    }
+    let target = loop {
+        match read_or_quit().parse::<Amount>() {
+            Ok(amount) if amount.is_negative() => println!("\nPlease enter a positive number."),
+            Ok(amount) => break amount,
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    };
+    let currency = get_currency_response(&user.base_currency);
+
+    let target_date = loop {
+        println!("\nWhen would you like to reach it by? (YYYY-MM or YYYY-MM-DD) Just hit Enter for no date.");
+        let response = read_or_quit();
+        if response.is_empty() {
+            break None;
+        }
+        match parse_target_date(&response) {
+            Ok(_) => break Some(response),
+            Err(error) => println!("\n{}", error),
+        }
+    };
+
+    // Collect the links one at a time until the user is done
+    let (asset_categories, asset_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    let (liability_categories, liability_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let mut choices: Vec<(String, GoalLink)> = vec![];
+    for item in asset_items.iter().chain(liability_items.iter()) {
+        choices.push((
+            format!(
+                "{} ({})",
+                item.item,
+                if item.is_asset { "asset" } else { "liability" }
+            ),
+            GoalLink::Item {
+                is_asset: item.is_asset,
+                item_original: item.timeline_original,
+            },
+        ));
+    }
+    for category in asset_categories.iter().chain(liability_categories.iter()) {
+        choices.push((
+            format!(
+                "{} (every {} in the category)",
+                category.category,
+                if category.is_asset {
+                    "asset"
+                } else {
+                    "liability"
+                }
+            ),
+            GoalLink::Category {
+                is_asset: category.is_asset,
+                category_lower: category.category_lower.clone(),
+            },
+        ));
+    }
+    let mut links: Vec<GoalLink> = vec![];
+    loop {
+        println!(
+            "\nWhich balance sheet items or categories measure this goal? Choose one at a time,\nor choose one again to take it back out."
+        );
+        for (idx, (description, link)) in choices.iter().enumerate() {
+            let chosen = if links.contains(link) {
+                "  [chosen]"
+            } else {
+                ""
+            };
+            println!("{}. {}{}", idx + 1, description, chosen);
+        }
+        println!("\n{}. DONE", choices.len() + 1);
+        println!("\n0. GO BACK");
+        match print_instr_get_response(0, choices.len() + 1, || {}) {
+            x if x > 0 && x <= choices.len() => {
+                let link = &choices[x - 1].1;
+                match links.iter().position(|l| l == link) {
+                    Some(position) => {
+                        links.remove(position);
+                    }
+                    None => links.push(link.clone()),
+                }
+            }
+            x if x == choices.len() + 1 => break,
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        }
+    }
+
+    let budget_item = match get_budget_item_response(conn, user)? {
+        Some(budget_item) => budget_item,
+        None => return Ok(()),
+    };
+
+    create_goal(
+        conn,
+        user,
+        &name,
+        kind,
+        target,
+        &currency,
+        target_date.as_deref(),
+        &links,
+        budget_item.as_ref(),
+    )?;
+    Ok(())
+}
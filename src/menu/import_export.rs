@@ -0,0 +1,335 @@
+use super::bs_visualizers::parse_space_delim_response_for_int_to_index;
+use super::report_error;
+use burkebudget::backup::{read_backup, restore_backup, write_backup};
+use burkebudget::csv_io::{
+    apply_csv_import, export_balance_sheet_csv, export_budget_csv, export_item_history_csv,
+    export_snapshots_csv, preview_csv_import, ITEM_COLUMNS,
+};
+use burkebudget::error::Result;
+use burkebudget::plain_text::{apply_balance_import, export_plain_text, preview_balance_import};
+use burkebudget::report::{write_report, REPORT_SNAPSHOTS};
+use burkebudget::snapshots::list_snapshots;
+use burkebudget::structs_utils::*;
+use rusqlite::Connection;
+
+/// Export data to files or import items from them
+pub fn import_export_menu(conn: &Connection, user: &User) {
+    loop {
+        println!("\n\nIMPORT AND EXPORT: Get your data in and out of files");
+        let result = match print_instr_get_response(0, 10, || {
+            println!("1. Export the Balance Sheet to CSV");
+            println!("2. Export the Budget to CSV");
+            println!("3. Export Every Snapshot to CSV");
+            println!("4. Export the Full Item History to CSV");
+            println!("5. Import Items from CSV");
+            println!("6. Back Up Everything to a JSON File");
+            println!("7. Restore a Backup as a New User");
+            println!("8. Export to Ledger, hledger or Beancount");
+            println!("9. Import Ledger or Beancount Balances as Snapshots");
+            println!("10. Write a Report to Share (HTML or Markdown)");
+            println!("\n0. GO BACK - Main Menu");
+        }) {
+            1 => export_prompt(conn, user, export_balance_sheet_csv),
+            2 => export_prompt(conn, user, export_budget_csv),
+            3 => export_prompt(conn, user, export_snapshots_csv),
+            4 => export_prompt(conn, user, export_item_history_csv),
+            5 => import_csv_prompt(conn, user),
+            6 => backup_prompt(conn, user),
+            7 => restore_prompt(conn),
+            8 => journal_export_prompt(conn, user),
+            9 => journal_import_prompt(conn, user),
+            10 => report_prompt(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return,
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print what an items CSV import would create, or the problems that stop it
+pub fn print_csv_import(import: &CsvImport) {
+    if !import.errors.is_empty() {
+        println!("\nThe file can't be imported until these are fixed:");
+        for error in &import.errors {
+            println!("    {}", error);
+        }
+        return;
+    }
+    if import.rows.is_empty() {
+        println!("\nThere are no items in the file to import");
+        return;
+    }
+    if !import.new_categories.is_empty() {
+        println!("\nNew categories:");
+        for (kind, category) in &import.new_categories {
+            println!("    {}  ({})", category, kind.to_str());
+        }
+    }
+    println!("\nNew items:");
+    for row in &import.rows {
+        let frequency = match row.frequency {
+            Some(frequency) => format!(", {}", frequency.describe()),
+            None => String::new(),
+        };
+        println!(
+            "    {:<width$}{:>18}  ({} in {}{})",
+            row.item,
+            to_money_string(row.value, &row.currency),
+            row.kind.to_str(),
+            row.category,
+            frequency,
+            width = MAX_CHARACTERS_ITEM_NAME + 2
+        );
+    }
+}
+
+/// Print who a backup is of, when it was taken, and how many rows it has of each kind of data
+pub fn print_backup(backup: &Backup) {
+    println!(
+        "\nBackup of {} taken {}  (backup version {}, database version {})",
+        backup.username(),
+        backup.created,
+        backup.version,
+        backup.schema_version
+    );
+    for (table, rows) in &backup.tables {
+        if !rows.is_empty() {
+            println!("    {:<26}{:>8}", table, rows.len());
+        }
+    }
+    if !backup.shared_exchange_rates.is_empty() {
+        println!(
+            "    {:<26}{:>8}",
+            "shared exchange_rates",
+            backup.shared_exchange_rates.len()
+        );
+    }
+}
+
+/// Print the snapshots a balance import would take, with each item's balance and the items and categories it would create
+pub fn print_balance_import(import: &BalanceImport) {
+    if !import.new_categories.is_empty() {
+        println!("\nNew categories:");
+        for (is_asset, category) in &import.new_categories {
+            println!(
+                "    {}  ({})",
+                category,
+                if *is_asset { "asset" } else { "liability" }
+            );
+        }
+    }
+    for date in &import.dates {
+        println!("\nSnapshot on {}", date.date_text);
+        for balance in &date.balances {
+            println!(
+                "    {:<width$}{:>18}  ({} in {}{})",
+                balance.item,
+                to_money_string(balance.value, &balance.currency),
+                if balance.is_asset {
+                    "asset"
+                } else {
+                    "liability"
+                },
+                balance.category,
+                if balance.item_original.is_none() {
+                    ", new"
+                } else {
+                    ""
+                },
+                width = MAX_CHARACTERS_ITEM_NAME + 2
+            );
+        }
+    }
+}
+
+/// Ask for the path of a file and export to it
+fn export_prompt(
+    conn: &Connection,
+    user: &User,
+    export: fn(&Connection, &User, &str) -> Result<usize>,
+) -> Result<()> {
+    println!("\nEnter the path of the CSV file to save to (an existing file is replaced):");
+    let path = read_or_quit();
+    let num_rows = export(conn, user, &path)?;
+    println!(
+        "\nSaved {} rows to {}. Press Enter to continue.",
+        num_rows, path
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for the path of an items CSV file, show what it would create and confirm before importing it
+fn import_csv_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!(
+        "\nEnter the path to a CSV file with a header line of {}",
+        ITEM_COLUMNS.join(",")
+    );
+    println!(
+        "The kind is asset, liability, income or expense. Only kind, item and value are required."
+    );
+    let path = read_or_quit();
+    let import = preview_csv_import(conn, user, &path)?;
+    print_csv_import(&import);
+    if !import.errors.is_empty() || import.rows.is_empty() {
+        println!("\nNothing was imported. Press Enter to continue.");
+        read_or_quit(); // Give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    println!("\nWould you like to import these?");
+    println!("1. Yes");
+    println!("2. No (Go back)");
+    if print_instr_get_response(1, 2, || {}) == 1 {
+        let num_items = apply_csv_import(conn, user, &import)?;
+        println!("\nImported {} items. Press Enter to continue.", num_items);
+        read_or_quit(); // Give the user a chance to acknowledge
+    }
+    Ok(())
+}
+
+/// Ask for the path of a JSON file and back everything up to it
+fn backup_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter the path of the JSON file to save to (an existing file is replaced):");
+    println!("It includes your password hash, so keep it somewhere safe.");
+    let path = read_or_quit();
+    let backup = write_backup(conn, user, &path)?;
+    print_backup(&backup);
+    println!("\nSaved the backup to {}. Press Enter to continue.", path);
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for the path of a backup and the username to restore it as, then restore it
+fn restore_prompt(conn: &Connection) -> Result<()> {
+    println!("\nEnter the path to the backup's JSON file:");
+    let backup = read_backup(&read_or_quit())?;
+    print_backup(&backup);
+    println!(
+        "\nWhat username should it be restored as? Just hit Enter to use {}.",
+        backup.username()
+    );
+    let response = read_or_quit();
+    let username = match response.is_empty() {
+        true => None,
+        false => Some(response.as_str()),
+    };
+    let restored = restore_backup(conn, &backup, username)?;
+    println!(
+        "\nRestored the backup as {}. Log in as them to see it. Press Enter to continue.",
+        restored.username
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask which plain-text accounting format and the path of the file, then export to it
+fn journal_export_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nWhich format?");
+    let format = match print_instr_get_response(0, 2, || {
+        println!("1. Ledger or hledger journal");
+        println!("2. Beancount");
+        println!("\n0. GO BACK");
+    }) {
+        1 => PlainTextFormat::Ledger,
+        2 => PlainTextFormat::Beancount,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(()),
+    };
+    println!("\nEnter the path of the file to save to (an existing file is replaced):");
+    let path = read_or_quit();
+    let (num_snapshots, num_items) = export_plain_text(conn, user, &path, format)?;
+    println!(
+        "\nSaved {} snapshots and {} budget items to {}. Press Enter to continue.",
+        num_snapshots, num_items, path
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Ask for the path of a beancount file or ledger journal, show the snapshots its balances would make and confirm before taking them
+fn journal_import_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter the path to a beancount file or a ledger or hledger journal:");
+    println!("Its balance directives or balance assertions become snapshots, one for each day.");
+    let import = preview_balance_import(conn, user, &read_or_quit())?;
+    print_balance_import(&import);
+
+    println!(
+        "\nWould you like to take these {} snapshots? The balance sheet will be left at the last one.",
+        import.dates.len()
+    );
+    println!("1. Yes");
+    println!("2. No (Go back)");
+    if print_instr_get_response(1, 2, || {}) == 1 {
+        let num_snapshots = apply_balance_import(conn, user, &import)?;
+        println!(
+            "\nTook {} snapshots. Press Enter to continue.",
+            num_snapshots
+        );
+        read_or_quit(); // Give the user a chance to acknowledge
+    }
+    Ok(())
+}
+
+/// Ask for the format, the snapshots to put side by side and the path of the file, then write the report to it
+fn report_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nWhich format?");
+    let format = match print_instr_get_response(0, 2, || {
+        println!("1. HTML page with charts (opens in any browser and prints cleanly)");
+        println!("2. Markdown");
+        println!("\n0. GO BACK");
+    }) {
+        1 => ReportFormat::Html,
+        2 => ReportFormat::Markdown,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(()),
+    };
+
+    let snapshots = list_snapshots(conn, user)?;
+    let mut chosen: Vec<Snapshot> = vec![];
+    if !snapshots.is_empty() {
+        println!("\nSaved Snapshots:");
+        for (idx, snapshot) in snapshots.iter().enumerate() {
+            println!(
+                "{}.  {}:  Net Worth  {}",
+                idx + 1,
+                snapshot.date_today,
+                to_money_string(snapshot.net_worth, &snapshot.currency)
+            );
+        }
+        println!("\nSelect up to 5 snapshots to show side by side by entering their numbers separated by a space (eg - 1 5 6)");
+        println!(
+            "Just hit Enter to use the last {}.",
+            REPORT_SNAPSHOTS.min(snapshots.len())
+        );
+        let response = read_or_quit();
+        if response.is_empty() {
+            chosen = snapshots[snapshots.len().saturating_sub(REPORT_SNAPSHOTS)..].to_vec();
+        } else {
+            match parse_space_delim_response_for_int_to_index(response, 1, snapshots.len()) {
+                Some(selected_indices) => {
+                    for selected_index in selected_indices {
+                        chosen.push(snapshots[selected_index].clone());
+                    }
+                }
+                None => {
+                    println!("\nNo correct inputs were given. Please try again.");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    println!("\nEnter the path of the file to save to (an existing file is replaced):");
+    let path = read_or_quit();
+    write_report(conn, user, &path, format, &chosen)?;
+    println!(
+        "\nSaved the {} report to {}. Press Enter to continue.",
+        format.to_str(),
+        path
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use burkebudget::error::Result;
+use burkebudget::ledger::{ledger_display_name, ledger_name, ledger_path, list_ledgers};
+use burkebudget::structs_utils::*;
+use std::path::{Path, PathBuf};
+
+/// List the named ledgers and let the user pick one or start a new one
+/// Returns the path of the ledger to switch to, or None to stay on the current one
+pub fn switch_ledger_menu(current: &Path) -> Result<Option<PathBuf>> {
+    let ledgers = list_ledgers()?;
+    let current_name = ledger_name(current);
+
+    println!("\n\nLEDGERS: Each ledger is a separate database with its own users");
+    println!("You are using {}", ledger_display_name(current));
+    println!("\nWhich ledger would you like to switch to? (Enter the number)");
+    let mut idx: usize = 0;
+    for ledger in &ledgers {
+        idx += 1;
+        if current_name.as_deref() == Some(ledger.as_str()) {
+            println!("{}. {} (current)", idx, ledger);
+        } else {
+            println!("{}. {}", idx, ledger);
+        }
+    }
+    println!("{}. Start a New Ledger", idx + 1);
+    println!("0. GO BACK");
+
+    match print_instr_get_response(0, idx + 1, || {}) {
+        x if x > 0 && x <= idx => {
+            let path = ledger_path(&ledgers[x - 1])?;
+            if path == current {
+                return Ok(None);
+            }
+            Ok(Some(path))
+        }
+        x if x == idx + 1 => {
+            println!("\nEnter a name for the new ledger (eg - household, business):");
+            let path = ledger_path(&read_or_quit())?;
+            Ok(Some(path))
+        }
+        // 0 (or anything unexpected) goes back
+        _ => Ok(None),
+    }
+}
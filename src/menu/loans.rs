@@ -0,0 +1,304 @@
+use super::report_error;
+use burkebudget::balance_sheet::{get_relevant_items_cats, initialize_balance_sheet};
+use burkebudget::error::Result;
+use burkebudget::loans::{
+    amortization_schedule, delete_loan_terms, get_loan, loan_checks, loan_summary, parse_apr,
+    projected_balance, set_loan_terms,
+};
+use burkebudget::structs_utils::*;
+use burkebudget::transactions::parse_date;
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+/// List the liabilities, showing which ones have loan terms, and offer to open one
+pub fn loans_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_balance_sheet(conn, user)?;
+    loop {
+        let (_, items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+        println!("\n\nLOANS: Loan terms and amortization schedules for your liabilities\n");
+        if items.is_empty() {
+            println!("You don't have any liabilities yet. Add them in the Liabilities menu.");
+        }
+        for (idx, item) in items.iter().enumerate() {
+            let terms = match get_loan(conn, user, item)? {
+                Some(loan) => format!(
+                    "  ({}% for {} months, {} a month)",
+                    loan.apr,
+                    loan.term_months,
+                    to_money_string(loan.payment, &loan.currency)
+                ),
+                None => String::from("  (no loan terms)"),
+            };
+            println!(
+                "{}. {}  {}{}",
+                idx + 1,
+                item.item,
+                to_money_string(item.value, &item.currency),
+                terms
+            );
+        }
+        println!("\n0. GO BACK - Balance Sheet Menu");
+        let response = print_instr_get_response(0, items.len(), || {
+            println!("\nEnter the number of a liability to open it");
+        });
+        let result = match response {
+            x if x > 0 && x <= items.len() => single_loan_menu(conn, user, &items[x - 1]),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Print the terms and payoff of a loan and how its recorded value has tracked the schedule
+pub fn print_loan(conn: &Connection, user: &User, item: &Item, loan: &Loan) -> Result<()> {
+    let currency = &loan.currency;
+    let summary = loan_summary(loan, Amount::ZERO)?;
+    println!("\n\nLOAN: {}", item.item);
+    println!(
+        "Borrowed {} on {} at {}% for {} months",
+        to_money_string(loan.principal, currency),
+        loan.start_date,
+        loan.apr,
+        loan.term_months
+    );
+    println!(
+        "Paying {} a month, it is paid off on {} after {} payments",
+        to_money_string(summary.payment, currency),
+        summary.payoff_date,
+        summary.num_payments
+    );
+    println!(
+        "Total interest: {}",
+        to_money_string(summary.total_interest, currency)
+    );
+    println!(
+        "Projected balance today: {}",
+        to_money_string(
+            projected_balance(loan, Local::now().date_naive(), Amount::ZERO)?,
+            currency
+        )
+    );
+
+    let checks = loan_checks(conn, user, loan)?;
+    if !checks.is_empty() {
+        println!("\nAt each snapshot:");
+        println!(
+            "    {:<12}{:>16}{:>16}{:>16}",
+            "Date", "Recorded", "Projected", "Difference"
+        );
+        for check in &checks {
+            println!(
+                "    {:<12}{:>16}{:>16}{:>16}",
+                check.date_text,
+                to_money_string(check.recorded, currency),
+                to_money_string(check.projected, currency),
+                to_money_string(check.recorded - check.projected, currency)
+            );
+        }
+        println!("A positive difference means more is owed than the schedule expects");
+    }
+    Ok(())
+}
+
+/// Print every payment in a loan's schedule
+pub fn print_schedule(loan: &Loan, extra: Amount) -> Result<()> {
+    let currency = &loan.currency;
+    println!(
+        "\n{:>5}  {:<12}{:>16}{:>16}{:>16}{:>16}",
+        "#", "Date", "Payment", "Interest", "Principal", "Balance"
+    );
+    for payment in amortization_schedule(loan, extra)? {
+        println!(
+            "{:>5}  {:<12}{:>16}{:>16}{:>16}{:>16}",
+            payment.number,
+            payment.date.to_string(),
+            to_money_string(payment.payment, currency),
+            to_money_string(payment.interest, currency),
+            to_money_string(payment.principal, currency),
+            to_money_string(payment.balance, currency)
+        );
+    }
+    Ok(())
+}
+
+/// Compare paying an extra amount each month with the regular schedule
+pub fn print_what_if(loan: &Loan, extra: Amount) -> Result<()> {
+    let currency = &loan.currency;
+    let regular = loan_summary(loan, Amount::ZERO)?;
+    let with_extra = loan_summary(loan, extra)?;
+    println!(
+        "\nPaying {} a month ({} extra):",
+        to_money_string(with_extra.payment, currency),
+        to_money_string(extra, currency)
+    );
+    println!(
+        "It is paid off on {} instead of {}, {} months sooner",
+        with_extra.payoff_date,
+        regular.payoff_date,
+        regular.num_payments - with_extra.num_payments
+    );
+    println!(
+        "Total interest is {} instead of {}, saving {}",
+        to_money_string(with_extra.total_interest, currency),
+        to_money_string(regular.total_interest, currency),
+        to_money_string(regular.total_interest - with_extra.total_interest, currency)
+    );
+    Ok(())
+}
+
+/// Ask for an amount of 0 or more until a valid one is given
+fn get_amount_response() -> Amount {
+    loop {
+        match read_or_quit().parse::<Amount>() {
+            Ok(amount) if amount.is_negative() => println!("\nPlease enter a positive number."),
+            Ok(amount) => return amount,
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    }
+}
+
+/// Show one liability's loan, or offer to add terms if it doesn't have any
+fn single_loan_menu(conn: &Connection, user: &User, item: &Item) -> Result<()> {
+    loop {
+        let loan = match get_loan(conn, user, item)? {
+            Some(loan) => loan,
+            None => {
+                println!("\n{} doesn't have loan terms yet.", item.item);
+                match print_instr_get_response(0, 1, || {
+                    println!("1. Add Loan Terms");
+                    println!("\n0. GO BACK");
+                }) {
+                    1 => {
+                        loan_terms_prompt(conn, user, item)?;
+                        continue;
+                    }
+                    // 0 (or anything unexpected) goes back
+                    _ => return Ok(()),
+                }
+            }
+        };
+        print_loan(conn, user, item, &loan)?;
+        println!("\n\nWhat would you like to do with this loan?");
+        let result = match print_instr_get_response(0, 5, || {
+            println!("1. View the Full Schedule");
+            println!("2. What if I Paid Extra Each Month?");
+            println!("3. Projected Balance on a Date");
+            println!("4. Change the Loan Terms");
+            println!("5. Remove the Loan Terms");
+            println!("\n0. GO BACK");
+        }) {
+            1 => {
+                let result = print_schedule(&loan, Amount::ZERO);
+                println!("\nHit Enter to go back.");
+                read_or_quit(); // Give the user a chance to acknowledge
+                result
+            }
+            2 => {
+                println!("\nHow much extra would you pay each month?");
+                let extra = get_amount_response();
+                let result = print_what_if(&loan, extra);
+                println!("\nHit Enter to go back.");
+                read_or_quit(); // Give the user a chance to acknowledge
+                result
+            }
+            3 => {
+                println!("\nWhich date? (YYYY-MM-DD)");
+                match parse_date(&read_or_quit()) {
+                    Ok(date) => {
+                        let balance = projected_balance(&loan, date, Amount::ZERO)?;
+                        println!(
+                            "\nThe projected balance on {} is {}",
+                            date,
+                            to_money_string(balance, &loan.currency)
+                        );
+                        println!("Hit Enter to go back.");
+                        read_or_quit(); // Give the user a chance to acknowledge
+                        Ok(())
+                    }
+                    Err(error) => Err(error),
+                }
+            }
+            4 => loan_terms_prompt(conn, user, item),
+            5 => {
+                println!(
+                    "\nAre you sure you'd like to remove the loan terms from {}? This cannot be undone.",
+                    item.item
+                );
+                println!("The liability itself won't be changed.");
+                println!("1. Yes");
+                println!("2. No (Go back)");
+                if print_instr_get_response(1, 2, || {}) == 1 {
+                    return delete_loan_terms(conn, user, item);
+                }
+                Ok(())
+            }
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Ask for the amount borrowed, interest rate, term, start date and payment of a loan
+fn loan_terms_prompt(conn: &Connection, user: &User, item: &Item) -> Result<()> {
+    println!(
+        "\nHow much was borrowed? (positive number, in {})",
+        item.currency
+    );
+    let principal = get_amount_response();
+
+    let apr = loop {
+        println!("\nWhat is the annual interest rate? (percent, eg - 6.5)");
+        match parse_apr(&read_or_quit()) {
+            Ok(apr) => break apr,
+            Err(error) => println!("\n{}", error),
+        }
+    };
+
+    let term_months = loop {
+        println!("\nHow many months is the loan for? (eg - 360 for 30 years)");
+        match read_or_quit().trim().parse::<u32>() {
+            Ok(term_months) if term_months > 0 => break term_months,
+            _ => println!("\nPlease enter a whole number of months."),
+        }
+    };
+
+    let start_date = loop {
+        println!("\nWhen did the loan start? (YYYY-MM-DD) The first payment is a month later.");
+        let response = read_or_quit();
+        match parse_date(&response) {
+            Ok(_) => break response,
+            Err(error) => println!("\n{}", error),
+        }
+    };
+
+    let payment = loop {
+        println!("\nWhat is the monthly payment? Just hit Enter to work it out from the terms.");
+        let response = read_or_quit();
+        if response.is_empty() {
+            break None;
+        }
+        match response.parse::<Amount>() {
+            Ok(amount) if amount > Amount::ZERO => break Some(amount),
+            Ok(_) => println!("\nThe payment must be greater than 0."),
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    };
+
+    set_loan_terms(
+        conn,
+        user,
+        item,
+        principal,
+        apr,
+        term_months,
+        payment,
+        &start_date,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use super::{read_password, report_error};
+use burkebudget::auth::{authenticate, change_password, MIN_PASSWORD_LENGTH};
+use burkebudget::error::{BudgetError, Result};
+use burkebudget::structs_utils::*;
+use burkebudget::users::{delete_user, purge_user, rename_user, update_name};
+use rusqlite::Connection;
+
+/// Display the profile menu
+/// The user is updated in place when their details change
+/// Returns true if the account was deleted and the user has been logged out
+pub fn profile_menu(conn: &Connection, user: &mut User) -> bool {
+    loop {
+        println!("\n\nPROFILE: {} ({})", user.fullname(), user.username);
+        if user.has_password {
+            println!("Your account is protected by a password");
+        } else {
+            println!("Your account has no password, so anyone can login as you");
+        }
+        let result = match print_instr_get_response(1, 7, || {
+            println!("\nWhat would you like to do? (Enter the number)");
+            println!("1. Edit Name");
+            println!("2. Change Username");
+            if user.has_password {
+                println!("3. Change Password");
+            } else {
+                println!("3. Set a Password");
+            }
+            println!("4. Remove Password");
+            println!("5. Delete Account");
+            println!("6. Main Menu");
+            println!("7. Quit");
+        }) {
+            1 => edit_name(conn, user),
+            2 => change_username(conn, user),
+            3 => set_password(conn, user),
+            4 => remove_password(conn, user),
+            5 => match delete_account(conn, user) {
+                Ok(true) => return true,
+                Ok(false) => Ok(()),
+                Err(error) => Err(error),
+            },
+            7 => {
+                println!(
+                    "\nYour budget is saved, and you have been logged out. See you next time!\n"
+                );
+                std::process::exit(0);
+            }
+            // 6 (or anything unexpected) goes back
+            _ => return false,
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Ask for the current password if there is one
+fn get_current_password(user: &User) -> String {
+    if !user.has_password {
+        return String::new();
+    }
+    println!("\nEnter your current password:");
+    read_password()
+}
+
+/// Change the first and last name
+fn edit_name(conn: &Connection, user: &mut User) -> Result<()> {
+    println!("\nWhat is your first name? (currently {})", user.firstname);
+    let firstname = read_or_quit();
+    println!("What is your last name? (currently {})", user.lastname);
+    let lastname = read_or_quit();
+    *user = update_name(conn, user, &firstname, &lastname)?;
+    println!(
+        "\nYour name is now {}. Press Enter to continue.",
+        user.fullname()
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Change the username used to login
+/// All of the budget, balance sheet and snapshot data moves to the new username
+fn change_username(conn: &Connection, user: &mut User) -> Result<()> {
+    println!("\nWhat would you like your new username to be?");
+    let new_username = read_or_quit();
+    *user = rename_user(conn, user, &new_username)?;
+    println!(
+        "\nYou will now login as {}. Press Enter to continue.",
+        user.username
+    );
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Delete the account, either so it can be restored later or permanently
+/// Returns true if the account was deleted
+fn delete_account(conn: &Connection, user: &User) -> Result<bool> {
+    match print_instr_get_response(1, 3, || {
+        println!(
+            "\nHow would you like to delete {}? (Enter the number)",
+            user.username
+        );
+        println!(
+            "1. Delete (it can be restored by logging in as {} again)",
+            user.username
+        );
+        println!("2. Permanently delete the account and everything in it");
+        println!("3. Go Back");
+    }) {
+        1 => {
+            authenticate(conn, user, &get_current_password(user))?;
+            delete_user(conn, user)?;
+            println!(
+                "\n{} has been deleted and you have been logged out. Press Enter to continue.",
+                user.username
+            );
+        }
+        2 => {
+            authenticate(conn, user, &get_current_password(user))?;
+            println!(
+                "\nThis cannot be undone. Type the username {} to permanently delete it:",
+                user.username
+            );
+            if read_or_quit() != user.username {
+                return Err(BudgetError::Validation(String::from(
+                    "The username didn't match, so nothing was deleted",
+                )));
+            }
+            purge_user(conn, user)?;
+            println!(
+                "\n{} has been permanently deleted and you have been logged out. Press Enter to continue.",
+                user.username
+            );
+        }
+        // 3 (or anything unexpected) goes back
+        _ => return Ok(false),
+    }
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(true)
+}
+
+/// Set a new password, checking it was typed the same way twice
+fn set_password(conn: &Connection, user: &mut User) -> Result<()> {
+    let current_password = get_current_password(user);
+    println!(
+        "\nEnter the new password (at least {} characters):",
+        MIN_PASSWORD_LENGTH
+    );
+    let new_password = read_password();
+    println!("Enter the new password again:");
+    if read_password() != new_password {
+        return Err(BudgetError::Validation(String::from(
+            "The passwords didn't match, so nothing was changed",
+        )));
+    }
+    *user = change_password(conn, user, &current_password, Some(&new_password))?;
+    println!("\nYour password has been saved. Press Enter to continue.");
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
+
+/// Remove the password so the account can be used without one
+fn remove_password(conn: &Connection, user: &mut User) -> Result<()> {
+    if !user.has_password {
+        return Err(BudgetError::NotFound(String::from(
+            "Your account doesn't have a password",
+        )));
+    }
+    let current_password = get_current_password(user);
+    *user = change_password(conn, user, &current_password, None)?;
+    println!("\nYour password has been removed. Press Enter to continue.");
+    read_or_quit(); // Give the user a chance to acknowledge
+    Ok(())
+}
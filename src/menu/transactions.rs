@@ -0,0 +1,513 @@
+use super::{get_currency_response, report_error};
+use burkebudget::balance_sheet::get_relevant_items_cats;
+use burkebudget::budget::{get_categories, get_relevant_items, initialize_budget};
+use burkebudget::error::Result;
+use burkebudget::statements::{
+    apply_statement_import, delete_payee_rule, list_payee_rules, preview_statement_import,
+    set_payee_rule,
+};
+use burkebudget::structs_utils::*;
+use burkebudget::transactions::{
+    add_transaction, budget_vs_actual, current_month, delete_transaction, list_transactions,
+    parse_date, parse_month,
+};
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+/// List one month of transactions and offer to add or delete them
+pub fn transactions_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    let mut month = current_month();
+    loop {
+        let transactions = list_transactions(conn, user, Some(&month))?;
+        let (categories, items) = transaction_targets(conn, user)?;
+
+        println!("\n\nTRANSACTIONS for {}", month);
+        if transactions.is_empty() {
+            println!("There are no transactions in this month yet");
+        }
+        let mut idx: usize = 0;
+        for transaction in &transactions {
+            idx += 1;
+            print!(
+                "{}.  {}  {} {}  ({})",
+                idx,
+                transaction.date_text,
+                if transaction.is_income { "+" } else { "-" },
+                to_money_string(transaction.amount, &transaction.currency),
+                target_name(transaction, &categories, &items)
+            );
+            if transaction.description.is_empty() {
+                println!();
+            } else {
+                println!("  \"{}\"", transaction.description);
+            }
+        }
+        println!("\n{}. ADD TRANSACTION", idx + 1);
+        println!("{}. CHANGE MONTH", idx + 2);
+        println!("{}. IMPORT A BANK STATEMENT (OFX, QFX or QIF)", idx + 3);
+        println!("{}. PAYEE RULES FOR IMPORTS", idx + 4);
+        println!("\n0. GO BACK - Budget Menu");
+        let response = print_instr_get_response(0, idx + 4, || {
+            println!(
+                "\nEnter the number of a transaction to delete it, or one of the other numbers"
+            );
+        });
+        let result = match response {
+            x if x > 0 && x <= idx => delete_transaction_prompt(conn, user, &transactions[x - 1]),
+            x if x == idx + 1 => create_new_transaction(conn, user),
+            x if x == idx + 2 => {
+                month = get_month_response();
+                Ok(())
+            }
+            x if x == idx + 3 => import_statement_prompt(conn, user),
+            x if x == idx + 4 => payee_rules_menu(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Every category and current item in both halves of the budget, for naming transactions
+pub fn transaction_targets(
+    conn: &Connection,
+    user: &User,
+) -> Result<(Vec<BudgetCategory>, Vec<BudgetItem>)> {
+    let mut categories = get_categories(conn, user, &BudgetHalf::Income)?;
+    categories.extend(get_categories(conn, user, &BudgetHalf::Expenses)?);
+    let (_, mut items) = get_relevant_items(conn, user, &BudgetHalf::Income)?;
+    items.extend(get_relevant_items(conn, user, &BudgetHalf::Expenses)?.1);
+    Ok((categories, items))
+}
+
+/// The item a transaction is assigned to, or its category if it has no current item
+pub fn target_name(
+    transaction: &BudgetTransaction,
+    categories: &[BudgetCategory],
+    items: &[BudgetItem],
+) -> String {
+    if let Some(item) = items
+        .iter()
+        .find(|i| Some(i.timeline_original) == transaction.item_original)
+    {
+        return item.item.clone();
+    }
+    categories
+        .iter()
+        .find(|c| {
+            c.category_lower == transaction.category_lower && c.is_income == transaction.is_income
+        })
+        .map(|c| c.category.clone())
+        .unwrap_or_else(|| transaction.category_lower.clone())
+}
+
+/// Ask for a YYYY-MM month until a valid one is given
+/// Just hitting Enter gives the current month
+pub fn get_month_response() -> String {
+    loop {
+        println!("\nWhich month? (YYYY-MM) Just hit Enter for this month.");
+        let response = read_or_quit();
+        if response.is_empty() {
+            return current_month();
+        }
+        match parse_month(&response) {
+            Ok(month) => return month,
+            Err(error) => println!("\n{}", error),
+        }
+    }
+}
+
+/// Ask whether it's income or an expense, then which item or category
+/// The categories are listed with their items underneath, and either can be chosen. None means go back.
+fn choose_transaction_target(conn: &Connection, user: &User) -> Result<Option<TransactionTarget>> {
+    println!("\nIs this income or an expense?");
+    let which_half = match print_instr_get_response(0, 2, || {
+        println!("1. Income");
+        println!("2. Expense");
+        println!("\n0. GO BACK");
+    }) {
+        1 => BudgetHalf::Income,
+        2 => BudgetHalf::Expenses,
+        // 0 (or anything unexpected) goes back
+        _ => return Ok(None),
+    };
+
+    let (categories, items) = get_relevant_items(conn, user, &which_half)?;
+    let mut targets: Vec<TransactionTarget> = vec![];
+    println!(
+        "\nWhich {} item or category is this for?",
+        if which_half.to_bool() {
+            "income"
+        } else {
+            "expense"
+        }
+    );
+    for category in categories {
+        println!("{}. {}", targets.len() + 1, category.category);
+        let category_lower = category.category_lower.clone();
+        targets.push(TransactionTarget::Category(category));
+        for item in &items {
+            if item.category_lower == category_lower {
+                targets.push(TransactionTarget::Item(item.clone()));
+                println!("    {}. {}", targets.len(), item.item);
+            }
+        }
+    }
+    println!("\n0. GO BACK");
+    let response = print_instr_get_response(0, targets.len(), || {});
+    if response == 0 || response > targets.len() {
+        return Ok(None);
+    }
+    Ok(Some(targets.remove(response - 1)))
+}
+
+/// Transaction Creator
+/// Asks which item or category it belongs to, then the details
+fn create_new_transaction(conn: &Connection, user: &User) -> Result<()> {
+    let Some(target) = choose_transaction_target(conn, user)? else {
+        return Ok(());
+    };
+
+    // Get the date, defaulting to today
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let date_text = loop {
+        println!("\nWhat date was this? (YYYY-MM-DD) Just hit Enter for today.");
+        let response = read_or_quit();
+        if response.is_empty() {
+            break today;
+        }
+        match parse_date(&response) {
+            Ok(date) => break date.format("%Y-%m-%d").to_string(),
+            Err(error) => println!("\n{}", error),
+        }
+    };
+
+    println!("\nEnter an optional description (Just hit Enter to skip):");
+    let description = read_or_quit();
+
+    println!("\nWhat was the amount? (positive number)");
+    let amount = loop {
+        match read_or_quit().parse::<Amount>() {
+            Ok(amount) if amount.is_negative() || amount == Amount::ZERO => {
+                println!("\nPlease enter a positive number.")
+            }
+            Ok(amount) => break amount,
+            Err(error) => println!("\n{}. Please enter a valid number.", error),
+        }
+    };
+    let currency = get_currency_response(&user.base_currency);
+
+    add_transaction(
+        conn,
+        user,
+        &target,
+        &date_text,
+        &description,
+        amount,
+        &currency,
+    )?;
+    Ok(())
+}
+
+/// Confirm and delete a transaction
+fn delete_transaction_prompt(
+    conn: &Connection,
+    user: &User,
+    transaction: &BudgetTransaction,
+) -> Result<()> {
+    println!(
+        "\nAre you sure you'd like to delete the transaction of {} on {}? This cannot be undone.",
+        to_money_string(transaction.amount, &transaction.currency),
+        transaction.date_text
+    );
+    println!("1. Yes");
+    println!("2. No (Go back)");
+    match print_instr_get_response(1, 2, || {}) {
+        1 => delete_transaction(conn, user, transaction.id),
+        // 2 (or anything unexpected) goes back
+        _ => Ok(()),
+    }
+}
+
+/// Print what a bank statement import would record, what it skips as already recorded, and the balances it updates
+pub fn print_statement_import(import: &StatementImport) {
+    println!(
+        "\n{} statement with {} transactions, {} of them new",
+        import.format.to_str(),
+        import.lines.len(),
+        import.num_new()
+    );
+    for (heading, is_duplicate) in [("New transactions:", false), ("Already recorded:", true)] {
+        let lines: Vec<&StatementLine> = import
+            .lines
+            .iter()
+            .filter(|line| line.is_duplicate == is_duplicate)
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        println!("\n{}", heading);
+        for line in lines {
+            let placed = match &line.rule {
+                Some(rule) => format!(
+                    "{}  (payee rule \"{}\")",
+                    line.item.as_deref().unwrap_or(&line.category),
+                    rule
+                ),
+                None => line.category.clone(),
+            };
+            println!(
+                "    {}  {} {:>16}  {:<width$}  -> {}",
+                line.entry.date_text,
+                if line.is_income { "+" } else { "-" },
+                to_money_string(line.entry.amount.abs(), &line.entry.currency),
+                line.entry.description(),
+                placed,
+                width = MAX_CHARACTERS_ITEM_NAME
+            );
+        }
+    }
+    for balance in &import.balances {
+        let as_of = match &balance.date_text {
+            Some(date_text) => format!(" as of {}", date_text),
+            None => String::new(),
+        };
+        println!(
+            "\nAccount {} has a balance of {}{}",
+            balance.account_id,
+            to_money_string(balance.amount, &balance.currency),
+            as_of
+        );
+        match &balance.item {
+            Some(item) if balance.changes_item() => println!(
+                "    {} will be updated from {} to {}",
+                item.item,
+                to_money_string(item.value, &item.currency),
+                to_money_string(balance.value, &balance.currency)
+            ),
+            Some(item) => println!("    {} is already up to date", item.item),
+            None => println!("    It isn't linked to a balance sheet item, so nothing is updated"),
+        }
+    }
+}
+
+/// Describe where a payee rule sends transactions
+pub fn describe_payee_rule(rule: &PayeeRule) -> String {
+    format!(
+        "\"{}\" -> {}  ({})",
+        rule.pattern,
+        match &rule.item {
+            Some(item) => format!("{} in {}", item, rule.category),
+            None => rule.category.clone(),
+        },
+        if rule.is_income { "income" } else { "expense" }
+    )
+}
+
+/// Ask for the path of a bank statement, show what it would record and confirm before importing it
+/// An account balance that isn't linked to a balance sheet item yet can be linked to one here
+fn import_statement_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter the path to an OFX, QFX or QIF bank statement:");
+    let path = read_or_quit();
+    let mut import = preview_statement_import(conn, user, &path, None)?;
+    if let [balance] = import.balances.as_slice() {
+        if balance.item.is_none() {
+            if let Some(item) = choose_balance_item(conn, user, &balance.account_id)? {
+                import = preview_statement_import(conn, user, &path, Some(&item))?;
+            }
+        }
+    }
+    print_statement_import(&import);
+    if import.num_new() == 0 && !import.balances.iter().any(|b| b.changes_item()) {
+        println!("\nThere is nothing new to import. Press Enter to continue.");
+        read_or_quit(); // Give the user a chance to acknowledge
+        return Ok(());
+    }
+
+    println!("\nWould you like to import this?");
+    println!("1. Yes");
+    println!("2. No (Go back)");
+    if print_instr_get_response(1, 2, || {}) == 1 {
+        let num_imported = apply_statement_import(conn, user, &import)?;
+        println!(
+            "\nImported {} transactions. Press Enter to continue.",
+            num_imported
+        );
+        read_or_quit(); // Give the user a chance to acknowledge
+    }
+    Ok(())
+}
+
+/// Ask which balance sheet item an account's balance should update, if any
+fn choose_balance_item(conn: &Connection, user: &User, account_id: &str) -> Result<Option<Item>> {
+    let (_, mut items) = get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    items.extend(get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?.1);
+    if items.is_empty() {
+        return Ok(None);
+    }
+    println!(
+        "\nThe statement has the balance of account {}. Which balance sheet item should it update?",
+        account_id
+    );
+    for (idx, item) in items.iter().enumerate() {
+        println!(
+            "{}. {}  ({})",
+            idx + 1,
+            item.item,
+            if item.is_asset { "asset" } else { "liability" }
+        );
+    }
+    println!("\n0. None of them");
+    let response = print_instr_get_response(0, items.len(), || {});
+    if response == 0 || response > items.len() {
+        return Ok(None);
+    }
+    Ok(Some(items.remove(response - 1)))
+}
+
+/// List the payee rules and offer to add or delete them
+fn payee_rules_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    loop {
+        let rules = list_payee_rules(conn, user)?;
+        println!("\n\nPAYEE RULES: Where imported transactions go by their payee");
+        if rules.is_empty() {
+            println!("There are no payee rules yet, so imported transactions go to Uncategorized");
+        }
+        for (idx, rule) in rules.iter().enumerate() {
+            println!("{}.  {}", idx + 1, describe_payee_rule(rule));
+        }
+        println!("\n{}. ADD RULE", rules.len() + 1);
+        println!("\n0. GO BACK - Transactions");
+        let response = print_instr_get_response(0, rules.len() + 1, || {
+            println!("\nEnter the number of a rule to delete it, or one of the other numbers");
+        });
+        let result = match response {
+            x if x > 0 && x <= rules.len() => {
+                let rule = &rules[x - 1];
+                delete_payee_rule(conn, user, &rule.pattern, Some(rule.is_income))
+            }
+            x if x == rules.len() + 1 => add_payee_rule_prompt(conn, user),
+            // 0 (or anything unexpected) goes back
+            _ => return Ok(()),
+        };
+        if let Err(error) = result {
+            report_error(&error);
+        }
+    }
+}
+
+/// Ask for the payee to match and where its transactions should go
+fn add_payee_rule_prompt(conn: &Connection, user: &User) -> Result<()> {
+    println!("\nEnter the payee to match. Any payee containing it matches, ignoring case:");
+    let pattern = read_or_quit();
+    let Some(target) = choose_transaction_target(conn, user)? else {
+        return Ok(());
+    };
+    set_payee_rule(conn, user, &pattern, &target)?;
+    Ok(())
+}
+
+/// Ask for a month and show the budget vs. actual report for it
+pub fn budget_vs_actual_menu(conn: &Connection, user: &User) -> Result<()> {
+    initialize_budget(conn, user)?;
+    let month = get_month_response();
+    let report = budget_vs_actual(conn, user, &month)?;
+    print_budget_vs_actual(&report);
+    println!("\n\nPress enter to return.");
+    read_or_quit();
+    Ok(())
+}
+
+/// Print the budget vs. actual report without prompting
+/// A month with its own budget also shows what each envelope carried in from last month
+pub fn print_budget_vs_actual(report: &BudgetVsActual) {
+    println!(
+        "\n\nBudget vs. Actual - {}  (Totals in {})",
+        report.month, report.currency
+    );
+    if report.is_month_budget {
+        println!("Planned from the budget for {}", report.month);
+    } else {
+        println!("Planned from the current budget");
+    }
+    let carried_width = if report.is_month_budget { 16 } else { 0 };
+    for (heading, categories) in [("INCOME", &report.income), ("EXPENSES", &report.expenses)] {
+        println!(
+            "\n{:<width$}{:>16}{:>carried_width$}{:>16}{:>16}{:>8}",
+            heading,
+            "Planned",
+            if report.is_month_budget {
+                "Carried in"
+            } else {
+                ""
+            },
+            "Actual",
+            "Remaining",
+            "Used",
+            width = MAX_CHARACTERS_ITEM_NAME + 4
+        );
+        let mut total = ActualLine {
+            name: format!("Total {}", heading.to_lowercase()),
+            planned: Amount::ZERO,
+            carried_in: Amount::ZERO,
+            actual: Amount::ZERO,
+        };
+        for category in categories {
+            let mut category_line = category.total.clone();
+            if category.rollover {
+                category_line.name += " (rolls over)";
+            }
+            print_actual_line(&category_line, "", report);
+            for item in &category.items {
+                print_actual_line(item, "    ", report);
+            }
+            if category.unassigned != Amount::ZERO {
+                print_actual_line(
+                    &ActualLine {
+                        name: String::from("Not assigned to an item"),
+                        planned: Amount::ZERO,
+                        carried_in: Amount::ZERO,
+                        actual: category.unassigned,
+                    },
+                    "    ",
+                    report,
+                );
+            }
+            total.planned += category.total.planned;
+            total.carried_in += category.total.carried_in;
+            total.actual += category.total.actual;
+        }
+        for _ in 0..(MAX_CHARACTERS_ITEM_NAME + 60 + carried_width) {
+            print!("_");
+        }
+        println!();
+        print_actual_line(&total, "", report);
+    }
+}
+
+/// Print one line of the budget vs. actual report
+fn print_actual_line(line: &ActualLine, indent: &str, report: &BudgetVsActual) {
+    let percent_used = match line.percent_used() {
+        Some(percent) => format!("{:.0}%", percent),
+        None => String::from("-"),
+    };
+    let carried_in = if report.is_month_budget {
+        format!("{:>16}", to_money_string(line.carried_in, &report.currency))
+    } else {
+        String::new()
+    };
+    println!(
+        "{:<width$}{:>16}{}{:>16}{:>16}{:>8}",
+        format!("{}{}", indent, line.name),
+        to_money_string(line.planned, &report.currency),
+        carried_in,
+        to_money_string(line.actual, &report.currency),
+        to_money_string(line.remaining(), &report.currency),
+        percent_used,
+        width = MAX_CHARACTERS_ITEM_NAME + 4
+    );
+}
@@ -0,0 +1,853 @@
+use crate::error::{BudgetError, Result};
+use crate::money::MINOR_PER_MAJOR;
+use chrono::prelude::*;
+use rusqlite::{Connection, OptionalExtension, Transaction};
+use std::path::PathBuf;
+
+/// One step in the history of the database schema
+/// The schema version is stored in PRAGMA user_version and is the number of migrations applied
+struct Migration {
+    description: &'static str,
+    apply: fn(&Transaction) -> Result<()>,
+}
+
+/// Every migration in the order it must run
+/// Only ever add to the end of this list. A migration that has shipped must never change.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "Create the original users, budget and balance sheet tables",
+        apply: create_original_tables,
+    },
+    Migration {
+        description: "Store money as integer cents instead of REAL dollars",
+        apply: money_to_integer_cents,
+    },
+    Migration {
+        description: "Add currencies and exchange rates",
+        apply: add_currencies,
+    },
+    Migration {
+        description: "Add optional passwords and login lockout to users",
+        apply: add_passwords,
+    },
+    Migration {
+        description: "Add budget snapshots",
+        apply: add_budget_snapshots,
+    },
+    Migration {
+        description: "Add actual income and expense transactions",
+        apply: add_transactions,
+    },
+    Migration {
+        description: "Add frequencies to budget items",
+        apply: add_budget_frequencies,
+    },
+    Migration {
+        description: "Add month by month budgets with envelope rollover",
+        apply: add_budget_months,
+    },
+    Migration {
+        description: "Add savings and payoff goals",
+        apply: add_goals,
+    },
+    Migration {
+        description: "Add loan terms for liabilities",
+        apply: add_loan_terms,
+    },
+    Migration {
+        description: "Add interest rates and minimum payments for debts",
+        apply: add_debt_terms,
+    },
+    Migration {
+        description: "Add assumed annual returns for asset categories",
+        apply: add_category_returns,
+    },
+    Migration {
+        description: "Add bank statement imports and payee rules",
+        apply: add_statement_imports,
+    },
+    Migration {
+        description: "Give each user their own exchange rates",
+        apply: add_user_exchange_rates,
+    },
+];
+
+/// The schema version this build of the app writes
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// What happened when the database was brought up to date
+pub struct MigrationOutcome {
+    /// The schema version before migrating
+    pub from_version: u32,
+    /// The schema version now (always SCHEMA_VERSION)
+    pub to_version: u32,
+    /// Copy of the database taken before migrating, if there was anything to back up
+    pub backup: Option<PathBuf>,
+}
+
+impl MigrationOutcome {
+    pub fn migrated(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+/// The schema version stored in the database (0 for a new or unversioned database)
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.query_row("PRAGMA user_version", (), |row| row.get(0))?)
+}
+
+/// Bring the database up to SCHEMA_VERSION, running each missing migration in its own transaction
+/// A database made by a newer version of the app is refused rather than risk damaging it
+/// Existing data is copied to a backup file next to the database before the first migration runs
+pub fn migrate(conn: &Connection) -> Result<MigrationOutcome> {
+    let from_version = schema_version(conn)?;
+    if from_version > SCHEMA_VERSION {
+        return Err(BudgetError::Conflict(format!(
+            "This database is from a newer version of Burke Budget (schema version {}, but this app only knows up to {}). Please upgrade the app.",
+            from_version, SCHEMA_VERSION
+        )));
+    }
+
+    let mut backup: Option<PathBuf> = None;
+    if from_version < SCHEMA_VERSION && has_tables(conn)? {
+        backup = backup_db(conn, from_version)?;
+    }
+
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(from_version as usize) {
+        let version = idx as u32 + 1;
+        // The version is bumped inside the transaction so a failed migration leaves nothing behind
+        let tx = conn.unchecked_transaction()?;
+        (migration.apply)(&tx).map_err(|error| {
+            BudgetError::Conflict(format!(
+                "Upgrading the database to version {} ({}) failed: {}",
+                version, migration.description, error
+            ))
+        })?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+
+    Ok(MigrationOutcome {
+        from_version,
+        to_version: SCHEMA_VERSION,
+        backup,
+    })
+}
+
+/// Whether the database has any tables yet (a brand new file has nothing to back up)
+fn has_tables(conn: &Connection) -> Result<bool> {
+    let num_tables: usize = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+        (),
+        |row| row.get(0),
+    )?;
+    Ok(num_tables > 0)
+}
+
+/// Write a copy of the database next to it (eg - default.db.v2-20240101-120000.bak)
+/// In-memory databases have no file and aren't backed up
+fn backup_db(conn: &Connection, from_version: u32) -> Result<Option<PathBuf>> {
+    let path = match conn.path() {
+        Some(path) if !path.is_empty() => path,
+        _ => return Ok(None),
+    };
+    let backup = PathBuf::from(format!(
+        "{}.v{}-{}.bak",
+        path,
+        from_version,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    let backup_text = backup.to_str().ok_or_else(|| {
+        BudgetError::Validation(String::from("The database path can't be used for a backup"))
+    })?;
+    // VACUUM INTO makes a consistent copy even while this connection has the database open
+    conn.execute("VACUUM INTO ?1", [backup_text])?;
+    Ok(Some(backup))
+}
+
+/// The declared type of a column, or None if the table or column doesn't exist
+fn column_type(conn: &Connection, table: &str, column: &str) -> Result<Option<String>> {
+    Ok(conn
+        .query_row(
+            "SELECT type FROM pragma_table_info(?1) WHERE name = ?2",
+            (table, column),
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Add a column unless it is already there
+/// Databases from before versioning may have been partly upgraded by older releases
+/// The definition must include a DEFAULT so the existing rows get a value
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if column_type(conn, table, column)?.is_none() {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            (),
+        )?;
+    }
+    Ok(())
+}
+
+/// Convert a money column from REAL dollars to INTEGER cents if it hasn't been done yet
+/// SQLite can't change the type of a column in place, so the table is rebuilt with create_sql
+/// ROUND to the nearest cent recovers the exact value that was entered before it was stored as a float
+fn migrate_real_money_column(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    create_sql: &str,
+) -> Result<()> {
+    if !column_type(conn, table, column)?.is_some_and(|t| t.eq_ignore_ascii_case("REAL")) {
+        return Ok(());
+    }
+
+    let old_table = format!("{}_real", table);
+    conn.execute(
+        &format!("ALTER TABLE {} RENAME TO {}", table, old_table),
+        (),
+    )?;
+    conn.execute(create_sql, ())?;
+    conn.execute(
+        &format!("INSERT INTO {} SELECT * FROM {}", table, old_table),
+        (),
+    )?;
+    conn.execute(
+        &format!(
+            "UPDATE {} SET {} = CAST(ROUND({} * {}) AS INTEGER)",
+            table, column, column, MINOR_PER_MAJOR
+        ),
+        (),
+    )?;
+    conn.execute(&format!("DROP TABLE {}", old_table), ())?;
+    Ok(())
+}
+
+/// Version 1
+/// The tables as the first release made them
+/// IF NOT EXISTS because databases from before versioning already have them
+fn create_original_tables(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS users (
+            username TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            firstname TEXT NOT NULL,
+            lastname TEXT NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            PRIMARY KEY (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_categories (
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            PRIMARY KEY (category_lower, username_lower, is_income),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_items (
+            item TEXT NOT NULL,
+            item_lower TEXT NOT NULL,
+            value REAL NOT NULL,
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            timeline_created INTEGER NOT NULL,
+            timeline_original INTEGER NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            timeline_deleted INTEGER NOT NULL,
+            PRIMARY KEY (item_lower, username_lower, timeline_created),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_timeline (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            PRIMARY KEY (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS balance_categories (
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            PRIMARY KEY (category_lower, username_lower, is_asset),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS balance_items (
+            item TEXT NOT NULL,
+            item_lower TEXT NOT NULL,
+            value REAL NOT NULL,
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            timeline_created INTEGER NOT NULL,
+            timeline_original INTEGER NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            timeline_deleted INTEGER NOT NULL,
+            PRIMARY KEY (item_lower, username_lower, timeline_created),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_asset) REFERENCES balance_categories
+                (category_lower, username_lower, is_asset) ON UPDATE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS balance_timeline (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            PRIMARY KEY (username_lower)
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS balance_snapshots (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            net_worth REAL NOT NULL,
+            comment TEXT NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            PRIMARY KEY (timestamp, username_lower, is_deleted)
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 2
+/// Values and net worths become an integer number of cents
+/// Skipped for tables an older release already converted
+fn money_to_integer_cents(tx: &Transaction) -> Result<()> {
+    migrate_real_money_column(
+        tx,
+        "budget_items",
+        "value",
+        "CREATE TABLE budget_items (
+            item TEXT NOT NULL,
+            item_lower TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            timeline_created INTEGER NOT NULL,
+            timeline_original INTEGER NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            timeline_deleted INTEGER NOT NULL,
+            PRIMARY KEY (item_lower, username_lower, timeline_created),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        )",
+    )?;
+    migrate_real_money_column(
+        tx,
+        "balance_items",
+        "value",
+        "CREATE TABLE balance_items (
+            item TEXT NOT NULL,
+            item_lower TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            category TEXT NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            timeline_created INTEGER NOT NULL,
+            timeline_original INTEGER NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            timeline_deleted INTEGER NOT NULL,
+            PRIMARY KEY (item_lower, username_lower, timeline_created),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_asset) REFERENCES balance_categories
+                (category_lower, username_lower, is_asset) ON UPDATE CASCADE
+        )",
+    )?;
+    migrate_real_money_column(
+        tx,
+        "balance_snapshots",
+        "net_worth",
+        "CREATE TABLE balance_snapshots (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            net_worth INTEGER NOT NULL,
+            comment TEXT NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            PRIMARY KEY (timestamp, username_lower, is_deleted)
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        )",
+    )?;
+    Ok(())
+}
+
+/// Version 3
+/// Everything from before currencies were supported was in USD
+fn add_currencies(tx: &Transaction) -> Result<()> {
+    add_column_if_missing(tx, "users", "base_currency", "TEXT NOT NULL DEFAULT 'USD'")?;
+    add_column_if_missing(
+        tx,
+        "budget_items",
+        "currency",
+        "TEXT NOT NULL DEFAULT 'USD'",
+    )?;
+    add_column_if_missing(
+        tx,
+        "balance_items",
+        "currency",
+        "TEXT NOT NULL DEFAULT 'USD'",
+    )?;
+    add_column_if_missing(
+        tx,
+        "balance_snapshots",
+        "currency",
+        "TEXT NOT NULL DEFAULT 'USD'",
+    )?;
+
+    // The latest known rate for each pair of currencies
+    // The rate is kept as decimal text so it isn't rounded by a float
+    // 1 unit of from_currency is worth rate units of to_currency
+    // snapshot_rates is a copy of the rates at the moment each snapshot was taken
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS exchange_rates (
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            PRIMARY KEY (from_currency, to_currency)
+        );
+
+        CREATE TABLE IF NOT EXISTS snapshot_rates (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            PRIMARY KEY (timestamp, username_lower, from_currency, to_currency),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 4
+/// A NULL password_hash means the account has no password
+/// locked_until is a unix time in seconds (0 when the account isn't locked)
+fn add_passwords(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE users ADD COLUMN password_hash TEXT;
+        ALTER TABLE users ADD COLUMN failed_logins INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE users ADD COLUMN locked_until INTEGER NOT NULL DEFAULT 0;",
+    )?;
+    Ok(())
+}
+
+/// Version 5
+/// Budget snapshots mirror balance_snapshots, with the monthly net in place of the net worth
+/// Their timestamps come from budget_timeline, so their frozen rates are kept apart from snapshot_rates
+fn add_budget_snapshots(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS budget_snapshots (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            monthly_net INTEGER NOT NULL,
+            comment TEXT NOT NULL,
+            is_deleted INTEGER NOT NULL,
+            currency TEXT NOT NULL,
+            PRIMARY KEY (timestamp, username_lower, is_deleted),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_snapshot_rates (
+            timestamp INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            PRIMARY KEY (timestamp, username_lower, from_currency, to_currency),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 6
+/// Amounts are positive integer cents and is_income says which way the money went
+/// item_original is the timeline_original of a budget item, or NULL when it only has a category
+fn add_transactions(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS transactions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username_lower TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            description TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            currency TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            category_lower TEXT NOT NULL,
+            item_original INTEGER,
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS transactions_by_date ON transactions (username_lower, date_text);",
+    )?;
+    Ok(())
+}
+
+/// Version 7
+/// The value of a budget item becomes the amount each time it comes in or goes out
+/// Every item before this was a monthly amount
+fn add_budget_frequencies(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE budget_items ADD COLUMN frequency TEXT NOT NULL DEFAULT 'monthly';",
+    )?;
+    Ok(())
+}
+
+/// Version 8
+/// budget_month_items follow budget_items, but each row is one item's plan for a single YYYY-MM month
+/// A category in budget_month_rollovers rolls what is left at the end of that month into the next one
+fn add_budget_months(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS budget_months (
+            username_lower TEXT NOT NULL,
+            month TEXT NOT NULL,
+            PRIMARY KEY (username_lower, month),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_month_items (
+            item TEXT NOT NULL,
+            item_lower TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            month TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            item_original INTEGER,
+            PRIMARY KEY (item_lower, username_lower, is_income, month),
+            FOREIGN KEY (username_lower, month) REFERENCES budget_months (username_lower, month),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS budget_month_rollovers (
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            month TEXT NOT NULL,
+            PRIMARY KEY (category_lower, username_lower, is_income, month),
+            FOREIGN KEY (username_lower, month) REFERENCES budget_months (username_lower, month),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 9
+/// A goal is measured by the balance sheet items and categories in goal_links
+/// An item link is the item's timeline_original so it follows the item through updates
+/// budget_item_original is the timeline_original of the budget expense item that pays into the goal (if any)
+fn add_goals(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            username_lower TEXT NOT NULL,
+            name TEXT NOT NULL,
+            name_lower TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            target INTEGER NOT NULL,
+            currency TEXT NOT NULL,
+            target_date TEXT,
+            start_value INTEGER NOT NULL,
+            budget_item_original INTEGER,
+            UNIQUE (username_lower, name_lower),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        CREATE TABLE IF NOT EXISTS goal_links (
+            goal_id INTEGER NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            item_original INTEGER,
+            category_lower TEXT,
+            FOREIGN KEY (goal_id) REFERENCES goals (id),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_asset) REFERENCES balance_categories
+                (category_lower, username_lower, is_asset) ON UPDATE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 10
+/// Loan terms belong to a liability by its timeline_original so they follow the item through updates
+/// apr is an annual percentage stored as exact decimal text, and payment is the regular monthly payment
+fn add_loan_terms(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS loan_terms (
+            username_lower TEXT NOT NULL,
+            item_original INTEGER NOT NULL,
+            principal INTEGER NOT NULL,
+            apr TEXT NOT NULL,
+            term_months INTEGER NOT NULL,
+            payment INTEGER NOT NULL,
+            start_date TEXT NOT NULL,
+            currency TEXT NOT NULL,
+            PRIMARY KEY (username_lower, item_original),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 11
+/// The interest rate and minimum monthly payment of a liability, for the debt payoff planner
+/// Like loan terms they belong to the item's timeline_original, and the minimum payment is in the item's currency
+fn add_debt_terms(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS debt_terms (
+            username_lower TEXT NOT NULL,
+            item_original INTEGER NOT NULL,
+            apr TEXT NOT NULL,
+            minimum_payment INTEGER NOT NULL,
+            PRIMARY KEY (username_lower, item_original),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 12
+/// The annual return assumed for an asset category when forecasting net worth, as exact decimal text
+/// is_asset is always 1, and is only kept so renaming the category cascades here
+fn add_category_returns(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE IF NOT EXISTS category_returns (
+            category_lower TEXT NOT NULL,
+            username_lower TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            rate TEXT NOT NULL,
+            PRIMARY KEY (category_lower, username_lower, is_asset),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_asset) REFERENCES balance_categories
+                (category_lower, username_lower, is_asset) ON UPDATE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 13
+/// fitid is the bank's id for an imported transaction, so the same statement can't be imported twice
+/// A payee rule sends imported transactions whose payee contains the pattern to a budget category or item
+/// A statement account remembers which balance sheet item a bank account's balance updates
+fn add_statement_imports(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE transactions ADD COLUMN fitid TEXT;
+
+        CREATE INDEX IF NOT EXISTS transactions_by_fitid ON transactions (username_lower, fitid);
+
+        CREATE TABLE IF NOT EXISTS payee_rules (
+            username_lower TEXT NOT NULL,
+            pattern TEXT NOT NULL,
+            pattern_lower TEXT NOT NULL,
+            is_income INTEGER NOT NULL,
+            category_lower TEXT NOT NULL,
+            item_original INTEGER,
+            PRIMARY KEY (username_lower, pattern_lower, is_income),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower),
+            FOREIGN KEY (category_lower, username_lower, is_income) REFERENCES budget_categories
+                (category_lower, username_lower, is_income) ON UPDATE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS statement_accounts (
+            username_lower TEXT NOT NULL,
+            account_id TEXT NOT NULL,
+            is_asset INTEGER NOT NULL,
+            item_original INTEGER NOT NULL,
+            PRIMARY KEY (username_lower, account_id),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );",
+    )?;
+    Ok(())
+}
+
+/// Version 14
+/// Exchange rates were shared by every user of a ledger, so each user starts with a copy of the shared rates
+fn add_user_exchange_rates(tx: &Transaction) -> Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE exchange_rates RENAME TO shared_exchange_rates;
+
+        CREATE TABLE exchange_rates (
+            username_lower TEXT NOT NULL,
+            from_currency TEXT NOT NULL,
+            to_currency TEXT NOT NULL,
+            rate TEXT NOT NULL,
+            date_text TEXT NOT NULL,
+            PRIMARY KEY (username_lower, from_currency, to_currency),
+            FOREIGN KEY (username_lower) REFERENCES users (username_lower)
+        );
+
+        INSERT INTO exchange_rates (username_lower, from_currency, to_currency, rate, date_text)
+            SELECT users.username_lower, shared.from_currency, shared.to_currency, shared.rate, shared.date_text
+            FROM users CROSS JOIN shared_exchange_rates AS shared;
+
+        DROP TABLE shared_exchange_rates;",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run the migrations up to a version, the way migrate does
+    fn migrate_to(conn: &Connection, version: u32) {
+        for (idx, migration) in MIGRATIONS.iter().enumerate().take(version as usize) {
+            let tx = conn.unchecked_transaction().unwrap();
+            (migration.apply)(&tx).unwrap();
+            tx.pragma_update(None, "user_version", idx as u32 + 1)
+                .unwrap();
+            tx.commit().unwrap();
+        }
+    }
+
+    fn add_original_user(conn: &Connection, username: &str) {
+        conn.execute(
+            "INSERT INTO users (username, username_lower, firstname, lastname, is_deleted)
+            VALUES (?1, ?2, 'First', 'Last', 0)",
+            (username, username.to_lowercase()),
+        )
+        .unwrap();
+    }
+
+    fn has_table(conn: &Connection, table: &str) -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |_| Ok(()),
+        )
+        .optional()
+        .unwrap()
+        .is_some()
+    }
+
+    #[test]
+    fn real_dollars_become_integer_cents() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 1);
+        add_original_user(&conn, "Alice");
+        conn.execute_batch(
+            "INSERT INTO budget_categories VALUES ('Food', 'food', 'alice', 0);
+            INSERT INTO budget_items VALUES ('Snacks', 'snacks', 0.1, 'Food', 'food', 'alice', 0, 1, 1, 0, 999999);
+            INSERT INTO budget_items VALUES ('Groceries', 'groceries', 0.1 + 0.2, 'Food', 'food', 'alice', 0, 2, 2, 0, 999999);
+            INSERT INTO balance_categories VALUES ('Cash', 'cash', 'alice', 1);
+            INSERT INTO balance_items VALUES ('Wallet', 'wallet', 0.2, 'Cash', 'cash', 'alice', 1, 1, 1, 0, 999999);
+            INSERT INTO balance_items VALUES ('Savings', 'savings', 1234.56, 'Cash', 'cash', 'alice', 1, 2, 2, 0, 999999);
+            INSERT INTO balance_snapshots VALUES (1, 'alice', '2024-01-01', -0.1 - 0.2, '', 0);",
+        )
+        .unwrap();
+
+        let outcome = migrate(&conn).unwrap();
+        assert_eq!(outcome.from_version, 1);
+        assert_eq!(outcome.to_version, MIGRATIONS.len() as u32);
+        assert!(outcome.backup.is_none());
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len() as u32);
+
+        let cents = |sql: &str| -> Vec<i64> {
+            let mut stmt = conn.prepare(sql).unwrap();
+            let rows = stmt.query_map((), |row| row.get(0)).unwrap();
+            rows.map(|value| value.unwrap()).collect()
+        };
+        assert_eq!(
+            cents("SELECT value FROM budget_items ORDER BY timeline_created"),
+            vec![10, 30]
+        );
+        assert_eq!(
+            cents("SELECT value FROM balance_items ORDER BY timeline_created"),
+            vec![20, 123456]
+        );
+        assert_eq!(cents("SELECT net_worth FROM balance_snapshots"), vec![-30]);
+        for (table, column) in [
+            ("budget_items", "value"),
+            ("balance_items", "value"),
+            ("balance_snapshots", "net_worth"),
+        ] {
+            assert_eq!(
+                column_type(&conn, table, column).unwrap().as_deref(),
+                Some("INTEGER")
+            );
+        }
+    }
+
+    #[test]
+    fn shared_exchange_rates_are_copied_to_every_user() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate_to(&conn, 13);
+        add_original_user(&conn, "Alice");
+        add_original_user(&conn, "Bob");
+        conn.execute_batch(
+            "INSERT INTO exchange_rates VALUES ('EUR', 'USD', '1.0825', '2024-01-01');
+            INSERT INTO exchange_rates VALUES ('GBP', 'USD', '1.27', '2024-01-02');",
+        )
+        .unwrap();
+
+        let outcome = migrate(&conn).unwrap();
+        assert_eq!(outcome.from_version, 13);
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len() as u32);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT username_lower, from_currency, to_currency, rate, date_text
+                FROM exchange_rates ORDER BY username_lower, from_currency",
+            )
+            .unwrap();
+        let rates: Vec<(String, String, String, String, String)> = stmt
+            .query_map((), |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .map(|rate| rate.unwrap())
+            .collect();
+        let rate = |user: &str, from: &str, rate: &str, date_text: &str| {
+            (
+                String::from(user),
+                String::from(from),
+                String::from("USD"),
+                String::from(rate),
+                String::from(date_text),
+            )
+        };
+        assert_eq!(
+            rates,
+            vec![
+                rate("alice", "EUR", "1.0825", "2024-01-01"),
+                rate("alice", "GBP", "1.27", "2024-01-02"),
+                rate("bob", "EUR", "1.0825", "2024-01-01"),
+                rate("bob", "GBP", "1.27", "2024-01-02"),
+            ]
+        );
+        assert!(!has_table(&conn, "shared_exchange_rates"));
+    }
+}
@@ -0,0 +1,307 @@
+use crate::error::{BudgetError, Result};
+use rusqlite::types::{FromSql, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::str::FromStr;
+
+/// Number of minor units (cents) in one major unit (dollar)
+pub(crate) const MINOR_PER_MAJOR: i64 = 100;
+
+/// An exact amount of money stored as integer minor units (cents)
+/// All sums are done on the integers so there is no floating point drift
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(i64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+
+    /// Build an amount from a number of minor units (eg - 1234 is $12.34)
+    pub const fn from_minor(minor: i64) -> Amount {
+        Amount(minor)
+    }
+
+    /// Build an amount from a whole number of major units (eg - 12 is $12.00)
+    pub const fn from_major(major: i64) -> Amount {
+        Amount(major * MINOR_PER_MAJOR)
+    }
+
+    /// The number of minor units (cents) in this amount
+    pub fn minor(&self) -> i64 {
+        self.0
+    }
+
+    /// Round a floating point value to the nearest minor unit
+    /// Only used for old REAL values and for chart coordinates
+    pub fn from_f64(value: f64) -> Amount {
+        Amount((value * MINOR_PER_MAJOR as f64).round() as i64)
+    }
+
+    /// Approximate value in major units, only for plotting and ratios
+    pub fn to_f64(&self) -> f64 {
+        self.0 as f64 / MINOR_PER_MAJOR as f64
+    }
+
+    pub fn abs(&self) -> Amount {
+        Amount(self.0.saturating_abs())
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    /// Parse an amount the way other programs write them in files
+    /// That allows a leading + or -, thousands separators, a decimal comma and extra zeros after the cents
+    pub(crate) fn parse_written(text: &str) -> Result<Amount> {
+        let invalid = || BudgetError::Parse(format!("{} is not a valid amount", text));
+        let mut number: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(rest) = number.strip_prefix('+') {
+            number = String::from(rest);
+        }
+        // A comma with only 1 or 2 digits after it is a decimal comma, otherwise commas separate thousands
+        number = match number.rfind(',') {
+            Some(idx)
+                if !number[idx..].contains('.') && (2..=3).contains(&(number.len() - idx)) =>
+            {
+                format!("{}.{}", number[..idx].replace('.', ""), &number[idx + 1..])
+            }
+            _ => number.replace(',', ""),
+        };
+        // Keep one zero so ".0" still has a digit after the point
+        if let Some((whole, fraction)) = number.split_once('.') {
+            let fraction = match fraction.trim_end_matches('0') {
+                "" if !fraction.is_empty() => "0",
+                trimmed => trimmed,
+            };
+            number = format!("{}.{}", whole, fraction);
+        }
+        number.parse::<Amount>().map_err(|_| invalid())
+    }
+
+    /// Multiply by numerator / denominator, rounded to the nearest minor unit (halves away from zero)
+    /// Used to turn a weekly or annual amount into its monthly equivalent
+    pub fn scale(&self, numerator: i64, denominator: i64) -> Amount {
+        let product = self.0 as i128 * numerator as i128;
+        let denominator = denominator as i128;
+        let magnitude = (product.abs() + denominator.abs() / 2) / denominator.abs();
+        let rounded = magnitude * product.signum() * denominator.signum();
+        Amount(rounded.clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+    }
+}
+
+/// Parse a plain decimal number such as "1234", "-5.5" or "0.07"
+/// More than two decimal places is an error rather than being silently rounded
+impl FromStr for Amount {
+    type Err = BudgetError;
+
+    fn from_str(input: &str) -> Result<Amount> {
+        let invalid = || BudgetError::Parse(format!("{} is not a valid amount", input));
+        let trimmed = input.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, fraction),
+            None => (digits, ""),
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+        if fraction.len() > 2 {
+            return Err(BudgetError::Parse(format!(
+                "{} has more than 2 decimal places",
+                input
+            )));
+        }
+
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().map_err(|_| invalid())?
+        };
+        // Pad the fraction out to 2 digits so "0.5" is 50 cents
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+        let minor = whole
+            .checked_mul(MINOR_PER_MAJOR)
+            .and_then(|m| m.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        Ok(Amount(if negative { -minor } else { minor }))
+    }
+}
+
+/// Plain decimal with 2 places (eg - 1234.50), the same format accepted by parse
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let per = MINOR_PER_MAJOR as u64;
+        write!(f, "{}{}.{:02}", sign, abs / per, abs % per)
+    }
+}
+
+impl Add for Amount {
+    type Output = Amount;
+    fn add(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Amount {
+    type Output = Amount;
+    fn sub(self, other: Amount) -> Amount {
+        Amount(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Neg for Amount {
+    type Output = Amount;
+    fn neg(self) -> Amount {
+        Amount(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Amount {
+    fn add_assign(&mut self, other: Amount) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Amount {
+    fn sub_assign(&mut self, other: Amount) {
+        *self = *self - other;
+    }
+}
+
+impl Sum for Amount {
+    fn sum<I: Iterator<Item = Amount>>(iter: I) -> Amount {
+        iter.fold(Amount::ZERO, |a, b| a + b)
+    }
+}
+
+impl<'a> Sum<&'a Amount> for Amount {
+    fn sum<I: Iterator<Item = &'a Amount>>(iter: I) -> Amount {
+        iter.fold(Amount::ZERO, |a, b| a + *b)
+    }
+}
+
+/// Stored in SQLite as an INTEGER number of minor units
+impl ToSql for Amount {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Amount {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Amount> {
+        i64::column_result(value).map(Amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<i64> {
+        text.parse::<Amount>().map(|amount| amount.minor())
+    }
+
+    fn parse_written(text: &str) -> Result<i64> {
+        Amount::parse_written(text).map(|amount| amount.minor())
+    }
+
+    #[test]
+    fn from_str() {
+        assert_eq!(parse("1234").unwrap(), 123400);
+        assert_eq!(parse("-5.5").unwrap(), -550);
+        assert_eq!(parse("0.07").unwrap(), 7);
+        assert_eq!(parse(".5").unwrap(), 50);
+        assert_eq!(parse("5.").unwrap(), 500);
+        assert_eq!(parse(" 12.30 ").unwrap(), 1230);
+        assert_eq!(
+            parse("1.234").unwrap_err().to_string(),
+            "1.234 has more than 2 decimal places"
+        );
+        for text in ["", "-", ".", "1,000", "$5", "1.2.3", "--1", "1e3"] {
+            assert_eq!(
+                parse(text).unwrap_err().to_string(),
+                format!("{} is not a valid amount", text)
+            );
+        }
+    }
+
+    #[test]
+    fn from_str_overflow() {
+        assert_eq!(parse("92233720368547758.07").unwrap(), i64::MAX);
+        assert_eq!(parse("-92233720368547758.07").unwrap(), -i64::MAX);
+        for text in [
+            "92233720368547758.08",
+            "92233720368547759",
+            "99999999999999999999",
+        ] {
+            assert_eq!(
+                parse(text).unwrap_err().to_string(),
+                format!("{} is not a valid amount", text)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_written_separators() {
+        // A comma followed by 1 or 2 digits is a decimal comma
+        assert_eq!(parse_written("12,34").unwrap(), 1234);
+        assert_eq!(parse_written("12,5").unwrap(), 1250);
+        assert_eq!(parse_written("1.234,56").unwrap(), 123456);
+        // Otherwise commas separate thousands
+        assert_eq!(parse_written("1,234").unwrap(), 123400);
+        assert_eq!(parse_written("1,234,567.89").unwrap(), 123456789);
+        assert_eq!(parse_written("1,234.5").unwrap(), 123450);
+        assert_eq!(parse_written("+1 500.00").unwrap(), 150000);
+        assert_eq!(parse_written("-0,99").unwrap(), -99);
+    }
+
+    #[test]
+    fn parse_written_extra_zeros() {
+        assert_eq!(parse_written("12.3400").unwrap(), 1234);
+        assert_eq!(parse_written("12.000").unwrap(), 1200);
+        assert_eq!(parse_written(".0").unwrap(), 0);
+        assert_eq!(parse_written("-.000").unwrap(), 0);
+        assert_eq!(parse_written("7.").unwrap(), 700);
+        for text in ["12.345", "1.2340", ".", "", "12,34,5"] {
+            assert_eq!(
+                parse_written(text).unwrap_err().to_string(),
+                format!("{} is not a valid amount", text)
+            );
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for minor in [0, 5, -5, 1234, -123456, i64::MAX] {
+            let text = Amount::from_minor(minor).to_string();
+            assert_eq!(parse(&text).unwrap(), minor, "{}", text);
+        }
+        assert_eq!(Amount::from_minor(-5).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn scale_rounds_halves_away_from_zero() {
+        assert_eq!(Amount::from_minor(5).scale(1, 2).minor(), 3);
+        assert_eq!(Amount::from_minor(-5).scale(1, 2).minor(), -3);
+        assert_eq!(Amount::from_minor(5).scale(1, -2).minor(), -3);
+        assert_eq!(Amount::from_minor(-5).scale(-1, -2).minor(), -3);
+        assert_eq!(Amount::from_minor(-1).scale(1, 3).minor(), 0);
+        assert_eq!(Amount::from_minor(-2).scale(1, 3).minor(), -1);
+        // 100.00 a week is 433.33 a month, and 1000.00 a year is 83.33
+        assert_eq!(Amount::from_major(100).scale(52, 12).minor(), 43333);
+        assert_eq!(Amount::from_major(-1000).scale(1, 12).minor(), -8333);
+        assert_eq!(Amount::from_minor(i64::MAX).scale(2, 1).minor(), i64::MAX);
+    }
+}
@@ -0,0 +1,913 @@
+use crate::balance_sheet::{
+    self, get_categories, get_relevant_items_cats, initialize_balance_sheet,
+};
+use crate::budget::get_relevant_items;
+use crate::currency::normalize_currency;
+use crate::error::{BudgetError, Result};
+use crate::frequency::month_abbreviation;
+use crate::snapshots::{create_snapshot_dated, list_snapshots, snapshot_date, snapshot_items_at};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use chrono::{Days, Months};
+use rusqlite::Connection;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// A snapshot, its date, and the balance at it of each account in each currency
+type SnapshotBalances = (Snapshot, NaiveDate, BTreeMap<(String, String), Amount>);
+
+/// The account each snapshot's change in the balance sheet is balanced against
+const SNAPSHOT_ACCOUNT: &str = "Equity:Snapshots";
+
+/// The account the periodic budget transactions are balanced against
+const BUDGET_ACCOUNT: &str = "Equity:Budget";
+
+/// The category that is left out of account names
+const UNCATEGORIZED: &str = "uncategorized";
+
+/// Make a name usable as one part of an account name
+/// Ledger only needs colons and runs of spaces taken out. Beancount parts are capitalized words joined by dashes.
+fn account_part(format: PlainTextFormat, name: &str) -> String {
+    match format {
+        PlainTextFormat::Ledger => name
+            .replace(':', "-")
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" "),
+        PlainTextFormat::Beancount => {
+            let words: Vec<String> = name
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(|word| {
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            match words.is_empty() {
+                true => String::from("Unnamed"),
+                false => words.join("-"),
+            }
+        }
+    }
+}
+
+/// The account of an item: the top level account, then its category (left out for Uncategorized), then the item
+/// eg - Assets:Retirement:401k or Liabilities:Mortgage
+fn account_name(format: PlainTextFormat, root: &str, category: &str, item: &str) -> String {
+    match category.to_lowercase() == UNCATEGORIZED {
+        true => format!("{}:{}", root, account_part(format, item)),
+        false => format!(
+            "{}:{}:{}",
+            root,
+            account_part(format, category),
+            account_part(format, item)
+        ),
+    }
+}
+
+/// The account of a balance sheet item
+fn item_account(format: PlainTextFormat, item: &Item) -> String {
+    let root = if item.is_asset {
+        "Assets"
+    } else {
+        "Liabilities"
+    };
+    account_name(format, root, &item.category, &item.item)
+}
+
+/// The account of a budget item
+fn budget_item_account(format: PlainTextFormat, item: &BudgetItem) -> String {
+    let root = if item.is_income { "Income" } else { "Expenses" };
+    account_name(format, root, &item.category, &item.item)
+}
+
+/// An amount as it is written in the books
+/// Liabilities and income are negative, the way double-entry books keep them
+fn book_amount(amount: Amount, currency: &str, is_negative: bool) -> String {
+    let amount = if is_negative { -amount } else { amount };
+    format!("{} {}", amount, currency)
+}
+
+/// The balance of every account at each snapshot, keeping only the last snapshot of each day
+/// The balances are in the item's own currency, keyed by account and currency
+fn snapshot_balances(
+    conn: &Connection,
+    user: &User,
+    format: PlainTextFormat,
+) -> Result<Vec<SnapshotBalances>> {
+    let mut history: Vec<SnapshotBalances> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        let date = snapshot_date(&snapshot)?;
+        let mut balances: BTreeMap<(String, String), Amount> = BTreeMap::new();
+        for which_half in [BalanceSheetHalf::Assets, BalanceSheetHalf::Liabilities] {
+            let (_, items) = snapshot_items_at(conn, user, &which_half, snapshot.timeline)?;
+            for item in items {
+                let key = (item_account(format, &item), item.currency.clone());
+                *balances.entry(key).or_default() += item.value;
+            }
+        }
+        if history.last().is_some_and(|(_, last, _)| *last == date) {
+            history.pop();
+        }
+        history.push((snapshot, date, balances));
+    }
+    Ok(history)
+}
+
+/// The first day of a month
+fn first_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default()
+}
+
+/// The ledger period expression for a budget item, and the amount for each period
+/// Semimonthly items are written as monthly ones of twice the amount, since ledger has no period for them
+fn ledger_period(frequency: Frequency, value: Amount, year: i32) -> (String, Amount) {
+    match frequency {
+        Frequency::Weekly => (String::from("weekly"), value),
+        Frequency::Biweekly => (String::from("biweekly"), value),
+        Frequency::SemiMonthly => (String::from("monthly"), value.scale(2, 1)),
+        Frequency::Monthly => (String::from("monthly"), value),
+        Frequency::Quarterly(month) => (
+            format!("every 3 months from {}", first_of_month(year, month)),
+            value,
+        ),
+        Frequency::Annual(month) => (
+            format!("yearly from {}", first_of_month(year, month)),
+            value,
+        ),
+        Frequency::OneOff(year, month) => {
+            let start = first_of_month(year, month);
+            let end = start + Months::new(1);
+            (format!("monthly from {} to {}", start, end), value)
+        }
+    }
+}
+
+/// The Fava budget entries for a budget item as (date, period, amount)
+/// Fava has no biweekly or semimonthly budgets, so those use their monthly equivalent
+/// A one-off item is budgeted for its month and then set back to 0
+fn beancount_budgets(
+    frequency: Frequency,
+    value: Amount,
+    start: NaiveDate,
+) -> Vec<(NaiveDate, &'static str, Amount)> {
+    match frequency {
+        Frequency::Weekly => vec![(start, "weekly", value)],
+        Frequency::Biweekly | Frequency::SemiMonthly | Frequency::Monthly => {
+            vec![(start, "monthly", frequency.monthly_equivalent(value))]
+        }
+        Frequency::Quarterly(_) => vec![(start, "quarterly", value)],
+        Frequency::Annual(_) => vec![(start, "yearly", value)],
+        Frequency::OneOff(year, month) => {
+            let month_start = first_of_month(year, month);
+            vec![
+                (month_start, "monthly", value),
+                (month_start + Months::new(1), "monthly", Amount::ZERO),
+            ]
+        }
+    }
+}
+
+/// Write the balance sheet history and the current budget to a plain-text accounting file, replacing it if it exists
+/// Each snapshot brings every account to its balance on the snapshot's date, and each budget item becomes a periodic transaction
+/// (a Fava budget in beancount). Returns the number of snapshots and budget items written.
+pub fn export_plain_text(
+    conn: &Connection,
+    user: &User,
+    path: &str,
+    format: PlainTextFormat,
+) -> Result<(usize, usize)> {
+    let history = snapshot_balances(conn, user, format)?;
+    let mut budget_items: Vec<BudgetItem> = vec![];
+    for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+        budget_items.extend(get_relevant_items(conn, user, &which_half)?.1);
+    }
+    let today = Local::now().date_naive();
+    let this_month = first_of_month(today.year(), today.month());
+
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "; Burke Budget export for {} ({}) on {}",
+        user.fullname(),
+        user.username,
+        today
+    )?;
+    match format {
+        PlainTextFormat::Ledger => write_ledger(&mut out, &history, &budget_items, today)?,
+        PlainTextFormat::Beancount => {
+            write_beancount(&mut out, user, &history, &budget_items, this_month)?
+        }
+    }
+    out.flush()?;
+    Ok((history.len(), budget_items.len()))
+}
+
+/// The ledger and hledger journal
+/// A snapshot is a transaction of balance assignments, so the books hold the balances as well as checking them
+fn write_ledger(
+    out: &mut impl Write,
+    history: &[SnapshotBalances],
+    budget_items: &[BudgetItem],
+    today: NaiveDate,
+) -> Result<()> {
+    let format = PlainTextFormat::Ledger;
+    let mut accounts: Vec<String> = history
+        .iter()
+        .flat_map(|(_, _, balances)| balances.keys().map(|(account, _)| account.clone()))
+        .chain(
+            budget_items
+                .iter()
+                .map(|item| budget_item_account(format, item)),
+        )
+        .collect();
+    accounts.extend([String::from(SNAPSHOT_ACCOUNT), String::from(BUDGET_ACCOUNT)]);
+    accounts.sort();
+    accounts.dedup();
+    writeln!(out)?;
+    for account in &accounts {
+        writeln!(out, "account {}", account)?;
+    }
+
+    writeln!(
+        out,
+        "\n; Balance sheet history, one transaction per snapshot"
+    )?;
+    let mut previous: BTreeMap<(String, String), Amount> = BTreeMap::new();
+    for (snapshot, date, balances) in history {
+        let description = match snapshot.comment.is_empty() {
+            true => String::from("Snapshot"),
+            false => format!("Snapshot - {}", snapshot.comment),
+        };
+        writeln!(out, "\n{} {}", date, description)?;
+        for ((account, currency), value) in balances {
+            let is_liability = account.starts_with("Liabilities");
+            writeln!(
+                out,
+                "    {:<50}= {}",
+                account,
+                book_amount(*value, currency, is_liability)
+            )?;
+        }
+        // Accounts that were in the last snapshot but not this one are closed out
+        for (account, currency) in previous.keys().filter(|key| !balances.contains_key(*key)) {
+            writeln!(out, "    {:<50}= 0 {}", account, currency)?;
+        }
+        writeln!(out, "    {}", SNAPSHOT_ACCOUNT)?;
+        previous = balances.clone();
+    }
+
+    writeln!(out, "\n; The current budget, as periodic transactions")?;
+    let mut periods: BTreeMap<String, Vec<(&BudgetItem, Amount)>> = BTreeMap::new();
+    for item in budget_items {
+        let (period, amount) = ledger_period(item.frequency, item.value, today.year());
+        periods.entry(period).or_default().push((item, amount));
+    }
+    for (period, items) in periods {
+        writeln!(out, "\n~ {}", period)?;
+        for (item, amount) in items {
+            write!(
+                out,
+                "    {:<50}{}",
+                budget_item_account(format, item),
+                book_amount(amount, &item.currency, item.is_income)
+            )?;
+            if item.frequency == Frequency::SemiMonthly {
+                write!(out, "  ; twice a month")?;
+            }
+            writeln!(out)?;
+        }
+        writeln!(out, "    {}", BUDGET_ACCOUNT)?;
+    }
+    Ok(())
+}
+
+/// The beancount file
+/// A balance directive checks the balance at the start of its day, so each snapshot's balances are dated the day after it.
+/// A pad on the snapshot's date brings the account to the balance whenever it has changed.
+fn write_beancount(
+    out: &mut impl Write,
+    user: &User,
+    history: &[SnapshotBalances],
+    budget_items: &[BudgetItem],
+    this_month: NaiveDate,
+) -> Result<()> {
+    let format = PlainTextFormat::Beancount;
+    writeln!(
+        out,
+        "option \"title\" \"Burke Budget - {}\"",
+        user.fullname().replace('"', "'")
+    )?;
+    writeln!(
+        out,
+        "option \"operating_currency\" \"{}\"",
+        user.base_currency
+    )?;
+
+    let opened = history
+        .first()
+        .map(|(_, date, _)| *date)
+        .unwrap_or(this_month)
+        .min(this_month);
+    let mut accounts: Vec<String> = history
+        .iter()
+        .flat_map(|(_, _, balances)| balances.keys().map(|(account, _)| account.clone()))
+        .chain(
+            budget_items
+                .iter()
+                .map(|item| budget_item_account(format, item)),
+        )
+        .collect();
+    accounts.push(String::from(SNAPSHOT_ACCOUNT));
+    accounts.sort();
+    accounts.dedup();
+    writeln!(out)?;
+    for account in &accounts {
+        writeln!(out, "{} open {}", opened, account)?;
+    }
+
+    writeln!(
+        out,
+        "\n; Balance sheet history, the balances at each snapshot"
+    )?;
+    let mut previous: BTreeMap<(String, String), Amount> = BTreeMap::new();
+    for (snapshot, date, balances) in history {
+        let next_day = *date + Days::new(1);
+        writeln!(out)?;
+        if !snapshot.comment.is_empty() {
+            writeln!(out, "; {}", snapshot.comment)?;
+        }
+        let closed: Vec<((String, String), Amount)> = previous
+            .keys()
+            .filter(|key| !balances.contains_key(*key))
+            .map(|key| (key.clone(), Amount::ZERO))
+            .collect();
+        for ((account, currency), value) in balances
+            .iter()
+            .map(|(k, v)| (k, *v))
+            .chain(closed.iter().map(|(key, value)| (key, *value)))
+        {
+            let was = previous
+                .get(&(account.clone(), currency.clone()))
+                .copied()
+                .unwrap_or(Amount::ZERO);
+            // Beancount refuses a pad that isn't needed, so only changed balances are padded
+            if value != was {
+                writeln!(out, "{} pad {} {}", date, account, SNAPSHOT_ACCOUNT)?;
+            }
+            let is_liability = account.starts_with("Liabilities");
+            writeln!(
+                out,
+                "{} balance {:<50}{}",
+                next_day,
+                account,
+                book_amount(value, currency, is_liability)
+            )?;
+        }
+        previous = balances.clone();
+    }
+
+    writeln!(out, "\n; The current budget, as Fava budgets")?;
+    for item in budget_items {
+        for (date, period, amount) in beancount_budgets(item.frequency, item.value, this_month) {
+            write!(
+                out,
+                "{} custom \"budget\" {} \"{}\" {}",
+                date,
+                budget_item_account(format, item),
+                period,
+                book_amount(amount, &item.currency, item.is_income)
+            )?;
+            if let Frequency::OneOff(_, month) = item.frequency {
+                if amount != Amount::ZERO {
+                    write!(out, "  ; one-off in {}", month_abbreviation(month))?;
+                }
+            }
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// A balance as it was read from the file, before it is matched to the balance sheet
+struct RawBalance {
+    line: usize,
+    date: NaiveDate,
+    account: String,
+    is_asset: bool,
+    amount: Amount,
+    currency: String,
+}
+
+/// Parse a date as YYYY-MM-DD, YYYY/MM/DD or YYYY.MM.DD, ignoring a ledger auxiliary date after an =
+fn parse_book_date(text: &str) -> Option<NaiveDate> {
+    let text = text.split('=').next().unwrap_or(text);
+    ["%Y-%m-%d", "%Y/%m/%d", "%Y.%m.%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+}
+
+/// Whether an account is an asset (Some(true)), a liability (Some(false)) or neither
+fn account_kind(account: &str) -> Option<bool> {
+    match account.split(':').next()?.to_lowercase().as_str() {
+        "assets" | "asset" => Some(true),
+        "liabilities" | "liability" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse an amount and its commodity, as in "1500.00 USD", "USD 1500.00" or "$1,500.00"
+/// An amount without a commodity is in the default currency
+fn parse_commodity_amount(text: &str, default_currency: &str) -> Result<(Amount, String)> {
+    let is_number = |c: &char| c.is_ascii_digit() || matches!(c, '.' | ',' | '-' | '+');
+    let number: String = text.chars().filter(is_number).collect();
+    let commodity: String = text
+        .chars()
+        .filter(|c| !is_number(c) && !c.is_whitespace() && *c != '"')
+        .collect();
+    let currency = match commodity.as_str() {
+        "" => String::from(default_currency),
+        "$" => String::from("USD"),
+        "€" => String::from("EUR"),
+        "£" => String::from("GBP"),
+        "¥" => String::from("JPY"),
+        code => normalize_currency(code)?,
+    };
+    Ok((Amount::parse_written(&number)?, currency))
+}
+
+/// Split a ledger posting into its account and the rest, since the account ends at two spaces or a tab
+fn split_posting(posting: &str) -> (&str, &str) {
+    let end = [posting.find("  "), posting.find('\t')]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(posting.len());
+    (posting[..end].trim(), posting[end..].trim())
+}
+
+/// Read the asset and liability balances in a beancount file or a ledger or hledger journal
+/// Beancount balance directives check the balance at the start of their day, so they are read as the balance at the end of the day before.
+/// Ledger balance assertions and assignments (a posting with = AMOUNT) are the balance on their transaction's date.
+fn parse_balances(text: &str, default_currency: &str) -> Result<Vec<RawBalance>> {
+    let mut balances: Vec<RawBalance> = vec![];
+    let mut date: Option<NaiveDate> = None;
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line_number = idx + 1;
+        let line = raw_line.split(';').next().unwrap_or_default();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (account, amount_text, balance_date) = if !raw_line.starts_with([' ', '\t']) {
+            let mut tokens = line.split_whitespace();
+            date = tokens.next().and_then(parse_book_date);
+            let (Some(day), Some("balance")) = (date, tokens.next()) else {
+                continue;
+            };
+            // A directive isn't a transaction, so the lines after it aren't its postings
+            date = None;
+            let account = tokens.next().unwrap_or_default();
+            // Leave out a tolerance, as in 100.00 ~ 0.01 USD
+            let mut amount_tokens: Vec<&str> = vec![];
+            let mut is_tolerance = false;
+            for token in tokens {
+                match (token, is_tolerance) {
+                    ("~", _) => is_tolerance = true,
+                    (_, true) => is_tolerance = false,
+                    (token, false) => amount_tokens.push(token),
+                }
+            }
+            (
+                String::from(account),
+                amount_tokens.join(" "),
+                day - Days::new(1),
+            )
+        } else {
+            let Some(day) = date else {
+                continue;
+            };
+            let posting = line.trim();
+            let posting = posting
+                .strip_prefix(['*', '!'])
+                .unwrap_or(posting)
+                .trim_start();
+            let (account, rest) = split_posting(posting);
+            let Some((_, assertion)) = rest.split_once('=') else {
+                continue;
+            };
+            (
+                String::from(account.trim_matches(['(', ')', '[', ']'])),
+                String::from(assertion.trim_start_matches(['=', '*'])),
+                day,
+            )
+        };
+
+        let Some(is_asset) = account_kind(&account) else {
+            continue;
+        };
+        let (amount, currency) = parse_commodity_amount(&amount_text, default_currency)
+            .map_err(|error| BudgetError::Parse(format!("Line {}: {}", line_number, error)))?;
+        balances.push(RawBalance {
+            line: line_number,
+            date: balance_date,
+            account,
+            is_asset,
+            amount,
+            currency,
+        });
+    }
+    Ok(balances)
+}
+
+/// The current items and categories of one half of the balance sheet, looked up by their account name in either format
+struct HalfAccounts {
+    items: HashMap<String, Item>,
+    categories: HashMap<String, String>,
+}
+impl HalfAccounts {
+    fn new(conn: &Connection, user: &User, which_half: &BalanceSheetHalf) -> Result<HalfAccounts> {
+        let mut accounts = HalfAccounts {
+            items: HashMap::new(),
+            categories: HashMap::new(),
+        };
+        for category in get_categories(conn, user, which_half)? {
+            for format in [PlainTextFormat::Ledger, PlainTextFormat::Beancount] {
+                accounts.categories.insert(
+                    account_part(format, &category.category).to_lowercase(),
+                    category.category.clone(),
+                );
+            }
+        }
+        let (_, items) = get_relevant_items_cats(conn, user, which_half)?;
+        for item in items {
+            for format in [PlainTextFormat::Ledger, PlainTextFormat::Beancount] {
+                accounts
+                    .items
+                    .insert(item_account(format, &item).to_lowercase(), item.clone());
+            }
+            accounts.items.insert(item.item_lower.clone(), item);
+        }
+        Ok(accounts)
+    }
+}
+
+/// Read the balances in a beancount file or a ledger or hledger journal and work out the snapshots they would make, without changing anything
+/// Each day with balances becomes a snapshot, and the balances must all be after the latest snapshot so snapshots stay in order
+/// An account is matched to the current item with the same account name (or the same name), otherwise an item is created:
+/// Assets:Retirement:401k is the item 401k in the Retirement category, and Liabilities:Mortgage is Mortgage in Uncategorized
+pub fn preview_balance_import(conn: &Connection, user: &User, path: &str) -> Result<BalanceImport> {
+    let contents = std::fs::read_to_string(path).map_err(|error| {
+        BudgetError::NotFound(format!("The file {} could not be read: {}", path, error))
+    })?;
+    let raw_balances = parse_balances(&contents, &user.base_currency)?;
+    if raw_balances.is_empty() {
+        return Err(BudgetError::Parse(format!(
+            "{} has no balances for asset or liability accounts",
+            path
+        )));
+    }
+    if let Some(latest) = list_snapshots(conn, user)?.last() {
+        let latest_date = snapshot_date(latest)?;
+        if let Some(early) = raw_balances.iter().find(|b| b.date <= latest_date) {
+            return Err(BudgetError::Validation(format!(
+                "Line {}: the balance is for {}, but there are already snapshots up to {}. Only balances after the latest snapshot can be imported.",
+                early.line, early.date, latest_date
+            )));
+        }
+    }
+
+    let assets = HalfAccounts::new(conn, user, &BalanceSheetHalf::Assets)?;
+    let liabilities = HalfAccounts::new(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let mut new_categories: Vec<(bool, String)> = vec![];
+    // The items that will be created, by account, and the account each new name was first used for
+    let mut new_items: HashMap<(bool, String), (String, String)> = HashMap::new();
+    let mut new_names: HashMap<(bool, String), String> = HashMap::new();
+    let mut dates: BTreeMap<NaiveDate, Vec<ImportedBalance>> = BTreeMap::new();
+
+    for raw in raw_balances {
+        let line_error =
+            |message: String| BudgetError::Validation(format!("Line {}: {}", raw.line, message));
+        let accounts = if raw.is_asset { &assets } else { &liabilities };
+        let account_lower = raw.account.to_lowercase();
+        let parts: Vec<&str> = raw.account.split(':').skip(1).collect();
+        let (category, item) = match parts.as_slice() {
+            [] => return Err(line_error(format!("{} has no item name", raw.account))),
+            [item] => (String::from("Uncategorized"), item.to_string()),
+            [category, rest @ ..] => (category.to_string(), rest.join(" ")),
+        };
+
+        let existing = accounts
+            .items
+            .get(&account_lower)
+            .or_else(|| accounts.items.get(&item.to_lowercase()));
+        let (category, item, item_original) = match existing {
+            Some(existing) => (
+                existing.category.clone(),
+                existing.item.clone(),
+                Some(existing.timeline_original),
+            ),
+            None => {
+                let key = (raw.is_asset, account_lower.clone());
+                if !new_items.contains_key(&key) {
+                    validate_item_name(&item).map_err(|error| line_error(error.to_string()))?;
+                    let name_key = (raw.is_asset, item.to_lowercase());
+                    if let Some(other) = new_names.get(&name_key) {
+                        return Err(line_error(format!(
+                            "{} and {} would both be items named {}",
+                            other, raw.account, item
+                        )));
+                    }
+                    new_names.insert(name_key, raw.account.clone());
+                    let category = match accounts.categories.get(&category.to_lowercase()) {
+                        Some(existing) => existing.clone(),
+                        None if category.to_lowercase() == UNCATEGORIZED => category,
+                        None => {
+                            if !new_categories.iter().any(|(is_asset, c)| {
+                                *is_asset == raw.is_asset
+                                    && c.to_lowercase() == category.to_lowercase()
+                            }) {
+                                new_categories.push((raw.is_asset, category.clone()));
+                            }
+                            category
+                        }
+                    };
+                    new_items.insert(key.clone(), (category, item));
+                }
+                let (category, item) = new_items[&key].clone();
+                (category, item, None)
+            }
+        };
+
+        // Liabilities are negative in the books and are kept as the amount owed
+        let value = match raw.is_asset {
+            true if raw.amount.is_negative() => {
+                return Err(line_error(format!(
+                    "the asset {} has a balance below 0",
+                    raw.account
+                )))
+            }
+            true => raw.amount,
+            false => raw.amount.abs(),
+        };
+        validate_item_value(value).map_err(|error| line_error(error.to_string()))?;
+
+        let balances = dates.entry(raw.date).or_default();
+        // A later balance for the same account on the same day replaces the earlier one
+        balances.retain(|b| !(b.is_asset == raw.is_asset && b.item == item));
+        balances.push(ImportedBalance {
+            line: raw.line,
+            account: raw.account,
+            is_asset: raw.is_asset,
+            category,
+            item,
+            value,
+            currency: raw.currency,
+            item_original,
+        });
+    }
+
+    Ok(BalanceImport {
+        dates: dates
+            .into_iter()
+            .map(|(date, balances)| BalanceDate {
+                date_text: date.format("%Y-%m-%d").to_string(),
+                balances,
+            })
+            .collect(),
+        new_categories,
+    })
+}
+
+/// Import previewed balances and return the number of snapshots taken
+/// For each day in order, the items are brought to their balances (a new version only where the value changed) and then a snapshot is taken on that date
+pub fn apply_balance_import(
+    conn: &Connection,
+    user: &User,
+    import: &BalanceImport,
+) -> Result<usize> {
+    in_transaction(conn, |conn| {
+        initialize_balance_sheet(conn, user)?;
+        for (is_asset, category) in &import.new_categories {
+            let which_half = if *is_asset {
+                BalanceSheetHalf::Assets
+            } else {
+                BalanceSheetHalf::Liabilities
+            };
+            balance_sheet::create_category(conn, user, &which_half, category)?;
+        }
+
+        // The timeline_original of each item created by this import, by half and name
+        let mut created: HashMap<(bool, String), usize> = HashMap::new();
+        for date in &import.dates {
+            for balance in &date.balances {
+                let which_half = if balance.is_asset {
+                    BalanceSheetHalf::Assets
+                } else {
+                    BalanceSheetHalf::Liabilities
+                };
+                let key = (balance.is_asset, balance.item.to_lowercase());
+                let Some(item_original) = balance.item_original.or(created.get(&key).copied())
+                else {
+                    let item = balance_sheet::create_item(
+                        conn,
+                        user,
+                        &which_half,
+                        &balance.item,
+                        balance.value,
+                        &balance.currency,
+                        &balance.category,
+                    )?;
+                    created.insert(key, item.timeline_original);
+                    continue;
+                };
+                let (_, items) = get_relevant_items_cats(conn, user, &which_half)?;
+                let item = items
+                    .into_iter()
+                    .find(|item| item.timeline_original == item_original)
+                    .ok_or_else(|| {
+                        BudgetError::NotFound(format!(
+                            "The balance sheet item {} was not found",
+                            balance.item
+                        ))
+                    })?;
+                if item.value != balance.value || item.currency != balance.currency {
+                    balance_sheet::update_item(
+                        conn,
+                        user,
+                        &item,
+                        &item.item,
+                        balance.value,
+                        &balance.currency,
+                        &item.category,
+                    )?;
+                }
+            }
+            create_snapshot_dated(conn, user, "Imported balances", &date.date_text)?;
+        }
+        Ok(import.dates.len())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parts of each balance that are checked, as (line, date, account, is_asset, amount in minor units, currency)
+    fn summarize(balances: &[RawBalance]) -> Vec<(usize, String, &str, bool, i64, &str)> {
+        balances
+            .iter()
+            .map(|balance| {
+                (
+                    balance.line,
+                    balance.date.format("%Y-%m-%d").to_string(),
+                    balance.account.as_str(),
+                    balance.is_asset,
+                    balance.amount.minor(),
+                    balance.currency.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn beancount_balances() {
+        let text = "option \"operating_currency\" \"USD\"
+2024-01-01 open Assets:Bank:Checking USD
+
+2024-02-01 balance Assets:Bank:Checking   1,500.25 USD
+2024-02-01 balance Liabilities:Visa  -320.10 ~ 0.01 USD ; the statement
+2024-02-01 balance Income:Salary  100 USD
+2024-03-01 * \"Shop\" \"Groceries\"
+  Assets:Bank:Checking  -20 USD
+  Expenses:Food
+2024-03-01 balance Assets:Savings 200 EUR
+";
+        let balances = parse_balances(text, "GBP").unwrap();
+        assert_eq!(
+            summarize(&balances),
+            vec![
+                (
+                    4,
+                    String::from("2024-01-31"),
+                    "Assets:Bank:Checking",
+                    true,
+                    150025,
+                    "USD"
+                ),
+                (
+                    5,
+                    String::from("2024-01-31"),
+                    "Liabilities:Visa",
+                    false,
+                    -32010,
+                    "USD"
+                ),
+                (
+                    10,
+                    String::from("2024-02-29"),
+                    "Assets:Savings",
+                    true,
+                    20000,
+                    "EUR"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn ledger_assertions() {
+        let text = "; opening balances
+2024/02/15 * Statement
+    Assets:Checking   $10.00 = $1,510.00
+    * Liabilities:Card  = -£45.5
+    (Assets:Cash)   = 30 ; no commodity
+    Expenses:Fees   $5 = $5
+    Income:Other
+
+2024.03.01=2024.03.02 Transfer
+\tAssets:Savings\t100 CAD = CAD 2500
+    Assets:Checking
+";
+        let balances = parse_balances(text, "NZD").unwrap();
+        assert_eq!(
+            summarize(&balances),
+            vec![
+                (
+                    3,
+                    String::from("2024-02-15"),
+                    "Assets:Checking",
+                    true,
+                    151000,
+                    "USD"
+                ),
+                (
+                    4,
+                    String::from("2024-02-15"),
+                    "Liabilities:Card",
+                    false,
+                    -4550,
+                    "GBP"
+                ),
+                (
+                    5,
+                    String::from("2024-02-15"),
+                    "Assets:Cash",
+                    true,
+                    3000,
+                    "NZD"
+                ),
+                (
+                    10,
+                    String::from("2024-03-01"),
+                    "Assets:Savings",
+                    true,
+                    250000,
+                    "CAD"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn postings_outside_a_transaction_are_ignored() {
+        let text = "  Assets:Checking  = 100 USD
+2024-01-31 balance Assets:Checking 100 USD
+  Assets:Checking  = 200 USD
+account Assets:Savings
+    Assets:Savings  = 50 USD
+";
+        let balances = parse_balances(text, "USD").unwrap();
+        assert_eq!(
+            summarize(&balances),
+            vec![(
+                2,
+                String::from("2024-01-30"),
+                "Assets:Checking",
+                true,
+                10000,
+                "USD"
+            )]
+        );
+    }
+
+    #[test]
+    fn balance_errors() {
+        let error = |text: &str| match parse_balances(text, "USD") {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error.to_string(),
+        };
+        assert_eq!(
+            error("2024-01-01 balance Assets:Checking 1.2.3 USD\n"),
+            "Line 1: 1.2.3 is not a valid amount"
+        );
+        assert_eq!(
+            error("2024-01-01 * Shop\n  Assets:Checking  = 100 DOLLARS\n"),
+            format!("Line 2: {}", normalize_currency("DOLLARS").unwrap_err())
+        );
+    }
+}
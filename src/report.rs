@@ -0,0 +1,1043 @@
+use crate::balance_sheet::{self, get_relevant_items_cats};
+use crate::budget::get_relevant_items;
+use crate::currency::{current_converter, snapshot_converter, Converter};
+use crate::error::Result;
+use crate::forecast::net_worth_history;
+use crate::snapshots::snapshot_items_at;
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// How many of the latest snapshots are put side by side when none are chosen
+pub const REPORT_SNAPSHOTS: usize = 3;
+
+/// The styles of the HTML report, written inline so the file stands on its own
+const REPORT_CSS: &str = "
+body { font-family: -apple-system, 'Segoe UI', Helvetica, Arial, sans-serif; color: #222; max-width: 960px; margin: 2em auto; padding: 0 1em; }
+h1 { margin-bottom: 0.2em; }
+h2 { border-bottom: 2px solid #ccd; padding-bottom: 0.2em; margin-top: 2em; }
+.subtitle { color: #666; margin-top: 0; }
+section { margin-bottom: 2em; }
+.scroll { overflow-x: auto; }
+table { border-collapse: collapse; width: 100%; margin: 1em 0; font-variant-numeric: tabular-nums; }
+th, td { padding: 0.3em 0.6em; text-align: left; }
+thead th { border-bottom: 2px solid #999; }
+tr.category th, tr.category td { background: #f0f2f7; border-top: 1px solid #ccd; }
+td.item { padding-left: 1.6em; }
+td.detail { color: #666; font-size: 0.9em; }
+.amount { text-align: right; white-space: nowrap; }
+.missing { color: #aaa; }
+tfoot th, tfoot td { border-top: 2px solid #999; font-weight: bold; }
+table.summary { width: auto; }
+table.summary th { font-weight: normal; }
+table.summary td { font-weight: bold; }
+svg { display: block; max-width: 100%; height: auto; margin: 1em 0; }
+svg text { font-family: inherit; font-size: 12px; fill: #444; }
+.bar-asset, .bar-income { fill: #3c9a5f; }
+.bar-liability, .bar-expense { fill: #c4553d; }
+.grid { stroke: #ddd; stroke-width: 1; }
+.trend { fill: none; stroke: #2f6db5; stroke-width: 2.5; }
+.point { fill: #2f6db5; }
+footer { color: #888; font-size: 0.85em; margin-top: 3em; }
+@media print {
+  body { margin: 0; max-width: none; }
+  section, svg, tr { break-inside: avoid; }
+  h2 { break-after: avoid; }
+}
+";
+
+/// Width and height of the SVG charts in pixels (they scale down to fit narrower pages)
+const CHART_WIDTH: f64 = 640.0;
+const TREND_HEIGHT: f64 = 260.0;
+
+/// Room on the left of a bar chart for the category names, and the height given to each bar
+const BAR_LABEL_WIDTH: f64 = 170.0;
+const BAR_ROW_HEIGHT: f64 = 26.0;
+
+/// The longest category name shown beside a bar before it is cut short
+const MAX_BAR_LABEL_CHARS: usize = 24;
+
+/// The blocks of the Markdown report's net worth sparkline, from lowest to highest
+const SPARK_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Add a category and its totals to a table, unless none of its items made it in
+fn push_category(table: &mut ReportTable, category: ReportCategory) {
+    if category.rows.is_empty() {
+        return;
+    }
+    for (total, category_total) in table.totals.iter_mut().zip(&category.totals) {
+        *total += *category_total;
+    }
+    table.categories.push(category);
+}
+
+/// One half of the current balance sheet, with each item's value in a single column
+fn balance_sheet_table(
+    converter: &Converter,
+    title: &str,
+    categories: &[Category],
+    items: &[Item],
+) -> Result<ReportTable> {
+    let mut table = ReportTable {
+        title: String::from(title),
+        categories: vec![],
+        totals: vec![Amount::ZERO],
+    };
+    for category in categories {
+        let mut section = ReportCategory {
+            category: category.category.clone(),
+            rows: vec![],
+            totals: vec![Amount::ZERO],
+        };
+        for item in items
+            .iter()
+            .filter(|item| item.category_lower == category.category_lower)
+        {
+            section.totals[0] += converter.convert(item.value, &item.currency)?;
+            section.rows.push(ReportRow {
+                item: item.item.clone(),
+                detail: String::new(),
+                cells: vec![Some((item.value, item.currency.clone()))],
+            });
+        }
+        push_category(&mut table, section);
+    }
+    Ok(table)
+}
+
+/// One half of the current budget in monthly equivalents
+/// Items that aren't monthly show the amount each time and how often it comes in or goes out as their detail
+fn budget_table(
+    converter: &Converter,
+    title: &str,
+    categories: &[BudgetCategory],
+    items: &[BudgetItem],
+) -> Result<ReportTable> {
+    let mut table = ReportTable {
+        title: String::from(title),
+        categories: vec![],
+        totals: vec![Amount::ZERO],
+    };
+    for category in categories {
+        let mut section = ReportCategory {
+            category: category.category.clone(),
+            rows: vec![],
+            totals: vec![Amount::ZERO],
+        };
+        for item in items
+            .iter()
+            .filter(|item| item.category_lower == category.category_lower)
+        {
+            section.totals[0] += converter.convert(item.monthly_value(), &item.currency)?;
+            let detail = match item.frequency {
+                Frequency::Monthly => String::new(),
+                frequency => format!(
+                    "{} {}",
+                    to_money_string(item.value, &item.currency),
+                    frequency.describe()
+                ),
+            };
+            section.rows.push(ReportRow {
+                item: item.item.clone(),
+                detail,
+                cells: vec![Some((item.monthly_value(), item.currency.clone()))],
+            });
+        }
+        push_category(&mut table, section);
+    }
+    Ok(table)
+}
+
+/// One half of the balance sheet at each of the snapshots, side by side
+/// An item is named and grouped by its latest version, so renamed items stay on one row
+fn snapshot_table(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    snapshots: &[Snapshot],
+    converters: &[Converter],
+) -> Result<ReportTable> {
+    let mut latest: HashMap<usize, Item> = HashMap::new();
+    for item in balance_sheet::get_all_items(conn, user, which_half)? {
+        match latest.get(&item.timeline_original) {
+            Some(newer) if newer.timeline_created > item.timeline_created => {}
+            _ => {
+                latest.insert(item.timeline_original, item);
+            }
+        }
+    }
+    let mut columns: Vec<Vec<Item>> = vec![];
+    for snapshot in snapshots {
+        columns.push(snapshot_items_at(conn, user, which_half, snapshot.timeline)?.1);
+    }
+
+    // The categories in their usual order, then any that only removed items were still in
+    let mut categories: Vec<(String, String)> =
+        balance_sheet::get_categories(conn, user, which_half)?
+            .into_iter()
+            .map(|category| (category.category_lower, category.category))
+            .collect();
+    let mut removed: Vec<&Item> = latest.values().collect();
+    removed.sort_by_key(|item| item.timeline_original);
+    for item in removed {
+        if !categories
+            .iter()
+            .any(|(lower, _)| *lower == item.category_lower)
+        {
+            categories.push((item.category_lower.clone(), item.category.clone()));
+        }
+    }
+
+    let mut table = ReportTable {
+        title: String::from(which_half.to_str()),
+        categories: vec![],
+        totals: vec![Amount::ZERO; snapshots.len()],
+    };
+    for (category_lower, category) in categories {
+        let mut originals: Vec<usize> = latest
+            .values()
+            .filter(|item| item.category_lower == category_lower)
+            .map(|item| item.timeline_original)
+            .filter(|original| {
+                columns
+                    .iter()
+                    .flatten()
+                    .any(|item| item.timeline_original == *original)
+            })
+            .collect();
+        originals.sort();
+        let mut section = ReportCategory {
+            category,
+            rows: vec![],
+            totals: vec![Amount::ZERO; snapshots.len()],
+        };
+        for original in originals {
+            let mut cells: Vec<Option<(Amount, String)>> = vec![];
+            for (col, items) in columns.iter().enumerate() {
+                let found = items.iter().find(|item| item.timeline_original == original);
+                if let Some(item) = found {
+                    section.totals[col] += converters[col].convert(item.value, &item.currency)?;
+                }
+                cells.push(found.map(|item| (item.value, item.currency.clone())));
+            }
+            section.rows.push(ReportRow {
+                item: latest[&original].item.clone(),
+                detail: String::new(),
+                cells,
+            });
+        }
+        push_category(&mut table, section);
+    }
+    Ok(table)
+}
+
+/// Gather the current balance sheet, the current budget, the chosen snapshots side by side and the net worth trend
+/// The snapshots are put in chronological order, and each converts its totals with the rates frozen in it
+pub fn build_report(conn: &Connection, user: &User, snapshots: &[Snapshot]) -> Result<Report> {
+    let converter = current_converter(conn, user)?;
+    let (asset_categories, asset_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Assets)?;
+    let (liability_categories, liability_items) =
+        get_relevant_items_cats(conn, user, &BalanceSheetHalf::Liabilities)?;
+    let (income_categories, income_items) = get_relevant_items(conn, user, &BudgetHalf::Income)?;
+    let (expense_categories, expense_items) =
+        get_relevant_items(conn, user, &BudgetHalf::Expenses)?;
+
+    let mut snapshots: Vec<Snapshot> = snapshots.to_vec();
+    snapshots.sort_by_key(|snapshot| snapshot.timeline);
+    snapshots.dedup_by_key(|snapshot| snapshot.timeline);
+    let mut converters: Vec<Converter> = vec![];
+    for snapshot in &snapshots {
+        converters.push(snapshot_converter(
+            conn,
+            user,
+            snapshot,
+            &user.base_currency,
+        )?);
+    }
+
+    Ok(Report {
+        fullname: user.fullname(),
+        date_text: Local::now().format("%Y-%m-%d").to_string(),
+        currency: String::from(converter.base()),
+        assets: balance_sheet_table(&converter, "Assets", &asset_categories, &asset_items)?,
+        liabilities: balance_sheet_table(
+            &converter,
+            "Liabilities",
+            &liability_categories,
+            &liability_items,
+        )?,
+        income: budget_table(&converter, "Income", &income_categories, &income_items)?,
+        expenses: budget_table(&converter, "Expenses", &expense_categories, &expense_items)?,
+        snapshot_dates: snapshots
+            .iter()
+            .map(|snapshot| snapshot.date_today.clone())
+            .collect(),
+        snapshot_assets: snapshot_table(
+            conn,
+            user,
+            &BalanceSheetHalf::Assets,
+            &snapshots,
+            &converters,
+        )?,
+        snapshot_liabilities: snapshot_table(
+            conn,
+            user,
+            &BalanceSheetHalf::Liabilities,
+            &snapshots,
+            &converters,
+        )?,
+        net_worth_trend: net_worth_history(conn, user)?,
+    })
+}
+
+/// Build a report and write it to a file as HTML or Markdown, replacing the file if it exists
+pub fn write_report(
+    conn: &Connection,
+    user: &User,
+    path: &str,
+    format: ReportFormat,
+    snapshots: &[Snapshot],
+) -> Result<Report> {
+    let report = build_report(conn, user, snapshots)?;
+    let mut out = BufWriter::new(File::create(path)?);
+    match format {
+        ReportFormat::Html => write_html(&mut out, &report)?,
+        ReportFormat::Markdown => write_markdown(&mut out, &report)?,
+    }
+    out.flush()?;
+    Ok(report)
+}
+
+/// The first column's total of a table, which is the whole total for the current balance sheet and budget
+fn first_total(table: &ReportTable) -> Amount {
+    table.totals.first().copied().unwrap_or_default()
+}
+
+/// The difference between two tables' totals, column by column (eg - the net worth at each snapshot)
+fn net_totals(plus: &ReportTable, minus: &ReportTable) -> Vec<Amount> {
+    plus.totals
+        .iter()
+        .zip(&minus.totals)
+        .map(|(plus, minus)| *plus - *minus)
+        .collect()
+}
+
+/// The headline numbers of a report as (label, amount) in the report's currency
+fn summary_lines(report: &Report) -> Vec<(String, Amount)> {
+    let net_worth = first_total(&report.assets) - first_total(&report.liabilities);
+    let mut lines = vec![
+        (String::from("Net worth"), net_worth),
+        (String::from("Total assets"), first_total(&report.assets)),
+        (
+            String::from("Total liabilities"),
+            first_total(&report.liabilities),
+        ),
+    ];
+    if let Some((date, first_net_worth)) = report.net_worth_trend.first() {
+        lines.push((
+            format!("Net worth change since {}", date),
+            net_worth - *first_net_worth,
+        ));
+    }
+    lines.extend([
+        (String::from("Monthly income"), first_total(&report.income)),
+        (
+            String::from("Monthly expenses"),
+            first_total(&report.expenses),
+        ),
+        (
+            String::from("Monthly net"),
+            first_total(&report.income) - first_total(&report.expenses),
+        ),
+    ]);
+    lines
+}
+
+/// Escape text for use in HTML, in element content or a quoted attribute
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// An amount in a table cell, or a dash where the item wasn't there
+fn html_cell(cell: &Option<(Amount, String)>) -> String {
+    match cell {
+        Some((value, currency)) => html_escape(&to_money_string(*value, currency)),
+        None => String::from("<span class=\"missing\">&ndash;</span>"),
+    }
+}
+
+/// Write the standalone HTML report with its styles and charts inline
+pub fn write_html(out: &mut impl Write, report: &Report) -> Result<()> {
+    let currency = &report.currency;
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(
+        out,
+        "<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">"
+    )?;
+    writeln!(
+        out,
+        "<title>Financial Report - {} - {}</title>",
+        html_escape(&report.fullname),
+        report.date_text
+    )?;
+    writeln!(out, "<style>{}</style>\n</head>\n<body>", REPORT_CSS)?;
+    writeln!(out, "<h1>Financial Report</h1>")?;
+    writeln!(
+        out,
+        "<p class=\"subtitle\">{} &middot; {} &middot; Totals in {}</p>",
+        html_escape(&report.fullname),
+        report.date_text,
+        html_escape(currency)
+    )?;
+
+    writeln!(out, "\n<section>\n<h2>Summary</h2>")?;
+    writeln!(out, "<table class=\"summary\">")?;
+    for (label, amount) in summary_lines(report) {
+        writeln!(
+            out,
+            "<tr><th>{}</th><td class=\"amount\">{}</td></tr>",
+            html_escape(&label),
+            html_escape(&to_money_string(amount, currency))
+        )?;
+    }
+    writeln!(out, "</table>\n</section>")?;
+
+    writeln!(out, "\n<section>\n<h2>Balance Sheet</h2>")?;
+    write_svg_bar_chart(
+        out,
+        &[
+            (&report.assets, "bar-asset"),
+            (&report.liabilities, "bar-liability"),
+        ],
+        currency,
+    )?;
+    let value_header = [String::from("Value")];
+    write_html_table(out, &report.assets, &value_header, currency)?;
+    write_html_table(out, &report.liabilities, &value_header, currency)?;
+    write_html_net(
+        out,
+        "Net worth",
+        &value_header,
+        &net_totals(&report.assets, &report.liabilities),
+        currency,
+    )?;
+    writeln!(out, "</section>")?;
+
+    writeln!(out, "\n<section>\n<h2>Monthly Budget</h2>")?;
+    write_svg_bar_chart(
+        out,
+        &[
+            (&report.income, "bar-income"),
+            (&report.expenses, "bar-expense"),
+        ],
+        currency,
+    )?;
+    let monthly_header = [String::from("Per month")];
+    write_html_table(out, &report.income, &monthly_header, currency)?;
+    write_html_table(out, &report.expenses, &monthly_header, currency)?;
+    write_html_net(
+        out,
+        "Monthly net",
+        &monthly_header,
+        &net_totals(&report.income, &report.expenses),
+        currency,
+    )?;
+    writeln!(out, "</section>")?;
+
+    if !report.snapshot_dates.is_empty() {
+        writeln!(out, "\n<section>\n<h2>Snapshots Side by Side</h2>")?;
+        writeln!(out, "<div class=\"scroll\">")?;
+        write_html_table(
+            out,
+            &report.snapshot_assets,
+            &report.snapshot_dates,
+            currency,
+        )?;
+        write_html_table(
+            out,
+            &report.snapshot_liabilities,
+            &report.snapshot_dates,
+            currency,
+        )?;
+        write_html_net(
+            out,
+            "Net worth",
+            &report.snapshot_dates,
+            &net_totals(&report.snapshot_assets, &report.snapshot_liabilities),
+            currency,
+        )?;
+        writeln!(out, "</div>\n</section>")?;
+    }
+
+    writeln!(out, "\n<section>\n<h2>Net Worth Trend</h2>")?;
+    if report.net_worth_trend.is_empty() {
+        writeln!(out, "<p>No snapshots have been taken yet.</p>")?;
+    } else {
+        write_svg_trend_chart(out, &report.net_worth_trend, currency)?;
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<thead><tr><th>Snapshot</th><th class=\"amount\">Net worth</th><th class=\"amount\">Change</th></tr></thead>\n<tbody>"
+        )?;
+        let mut previous: Option<Amount> = None;
+        for (date, net_worth) in &report.net_worth_trend {
+            let change = match previous {
+                Some(previous) => html_escape(&to_money_string(*net_worth - previous, currency)),
+                None => String::from("<span class=\"missing\">&ndash;</span>"),
+            };
+            writeln!(
+                out,
+                "<tr><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td></tr>",
+                date,
+                html_escape(&to_money_string(*net_worth, currency)),
+                change
+            )?;
+            previous = Some(*net_worth);
+        }
+        writeln!(out, "</tbody>\n</table>")?;
+    }
+    writeln!(out, "</section>")?;
+
+    writeln!(
+        out,
+        "\n<footer>Generated by Burke Budget on {}. Items are shown in their own currency and totals in {}.</footer>",
+        report.date_text,
+        html_escape(currency)
+    )?;
+    writeln!(out, "</body>\n</html>")?;
+    Ok(())
+}
+
+/// A table of one half of the balance sheet or budget, with a subtotal on each category's row and the totals at the bottom
+fn write_html_table(
+    out: &mut impl Write,
+    table: &ReportTable,
+    headers: &[String],
+    currency: &str,
+) -> Result<()> {
+    if table.categories.is_empty() {
+        writeln!(
+            out,
+            "<p>There are no {}.</p>",
+            html_escape(&table.title.to_lowercase())
+        )?;
+        return Ok(());
+    }
+    let show_detail = table
+        .categories
+        .iter()
+        .flat_map(|category| &category.rows)
+        .any(|row| !row.detail.is_empty());
+    let detail_cell = if show_detail { "<td></td>" } else { "" };
+
+    writeln!(out, "<table>\n<thead><tr>")?;
+    write!(out, "<th>{}</th>", html_escape(&table.title))?;
+    if show_detail {
+        write!(out, "<th></th>")?;
+    }
+    for header in headers {
+        write!(out, "<th class=\"amount\">{}</th>", html_escape(header))?;
+    }
+    writeln!(out, "</tr></thead>\n<tbody>")?;
+    for category in &table.categories {
+        write!(
+            out,
+            "<tr class=\"category\"><th>{}</th>{}",
+            html_escape(&category.category),
+            detail_cell
+        )?;
+        for total in &category.totals {
+            write!(
+                out,
+                "<td class=\"amount\">{}</td>",
+                html_escape(&to_money_string(*total, currency))
+            )?;
+        }
+        writeln!(out, "</tr>")?;
+        for row in &category.rows {
+            write!(
+                out,
+                "<tr><td class=\"item\">{}</td>",
+                html_escape(&row.item)
+            )?;
+            if show_detail {
+                write!(
+                    out,
+                    "<td class=\"detail\">{}</td>",
+                    html_escape(&row.detail)
+                )?;
+            }
+            for cell in &row.cells {
+                write!(out, "<td class=\"amount\">{}</td>", html_cell(cell))?;
+            }
+            writeln!(out, "</tr>")?;
+        }
+    }
+    write!(
+        out,
+        "</tbody>\n<tfoot><tr><th>Total {}</th>{}",
+        html_escape(&table.title),
+        detail_cell
+    )?;
+    for total in &table.totals {
+        write!(
+            out,
+            "<td class=\"amount\">{}</td>",
+            html_escape(&to_money_string(*total, currency))
+        )?;
+    }
+    writeln!(out, "</tr></tfoot>\n</table>")?;
+    Ok(())
+}
+
+/// A one row table of the net worth or monthly net under a pair of tables
+fn write_html_net(
+    out: &mut impl Write,
+    label: &str,
+    headers: &[String],
+    totals: &[Amount],
+    currency: &str,
+) -> Result<()> {
+    write!(out, "<table>\n<thead><tr><th></th>")?;
+    for header in headers {
+        write!(out, "<th class=\"amount\">{}</th>", html_escape(header))?;
+    }
+    write!(
+        out,
+        "</tr></thead>\n<tfoot><tr><th>{}</th>",
+        html_escape(label)
+    )?;
+    for total in totals {
+        write!(
+            out,
+            "<td class=\"amount\">{}</td>",
+            html_escape(&to_money_string(*total, currency))
+        )?;
+    }
+    writeln!(out, "</tr></tfoot>\n</table>")?;
+    Ok(())
+}
+
+/// Cut a label short with an ellipsis so it fits beside a bar
+fn bar_label(label: &str) -> String {
+    match label.chars().count() > MAX_BAR_LABEL_CHARS {
+        true => {
+            let short: String = label.chars().take(MAX_BAR_LABEL_CHARS - 1).collect();
+            format!("{}…", short)
+        }
+        false => String::from(label),
+    }
+}
+
+/// A horizontal bar chart of the category totals of one or more tables, scaled to the largest total
+/// Each table's bars have its CSS class, with a key to the tables along the top
+fn write_svg_bar_chart(
+    out: &mut impl Write,
+    tables: &[(&ReportTable, &str)],
+    currency: &str,
+) -> Result<()> {
+    let mut bars: Vec<(&str, Amount, &str)> = vec![];
+    for (table, class) in tables {
+        for category in &table.categories {
+            bars.push((&category.category, category.totals[0], class));
+        }
+    }
+    if bars.is_empty() {
+        return Ok(());
+    }
+    // Leave room to the right of the longest bar for its amount
+    let bar_space = CHART_WIDTH - BAR_LABEL_WIDTH - 110.0;
+    let largest = bars
+        .iter()
+        .map(|(_, amount, _)| amount.to_f64().abs())
+        .fold(0.0, f64::max)
+        .max(0.01);
+    let key_height = 26.0;
+    let height = key_height + bars.len() as f64 * BAR_ROW_HEIGHT + 8.0;
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" role=\"img\">",
+        CHART_WIDTH, height, CHART_WIDTH, height
+    )?;
+    let mut key_x = BAR_LABEL_WIDTH;
+    for (table, class) in tables {
+        writeln!(
+            out,
+            "<rect class=\"{}\" x=\"{:.1}\" y=\"4\" width=\"12\" height=\"12\" rx=\"2\"/><text x=\"{:.1}\" y=\"15\">{}</text>",
+            class,
+            key_x,
+            key_x + 18.0,
+            html_escape(&table.title)
+        )?;
+        key_x += 110.0;
+    }
+    for (idx, (label, amount, class)) in bars.iter().enumerate() {
+        let y = key_height + 4.0 + idx as f64 * BAR_ROW_HEIGHT;
+        let width = (amount.to_f64().abs() / largest * bar_space).max(1.0);
+        let money = html_escape(&to_money_string(*amount, currency));
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            BAR_LABEL_WIDTH - 8.0,
+            y + 14.0,
+            html_escape(&bar_label(label))
+        )?;
+        writeln!(
+            out,
+            "<rect class=\"{}\" x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"18\" rx=\"2\"><title>{}: {}</title></rect>",
+            class,
+            BAR_LABEL_WIDTH,
+            y,
+            width,
+            html_escape(label),
+            money
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            BAR_LABEL_WIDTH + width + 6.0,
+            y + 14.0,
+            money
+        )?;
+    }
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+/// A line chart of the net worth at each snapshot, with the dates to scale along the bottom
+fn write_svg_trend_chart(
+    out: &mut impl Write,
+    trend: &[(NaiveDate, Amount)],
+    currency: &str,
+) -> Result<()> {
+    let (left, right, top, bottom) = (100.0, 20.0, 16.0, 36.0);
+    let plot_width = CHART_WIDTH - left - right;
+    let plot_height = TREND_HEIGHT - top - bottom;
+
+    let first_date = trend[0].0;
+    let num_days = (trend[trend.len() - 1].0 - first_date).num_days() as f64;
+    let mut min_val = trend
+        .iter()
+        .map(|(_, amount)| amount.to_f64())
+        .fold(f64::MAX, f64::min);
+    let mut max_val = trend
+        .iter()
+        .map(|(_, amount)| amount.to_f64())
+        .fold(f64::MIN, f64::max);
+    // Give a flat line some room above and below it
+    if max_val - min_val < 0.01 {
+        let pad = (max_val.abs() * 0.1).max(1.0);
+        min_val -= pad;
+        max_val += pad;
+    }
+    let x_at = |date: NaiveDate| match num_days > 0.0 {
+        true => left + (date - first_date).num_days() as f64 / num_days * plot_width,
+        false => left + plot_width / 2.0,
+    };
+    let y_at = |value: f64| top + (max_val - value) / (max_val - min_val) * plot_height;
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" width=\"{}\" height=\"{}\" role=\"img\">",
+        CHART_WIDTH, TREND_HEIGHT, CHART_WIDTH, TREND_HEIGHT
+    )?;
+    // Horizontal grid lines with their amounts on the left
+    for step in 0..=4 {
+        let value = min_val + (max_val - min_val) * step as f64 / 4.0;
+        let y = y_at(value);
+        writeln!(
+            out,
+            "<line class=\"grid\" x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\"/>",
+            left,
+            y,
+            CHART_WIDTH - right,
+            y
+        )?;
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            left - 8.0,
+            y + 4.0,
+            html_escape(&to_money_string(Amount::from_f64(value), currency))
+        )?;
+    }
+    // The first and last dates along the bottom
+    let mut labels = vec![(trend[0].0, "start")];
+    if num_days > 0.0 {
+        labels.push((trend[trend.len() - 1].0, "end"));
+    }
+    for (date, anchor) in labels {
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"{}\">{}</text>",
+            x_at(date),
+            TREND_HEIGHT - 12.0,
+            if num_days > 0.0 { anchor } else { "middle" },
+            date
+        )?;
+    }
+    let points: Vec<String> = trend
+        .iter()
+        .map(|(date, amount)| format!("{:.1},{:.1}", x_at(*date), y_at(amount.to_f64())))
+        .collect();
+    writeln!(
+        out,
+        "<polyline class=\"trend\" points=\"{}\"/>",
+        points.join(" ")
+    )?;
+    for (date, amount) in trend {
+        writeln!(
+            out,
+            "<circle class=\"point\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"3.5\"><title>{}: {}</title></circle>",
+            x_at(*date),
+            y_at(amount.to_f64()),
+            date,
+            html_escape(&to_money_string(*amount, currency))
+        )?;
+    }
+    writeln!(out, "</svg>")?;
+    Ok(())
+}
+
+/// Escape the characters that Markdown would otherwise read as formatting or table borders
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        if "\\`*_[]<>|#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// An amount in a Markdown table cell, or a dash where the item wasn't there
+fn markdown_cell(cell: &Option<(Amount, String)>) -> String {
+    match cell {
+        Some((value, currency)) => to_money_string(*value, currency),
+        None => String::from("–"),
+    }
+}
+
+/// A row of a Markdown table
+fn markdown_row(out: &mut impl Write, cells: &[String]) -> Result<()> {
+    writeln!(out, "| {} |", cells.join(" | "))?;
+    Ok(())
+}
+
+/// A line of block characters that rises and falls with the values
+fn sparkline(values: &[Amount]) -> String {
+    let min_val = values.iter().min().copied().unwrap_or_default();
+    let max_val = values.iter().max().copied().unwrap_or_default();
+    let range = (max_val - min_val).to_f64();
+    values
+        .iter()
+        .map(|value| match range > 0.0 {
+            true => {
+                let level = (*value - min_val).to_f64() / range * (SPARK_BLOCKS.len() - 1) as f64;
+                SPARK_BLOCKS[level.round() as usize]
+            }
+            false => SPARK_BLOCKS[SPARK_BLOCKS.len() / 2],
+        })
+        .collect()
+}
+
+/// Write the Markdown report, with tables in place of the charts
+pub fn write_markdown(out: &mut impl Write, report: &Report) -> Result<()> {
+    let currency = &report.currency;
+    writeln!(out, "# Financial Report\n")?;
+    writeln!(
+        out,
+        "{} · {} · Totals in {}",
+        markdown_escape(&report.fullname),
+        report.date_text,
+        currency
+    )?;
+
+    writeln!(out, "\n## Summary\n")?;
+    markdown_row(out, &[String::new(), String::from("Amount")])?;
+    markdown_row(out, &[String::from("---"), String::from("---:")])?;
+    for (label, amount) in summary_lines(report) {
+        markdown_row(out, &[label, to_money_string(amount, currency)])?;
+    }
+
+    writeln!(out, "\n## Balance Sheet")?;
+    let value_header = [String::from("Value")];
+    write_markdown_table(out, &report.assets, &value_header, currency)?;
+    write_markdown_table(out, &report.liabilities, &value_header, currency)?;
+    writeln!(
+        out,
+        "\n**Net worth: {}**",
+        to_money_string(
+            first_total(&report.assets) - first_total(&report.liabilities),
+            currency
+        )
+    )?;
+
+    writeln!(out, "\n## Monthly Budget")?;
+    let monthly_header = [String::from("Per month")];
+    write_markdown_table(out, &report.income, &monthly_header, currency)?;
+    write_markdown_table(out, &report.expenses, &monthly_header, currency)?;
+    writeln!(
+        out,
+        "\n**Monthly net: {}**",
+        to_money_string(
+            first_total(&report.income) - first_total(&report.expenses),
+            currency
+        )
+    )?;
+
+    if !report.snapshot_dates.is_empty() {
+        writeln!(out, "\n## Snapshots Side by Side")?;
+        write_markdown_table(
+            out,
+            &report.snapshot_assets,
+            &report.snapshot_dates,
+            currency,
+        )?;
+        write_markdown_table(
+            out,
+            &report.snapshot_liabilities,
+            &report.snapshot_dates,
+            currency,
+        )?;
+        writeln!(out)?;
+        let mut header = vec![String::new()];
+        header.extend(report.snapshot_dates.iter().cloned());
+        markdown_row(out, &header)?;
+        let mut divider = vec![String::from("---")];
+        divider.extend(report.snapshot_dates.iter().map(|_| String::from("---:")));
+        markdown_row(out, &divider)?;
+        let mut net = vec![String::from("**Net worth**")];
+        net.extend(
+            net_totals(&report.snapshot_assets, &report.snapshot_liabilities)
+                .iter()
+                .map(|total| format!("**{}**", to_money_string(*total, currency))),
+        );
+        markdown_row(out, &net)?;
+    }
+
+    writeln!(out, "\n## Net Worth Trend\n")?;
+    if report.net_worth_trend.is_empty() {
+        writeln!(out, "No snapshots have been taken yet.")?;
+    } else {
+        let values: Vec<Amount> = report
+            .net_worth_trend
+            .iter()
+            .map(|(_, amount)| *amount)
+            .collect();
+        writeln!(out, "`{}`\n", sparkline(&values))?;
+        markdown_row(
+            out,
+            &[
+                String::from("Snapshot"),
+                String::from("Net worth"),
+                String::from("Change"),
+            ],
+        )?;
+        markdown_row(
+            out,
+            &[
+                String::from("---"),
+                String::from("---:"),
+                String::from("---:"),
+            ],
+        )?;
+        let mut previous: Option<Amount> = None;
+        for (date, net_worth) in &report.net_worth_trend {
+            let change = match previous {
+                Some(previous) => to_money_string(*net_worth - previous, currency),
+                None => String::from("–"),
+            };
+            markdown_row(
+                out,
+                &[
+                    date.to_string(),
+                    to_money_string(*net_worth, currency),
+                    change,
+                ],
+            )?;
+            previous = Some(*net_worth);
+        }
+    }
+
+    writeln!(
+        out,
+        "\n_Generated by Burke Budget on {}. Items are shown in their own currency and totals in {}._",
+        report.date_text, currency
+    )?;
+    Ok(())
+}
+
+/// A Markdown table of one half of the balance sheet or budget, with each category's subtotal in bold above its items
+fn write_markdown_table(
+    out: &mut impl Write,
+    table: &ReportTable,
+    headers: &[String],
+    currency: &str,
+) -> Result<()> {
+    writeln!(out, "\n### {}\n", table.title)?;
+    if table.categories.is_empty() {
+        writeln!(out, "There are no {}.", table.title.to_lowercase())?;
+        return Ok(());
+    }
+    let show_detail = table
+        .categories
+        .iter()
+        .flat_map(|category| &category.rows)
+        .any(|row| !row.detail.is_empty());
+
+    let mut header = vec![String::from("Item")];
+    let mut divider = vec![String::from("---")];
+    if show_detail {
+        header.push(String::new());
+        divider.push(String::from("---"));
+    }
+    header.extend(headers.iter().cloned());
+    divider.extend(headers.iter().map(|_| String::from("---:")));
+    markdown_row(out, &header)?;
+    markdown_row(out, &divider)?;
+
+    for category in &table.categories {
+        let mut cells = vec![format!("**{}**", markdown_escape(&category.category))];
+        if show_detail {
+            cells.push(String::new());
+        }
+        cells.extend(
+            category
+                .totals
+                .iter()
+                .map(|total| format!("**{}**", to_money_string(*total, currency))),
+        );
+        markdown_row(out, &cells)?;
+        for row in &category.rows {
+            let mut cells = vec![format!("&nbsp;&nbsp;{}", markdown_escape(&row.item))];
+            if show_detail {
+                cells.push(markdown_escape(&row.detail));
+            }
+            cells.extend(row.cells.iter().map(markdown_cell));
+            markdown_row(out, &cells)?;
+        }
+    }
+    let mut cells = vec![format!("**Total {}**", table.title)];
+    if show_detail {
+        cells.push(String::new());
+    }
+    cells.extend(
+        table
+            .totals
+            .iter()
+            .map(|total| format!("**{}**", to_money_string(*total, currency))),
+    );
+    markdown_row(out, &cells)?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use crate::balance_sheet::{
+    current_net_worth, get_and_update_timeline, get_categories, item_from_row,
+};
+use crate::currency::{freeze_rates, snapshot_converter};
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::Connection;
+
+/// Store a snapshot of the current balance sheet in the database and return it
+/// Note that it mainly stores a timestamp and sparse details
+/// The Balance Sheet can be reconstructed by accessing the database with this information
+/// The net worth is in the user's base currency and the current exchange rates are frozen with it
+pub fn create_snapshot(conn: &Connection, user: &User, comment: &str) -> Result<Snapshot> {
+    let date_today = Local::now().format("%Y-%m-%d").to_string(); // YYYY-MM-DD
+    create_snapshot_dated(conn, user, comment, &date_today)
+}
+
+/// Store a snapshot of the current balance sheet with a YYYY-MM-DD date other than today
+/// Used when past balances are imported, after the balance sheet has been brought to what it was on that date
+pub fn create_snapshot_dated(
+    conn: &Connection,
+    user: &User,
+    comment: &str,
+    date_text: &str,
+) -> Result<Snapshot> {
+    in_transaction(conn, |conn| {
+        let net_worth = current_net_worth(conn, user)?;
+
+        // The timestamp is incremented with a new timestamp to allow for multiple snapshots for the same balance sheet state
+        let timestamp = get_and_update_timeline(conn, user)?;
+
+        // Insert the snapshot into the table
+        conn.execute(
+            "INSERT INTO balance_snapshots
+            (timestamp, username_lower, date_text, net_worth, comment, is_deleted, currency)
+            VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6)",
+            (
+                timestamp,
+                &user.username_lower,
+                date_text,
+                net_worth,
+                comment,
+                &user.base_currency,
+            ),
+        )?;
+        freeze_rates(conn, user, timestamp)?;
+
+        Ok(Snapshot {
+            timeline: timestamp,
+            username_lower: String::from(&user.username_lower),
+            date_today: String::from(date_text),
+            net_worth,
+            comment: String::from(comment),
+            is_deleted: false,
+            currency: String::from(&user.base_currency),
+        })
+    })
+}
+
+/// Get all of the saved (non-deleted) snapshots for a user in chronological order
+pub fn list_snapshots(conn: &Connection, user: &User) -> Result<Vec<Snapshot>> {
+    let mut snapshots: Vec<Snapshot> = vec![];
+    let mut stmt = conn
+        .prepare("SELECT * FROM balance_snapshots WHERE username_lower = ?1 AND is_deleted = 0")?;
+    let mut rows = stmt.query(rusqlite::params![user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        snapshots.push(Snapshot {
+            timeline: row.get(0)?,
+            username_lower: row.get(1)?,
+            date_today: row.get(2)?,
+            net_worth: row.get(3)?,
+            comment: row.get(4)?,
+            is_deleted: row.get(5)?,
+            currency: row.get(6)?,
+        })
+    }
+
+    // Sort the snapshots in chronological order
+    snapshots.sort_by_key(|a| a.timeline);
+    Ok(snapshots)
+}
+
+/// Parse the YYYY-MM-DD date stored with a snapshot
+pub fn snapshot_date(snapshot: &Snapshot) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(&snapshot.date_today, "%Y-%m-%d").map_err(|error| {
+        BudgetError::Parse(format!(
+            "The snapshot date {} could not be read: {}",
+            snapshot.date_today, error
+        ))
+    })
+}
+
+/// Get the items and categories relevant to a snapshot timeline
+/// This will return either the assets or liabilities (call once for each)
+pub fn snapshot_items_at(
+    conn: &Connection,
+    user: &User,
+    which_half: &BalanceSheetHalf,
+    timeline: usize,
+) -> Result<(Vec<Category>, Vec<Item>)> {
+    // Push all of the categories to a vector first
+    let categories = get_categories(conn, user, which_half)?;
+    // Next push all of the items that fit the snapshot into a vector
+    let mut items: Vec<Item> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM balance_items
+        WHERE is_asset=?1 AND username_lower=?2 AND timeline_created<=?3 AND timeline_deleted>?3",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![
+        which_half.to_bool_int(),
+        user.username_lower,
+        timeline
+    ])?;
+    while let Some(row) = rows.next()? {
+        items.push(item_from_row(row)?)
+    }
+    Ok((categories, items))
+}
+
+/// The value of a trend line at each snapshot, in the user's base currency with the rates frozen in the snapshot
+/// An item's line skips the snapshots it wasn't on the balance sheet for
+pub fn snapshot_trend(
+    conn: &Connection,
+    user: &User,
+    line: &TrendLine,
+) -> Result<Vec<(NaiveDate, Amount)>> {
+    let which_half = match line {
+        TrendLine::Item { is_asset, .. }
+        | TrendLine::Category { is_asset, .. }
+        | TrendLine::Total { is_asset } => match is_asset {
+            true => BalanceSheetHalf::Assets,
+            false => BalanceSheetHalf::Liabilities,
+        },
+    };
+    let mut trend: Vec<(NaiveDate, Amount)> = vec![];
+    for snapshot in list_snapshots(conn, user)? {
+        let (_, items) = snapshot_items_at(conn, user, &which_half, snapshot.timeline)?;
+        let included: Vec<&Item> = items
+            .iter()
+            .filter(|item| match line {
+                TrendLine::Item { item_original, .. } => item.timeline_original == *item_original,
+                TrendLine::Category { category_lower, .. } => {
+                    item.category_lower == *category_lower
+                }
+                TrendLine::Total { .. } => true,
+            })
+            .collect();
+        if included.is_empty() && matches!(line, TrendLine::Item { .. }) {
+            continue;
+        }
+        let converter = snapshot_converter(conn, user, &snapshot, &user.base_currency)?;
+        let mut total = Amount::ZERO;
+        for item in included {
+            total += converter.convert(item.value, &item.currency)?;
+        }
+        trend.push((snapshot_date(&snapshot)?, total));
+    }
+    Ok(trend)
+}
+
+/// Delete a saved snapshot
+/// All of the items and categories remain unchanged
+pub fn delete_snapshot(conn: &Connection, user: &User, snapshot: &Snapshot) -> Result<()> {
+    in_transaction(conn, |conn| {
+        // Can have multiple deleted snapshots at the same timeline value
+        // Must find the last deleted one and increment the deletion integer for this one
+        let mut stmt = conn.prepare(
+            "SELECT is_deleted FROM balance_snapshots
+            WHERE username_lower = ?1 AND timestamp = ?2 AND is_deleted > 0",
+        )?;
+        let mut rows = stmt.query(rusqlite::params![user.username_lower, snapshot.timeline])?;
+        let mut prev_deleted: Vec<usize> = vec![];
+        while let Some(row) = rows.next()? {
+            prev_deleted.push(row.get(0)?)
+        }
+
+        prev_deleted.sort(); // Low to high
+        let mut deletion_number: usize = 1;
+        if let Some(last_deleted_timeline) = prev_deleted.last() {
+            if last_deleted_timeline >= &deletion_number {
+                deletion_number = *last_deleted_timeline + 1;
+            }
+        }
+        // Set the is_deleted value to be the number calculated above
+        let num_updated = conn.execute(
+            "UPDATE balance_snapshots
+            SET is_deleted = ?1
+            WHERE timestamp = ?2 AND username_lower = ?3 AND is_deleted = 0",
+            (deletion_number, snapshot.timeline, &user.username_lower),
+        )?;
+        if num_updated == 0 {
+            return Err(BudgetError::NotFound(format!(
+                "The snapshot from {} was not found",
+                snapshot.date_today
+            )));
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,934 @@
+use crate::balance_sheet::{self, get_relevant_items_cats};
+use crate::budget::initialize_budget;
+use crate::currency::normalize_currency;
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use crate::transactions::insert_transaction;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+
+/// Where a transaction goes when no payee rule matches it
+const UNCATEGORIZED: &str = "Uncategorized";
+
+/// One account's part of a statement file, before it is matched against the budget
+/// currency is the statement's own currency (CURDEF), and balance is the ledger balance with its date
+struct ParsedStatement {
+    account_id: Option<String>,
+    currency: Option<String>,
+    entries: Vec<StatementEntry>,
+    balance: Option<(Amount, Option<String>)>,
+}
+impl ParsedStatement {
+    fn new() -> ParsedStatement {
+        ParsedStatement {
+            account_id: None,
+            currency: None,
+            entries: vec![],
+            balance: None,
+        }
+    }
+
+    /// Fill in each entry's currency and make its fitid unique to the account
+    /// Entries of 0 are left out since a transaction has to be more than 0
+    fn finish(mut self, default_currency: &str) -> Result<ParsedStatement> {
+        self.currency = self
+            .currency
+            .as_deref()
+            .map(normalize_currency)
+            .transpose()?;
+        let currency = self.currency.as_deref().unwrap_or(default_currency);
+        self.entries.retain(|entry| entry.amount != Amount::ZERO);
+        for entry in &mut self.entries {
+            entry.currency = String::from(currency);
+            if let (Some(account_id), Some(fitid)) = (&self.account_id, &entry.fitid) {
+                entry.fitid = Some(format!("{}:{}", account_id, fitid));
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// The fields of a statement transaction as they are read, before they are checked
+#[derive(Default)]
+struct RawTransaction {
+    date: Option<String>,
+    amount: Option<String>,
+    fitid: Option<String>,
+    name: String,
+    memo: String,
+}
+
+/// Turn the escaped characters OFX allows back into text
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Parse an OFX date (YYYYMMDD, usually followed by a time and time zone) into YYYY-MM-DD
+fn parse_ofx_date(text: &str) -> Result<String> {
+    text.get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| BudgetError::Parse(format!("{} is not a valid OFX date", text)))
+}
+
+/// Read every statement in an OFX or QFX file, in either the older SGML form or XML
+/// SGML leaves out the closing tag of a value, so a value is the text up to the next tag either way
+fn parse_ofx(text: &str, default_currency: &str) -> Result<Vec<ParsedStatement>> {
+    let mut statements: Vec<ParsedStatement> = vec![];
+    let mut statement = ParsedStatement::new();
+    let mut transaction: Option<RawTransaction> = None;
+    let mut num_transactions: usize = 0;
+    let mut in_ledger_balance = false;
+
+    // Everything before the first tag is the header
+    for piece in text.split('<').skip(1) {
+        let Some((tag, value)) = piece.split_once('>') else {
+            continue;
+        };
+        let tag = tag.trim().to_uppercase();
+        let value = decode_entities(value.trim());
+
+        if let Some(closing) = tag.strip_prefix('/') {
+            match closing {
+                "STMTTRN" => {
+                    if let Some(fields) = transaction.take() {
+                        num_transactions += 1;
+                        statement.entries.push(ofx_entry(fields, num_transactions)?);
+                    }
+                }
+                "LEDGERBAL" => in_ledger_balance = false,
+                "STMTRS" | "CCSTMTRS" => statements.push(
+                    std::mem::replace(&mut statement, ParsedStatement::new())
+                        .finish(default_currency)?,
+                ),
+                _ => {}
+            }
+            continue;
+        }
+        match (tag.as_str(), transaction.as_mut()) {
+            ("STMTTRN", _) => transaction = Some(RawTransaction::default()),
+            ("LEDGERBAL", _) => in_ledger_balance = true,
+            ("DTPOSTED", Some(fields)) => fields.date = Some(value),
+            ("TRNAMT", Some(fields)) => fields.amount = Some(value),
+            ("FITID", Some(fields)) => fields.fitid = Some(value),
+            ("NAME", Some(fields)) => fields.name = value,
+            ("PAYEE", Some(fields)) if fields.name.is_empty() => fields.name = value,
+            ("MEMO", Some(fields)) => fields.memo = value,
+            // An ACCTID inside a transaction is the other side of a transfer
+            ("ACCTID", None) if !value.is_empty() => statement.account_id = Some(value),
+            ("CURDEF", None) if !value.is_empty() => statement.currency = Some(value),
+            ("BALAMT", None) if in_ledger_balance => {
+                let date_text = statement.balance.take().and_then(|(_, date)| date);
+                statement.balance = Some((Amount::parse_written(&value)?, date_text));
+            }
+            ("DTASOF", None) if in_ledger_balance => {
+                let date_text = Some(parse_ofx_date(&value)?);
+                statement.balance = Some(match statement.balance.take() {
+                    Some((amount, _)) => (amount, date_text),
+                    None => (Amount::ZERO, date_text),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    // A file cut short, or one without the statement wrappers, still has its transactions read
+    if !statement.entries.is_empty() || statement.balance.is_some() {
+        statements.push(statement.finish(default_currency)?);
+    }
+    if statements.is_empty() {
+        return Err(BudgetError::Parse(String::from(
+            "No statements were found in the OFX file",
+        )));
+    }
+    Ok(statements)
+}
+
+/// Check the fields of the nth transaction in an OFX file and make an entry of them
+fn ofx_entry(fields: RawTransaction, nth: usize) -> Result<StatementEntry> {
+    let missing = |field: &str| {
+        BudgetError::Parse(format!(
+            "Transaction {} in the statement has no {}",
+            nth, field
+        ))
+    };
+    let date_text = parse_ofx_date(&fields.date.ok_or_else(|| missing("date (DTPOSTED)"))?)?;
+    let amount = Amount::parse_written(&fields.amount.ok_or_else(|| missing("amount (TRNAMT)"))?)?;
+    Ok(StatementEntry {
+        date_text,
+        payee: fields.name,
+        memo: fields.memo,
+        amount,
+        currency: String::new(),
+        fitid: fields.fitid.filter(|fitid| !fitid.is_empty()),
+    })
+}
+
+/// Parse a QIF date into YYYY-MM-DD
+/// QIF dates are month first, as MM/DD/YYYY, MM/DD/YY or M/D'YY (the apostrophe marks a year after 2000), or YYYY-MM-DD
+fn parse_qif_date(text: &str) -> Result<String> {
+    let invalid = || BudgetError::Parse(format!("{} is not a valid QIF date", text));
+    let cleaned: String = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| if c == '\'' { '/' } else { c })
+        .collect();
+    if let Ok(date) = NaiveDate::parse_from_str(&cleaned, "%Y-%m-%d") {
+        return Ok(date.format("%Y-%m-%d").to_string());
+    }
+    let parts: Vec<&str> = cleaned.split(['/', '-', '.']).collect();
+    let [month, day, year] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+    let year: i32 = match year.parse().map_err(|_| invalid())? {
+        year @ 0..=69 => 2000 + year,
+        year @ 70..=99 => 1900 + year,
+        year => year,
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(invalid)
+}
+
+/// Read the transactions in a QIF file
+/// Only bank, cash, credit card and other asset or liability accounts are read. QIF has no account ids, bank ids or balances.
+fn parse_qif(text: &str, default_currency: &str) -> Result<Vec<ParsedStatement>> {
+    let mut statement = ParsedStatement::new();
+    let mut is_skipped = false;
+    let mut fields = RawTransaction::default();
+    let mut record_start: usize = 0;
+
+    let mut end_record = |fields: &mut RawTransaction, line_number: usize| -> Result<()> {
+        let fields = std::mem::take(fields);
+        let (date_text, amount) = match (fields.date, fields.amount) {
+            (Some(date_text), Some(amount)) => (date_text, amount),
+            (None, None) => return Ok(()),
+            _ => {
+                return Err(BudgetError::Parse(format!(
+                    "Line {}: the transaction needs both a date (D) and an amount (T)",
+                    line_number
+                )))
+            }
+        };
+        statement.entries.push(StatementEntry {
+            date_text: parse_qif_date(&date_text)?,
+            payee: fields.name,
+            memo: fields.memo,
+            amount: Amount::parse_written(&amount)?,
+            currency: String::new(),
+            fitid: None,
+        });
+        Ok(())
+    };
+
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = line[code.len_utf8()..].trim();
+        if code == '!' {
+            let header = value.to_lowercase();
+            if let Some(account_type) = header.strip_prefix("type:") {
+                is_skipped = !["bank", "cash", "ccard", "oth a", "oth l"].contains(&account_type);
+            } else if header.starts_with("account") {
+                is_skipped = true;
+            }
+            continue;
+        }
+        if is_skipped {
+            continue;
+        }
+        if fields.date.is_none() && fields.amount.is_none() {
+            record_start = idx + 1;
+        }
+        match code {
+            'D' => fields.date = Some(String::from(value)),
+            'T' | 'U' => fields.amount = Some(String::from(value)),
+            'P' => fields.name = String::from(value),
+            'M' => fields.memo = String::from(value),
+            '^' => end_record(&mut fields, record_start)?,
+            _ => {}
+        }
+    }
+    end_record(&mut fields, record_start)?;
+    Ok(vec![statement.finish(default_currency)?])
+}
+
+/// Work out whether a file is OFX or QIF from its extension, or failing that its contents
+fn detect_format(path: &str, text: &str) -> Result<StatementFormat> {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("ofx") | Some("qfx") => return Ok(StatementFormat::Ofx),
+        Some("qif") => return Ok(StatementFormat::Qif),
+        _ => {}
+    }
+    if text.to_uppercase().contains("<OFX") {
+        Ok(StatementFormat::Ofx)
+    } else if text.trim_start().starts_with('!') {
+        Ok(StatementFormat::Qif)
+    } else {
+        Err(BudgetError::Parse(format!(
+            "{} is not an OFX, QFX or QIF file",
+            path
+        )))
+    }
+}
+
+/// Send imported transactions whose payee contains the pattern to a budget category or item
+/// A rule for the same pattern going the same way (income or expense) is replaced
+pub fn set_payee_rule(
+    conn: &Connection,
+    user: &User,
+    pattern: &str,
+    target: &TransactionTarget,
+) -> Result<PayeeRule> {
+    let pattern = pattern.trim();
+    if pattern.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The payee to match can't be blank",
+        )));
+    }
+    let rule = match target {
+        TransactionTarget::Category(category) => PayeeRule {
+            pattern: String::from(pattern),
+            is_income: category.is_income,
+            category: category.category.clone(),
+            category_lower: category.category_lower.clone(),
+            item: None,
+            item_original: None,
+        },
+        TransactionTarget::Item(item) => PayeeRule {
+            pattern: String::from(pattern),
+            is_income: item.is_income,
+            category: item.category.clone(),
+            category_lower: item.category_lower.clone(),
+            item: Some(item.item.clone()),
+            item_original: Some(item.timeline_original),
+        },
+    };
+    conn.execute(
+        "INSERT OR REPLACE INTO payee_rules
+        (username_lower, pattern, pattern_lower, is_income, category_lower, item_original)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            &user.username_lower,
+            &rule.pattern,
+            &rule.pattern.to_lowercase(),
+            rule.is_income,
+            &rule.category_lower,
+            rule.item_original,
+        ),
+    )?;
+    Ok(rule)
+}
+
+/// Get a user's payee rules, income first and then by pattern
+/// A rule for an item that has moved category goes to the item's current category
+pub fn list_payee_rules(conn: &Connection, user: &User) -> Result<Vec<PayeeRule>> {
+    let mut rules: Vec<PayeeRule> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT r.pattern, r.is_income, COALESCE(i.category, c.category),
+            COALESCE(i.category_lower, r.category_lower), i.item, i.timeline_original
+        FROM payee_rules r
+        JOIN budget_categories c ON c.username_lower = r.username_lower
+            AND c.category_lower = r.category_lower AND c.is_income = r.is_income
+        LEFT JOIN budget_items i ON i.username_lower = r.username_lower
+            AND i.timeline_original = r.item_original AND i.is_deleted = 0
+        WHERE r.username_lower = ?1
+        ORDER BY r.is_income DESC, r.pattern_lower",
+    )?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        rules.push(PayeeRule {
+            pattern: row.get(0)?,
+            is_income: row.get(1)?,
+            category: row.get(2)?,
+            category_lower: row.get(3)?,
+            item: row.get(4)?,
+            item_original: row.get(5)?,
+        })
+    }
+    Ok(rules)
+}
+
+/// Remove the payee rules for a pattern, only the income or expense one if is_income is given
+pub fn delete_payee_rule(
+    conn: &Connection,
+    user: &User,
+    pattern: &str,
+    is_income: Option<bool>,
+) -> Result<()> {
+    let num_deleted = conn.execute(
+        "DELETE FROM payee_rules
+        WHERE username_lower = ?1 AND pattern_lower = ?2 AND (?3 IS NULL OR is_income = ?3)",
+        (
+            &user.username_lower,
+            &pattern.trim().to_lowercase(),
+            is_income,
+        ),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "There is no payee rule for {}",
+            pattern
+        )));
+    }
+    Ok(())
+}
+
+/// The rule for a transaction going this way whose pattern is in the description, the longest pattern if several are
+fn matching_rule<'a>(
+    rules: &'a [PayeeRule],
+    description: &str,
+    is_income: bool,
+) -> Option<&'a PayeeRule> {
+    let description = description.to_lowercase();
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.is_income == is_income && description.contains(&rule.pattern.to_lowercase())
+        })
+        .max_by_key(|rule| rule.pattern.len())
+}
+
+/// How a transaction already recorded is recognized when it is in a statement again
+type TransactionKey = (String, Amount, bool, String);
+
+/// The bank ids of the transactions already recorded, and how many there are of each date, amount and description
+fn existing_transactions(
+    conn: &Connection,
+    user: &User,
+) -> Result<(HashSet<String>, HashMap<TransactionKey, usize>)> {
+    let mut fitids: HashSet<String> = HashSet::new();
+    let mut keys: HashMap<TransactionKey, usize> = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT fitid, date_text, amount, is_income, description FROM transactions
+        WHERE username_lower = ?1",
+    )?;
+    let mut rows = stmt.query([&user.username_lower])?;
+    while let Some(row) = rows.next()? {
+        if let Some(fitid) = row.get::<_, Option<String>>(0)? {
+            fitids.insert(fitid);
+        }
+        let description: String = row.get(4)?;
+        let key = (
+            row.get(1)?,
+            row.get(2)?,
+            row.get(3)?,
+            description.to_lowercase(),
+        );
+        *keys.entry(key).or_default() += 1;
+    }
+    Ok((fitids, keys))
+}
+
+/// The current version of the balance sheet item a bank account is linked to, if it has one
+fn linked_item(conn: &Connection, user: &User, account_id: &str) -> Result<Option<Item>> {
+    let mut stmt = conn.prepare(
+        "SELECT is_asset, item_original FROM statement_accounts
+        WHERE username_lower = ?1 AND account_id = ?2",
+    )?;
+    let mut rows = stmt.query((&user.username_lower, account_id))?;
+    let Some(row) = rows.next()? else {
+        return Ok(None);
+    };
+    let is_asset: bool = row.get(0)?;
+    let item_original: usize = row.get(1)?;
+    let which_half = match is_asset {
+        true => BalanceSheetHalf::Assets,
+        false => BalanceSheetHalf::Liabilities,
+    };
+    let (_, items) = get_relevant_items_cats(conn, user, &which_half)?;
+    Ok(items
+        .into_iter()
+        .find(|item| item.timeline_original == item_original))
+}
+
+/// The value an item takes from an account balance
+/// A liability is the size of the balance, since banks sign credit card balances both ways
+fn balance_value(item: &Item, account_id: &str, balance: Amount) -> Result<Amount> {
+    if !item.is_asset {
+        return Ok(balance.abs());
+    }
+    if balance.is_negative() {
+        return Err(BudgetError::Validation(format!(
+            "The balance of account {} is below 0, so it can't be the value of the asset {}",
+            account_id, item.item
+        )));
+    }
+    Ok(balance)
+}
+
+/// Read a bank statement (OFX, QFX or QIF) and work out what importing it would do, without changing anything
+/// Each transaction is placed by the payee rules, and one already recorded is marked as a duplicate:
+/// by its bank id when it has one, otherwise by its date, amount and payee
+/// item is the balance sheet item that the statement's account balance should update from now on
+pub fn preview_statement_import(
+    conn: &Connection,
+    user: &User,
+    path: &str,
+    item: Option<&Item>,
+) -> Result<StatementImport> {
+    let contents = std::fs::read(path).map_err(|error| {
+        BudgetError::NotFound(format!("The file {} could not be read: {}", path, error))
+    })?;
+    let text = String::from_utf8_lossy(&contents);
+    let format = detect_format(path, &text)?;
+    let statements = match format {
+        StatementFormat::Ofx => parse_ofx(&text, &user.base_currency)?,
+        StatementFormat::Qif => parse_qif(&text, &user.base_currency)?,
+    };
+
+    let link = match item {
+        None => None,
+        Some(item) => {
+            let mut account_ids: Vec<&String> = statements
+                .iter()
+                .filter_map(|statement| statement.account_id.as_ref())
+                .collect();
+            account_ids.sort();
+            account_ids.dedup();
+            match account_ids.as_slice() {
+                [account_id] => Some((account_id.to_string(), item.clone())),
+                [] => {
+                    return Err(BudgetError::Validation(format!(
+                        "The statement has no account id, so it can't be linked to {}",
+                        item.item
+                    )))
+                }
+                _ => {
+                    return Err(BudgetError::Validation(format!(
+                        "The statement has more than one account, so it can't be linked to {}",
+                        item.item
+                    )))
+                }
+            }
+        }
+    };
+
+    let rules = list_payee_rules(conn, user)?;
+    let (mut fitids, mut keys) = existing_transactions(conn, user)?;
+    let mut lines: Vec<StatementLine> = vec![];
+    let mut balances: Vec<StatementBalance> = vec![];
+    for statement in statements {
+        for entry in statement.entries {
+            let is_income = !entry.amount.is_negative();
+            let is_duplicate = match &entry.fitid {
+                Some(fitid) => !fitids.insert(fitid.clone()),
+                None => {
+                    let key = (
+                        entry.date_text.clone(),
+                        entry.amount.abs(),
+                        is_income,
+                        entry.description().to_lowercase(),
+                    );
+                    match keys.get_mut(&key) {
+                        Some(count) if *count > 0 => {
+                            *count -= 1;
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+            };
+            let line = match matching_rule(&rules, entry.description(), is_income) {
+                Some(rule) => StatementLine {
+                    is_income,
+                    category: rule.category.clone(),
+                    category_lower: rule.category_lower.clone(),
+                    item: rule.item.clone(),
+                    item_original: rule.item.as_ref().and(rule.item_original),
+                    rule: Some(rule.pattern.clone()),
+                    is_duplicate,
+                    entry,
+                },
+                None => StatementLine {
+                    is_income,
+                    category: String::from(UNCATEGORIZED),
+                    category_lower: UNCATEGORIZED.to_lowercase(),
+                    item: None,
+                    item_original: None,
+                    rule: None,
+                    is_duplicate,
+                    entry,
+                },
+            };
+            lines.push(line);
+        }
+
+        if let (Some(account_id), Some((amount, date_text))) =
+            (statement.account_id, statement.balance)
+        {
+            let item = match &link {
+                Some((linked_id, item)) if *linked_id == account_id => Some(item.clone()),
+                _ => linked_item(conn, user, &account_id)?,
+            };
+            let value = match &item {
+                Some(item) => balance_value(item, &account_id, amount)?,
+                None => amount,
+            };
+            let currency = match (statement.currency, &item) {
+                (Some(currency), _) => currency,
+                (None, Some(item)) => item.currency.clone(),
+                (None, None) => user.base_currency.clone(),
+            };
+            balances.push(StatementBalance {
+                account_id,
+                amount,
+                currency,
+                date_text,
+                item,
+                value,
+            });
+        }
+    }
+    Ok(StatementImport {
+        format,
+        lines,
+        balances,
+        link,
+    })
+}
+
+/// Import a previewed bank statement and return the number of transactions recorded
+/// Duplicates are skipped, and each linked balance sheet item whose value changed gets a new version
+pub fn apply_statement_import(
+    conn: &Connection,
+    user: &User,
+    import: &StatementImport,
+) -> Result<usize> {
+    in_transaction(conn, |conn| {
+        initialize_budget(conn, user)?;
+        if let Some((account_id, item)) = &import.link {
+            conn.execute(
+                "INSERT OR REPLACE INTO statement_accounts
+                (username_lower, account_id, is_asset, item_original)
+                VALUES (?1, ?2, ?3, ?4)",
+                (
+                    &user.username_lower,
+                    account_id,
+                    item.is_asset,
+                    item.timeline_original,
+                ),
+            )?;
+        }
+
+        let mut num_imported: usize = 0;
+        for line in import.lines.iter().filter(|line| !line.is_duplicate) {
+            insert_transaction(
+                conn,
+                user,
+                line.is_income,
+                &line.category_lower,
+                line.item_original,
+                &line.entry.date_text,
+                line.entry.description(),
+                line.entry.amount.abs(),
+                &line.entry.currency,
+                line.entry.fitid.as_deref(),
+            )?;
+            num_imported += 1;
+        }
+
+        for balance in import.balances.iter().filter(|b| b.changes_item()) {
+            if let Some(item) = &balance.item {
+                balance_sheet::update_item(
+                    conn,
+                    user,
+                    item,
+                    &item.item,
+                    balance.value,
+                    &balance.currency,
+                    &item.category,
+                )?;
+            }
+        }
+        Ok(num_imported)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The message of an error that was expected
+    fn error_message<T>(result: Result<T>) -> String {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    const SGML_STATEMENT: &str = "OFXHEADER:100
+DATA:OFXSGML
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>usd
+<BANKACCTFROM><BANKID>121000248<ACCTID>1234<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20240105120000.000[-5:EST]
+<TRNAMT>-12.34
+<FITID>A1
+<NAME>Corner Shop &amp; Deli
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20240131
+<TRNAMT>+1,000.00
+<FITID>A2
+<PAYEE>Employer
+<MEMO>Salary
+</STMTTRN>
+<STMTTRN>
+<DTPOSTED>20240201
+<TRNAMT>0.00
+<FITID>A3
+</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>987.66<DTASOF>20240201083000[+1:CET]</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+
+    #[test]
+    fn ofx_sgml_statement() {
+        let statements = parse_ofx(SGML_STATEMENT, "EUR").unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account_id.as_deref(), Some("1234"));
+        assert_eq!(statement.currency.as_deref(), Some("USD"));
+        assert_eq!(
+            statement.balance,
+            Some((Amount::from_minor(98766), Some(String::from("2024-02-01"))))
+        );
+        // The entry of 0 is left out
+        assert_eq!(
+            statement.entries,
+            vec![
+                StatementEntry {
+                    date_text: String::from("2024-01-05"),
+                    payee: String::from("Corner Shop & Deli"),
+                    memo: String::new(),
+                    amount: Amount::from_minor(-1234),
+                    currency: String::from("USD"),
+                    fitid: Some(String::from("1234:A1")),
+                },
+                StatementEntry {
+                    date_text: String::from("2024-01-31"),
+                    payee: String::from("Employer"),
+                    memo: String::from("Salary"),
+                    amount: Amount::from_major(1000),
+                    currency: String::from("USD"),
+                    fitid: Some(String::from("1234:A2")),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ofx_xml_statements() {
+        let text = "<?xml version=\"1.0\"?>
+<OFX>
+<CREDITCARDMSGSRSV1><CCSTMTTRNRS><CCSTMTRS>
+<CCACCTFROM><ACCTID>9876</ACCTID></CCACCTFROM>
+<BANKTRANLIST>
+<STMTTRN><DTPOSTED>20240310000000.000[0:GMT]</DTPOSTED><TRNAMT>-45,50</TRNAMT><NAME>Fuel</NAME></STMTTRN>
+</BANKTRANLIST>
+</CCSTMTRS></CCSTMTTRNRS>
+<CCSTMTTRNRS><CCSTMTRS>
+<CURDEF>GBP</CURDEF>
+<CCACCTFROM><ACCTID>5555</ACCTID></CCACCTFROM>
+<BANKTRANLIST>
+<STMTTRN><DTPOSTED>20240311</DTPOSTED><TRNAMT>20</TRNAMT><FITID></FITID><NAME>Refund</NAME></STMTTRN>
+</BANKTRANLIST>
+</CCSTMTRS></CCSTMTTRNRS></CREDITCARDMSGSRSV1>
+</OFX>";
+        let statements = parse_ofx(text, "EUR").unwrap();
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].account_id.as_deref(), Some("9876"));
+        assert_eq!(statements[0].currency, None);
+        assert_eq!(statements[0].entries[0].date_text, "2024-03-10");
+        assert_eq!(statements[0].entries[0].amount, Amount::from_minor(-4550));
+        assert_eq!(statements[0].entries[0].currency, "EUR");
+        assert_eq!(statements[0].entries[0].fitid, None);
+        assert_eq!(statements[1].account_id.as_deref(), Some("5555"));
+        assert_eq!(statements[1].entries[0].amount, Amount::from_major(20));
+        assert_eq!(statements[1].entries[0].currency, "GBP");
+        assert_eq!(statements[1].entries[0].fitid, None);
+    }
+
+    #[test]
+    fn ofx_errors() {
+        assert_eq!(
+            error_message(parse_ofx("OFXHEADER:100\n<OFX></OFX>", "USD")),
+            "No statements were found in the OFX file"
+        );
+        assert_eq!(
+            error_message(parse_ofx(
+                "<OFX><STMTTRN><DTPOSTED>20240105<NAME>Shop</STMTTRN></OFX>",
+                "USD"
+            )),
+            "Transaction 1 in the statement has no amount (TRNAMT)"
+        );
+        assert_eq!(
+            error_message(parse_ofx(
+                "<OFX><STMTTRN><DTPOSTED>20240105<TRNAMT>1</STMTTRN><STMTTRN><TRNAMT>2</STMTTRN></OFX>",
+                "USD"
+            )),
+            "Transaction 2 in the statement has no date (DTPOSTED)"
+        );
+        assert_eq!(
+            error_message(parse_ofx(
+                "<OFX><STMTTRN><DTPOSTED>20241305<TRNAMT>1</STMTTRN></OFX>",
+                "USD"
+            )),
+            "20241305 is not a valid OFX date"
+        );
+        assert_eq!(
+            error_message(parse_ofx(
+                "<OFX><STMTTRN><DTPOSTED>20240105<TRNAMT>ten</STMTTRN></OFX>",
+                "USD"
+            )),
+            "ten is not a valid amount"
+        );
+    }
+
+    #[test]
+    fn qif_dates() {
+        for text in [
+            "01/05/2024",
+            "1/5/24",
+            "1/5'24",
+            " 1/ 5'24",
+            "01-05-2024",
+            "2024-01-05",
+        ] {
+            assert_eq!(parse_qif_date(text).unwrap(), "2024-01-05", "{}", text);
+        }
+        assert_eq!(parse_qif_date("12/31/99").unwrap(), "1999-12-31");
+        assert_eq!(parse_qif_date("2/29/2024").unwrap(), "2024-02-29");
+        for text in ["31/01/2024", "2/30/2024", "1/5", "Jan 5 2024", ""] {
+            assert_eq!(
+                error_message(parse_qif_date(text)),
+                format!("{} is not a valid QIF date", text)
+            );
+        }
+    }
+
+    #[test]
+    fn qif_records() {
+        let text = "!Type:Bank
+D01/05/2024
+T-1,234.56
+PLandlord
+MRent
+^
+D1/6'24
+U-50.00
+T-50.00
+PSupermarket
+SGroceries
+$-30.00
+SHousehold
+$-20.00
+^
+!Type:Invst
+D01/07/2024
+T-999.00
+PBroker
+^
+!Type:CCard
+D01/08/2024
+T25
+PRefund
+";
+        let statements = parse_qif(text, "CAD").unwrap();
+        assert_eq!(statements.len(), 1);
+        let statement = &statements[0];
+        assert_eq!(statement.account_id, None);
+        assert_eq!(statement.balance, None);
+        // The split lines don't replace the total, and the investment account is skipped
+        assert_eq!(
+            statement.entries,
+            vec![
+                StatementEntry {
+                    date_text: String::from("2024-01-05"),
+                    payee: String::from("Landlord"),
+                    memo: String::from("Rent"),
+                    amount: Amount::from_minor(-123456),
+                    currency: String::from("CAD"),
+                    fitid: None,
+                },
+                StatementEntry {
+                    date_text: String::from("2024-01-06"),
+                    payee: String::from("Supermarket"),
+                    memo: String::new(),
+                    amount: Amount::from_major(-50),
+                    currency: String::from("CAD"),
+                    fitid: None,
+                },
+                StatementEntry {
+                    date_text: String::from("2024-01-08"),
+                    payee: String::from("Refund"),
+                    memo: String::new(),
+                    amount: Amount::from_major(25),
+                    currency: String::from("CAD"),
+                    fitid: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn qif_account_list_is_skipped() {
+        let text = "!Account
+NChecking
+TBank
+^
+!Type:Bank
+D01/05/2024
+T10
+^
+";
+        let statements = parse_qif(text, "USD").unwrap();
+        assert_eq!(statements[0].entries.len(), 1);
+        assert_eq!(statements[0].entries[0].amount, Amount::from_major(10));
+    }
+
+    #[test]
+    fn qif_errors() {
+        assert_eq!(
+            error_message(parse_qif(
+                "!Type:Bank\nD01/05/2024\nT1\n^\nPShop\nD01/06/2024\n^\n",
+                "USD"
+            )),
+            "Line 6: the transaction needs both a date (D) and an amount (T)"
+        );
+        assert_eq!(
+            error_message(parse_qif("!Type:Bank\nD01/05/2024\nT1\n^\nT2\n", "USD")),
+            "Line 5: the transaction needs both a date (D) and an amount (T)"
+        );
+        assert_eq!(
+            error_message(parse_qif("!Type:Bank\nD13/05/2024\nT1\n^\n", "USD")),
+            "13/05/2024 is not a valid QIF date"
+        );
+        assert_eq!(
+            error_message(parse_qif("!Type:Bank\nD01/05/2024\nT1.234\n^\n", "USD")),
+            "1.234 is not a valid amount"
+        );
+    }
+}
@@ -1,3 +1,9 @@
+use crate::error::{BudgetError, Result};
+pub use crate::frequency::Frequency;
+pub use crate::money::Amount;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
 use rusty_money::{iso, Money};
 
 /// The User struct for the application
@@ -8,6 +14,10 @@ pub struct User {
     pub firstname: String,
     pub lastname: String,
     pub is_deleted: bool,
+    /// ISO code that totals and snapshots are shown in
+    pub base_currency: String,
+    /// Whether a password must be entered to login as this user
+    pub has_password: bool,
 }
 impl User {
     pub fn fullname(&self) -> String {
@@ -39,11 +49,12 @@ pub struct BudgetCategory {
 /// timeline_original refers to when the first iteration of this item was created (not changed with updates)
 ///     This may be used when making a view of snapshots in the Snapshot section
 /// timeline_deleted is set to a very high number by default. When an item is deleted it is given a timeline value instead
-#[derive(Debug, PartialEq)]
+/// currency is the ISO code the value is in
+#[derive(Debug, PartialEq, Clone)]
 pub struct Item {
     pub item: String,
     pub item_lower: String,
-    pub value: f64,
+    pub value: Amount,
     pub category: String,
     pub category_lower: String,
     pub username_lower: String,
@@ -52,14 +63,16 @@ pub struct Item {
     pub timeline_original: usize,
     pub is_deleted: bool,
     pub timeline_deleted: usize,
+    pub currency: String,
 }
 
 /// Income or Expense Budget Item
-#[derive(Debug, PartialEq)]
+/// The value is the amount each time, and the frequency says how often that is
+#[derive(Debug, PartialEq, Clone)]
 pub struct BudgetItem {
     pub item: String,
     pub item_lower: String,
-    pub value: f64,
+    pub value: Amount,
     pub category: String,
     pub category_lower: String,
     pub username_lower: String,
@@ -68,23 +81,692 @@ pub struct BudgetItem {
     pub timeline_original: usize,
     pub is_deleted: bool,
     pub timeline_deleted: usize,
+    pub currency: String,
+    pub frequency: Frequency,
 }
 
+/// One item in the budget for a single YYYY-MM month
+/// The value is the amount planned for that month only, so changing it leaves every other month alone
+/// item_original is the timeline_original of the budget item it was started from, or None if it was only added to this month
+#[derive(Debug, PartialEq, Clone)]
+pub struct MonthItem {
+    pub item: String,
+    pub item_lower: String,
+    pub value: Amount,
+    pub category_lower: String,
+    pub username_lower: String,
+    pub is_income: bool,
+    pub month: String,
+    pub currency: String,
+    pub item_original: Option<usize>,
+}
+
+/// The net worth is in currency, the user's base currency when the snapshot was taken
 #[derive(Debug, PartialEq, Clone)]
 pub struct Snapshot {
     pub timeline: usize,
     pub username_lower: String,
     pub date_today: String,
-    pub net_worth: f64,
+    pub net_worth: Amount,
+    pub comment: String,
+    pub is_deleted: bool,
+    pub currency: String,
+}
+
+/// The monthly net is in currency, the user's base currency when the snapshot was taken
+/// The timeline is from the budget timeline rather than the balance sheet timeline
+#[derive(Debug, PartialEq, Clone)]
+pub struct BudgetSnapshot {
+    pub timeline: usize,
+    pub username_lower: String,
+    pub date_today: String,
+    pub monthly_net: Amount,
     pub comment: String,
     pub is_deleted: bool,
+    pub currency: String,
+}
+
+impl BudgetItem {
+    /// The value averaged out over a month
+    pub fn monthly_value(&self) -> Amount {
+        self.frequency.monthly_equivalent(self.value)
+    }
+}
+
+/// One month of the budget calendar
+/// lumpy_items are the quarterly, annual and one-off items that land in this month, with the amount in currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct CalendarMonth {
+    pub month: String,
+    pub currency: String,
+    pub income: Amount,
+    pub expenses: Amount,
+    pub lumpy_items: Vec<(BudgetItem, Amount)>,
+}
+
+/// A dated income or expense that actually happened, as opposed to a planned BudgetItem
+/// item_original is the timeline_original of the budget item it was assigned to (if any), so it follows the item through updates
+/// fitid is the bank's id for a transaction imported from a statement, prefixed with the account id when there is one
+#[derive(Debug, PartialEq, Clone)]
+pub struct BudgetTransaction {
+    pub id: i64,
+    pub username_lower: String,
+    pub date_text: String,
+    pub description: String,
+    pub amount: Amount,
+    pub currency: String,
+    pub is_income: bool,
+    pub category_lower: String,
+    pub item_original: Option<usize>,
+    pub fitid: Option<String>,
+}
+
+/// What a transaction counts against in the budget
+pub enum TransactionTarget {
+    Category(BudgetCategory),
+    Item(BudgetItem),
+}
+
+/// Planned and actual amounts for one line of the budget vs. actual report
+/// carried_in is what was left unspent in the category's envelope last month (only ever set on a category total)
+/// All are in the report's currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct ActualLine {
+    pub name: String,
+    pub planned: Amount,
+    pub carried_in: Amount,
+    pub actual: Amount,
+}
+impl ActualLine {
+    /// The plan plus anything carried in from last month
+    pub fn available(&self) -> Amount {
+        self.planned + self.carried_in
+    }
+    /// What is left of what was available (negative when it has been overspent)
+    pub fn remaining(&self) -> Amount {
+        self.available() - self.actual
+    }
+    /// The percent of what was available used so far, or None when nothing was available
+    pub fn percent_used(&self) -> Option<f64> {
+        if self.available() == Amount::ZERO {
+            return None;
+        }
+        Some(self.actual.to_f64() / self.available().to_f64() * 100.0)
+    }
+}
+
+/// One category of the budget vs. actual report
+/// unassigned holds the transactions in the category that aren't assigned to one of its current items
+#[derive(Debug, PartialEq, Clone)]
+pub struct CategoryActuals {
+    pub category_lower: String,
+    pub total: ActualLine,
+    pub items: Vec<ActualLine>,
+    pub unassigned: Amount,
+    /// Whether what is left at the end of the month rolls into next month's envelope
+    pub rollover: bool,
+}
+
+/// Budget vs. actual for one calendar month
+/// is_month_budget says whether the plan is that month's own budget rather than the current budget
+#[derive(Debug, PartialEq, Clone)]
+pub struct BudgetVsActual {
+    pub month: String,
+    pub currency: String,
+    pub is_month_budget: bool,
+    pub income: Vec<CategoryActuals>,
+    pub expenses: Vec<CategoryActuals>,
+}
+
+/// Whether a goal is reached by building a balance up or by paying one down
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GoalKind {
+    /// Reached when the linked value is at or above the target (eg - an emergency fund)
+    Save,
+    /// Reached when the linked value is at or below the target (eg - a loan paid off to 0)
+    PayOff,
+}
+impl GoalKind {
+    pub fn to_str(&self) -> &str {
+        match self {
+            GoalKind::Save => "save",
+            GoalKind::PayOff => "payoff",
+        }
+    }
+}
+impl std::str::FromStr for GoalKind {
+    type Err = BudgetError;
+
+    fn from_str(kind: &str) -> Result<GoalKind> {
+        match kind {
+            "save" => Ok(GoalKind::Save),
+            "payoff" => Ok(GoalKind::PayOff),
+            _ => Err(BudgetError::Parse(format!(
+                "{} is not a kind of goal. Use save or payoff.",
+                kind
+            ))),
+        }
+    }
+}
+
+/// A balance sheet item (by its timeline_original) or a whole category that counts towards a goal
+#[derive(Debug, PartialEq, Clone)]
+pub enum GoalLink {
+    Item {
+        is_asset: bool,
+        item_original: usize,
+    },
+    Category {
+        is_asset: bool,
+        category_lower: String,
+    },
+}
+
+/// Something whose value can be followed across the snapshots in a trend chart
+/// An item is followed by its timeline_original, so renaming or revaluing it keeps the same line
+#[derive(Debug, PartialEq, Clone)]
+pub enum TrendLine {
+    Item {
+        is_asset: bool,
+        item_original: usize,
+    },
+    Category {
+        is_asset: bool,
+        category_lower: String,
+    },
+    /// Total assets or total liabilities
+    Total { is_asset: bool },
+}
+
+/// A savings or payoff goal measured by the balance sheet items and categories it links to
+/// The target and start_value (the linked value when the goal was made) are in currency
+/// target_date is YYYY-MM-DD, and budget_item_original is the budget expense item planned to pay into it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Goal {
+    pub id: i64,
+    pub username_lower: String,
+    pub name: String,
+    pub kind: GoalKind,
+    pub target: Amount,
+    pub currency: String,
+    pub target_date: Option<String>,
+    pub start_value: Amount,
+    pub budget_item_original: Option<usize>,
+    pub links: Vec<GoalLink>,
+}
+
+/// How far along a goal is, and when it looks like it will be reached
+/// history is the linked value at each snapshot, and all amounts are in the goal's currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct GoalProgress {
+    pub current: Amount,
+    pub percent: f64,
+    pub reached: bool,
+    pub history: Vec<(String, Amount)>,
+    /// When the snapshot trend reaches the target, or None if it isn't heading towards it
+    pub projected_date: Option<NaiveDate>,
+    /// What has to go in (or be paid off) each month to reach the target by the target date
+    pub monthly_needed: Option<Amount>,
+    /// The linked budget item's monthly equivalent
+    pub planned_contribution: Option<Amount>,
+    /// When the target is reached if the planned contribution goes in every month
+    pub planned_date: Option<NaiveDate>,
+}
+
+/// Loan terms on a liability item
+/// item_original is the item's timeline_original, so the terms follow it through updates
+/// apr is the annual percentage rate (eg - 6.5 for 6.5%), and the amounts are in currency
+/// Payments are monthly, with the first one month after start_date (YYYY-MM-DD)
+#[derive(Debug, PartialEq, Clone)]
+pub struct Loan {
+    pub username_lower: String,
+    pub item_original: usize,
+    pub principal: Amount,
+    pub apr: Decimal,
+    pub term_months: u32,
+    pub payment: Amount,
+    pub start_date: String,
+    pub currency: String,
+}
+
+/// One line of an amortization schedule
+/// The balance is what is still owed after this payment
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoanPayment {
+    pub number: u32,
+    pub date: NaiveDate,
+    pub payment: Amount,
+    pub interest: Amount,
+    pub principal: Amount,
+    pub balance: Amount,
+}
+
+/// The totals of an amortization schedule
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoanSummary {
+    /// The regular monthly payment, including any extra
+    pub payment: Amount,
+    pub num_payments: u32,
+    pub payoff_date: NaiveDate,
+    pub total_interest: Amount,
+}
+
+/// A liability's value recorded in a snapshot next to what its loan terms project for that date
+#[derive(Debug, PartialEq, Clone)]
+pub struct LoanCheck {
+    pub date_text: String,
+    pub recorded: Amount,
+    pub projected: Amount,
+}
+
+/// A liability in the debt payoff planner
+/// The balance and minimum payment are in the user's base currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct Debt {
+    pub item: String,
+    pub item_original: usize,
+    pub balance: Amount,
+    pub apr: Decimal,
+    pub minimum_payment: Amount,
+}
+
+/// How the money left over after the minimum payments is put towards the debts
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DebtStrategy {
+    /// The highest interest rate first, which pays the least interest
+    Avalanche,
+    /// The smallest balance first, which pays off whole debts soonest
+    Snowball,
+    /// Only the minimum payments, with nothing extra (the baseline for interest saved)
+    MinimumsOnly,
+}
+impl DebtStrategy {
+    pub fn to_str(&self) -> &str {
+        match self {
+            DebtStrategy::Avalanche => "Avalanche",
+            DebtStrategy::Snowball => "Snowball",
+            DebtStrategy::MinimumsOnly => "Minimum payments only",
+        }
+    }
+}
+
+/// The debts at the end of one month of a payoff plan
+/// balances are in the same order as the plan's debts
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebtMonth {
+    pub date: NaiveDate,
+    pub balances: Vec<Amount>,
+    pub total: Amount,
+    pub interest: Amount,
+}
+
+/// A month by month plan for paying off every debt
+#[derive(Debug, PartialEq, Clone)]
+pub struct DebtPlan {
+    pub strategy: DebtStrategy,
+    pub debts: Vec<Debt>,
+    pub months: Vec<DebtMonth>,
+    /// Each debt's name and the month it is paid off, in the order they are paid off
+    pub payoff_order: Vec<(String, NaiveDate)>,
+    pub debt_free_date: NaiveDate,
+    pub total_interest: Amount,
+}
+
+/// How a net worth forecast is worked out
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ForecastMethod {
+    /// Continue the least squares trend through the snapshots
+    Trend,
+    /// Grow each asset category at its assumed annual return and add the budget's monthly net
+    Returns,
+}
+impl ForecastMethod {
+    pub fn to_str(&self) -> &str {
+        match self {
+            ForecastMethod::Trend => "Snapshot trend",
+            ForecastMethod::Returns => "Assumed returns and budget",
+        }
+    }
+}
+
+/// Projected net worth at the start of each month from today, in the user's base currency
+/// monthly_change is how much the forecast adds each month to begin with
+#[derive(Debug, PartialEq, Clone)]
+pub struct Forecast {
+    pub method: ForecastMethod,
+    pub points: Vec<(NaiveDate, Amount)>,
+    pub monthly_change: Amount,
+}
+
+/// Financial independence numbers worked out from the budget and a forecast, in the user's base currency
+/// savings_rate is a percentage of income, and None when there is no income
+#[derive(Debug, PartialEq, Clone)]
+pub struct FireMetrics {
+    pub net_worth: Amount,
+    pub monthly_income: Amount,
+    pub monthly_expenses: Amount,
+    pub savings_rate: Option<f64>,
+    pub withdrawal_rate: Decimal,
+    pub target: Amount,
+    /// When the forecast first reaches the target, or None if it doesn't within the forecast
+    pub target_date: Option<NaiveDate>,
+    pub years_to_target: Option<f64>,
+    /// The yearly amount that can be withdrawn at the withdrawal rate, now and at the end of the forecast
+    pub safe_withdrawal: Amount,
+    pub projected_safe_withdrawal: Amount,
+}
+
+/// What a row of an items CSV file is: a balance sheet item or a budget item
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CsvItemKind {
+    Asset,
+    Liability,
+    Income,
+    Expense,
+}
+impl CsvItemKind {
+    /// The name used in the kind column
+    pub fn to_str(&self) -> &str {
+        match self {
+            CsvItemKind::Asset => "asset",
+            CsvItemKind::Liability => "liability",
+            CsvItemKind::Income => "income",
+            CsvItemKind::Expense => "expense",
+        }
+    }
+    /// Whether the row is a budget item rather than a balance sheet item
+    pub fn is_budget(&self) -> bool {
+        matches!(self, CsvItemKind::Income | CsvItemKind::Expense)
+    }
+}
+impl std::str::FromStr for CsvItemKind {
+    type Err = BudgetError;
+
+    fn from_str(input: &str) -> Result<CsvItemKind> {
+        match input.trim().to_lowercase().as_str() {
+            "asset" | "assets" => Ok(CsvItemKind::Asset),
+            "liability" | "liabilities" => Ok(CsvItemKind::Liability),
+            "income" => Ok(CsvItemKind::Income),
+            "expense" | "expenses" => Ok(CsvItemKind::Expense),
+            _ => Err(BudgetError::Parse(format!(
+                "{} is not a kind. Use asset, liability, income or expense.",
+                input
+            ))),
+        }
+    }
+}
+
+/// A checked row of an items CSV file, ready to be created
+/// line is the line number in the file, and frequency is only set for budget items
+#[derive(Debug, PartialEq, Clone)]
+pub struct CsvItemRow {
+    pub line: usize,
+    pub kind: CsvItemKind,
+    pub category: String,
+    pub item: String,
+    pub value: Amount,
+    pub currency: String,
+    pub frequency: Option<Frequency>,
+}
+
+/// The dry run of an items CSV import
+/// The rows and new categories are what would be created, and any errors (one per bad line) stop the import
+#[derive(Debug, PartialEq, Clone)]
+pub struct CsvImport {
+    pub rows: Vec<CsvItemRow>,
+    pub new_categories: Vec<(CsvItemKind, String)>,
+    pub errors: Vec<String>,
+}
+
+/// One row of a database table in a backup, as column names to values
+pub type BackupRow = serde_json::Map<String, serde_json::Value>;
+
+/// A whole user's data as a versioned document, saved as JSON
+/// The profile is the user's row of the users table, and each table holds every row of the user's (deleted ones too)
+/// schema_version is the database schema the rows were taken from
+/// shared_exchange_rates are only in version 1 backups, from when every user of a ledger shared the same rates.
+/// Newer backups keep the user's own rates in the exchange_rates table.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Backup {
+    pub version: u32,
+    pub schema_version: u32,
+    pub created: String,
+    pub profile: BackupRow,
+    pub tables: Vec<(String, Vec<BackupRow>)>,
+    pub shared_exchange_rates: Vec<BackupRow>,
+}
+impl Backup {
+    /// The username the backup was taken from
+    pub fn username(&self) -> &str {
+        self.profile
+            .get("username")
+            .and_then(|username| username.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// The file format of a bank statement
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum StatementFormat {
+    /// OFX, and QFX which is OFX with a few extra tags
+    Ofx,
+    Qif,
+}
+impl StatementFormat {
+    pub fn to_str(&self) -> &str {
+        match self {
+            StatementFormat::Ofx => "OFX",
+            StatementFormat::Qif => "QIF",
+        }
+    }
+}
+
+/// One transaction read from a bank statement
+/// The amount is signed the way the bank gives it, so money going out is negative
+/// fitid is the bank's id for it (only OFX has one), prefixed with the account id when there is one
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatementEntry {
+    pub date_text: String,
+    pub payee: String,
+    pub memo: String,
+    pub amount: Amount,
+    pub currency: String,
+    pub fitid: Option<String>,
+}
+impl StatementEntry {
+    /// The payee, or the memo if the bank left the payee out
+    pub fn description(&self) -> &str {
+        match self.payee.is_empty() {
+            true => &self.memo,
+            false => &self.payee,
+        }
+    }
+}
+
+/// A statement transaction and where it would go in the budget
+/// rule is the pattern of the payee rule that placed it, and it goes to Uncategorized when there isn't one
+/// A duplicate has already been imported (or entered) and is left out
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatementLine {
+    pub entry: StatementEntry,
+    pub is_income: bool,
+    pub category: String,
+    pub category_lower: String,
+    pub item: Option<String>,
+    pub item_original: Option<usize>,
+    pub rule: Option<String>,
+    pub is_duplicate: bool,
+}
+
+/// The ledger balance (LEDGERBAL) of one account in an OFX statement
+/// item is the balance sheet item linked to the account, and value is what the item would be updated to
+#[derive(Debug, PartialEq)]
+pub struct StatementBalance {
+    pub account_id: String,
+    pub amount: Amount,
+    pub currency: String,
+    pub date_text: Option<String>,
+    pub item: Option<Item>,
+    pub value: Amount,
+}
+impl StatementBalance {
+    /// Whether importing would make a new version of the linked item
+    pub fn changes_item(&self) -> bool {
+        match &self.item {
+            Some(item) => item.value != self.value || item.currency != self.currency,
+            None => false,
+        }
+    }
+}
+
+/// The dry run of a bank statement import
+/// link is the account id and balance sheet item that will be linked, when an item was chosen for the statement
+#[derive(Debug, PartialEq)]
+pub struct StatementImport {
+    pub format: StatementFormat,
+    pub lines: Vec<StatementLine>,
+    pub balances: Vec<StatementBalance>,
+    pub link: Option<(String, Item)>,
+}
+impl StatementImport {
+    /// The number of transactions that would be imported
+    pub fn num_new(&self) -> usize {
+        self.lines.iter().filter(|line| !line.is_duplicate).count()
+    }
+}
+
+/// Sends imported transactions whose payee contains the pattern (ignoring case) to a budget category or item
+/// A rule only applies to transactions going the same way (income or expense) as its category
+/// item is the name of the current version of the budget item, if the rule is for one
+#[derive(Debug, PartialEq, Clone)]
+pub struct PayeeRule {
+    pub pattern: String,
+    pub is_income: bool,
+    pub category: String,
+    pub category_lower: String,
+    pub item: Option<String>,
+    pub item_original: Option<usize>,
+}
+
+/// A plain-text accounting file format
+/// Ledger and hledger read the same journal format
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PlainTextFormat {
+    Ledger,
+    Beancount,
+}
+impl PlainTextFormat {
+    pub fn to_str(&self) -> &str {
+        match self {
+            PlainTextFormat::Ledger => "ledger",
+            PlainTextFormat::Beancount => "beancount",
+        }
+    }
+}
+
+/// One balance read from a plain-text accounting file, matched to a balance sheet item
+/// value is what the item will be (the size of the balance for a liability)
+/// item_original is the item it updates, or None if the item will be created
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImportedBalance {
+    pub line: usize,
+    pub account: String,
+    pub is_asset: bool,
+    pub category: String,
+    pub item: String,
+    pub value: Amount,
+    pub currency: String,
+    pub item_original: Option<usize>,
+}
+
+/// The balances on one day of a plain-text accounting file, which become one snapshot
+#[derive(Debug, PartialEq, Clone)]
+pub struct BalanceDate {
+    pub date_text: String,
+    pub balances: Vec<ImportedBalance>,
+}
+
+/// The dry run of importing balances as snapshots
+/// new_categories are (is_asset, category) for the categories that will be created
+#[derive(Debug, PartialEq, Clone)]
+pub struct BalanceImport {
+    pub dates: Vec<BalanceDate>,
+    pub new_categories: Vec<(bool, String)>,
+}
+
+/// How a report is written: a standalone HTML page with its styles and charts inline, or Markdown
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ReportFormat {
+    Html,
+    Markdown,
+}
+impl ReportFormat {
+    pub fn to_str(&self) -> &str {
+        match self {
+            ReportFormat::Html => "HTML",
+            ReportFormat::Markdown => "Markdown",
+        }
+    }
+}
+
+/// One item in a report table with a cell for each column, in the item's own currency
+/// A cell is None when the item wasn't on the balance sheet at that column's snapshot
+/// detail is shown beside the item's name (eg - how often a budget item comes in or goes out)
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportRow {
+    pub item: String,
+    pub detail: String,
+    pub cells: Vec<Option<(Amount, String)>>,
+}
+
+/// The items of one category in a report table, with the category's total for each column in the report's currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportCategory {
+    pub category: String,
+    pub rows: Vec<ReportRow>,
+    pub totals: Vec<Amount>,
+}
+
+/// One half of the balance sheet or budget in a report, with the total of each column in the report's currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReportTable {
+    pub title: String,
+    pub categories: Vec<ReportCategory>,
+    pub totals: Vec<Amount>,
+}
+
+/// Everything that goes into a report, gathered so it can be written as HTML or Markdown
+/// Totals and the net worth trend are in currency, the user's base currency. Snapshot totals use the rates frozen with each snapshot.
+/// The budget is in monthly equivalents. snapshot_dates label the columns of the side by side snapshot tables.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Report {
+    pub fullname: String,
+    pub date_text: String,
+    pub currency: String,
+    pub assets: ReportTable,
+    pub liabilities: ReportTable,
+    pub income: ReportTable,
+    pub expenses: ReportTable,
+    pub snapshot_dates: Vec<String>,
+    pub snapshot_assets: ReportTable,
+    pub snapshot_liabilities: ReportTable,
+    pub net_worth_trend: Vec<(NaiveDate, Amount)>,
+}
+
+/// 1 unit of from_currency is worth rate units of to_currency
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExchangeRate {
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: rust_decimal::Decimal,
+    pub date_text: String,
 }
 
 /// The maximum length is related to the formatting used
 pub const MAX_CHARACTERS_ITEM_NAME: usize = 28;
 
 /// 1 quadrillion is the maximum value related to plot formatting
-pub const MAX_ITEM_VALUE: f64 = 1_000_000_000_000_000.0;
+pub const MAX_ITEM_VALUE: Amount = Amount::from_major(1_000_000_000_000_000);
 
 /// Assets or Liabilities
 #[derive(PartialEq)]
@@ -143,12 +825,46 @@ impl BudgetHalf {
     }
 }
 
+/// Check the item name against the formatting limits
+pub fn validate_item_name(item_name: &str) -> Result<()> {
+    if item_name.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The item name cannot be empty",
+        )));
+    }
+    // Enforce MAX_CHARACTERS_ITEM_NAME character maximum
+    if item_name.len() > MAX_CHARACTERS_ITEM_NAME {
+        return Err(BudgetError::Validation(format!(
+            "There is currently a {} character limit on the item name",
+            MAX_CHARACTERS_ITEM_NAME
+        )));
+    }
+    Ok(())
+}
+
+/// Check that an item value is a positive number within the plotting limits
+pub fn validate_item_value(value: Amount) -> Result<()> {
+    if value.is_negative() {
+        return Err(BudgetError::Validation(String::from(
+            "The value must be a positive number or 0",
+        )));
+    }
+    if value > MAX_ITEM_VALUE {
+        return Err(BudgetError::Validation(String::from(
+            "1 quadrillion is the maximum value for an item currently",
+        )));
+    }
+    Ok(())
+}
+
 /// Utilizes the read!() macro but exits the program if the user has input "quit"
+/// The end of the input (or an unreadable input) is treated the same as "quit"
 pub fn read_or_quit() -> String {
     let mut input = String::new();
-    std::io::stdin()
-        .read_line(&mut input)
-        .expect("Error getting input");
+    if !matches!(std::io::stdin().read_line(&mut input), Ok(n) if n > 0) {
+        println!("\nYour budget is saved, and you have been logged out. See you next time!\n");
+        std::process::exit(0);
+    }
     input = input.trim_end().to_string(); // Trim whitespace
     let words: Vec<&str> = input.split(' ').collect();
     for word in words {
@@ -185,7 +901,24 @@ pub fn print_instr_get_response<F: Fn()>(minval: usize, maxval: usize, instructi
     selection
 }
 
-pub fn to_money_string(input: f64) -> String {
-    let money = Money::from_str(input.to_string().as_str(), iso::USD).unwrap();
-    money.to_string()
+/// Format an amount for display in its currency (eg - $1,234.50 or €1.234,50)
+pub fn to_money_string(input: Amount, currency: &str) -> String {
+    let currency = iso::find(currency).unwrap_or(iso::USD);
+    Money::from_decimal(Decimal::new(input.minor(), 2), currency).to_string()
+}
+
+/// Run a logical operation as one transaction so it either fully applies or not at all
+/// If a transaction is already open (one operation calling another) the outer one is used
+pub fn in_transaction<T, F>(conn: &Connection, operation: F) -> Result<T>
+where
+    F: FnOnce(&Connection) -> Result<T>,
+{
+    if !conn.is_autocommit() {
+        return operation(conn);
+    }
+    // Dropping the transaction without committing rolls it back
+    let tx = conn.unchecked_transaction()?;
+    let result = operation(&tx)?;
+    tx.commit()?;
+    Ok(result)
 }
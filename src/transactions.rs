@@ -0,0 +1,501 @@
+use crate::budget::get_relevant_items;
+use crate::budget_months::{get_month_items, get_rollovers, has_budget_month, shift_month};
+use crate::currency::{current_converter, normalize_currency, Converter};
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use chrono::prelude::*;
+use rusqlite::{Connection, Row};
+
+/// Build a BudgetTransaction from a full row of the transactions table
+fn transaction_from_row(row: &Row) -> rusqlite::Result<BudgetTransaction> {
+    Ok(BudgetTransaction {
+        id: row.get(0)?,
+        username_lower: row.get(1)?,
+        date_text: row.get(2)?,
+        description: row.get(3)?,
+        amount: row.get(4)?,
+        currency: row.get(5)?,
+        is_income: row.get(6)?,
+        category_lower: row.get(7)?,
+        item_original: row.get(8)?,
+        fitid: row.get(9)?,
+    })
+}
+
+/// Parse a YYYY-MM-DD date
+pub fn parse_date(date_text: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date_text, "%Y-%m-%d").map_err(|_| {
+        BudgetError::Parse(format!(
+            "{} is not a valid date. Use the format YYYY-MM-DD.",
+            date_text
+        ))
+    })
+}
+
+/// Parse a YYYY-MM month and return it in the same form
+pub fn parse_month(month_text: &str) -> Result<String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month_text), "%Y-%m-%d")
+        .map(|date| date.format("%Y-%m").to_string())
+        .map_err(|_| {
+            BudgetError::Parse(format!(
+                "{} is not a valid month. Use the format YYYY-MM.",
+                month_text
+            ))
+        })
+}
+
+/// The current month as YYYY-MM
+pub fn current_month() -> String {
+    Local::now().format("%Y-%m").to_string()
+}
+
+/// Record an income or expense that actually happened
+/// It is counted against the category, or against the item and the item's category
+pub fn add_transaction(
+    conn: &Connection,
+    user: &User,
+    target: &TransactionTarget,
+    date_text: &str,
+    description: &str,
+    amount: Amount,
+    currency: &str,
+) -> Result<BudgetTransaction> {
+    let (is_income, category_lower, item_original) = match target {
+        TransactionTarget::Category(category) => {
+            (category.is_income, category.category_lower.clone(), None)
+        }
+        TransactionTarget::Item(item) => (
+            item.is_income,
+            item.category_lower.clone(),
+            Some(item.timeline_original),
+        ),
+    };
+    insert_transaction(
+        conn,
+        user,
+        is_income,
+        &category_lower,
+        item_original,
+        date_text,
+        description,
+        amount,
+        currency,
+        None,
+    )
+}
+
+/// Check and insert a transaction, with the bank's id for it if it came from a statement
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn insert_transaction(
+    conn: &Connection,
+    user: &User,
+    is_income: bool,
+    category_lower: &str,
+    item_original: Option<usize>,
+    date_text: &str,
+    description: &str,
+    amount: Amount,
+    currency: &str,
+    fitid: Option<&str>,
+) -> Result<BudgetTransaction> {
+    let date_text = parse_date(date_text)?.format("%Y-%m-%d").to_string();
+    if amount.is_negative() || amount == Amount::ZERO {
+        return Err(BudgetError::Validation(String::from(
+            "The amount must be a positive number",
+        )));
+    }
+    validate_item_value(amount)?;
+    let currency = normalize_currency(currency)?;
+
+    conn.execute(
+        "INSERT INTO transactions
+        (username_lower, date_text, description, amount, currency, is_income, category_lower, item_original, fitid)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        (
+            &user.username_lower,
+            &date_text,
+            description,
+            amount,
+            &currency,
+            is_income,
+            category_lower,
+            item_original,
+            fitid,
+        ),
+    )?;
+    Ok(BudgetTransaction {
+        id: conn.last_insert_rowid(),
+        username_lower: String::from(&user.username_lower),
+        date_text,
+        description: String::from(description),
+        amount,
+        currency,
+        is_income,
+        category_lower: String::from(category_lower),
+        item_original,
+        fitid: fitid.map(String::from),
+    })
+}
+
+/// Get a user's transactions in date order, only from one YYYY-MM month if one is given
+pub fn list_transactions(
+    conn: &Connection,
+    user: &User,
+    month: Option<&str>,
+) -> Result<Vec<BudgetTransaction>> {
+    let mut transactions: Vec<BudgetTransaction> = vec![];
+    let mut stmt = conn.prepare(
+        "SELECT * FROM transactions
+        WHERE username_lower = ?1 AND (?2 IS NULL OR substr(date_text, 1, 7) = ?2)
+        ORDER BY date_text, id",
+    )?;
+    let mut rows = stmt.query((&user.username_lower, month))?;
+    while let Some(row) = rows.next()? {
+        transactions.push(transaction_from_row(row)?)
+    }
+    Ok(transactions)
+}
+
+/// Permanently remove a transaction
+pub fn delete_transaction(conn: &Connection, user: &User, id: i64) -> Result<()> {
+    let num_deleted = conn.execute(
+        "DELETE FROM transactions WHERE id = ?1 AND username_lower = ?2",
+        (id, &user.username_lower),
+    )?;
+    if num_deleted == 0 {
+        return Err(BudgetError::NotFound(format!(
+            "Transaction {} was not found",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// Compare the budget against the transactions in one YYYY-MM month
+/// The plan is the month's own budget if it has one, otherwise the current budget
+/// From the current budget each item is planned at the amount that lands in that month (so an annual item is only planned in its month)
+/// Everything is converted into the user's base currency with the current exchange rates
+pub fn budget_vs_actual(conn: &Connection, user: &User, month: &str) -> Result<BudgetVsActual> {
+    let month = parse_month(month)?;
+    let converter = current_converter(conn, user)?;
+    month_actuals(conn, user, &converter, &month)
+}
+
+/// The budget vs. actual report for a month that has already been parsed
+/// An envelope carries over from the month right before when both months have their own budget
+fn month_actuals(
+    conn: &Connection,
+    user: &User,
+    converter: &Converter,
+    month: &str,
+) -> Result<BudgetVsActual> {
+    let transactions = list_transactions(conn, user, Some(month))?;
+    let is_month_budget = has_budget_month(conn, user, month)?;
+    let previous_month = shift_month(month, false)?;
+    let previous = if is_month_budget
+        && has_budget_month(conn, user, &previous_month)?
+        && !get_rollovers(conn, user, &previous_month)?.is_empty()
+    {
+        Some(month_actuals(conn, user, converter, &previous_month)?)
+    } else {
+        None
+    };
+    let rollovers = if is_month_budget {
+        get_rollovers(conn, user, month)?
+    } else {
+        vec![]
+    };
+
+    let mut report = BudgetVsActual {
+        month: String::from(month),
+        currency: String::from(converter.base()),
+        is_month_budget,
+        income: vec![],
+        expenses: vec![],
+    };
+    for which_half in [BudgetHalf::Income, BudgetHalf::Expenses] {
+        let (categories, planned) =
+            planned_lines(conn, user, converter, month, is_month_budget, &which_half)?;
+        let carried = match (&previous, &which_half) {
+            (Some(previous), BudgetHalf::Expenses) => previous.expenses.as_slice(),
+            _ => &[],
+        };
+        let actuals = half_actuals(
+            converter,
+            &transactions,
+            &which_half,
+            &categories,
+            &planned,
+            &rollovers,
+            carried,
+        )?;
+        match which_half {
+            BudgetHalf::Income => report.income = actuals,
+            BudgetHalf::Expenses => report.expenses = actuals,
+        }
+    }
+    Ok(report)
+}
+
+/// One item's plan for the month, before the transactions are matched to it
+struct PlannedLine {
+    name: String,
+    category_lower: String,
+    item_original: Option<usize>,
+    planned: Amount,
+}
+
+/// The categories and planned items for one half of a month, from its own budget or the current budget
+fn planned_lines(
+    conn: &Connection,
+    user: &User,
+    converter: &Converter,
+    month: &str,
+    is_month_budget: bool,
+    which_half: &BudgetHalf,
+) -> Result<(Vec<BudgetCategory>, Vec<PlannedLine>)> {
+    let mut lines: Vec<PlannedLine> = vec![];
+    if is_month_budget {
+        let (categories, items) = get_month_items(conn, user, month, which_half)?;
+        for item in items {
+            lines.push(PlannedLine {
+                planned: converter.convert(item.value, &item.currency)?,
+                name: item.item,
+                category_lower: item.category_lower,
+                item_original: item.item_original,
+            });
+        }
+        return Ok((categories, lines));
+    }
+
+    let first_day = parse_date(&format!("{}-01", month))?;
+    let (categories, items) = get_relevant_items(conn, user, which_half)?;
+    for item in items {
+        lines.push(PlannedLine {
+            planned: converter.convert(
+                item.frequency
+                    .amount_in_month(item.value, first_day.year(), first_day.month()),
+                &item.currency,
+            )?,
+            name: item.item,
+            category_lower: item.category_lower,
+            item_original: Some(item.timeline_original),
+        });
+    }
+    Ok((categories, lines))
+}
+
+/// Planned and actual amounts for each category and item in one half of the budget
+/// rollovers are the expense categories that roll over at the end of this month
+/// carried is last month's report for this half, and a category that rolled over there starts with what it had left
+/// Categories with nothing planned, carried in or spent are left out
+fn half_actuals(
+    converter: &Converter,
+    transactions: &[BudgetTransaction],
+    which_half: &BudgetHalf,
+    categories: &[BudgetCategory],
+    planned: &[PlannedLine],
+    rollovers: &[String],
+    carried: &[CategoryActuals],
+) -> Result<Vec<CategoryActuals>> {
+    let transactions: Vec<&BudgetTransaction> = transactions
+        .iter()
+        .filter(|t| t.is_income == which_half.to_bool())
+        .collect();
+
+    let mut report: Vec<CategoryActuals> = vec![];
+    for category in categories {
+        let mut item_lines: Vec<ActualLine> = vec![];
+        for line in planned
+            .iter()
+            .filter(|l| l.category_lower == category.category_lower)
+        {
+            let mut actual = Amount::ZERO;
+            for transaction in &transactions {
+                if line.item_original.is_some() && transaction.item_original == line.item_original {
+                    actual += converter.convert(transaction.amount, &transaction.currency)?;
+                }
+            }
+            item_lines.push(ActualLine {
+                name: line.name.clone(),
+                planned: line.planned,
+                carried_in: Amount::ZERO,
+                actual,
+            });
+        }
+
+        // Transactions in this category that don't belong to one of the planned items
+        // A transaction for an item that has since moved category is counted with the item instead
+        let mut unassigned = Amount::ZERO;
+        for transaction in &transactions {
+            let has_planned_item = transaction
+                .item_original
+                .is_some_and(|original| planned.iter().any(|l| l.item_original == Some(original)));
+            if transaction.category_lower == category.category_lower && !has_planned_item {
+                unassigned += converter.convert(transaction.amount, &transaction.currency)?;
+            }
+        }
+
+        // Only what was left over is carried in, so overspending doesn't come out of next month
+        let carried_in = carried
+            .iter()
+            .find(|c| c.rollover && c.category_lower == category.category_lower)
+            .map(|c| c.total.remaining())
+            .filter(|remaining| !remaining.is_negative())
+            .unwrap_or(Amount::ZERO);
+
+        let planned: Amount = item_lines.iter().map(|line| line.planned).sum();
+        let actual: Amount = item_lines.iter().map(|line| line.actual).sum::<Amount>() + unassigned;
+        if item_lines.is_empty() && actual == Amount::ZERO && carried_in == Amount::ZERO {
+            continue;
+        }
+        report.push(CategoryActuals {
+            category_lower: category.category_lower.clone(),
+            total: ActualLine {
+                name: category.category.clone(),
+                planned,
+                carried_in,
+                actual,
+            },
+            items: item_lines,
+            unassigned,
+            rollover: !which_half.to_bool() && rollovers.contains(&category.category_lower),
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(name: &str) -> BudgetCategory {
+        BudgetCategory {
+            category: String::from(name),
+            category_lower: name.to_lowercase(),
+            username_lower: String::from("alice"),
+            is_income: false,
+        }
+    }
+
+    fn planned_line(
+        name: &str,
+        category_lower: &str,
+        original: usize,
+        planned: i64,
+    ) -> PlannedLine {
+        PlannedLine {
+            name: String::from(name),
+            category_lower: String::from(category_lower),
+            item_original: Some(original),
+            planned: Amount::from_major(planned),
+        }
+    }
+
+    fn expense(
+        amount: i64,
+        category_lower: &str,
+        item_original: Option<usize>,
+    ) -> BudgetTransaction {
+        BudgetTransaction {
+            id: 0,
+            username_lower: String::from("alice"),
+            date_text: String::from("2025-01-15"),
+            description: String::new(),
+            amount: Amount::from_major(amount),
+            currency: String::from("USD"),
+            is_income: false,
+            category_lower: String::from(category_lower),
+            item_original,
+            fitid: None,
+        }
+    }
+
+    /// The expenses of one month with a Food category planned at 400 and spent as given
+    fn food_month(
+        spent: i64,
+        rollovers: &[String],
+        carried: &[CategoryActuals],
+    ) -> Vec<CategoryActuals> {
+        half_actuals(
+            &Converter::new("USD", vec![]),
+            &[expense(spent, "food", Some(1))],
+            &BudgetHalf::Expenses,
+            &[category("Food")],
+            &[planned_line("Groceries", "food", 1, 400)],
+            rollovers,
+            carried,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn carried_in_matches_the_category_whatever_its_case() {
+        let mut january = food_month(300, &[String::from("food")], &[]);
+        january[0].total.name = String::from("FOOD");
+        let february = food_month(0, &[], &january);
+        assert_eq!(february[0].total.name, "Food");
+        assert_eq!(february[0].total.carried_in, Amount::from_major(100));
+        assert_eq!(february[0].total.available(), Amount::from_major(500));
+    }
+
+    #[test]
+    fn only_left_over_rollovers_are_carried() {
+        let overspent = food_month(450, &[String::from("food")], &[]);
+        assert_eq!(overspent[0].total.remaining(), Amount::from_major(-50));
+        assert_eq!(
+            food_month(0, &[], &overspent)[0].total.carried_in,
+            Amount::ZERO
+        );
+
+        let not_rolled_over = food_month(300, &[], &[]);
+        assert!(!not_rolled_over[0].rollover);
+        assert_eq!(
+            food_month(0, &[], &not_rolled_over)[0].total.carried_in,
+            Amount::ZERO
+        );
+    }
+
+    #[test]
+    fn envelopes_carry_from_month_to_month() {
+        let rollovers = [String::from("food")];
+        let january = food_month(300, &rollovers, &[]);
+        assert_eq!(january[0].total.remaining(), Amount::from_major(100));
+        let february = food_month(350, &rollovers, &january);
+        assert_eq!(february[0].total.carried_in, Amount::from_major(100));
+        assert_eq!(february[0].total.remaining(), Amount::from_major(150));
+        let march = food_month(0, &[], &february);
+        assert_eq!(march[0].total.carried_in, Amount::from_major(150));
+        assert_eq!(march[0].total.remaining(), Amount::from_major(550));
+    }
+
+    #[test]
+    fn transactions_follow_items_that_moved_category() {
+        // Groceries moved from Household to Food after these were recorded
+        let transactions = [
+            expense(80, "household", Some(1)),
+            expense(20, "household", None),
+            expense(5, "household", Some(9)),
+        ];
+        let report = half_actuals(
+            &Converter::new("USD", vec![]),
+            &transactions,
+            &BudgetHalf::Expenses,
+            &[category("Food"), category("Household"), category("Travel")],
+            &[planned_line("Groceries", "food", 1, 400)],
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        // Travel has nothing planned, carried in or spent, so it is left out
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].total.name, "Food");
+        assert_eq!(report[0].items[0].actual, Amount::from_major(80));
+        assert_eq!(report[0].unassigned, Amount::ZERO);
+        // A transaction for an item that is no longer planned stays with its category
+        assert_eq!(report[1].total.name, "Household");
+        assert_eq!(report[1].unassigned, Amount::from_major(25));
+        assert_eq!(report[1].total.actual, Amount::from_major(25));
+    }
+}
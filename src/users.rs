@@ -0,0 +1,205 @@
+use crate::currency::DEFAULT_CURRENCY;
+use crate::error::{BudgetError, Result};
+use crate::structs_utils::*;
+use rusqlite::{Connection, Row};
+
+/// Build a User from a full row of the users table
+fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        username: row.get(0)?,
+        username_lower: row.get(1)?,
+        firstname: row.get(2)?,
+        lastname: row.get(3)?,
+        is_deleted: row.get(4)?,
+        base_currency: row.get(5)?,
+        has_password: row.get::<_, Option<String>>(6)?.is_some(),
+    })
+}
+
+/// Look up a user by username (case insensitive)
+pub fn find_user(conn: &Connection, username: &str) -> Result<Option<User>> {
+    let mut stmt = conn.prepare("SELECT * FROM users WHERE username_lower = ?1")?;
+    let mut rows = stmt.query(rusqlite::params![username.to_lowercase()])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(user_from_row(row)?)),
+        None => Ok(None),
+    }
+}
+
+/// Look up a user by username (case insensitive) and fail if they don't exist
+pub fn get_user(conn: &Connection, username: &str) -> Result<User> {
+    find_user(conn, username)?
+        .ok_or_else(|| BudgetError::NotFound(format!("The user {} was not found", username)))
+}
+
+/// Get all of the users in the database
+pub fn list_users(conn: &Connection) -> Result<Vec<User>> {
+    let mut users: Vec<User> = vec![];
+    let mut stmt = conn.prepare("SELECT * FROM users")?;
+    let mut rows = stmt.query(rusqlite::params![])?;
+    while let Some(row) = rows.next()? {
+        users.push(user_from_row(row)?)
+    }
+    Ok(users)
+}
+
+/// Insert a new user into the database and return it
+pub fn create_user(
+    conn: &Connection,
+    username: &str,
+    firstname: &str,
+    lastname: &str,
+) -> Result<User> {
+    if username.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The username cannot be empty",
+        )));
+    }
+    if find_user(conn, username)?.is_some() {
+        return Err(BudgetError::Conflict(format!(
+            "The user {} already exists",
+            username
+        )));
+    }
+
+    conn.execute(
+        "INSERT INTO users (username, username_lower, firstname, lastname, is_deleted, base_currency) VALUES (?1, ?2, ?3, ?4, 0, ?5)",
+        (username, &username.to_lowercase(), firstname, lastname, DEFAULT_CURRENCY),
+    )?;
+
+    Ok(User {
+        username: String::from(username),
+        username_lower: username.to_lowercase(),
+        firstname: String::from(firstname),
+        lastname: String::from(lastname),
+        is_deleted: false,
+        base_currency: String::from(DEFAULT_CURRENCY),
+        has_password: false,
+    })
+}
+
+/// Every table with rows belonging to a user, children before the tables they reference
+/// New tables keyed on username_lower must be added here so renames, purges and backups reach them
+pub(crate) const USER_TABLES: &[&str] = &[
+    "exchange_rates",
+    "statement_accounts",
+    "payee_rules",
+    "category_returns",
+    "debt_terms",
+    "loan_terms",
+    "goal_links",
+    "goals",
+    "budget_month_rollovers",
+    "budget_month_items",
+    "budget_months",
+    "transactions",
+    "snapshot_rates",
+    "balance_snapshots",
+    "budget_snapshot_rates",
+    "budget_snapshots",
+    "balance_timeline",
+    "balance_items",
+    "balance_categories",
+    "budget_timeline",
+    "budget_items",
+    "budget_categories",
+];
+
+/// Change a user's first and last name
+pub fn update_name(
+    conn: &Connection,
+    user: &User,
+    firstname: &str,
+    lastname: &str,
+) -> Result<User> {
+    conn.execute(
+        "UPDATE users SET firstname = ?1, lastname = ?2 WHERE username_lower = ?3",
+        (firstname, lastname, &user.username_lower),
+    )?;
+    let mut user = user.clone();
+    user.firstname = String::from(firstname);
+    user.lastname = String::from(lastname);
+    Ok(user)
+}
+
+/// Change a user's username, moving all of their budget, balance sheet and snapshot data with it
+pub fn rename_user(conn: &Connection, user: &User, new_username: &str) -> Result<User> {
+    if new_username.is_empty() {
+        return Err(BudgetError::Validation(String::from(
+            "The username cannot be empty",
+        )));
+    }
+    let new_lower = new_username.to_lowercase();
+    // Only changing the capitalization doesn't clash with this user
+    if new_lower != user.username_lower {
+        if let Some(existing) = find_user(conn, new_username)? {
+            return Err(BudgetError::Conflict(format!(
+                "The username {} is already taken",
+                existing.username
+            )));
+        }
+    }
+
+    in_transaction(conn, |conn| {
+        // The other tables point at users, so the checks wait until every table has the new name
+        conn.execute("PRAGMA defer_foreign_keys = ON", ())?;
+        for table in USER_TABLES {
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET username_lower = ?1 WHERE username_lower = ?2",
+                    table
+                ),
+                (&new_lower, &user.username_lower),
+            )?;
+        }
+        conn.execute(
+            "UPDATE users SET username = ?1, username_lower = ?2 WHERE username_lower = ?3",
+            (new_username, &new_lower, &user.username_lower),
+        )?;
+        Ok(())
+    })?;
+
+    let mut user = user.clone();
+    user.username = String::from(new_username);
+    user.username_lower = new_lower;
+    Ok(user)
+}
+
+/// Mark a user as deleted
+/// Their data is kept so the account can be restored, but they are hidden from the list of users
+pub fn delete_user(conn: &Connection, user: &User) -> Result<()> {
+    conn.execute(
+        "UPDATE users SET is_deleted = 1 WHERE username_lower = ?1",
+        [&user.username_lower],
+    )?;
+    Ok(())
+}
+
+/// Bring back a deleted user with all of their data
+pub fn restore_user(conn: &Connection, user: &User) -> Result<User> {
+    conn.execute(
+        "UPDATE users SET is_deleted = 0 WHERE username_lower = ?1",
+        [&user.username_lower],
+    )?;
+    let mut user = user.clone();
+    user.is_deleted = false;
+    Ok(user)
+}
+
+/// Permanently remove a user and everything they have stored
+/// This cannot be undone
+pub fn purge_user(conn: &Connection, user: &User) -> Result<()> {
+    in_transaction(conn, |conn| {
+        for table in USER_TABLES {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE username_lower = ?1", table),
+                [&user.username_lower],
+            )?;
+        }
+        conn.execute(
+            "DELETE FROM users WHERE username_lower = ?1",
+            [&user.username_lower],
+        )?;
+        Ok(())
+    })
+}